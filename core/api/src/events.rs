@@ -0,0 +1,56 @@
+use core_common::{
+    database::{Database, FetchAll, FetchById},
+    http::{
+        method::Method,
+        response::{self, Response},
+    },
+    objects::{Entity, Event, EventFilter},
+    sec::Auth,
+    url::form_urlencoded,
+    web::{
+        get_query_parameters, invalid_method, not_found, route_at, serve_json,
+        AppError, Request, ResponseType, TemplateEngine,
+    },
+};
+use core_views::EventListView;
+
+/// Serves the events api route. Returns one page of events visible to
+/// the current user as json.
+///
+/// # Errors
+/// Fails when the communication with the database fails
+#[inline]
+#[allow(single_use_lifetimes)]
+pub async fn index<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    path: &[String],
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>
+        + FetchById<'b, A, Entity<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(clippy::wildcard_enum_match_arm)]
+    match (route_at(path, 3), req.get_method()) {
+        (Some(""), &Method::GET) => {
+            let uri = req.get_uri();
+            let filter = uri
+                .query()
+                .map(str::as_bytes)
+                .map(form_urlencoded::parse)
+                .map_or_else(EventFilter::default, EventFilter::from);
+            let page = get_query_parameters(req)
+                .find(|(k, _)| k == "page")
+                .and_then(|(_, v)| v.parse().ok())
+                .unwrap_or(1);
+            let view = EventListView::fetch(req, &filter, page).await?;
+            serve_json(res, &view.0)
+        }
+        (Some(""), _) => invalid_method(&[Method::GET]),
+        _ => not_found(),
+    }
+}
@@ -51,3 +51,9 @@
     clippy::multiple_crate_versions,
     clippy::module_name_repetitions
 )]
+
+/// Contains api routes for the audit log
+pub mod events;
+pub mod public_keys;
+/// Contains api routes for servers
+pub mod servers;
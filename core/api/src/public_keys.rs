@@ -0,0 +1,268 @@
+use core_common::{
+    chrono::Utc,
+    database::{Create, Database, DatabaseError, FetchAll, FetchByUid, KeyCount},
+    http::{
+        method::Method,
+        response::{self, Response},
+        status::StatusCode,
+    },
+    objects::{PublicKey, PublicKeyConversionError, PublicKeyFilter, User},
+    sec::Auth,
+    serde_json::{self, json, Value},
+    types::Id,
+    url::form_urlencoded,
+    web::{
+        get_query_parameters, invalid_method, not_found, route_at, serve_json,
+        serve_json_status, AppError, Request, ResponseType, TemplateEngine,
+    },
+};
+use core_views::PublicKeyListView;
+
+/// Serves the public keys api route. `GET` returns one page of public
+/// keys visible to the current user. `POST` expects a json object
+/// containing the key in `data` and optionally the `uid` of its owner
+/// and an `expires` date in the format `%Y-%m-%d`.
+/// `POST` on `import/` accepts a whole `authorized_keys` file in `data`
+/// and returns a summary for every line. Options of the lines are reported
+/// but not applied.
+///
+/// # Errors
+/// Fails when the communication with the database fails
+#[inline]
+#[allow(single_use_lifetimes)]
+pub async fn index<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    path: &[String],
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + KeyCount
+        + FetchByUid<A, User<'a>, D>
+        + Create<A, PublicKey<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(clippy::wildcard_enum_match_arm)]
+    match (route_at(path, 3), req.get_method()) {
+        (Some(""), &Method::GET) => {
+            let uri = req.get_uri();
+            let filter = uri
+                .query()
+                .map(str::as_bytes)
+                .map(form_urlencoded::parse)
+                .map_or_else(PublicKeyFilter::default, PublicKeyFilter::from);
+            let page = get_query_parameters(req)
+                .find(|(k, _)| k == "page")
+                .and_then(|(_, v)| v.parse().ok())
+                .unwrap_or(1);
+            let db = req.get_database();
+            let keys = db.fetch_all(&filter, req.get_auth(), page)?;
+            serve_json(res, &keys)
+        }
+        (Some(""), &Method::POST) => index_post(req, res).await,
+        (Some(""), _) => invalid_method(&[Method::GET, Method::POST]),
+        (Some("import"), &Method::POST) => import_post(req, res).await,
+        (Some("import"), _) => invalid_method(&[Method::POST]),
+        _ => not_found(),
+    }
+}
+
+fn error<A, D, T, R>(
+    res: response::Builder,
+    status: StatusCode,
+    para: &str,
+    message: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    D: Database,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    serve_json_status(res, status, &json!({ "para": para, "error": message }))
+}
+
+/// Resolves the owner of new keys. Defaults to the current user if no
+/// `uid` is given. Only admins may add keys to other accounts
+#[allow(single_use_lifetimes)]
+fn owner<A, D, T, R>(
+    req: &R,
+    body: &Value,
+) -> Result<Result<Id, (StatusCode, &'static str)>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a> D: Database + FetchByUid<A, User<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let db = req.get_database();
+    let auth = req.get_auth();
+    let owner = match body.get("uid").and_then(Value::as_str) {
+        Some(uid) => db.fetch_by_uid(uid, auth)?,
+        None => None,
+    };
+    let owner = match owner {
+        Some(owner) => owner.entity_id.into_owned(),
+        None if body.get("uid").is_none() => auth.get_id().clone(),
+        None => {
+            return Ok(Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "User does not exist",
+            )))
+        }
+    };
+    if !auth.is_admin() && owner != *auth.get_id() {
+        return Ok(Err((
+            StatusCode::FORBIDDEN,
+            "Keys may only be added to your own account",
+        )));
+    }
+    Ok(Ok(owner))
+}
+
+/// Parses the json body and extracts the key data. Returns the status,
+/// parameter and message on failure
+fn parse_body(
+    bytes: &[u8],
+) -> Result<(Value, String), (StatusCode, &'static str, String)> {
+    let body: Value = serde_json::from_slice(bytes)
+        .map_err(|err| (StatusCode::BAD_REQUEST, "body", err.to_string()))?;
+    let data = match body.get("data").and_then(Value::as_str) {
+        Some(data) if !data.is_empty() => data.to_owned(),
+        _ => {
+            return Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "data",
+                "Public key data is missing".into(),
+            ))
+        }
+    };
+    Ok((body, data))
+}
+
+#[allow(single_use_lifetimes)]
+async fn import_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + KeyCount
+        + FetchByUid<A, User<'a>, D>
+        + Create<A, PublicKey<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let (body, data) = match parse_body(&bytes) {
+        Ok(body) => body,
+        Err((status, para, message)) => return error(res, status, para, &message),
+    };
+    let owner = match owner(req, &body)? {
+        Ok(owner) => owner,
+        Err((status, message)) => return error(res, status, "uid", message),
+    };
+    let summary = PublicKeyListView::import(req, &data, &owner).await?;
+    serve_json(res, &summary)
+}
+
+#[allow(single_use_lifetimes)]
+async fn index_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + KeyCount
+        + FetchByUid<A, User<'a>, D>
+        + Create<A, PublicKey<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let (body, data) = match parse_body(&bytes) {
+        Ok(body) => body,
+        Err((status, para, message)) => return error(res, status, para, &message),
+    };
+    let owner = match owner(req, &body)? {
+        Ok(owner) => owner,
+        Err((status, message)) => return error(res, status, "uid", message),
+    };
+    let expires = match body.get("expires").and_then(Value::as_str) {
+        Some(expires) => match PublicKey::parse_expiry(expires) {
+            Some(expires) => Some(expires),
+            None => {
+                return error(
+                    res,
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "expires",
+                    "Expiry date must be in the format YYYY-MM-DD",
+                )
+            }
+        },
+        None => None,
+    };
+    let expires = req
+        .get_base_data()
+        .key_policy
+        .expiry(expires, Utc::now().naive_utc());
+    let db = req.get_database();
+    let auth = req.get_auth();
+
+    let blocklist = &req.get_base_data().key_blocklist;
+    let key = match PublicKey::parse(&data, &owner, db, blocklist) {
+        Ok(key) => PublicKey { expires, ..key },
+        Err(PublicKeyConversionError::DatabaseError(err)) => {
+            return Err(AppError::DatabaseError(err))
+        }
+        Err(PublicKeyConversionError::OpenSshError(err)) => {
+            return error(
+                res,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "data",
+                &err.to_string(),
+            )
+        }
+        Err(PublicKeyConversionError::InvalidSecurityKey) => {
+            return error(
+                res,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "data",
+                "Security key data is malformed",
+            )
+        }
+        Err(PublicKeyConversionError::Compromised) => {
+            return error(
+                res,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "data",
+                "Public key is known to be compromised",
+            )
+        }
+    };
+    if let Err(violation) = PublicKeyListView::check_policy(req, &key).await? {
+        return error(
+            res,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            violation.para(),
+            &violation.to_string(),
+        );
+    }
+    match db.create(&key, auth) {
+        Err(err @ DatabaseError::Custom(_)) => Err(AppError::DatabaseError(err)),
+        Err(DatabaseError::NonUnique) => error(
+            res,
+            StatusCode::CONFLICT,
+            "fingerprint",
+            "Public key with this fingerprint already exists",
+        ),
+        Ok(()) => serve_json_status(res, StatusCode::CREATED, &key),
+    }
+}
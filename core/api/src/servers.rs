@@ -0,0 +1,285 @@
+use core_common::{
+    database::{
+        CertificateStore, Create, Database, FetchAllFor, FetchById, LoginStore,
+        ServerTokenStore,
+    },
+    http::{
+        method::Method,
+        response::{self, Response},
+        status::StatusCode,
+    },
+    log,
+    objects::{Event, Server, ServerFilter},
+    sec::{token_digest, Auth},
+    serde_json::{self, json, Value},
+    types::{Id, KeyManagement},
+    web::{
+        get_query_parameters, invalid_method, not_found, route_at, serve_download,
+        serve_json, serve_json_status, unauthorized, AppError, Request,
+        ResponseType, TemplateEngine,
+    },
+};
+use core_views::{
+    HostCertificateView, KnownHostsView, ServerCertificatesView, SshConfigView,
+};
+
+/// Serves the servers api route for users. Servers fetch their
+/// certificate configuration from `certificates` instead.
+/// `GET` on `<id>/host_certificate/` returns the current host certificate
+/// and whether it should be renewed. `POST` expects a json object
+/// containing the host key in `data` and issues a new host certificate.
+/// `GET` on `known_hosts/` returns the `known_hosts` file of all servers
+/// the user can access. Hostnames are hashed if `hashed=1` is given.
+/// `GET` on `ssh_config/` returns a `ssh_config` entry for every server
+/// account the user can log into.
+///
+/// # Errors
+/// Fails when the communication with the database fails
+#[inline]
+#[allow(single_use_lifetimes)]
+pub async fn index<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    path: &[String],
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b> D: Database
+        + FetchById<'b, A, Server<'a>, D>
+        + FetchAllFor<A, Server<'a>, ServerFilter<'b>, D>
+        + CertificateStore
+        + LoginStore
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(clippy::wildcard_enum_match_arm)]
+    match route_at(path, 3) {
+        Some("known_hosts") => return known_hosts(req, res, path).await,
+        Some("ssh_config") => return ssh_config(req, res, path).await,
+        _ => {}
+    }
+    let server = match route_at(path, 3).map(Id::from_string) {
+        Some(Ok(id)) => req.get_database().fetch(&id, req.get_auth())?,
+        Some(Err(_)) | None => None,
+    };
+    let server: Server<'_> = match server {
+        Some(server) => server,
+        None => return not_found(),
+    };
+    #[allow(clippy::wildcard_enum_match_arm)]
+    match (route_at(path, 4), req.get_method()) {
+        (Some("host_certificate"), &Method::GET)
+            if route_at(path, 5) == Some("") =>
+        {
+            let view = HostCertificateView::fetch(req, &server.id).await?;
+            serve_json(res, &view)
+        }
+        (Some("host_certificate"), &Method::POST)
+            if route_at(path, 5) == Some("") =>
+        {
+            host_certificate_post(req, res, &server).await
+        }
+        (Some("host_certificate"), _) if route_at(path, 5) == Some("") => {
+            invalid_method(&[Method::GET, Method::POST])
+        }
+        _ => not_found(),
+    }
+}
+
+#[allow(single_use_lifetimes)]
+async fn known_hosts<A, D, T, R>(
+    req: &R,
+    res: response::Builder,
+    path: &[String],
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b> D: Database
+        + CertificateStore
+        + FetchAllFor<A, Server<'a>, ServerFilter<'b>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    if route_at(path, 4) != Some("") {
+        return not_found();
+    }
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => {
+            let hashed =
+                get_query_parameters(req).any(|(k, v)| k == "hashed" && v == "1");
+            let view = KnownHostsView::fetch(req).await?;
+            match view.to_file(hashed) {
+                Ok(file) => serve_download(
+                    res,
+                    "text/plain; charset=UTF-8",
+                    "known_hosts",
+                    file,
+                ),
+                Err(err) => {
+                    log::error!("Unable to create known_hosts: {}", err);
+                    not_found()
+                }
+            }
+        }
+        _ => invalid_method(&[Method::GET]),
+    }
+}
+
+#[allow(single_use_lifetimes)]
+async fn ssh_config<A, D, T, R>(
+    req: &R,
+    res: response::Builder,
+    path: &[String],
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    D: Database + LoginStore,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    if route_at(path, 4) != Some("") {
+        return not_found();
+    }
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => {
+            let view = SshConfigView::fetch(req).await?;
+            serve_download(
+                res,
+                "text/plain; charset=UTF-8",
+                "ssh_config",
+                view.to_file(),
+            )
+        }
+        _ => invalid_method(&[Method::GET]),
+    }
+}
+
+/// Serves `<id>/certificates/` of the servers api route to the server
+/// itself. The server authenticates with its server token, sent as
+/// `Bearer` token in the `Authorization` header. `GET` on
+/// `<id>/certificates/` returns the certificate authority and the
+/// principals of every account as json if the server uses
+/// `KeyManagement::Certificates`. `<id>/certificates/ca/` and
+/// `<id>/certificates/principals/<account>/` return the plain files for
+/// `TrustedUserCAKeys` and `AuthorizedPrincipalsFile`.
+///
+/// # Errors
+/// Fails when the communication with the database fails
+#[inline]
+pub async fn certificates<A, D, T, R>(
+    req: &R,
+    res: response::Builder,
+    path: &[String],
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    D: Database + CertificateStore + ServerTokenStore,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let token = match req
+        .get_authorization()
+        .and_then(|v| v.strip_prefix("Bearer "))
+    {
+        Some(token) => token,
+        None => return unauthorized(),
+    };
+    let server = match route_at(path, 3).map(Id::from_string) {
+        Some(Ok(id)) => req
+            .get_database()
+            .fetch_server_by_token(&id, &token_digest(token))?,
+        Some(Err(_)) | None => None,
+    };
+    let server = match server {
+        Some(server) => server,
+        None => return unauthorized(),
+    };
+    if *req.get_method() != Method::GET {
+        return invalid_method(&[Method::GET]);
+    }
+    if server.key_management != KeyManagement::Certificates {
+        return serve_json_status(
+            res,
+            StatusCode::CONFLICT,
+            &json!({
+                "para": "key_management",
+                "error": "Server does not use certificates",
+            }),
+        );
+    }
+    let view = match ServerCertificatesView::fetch(req, &server.id).await? {
+        Ok(view) => view,
+        Err(err) => {
+            log::error!("Unable to create certificate authority: {}", err);
+            return not_found();
+        }
+    };
+    #[allow(clippy::wildcard_enum_match_arm)]
+    match (route_at(path, 5), route_at(path, 6)) {
+        (Some(""), None) => serve_json(res, &view),
+        (Some("ca"), Some("")) => serve_download(
+            res,
+            "text/plain; charset=UTF-8",
+            "user_ca.pub",
+            view.authority.into_owned(),
+        ),
+        (Some("principals"), Some(account)) => {
+            match view.accounts.iter().find(|v| v.account == account) {
+                Some(principals) => serve_download(
+                    res,
+                    "text/plain; charset=UTF-8",
+                    account,
+                    principals.to_file(),
+                ),
+                None => not_found(),
+            }
+        }
+        _ => not_found(),
+    }
+}
+
+#[allow(single_use_lifetimes)]
+async fn host_certificate_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    server: &Server<'_>,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a> D: Database + CertificateStore + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let body: Value = match serde_json::from_slice(&bytes) {
+        Ok(body) => body,
+        Err(err) => {
+            return serve_json_status(
+                res,
+                StatusCode::BAD_REQUEST,
+                &json!({ "para": "body", "error": err.to_string() }),
+            )
+        }
+    };
+    let data = match body.get("data").and_then(Value::as_str) {
+        Some(data) if !data.is_empty() => data,
+        _ => {
+            return serve_json_status(
+                res,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                &json!({ "para": "data", "error": "Host key data is missing" }),
+            )
+        }
+    };
+    match HostCertificateView::issue(req, server, data).await? {
+        Ok(certificate) => serve_json(res, &certificate),
+        Err(err) => serve_json_status(
+            res,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            &json!({ "para": err.para(), "error": err.to_string() }),
+        ),
+    }
+}
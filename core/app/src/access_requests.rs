@@ -0,0 +1,247 @@
+use core_common::{
+    database::{AccessRequestStore, BreakGlassStore, Create, Database, LoginStore},
+    http::{
+        method::Method,
+        response::{self, Response},
+    },
+    objects::Event,
+    sec::{Auth, CsrfToken},
+    url::form_urlencoded,
+    web::{
+        invalid_method, not_found, redirect, route_at, serve_template, AppError,
+        BaseContainer, Notification, Request, ResponseType, TemplateEngine,
+    },
+};
+use core_views::{
+    AccessRequestForm, AccessRequestListView, AccessRequestView, BreakGlassForm,
+    BreakGlassView,
+};
+use std::borrow::Cow;
+
+/// Serves the access requests route. Users request access to server
+/// accounts and server admins approve or reject the requests. In an
+/// emergency users take short-lived access without approval
+///
+/// # Errors
+/// Fails when the communication with the database fails
+#[inline]
+#[allow(single_use_lifetimes)]
+pub async fn index<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    path: &[String],
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a> D: Database
+        + AccessRequestStore
+        + BreakGlassStore
+        + LoginStore
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(clippy::wildcard_enum_match_arm)]
+    match route_at(path, 3) {
+        Some("") => index_method(req, res).await,
+        Some(key) => request_method(req, res, key).await,
+        _ => not_found(),
+    }
+}
+
+#[inline]
+#[allow(single_use_lifetimes)]
+async fn index_method<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a> D: Database
+        + AccessRequestStore
+        + BreakGlassStore
+        + LoginStore
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => index_get(req, res, None, CsrfToken::from(req)).await,
+        Method::POST => index_post(req, res).await,
+        _ => invalid_method(&[Method::GET, Method::POST]),
+    }
+}
+
+async fn index_get<A, D, T, R>(
+    req: &R,
+    mut res: response::Builder,
+    noti: Option<&[Notification<'_>]>,
+    csrf_token: CsrfToken,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    D: Database + AccessRequestStore + LoginStore,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let view = AccessRequestListView::fetch(req).await?;
+    let user = req.get_auth().get_user_container();
+    let url = req.get_uri().path();
+    let csrf = csrf_token.generate(req, &mut res)?;
+    let container = BaseContainer {
+        csrf: Some(csrf),
+        base: Cow::Borrowed("../"),
+        user,
+        noti,
+        ..BaseContainer::new(req.get_base_view(), &view, &(), url)
+    };
+    serve_template(req, res, "site_access_requests", &container)
+}
+
+#[allow(single_use_lifetimes)]
+async fn index_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a> D: Database
+        + AccessRequestStore
+        + BreakGlassStore
+        + LoginStore
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let mut form = AccessRequestForm::default();
+    let mut hours = None;
+    let mut break_glass = false;
+    let mut csrf = None;
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        if v.is_empty() {
+            continue;
+        }
+        match k.as_ref() {
+            "hostname" => form.hostname = Some(v),
+            "account" => form.account = Some(v),
+            "reason" => form.reason = Some(v),
+            "valid_from" => form.valid_from = Some(v),
+            "valid_until" => form.valid_until = Some(v),
+            "hours" => hours = Some(v),
+            "break_glass" => break_glass = true,
+            "csrf" => csrf = Some(v),
+            _ => {}
+        }
+    }
+    let csrf = CsrfToken::verify(req, csrf.as_deref());
+    let noti = if break_glass {
+        let form = BreakGlassForm {
+            hostname: form.hostname,
+            account: form.account,
+            reason: form.reason,
+            hours,
+        };
+        BreakGlassView::create(req, form, &csrf).await?
+    } else {
+        AccessRequestListView::create(req, form, &csrf).await?
+    };
+    index_get(req, res, Some(&noti), csrf).await
+}
+
+#[inline]
+#[allow(single_use_lifetimes)]
+async fn request_method<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    key: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a> D: Database + AccessRequestStore + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => request_get(req, res, key, None).await,
+        Method::POST => request_post(req, res, key).await,
+        _ => invalid_method(&[Method::GET, Method::POST]),
+    }
+}
+
+async fn request_get<A, D, T, R>(
+    req: &R,
+    mut res: response::Builder,
+    key: &str,
+    noti: Option<&[Notification<'_>]>,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    D: Database + AccessRequestStore,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let view = match AccessRequestView::fetch(req, key).await? {
+        Some(view) => view,
+        None => return not_found(),
+    };
+    let user = req.get_auth().get_user_container();
+    let url = req.get_uri().path();
+    let csrf_token = CsrfToken::from(req);
+    let csrf = csrf_token.generate(req, &mut res)?;
+    let container = BaseContainer {
+        csrf: Some(csrf),
+        base: Cow::Borrowed("../../"),
+        user,
+        noti,
+        ..BaseContainer::new(req.get_base_view(), &view, &(), url)
+    };
+    serve_template(req, res, "site_access_request", &container)
+}
+
+#[allow(single_use_lifetimes)]
+async fn request_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    key: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a> D: Database + AccessRequestStore + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let mut csrf = None;
+    let mut approve = None;
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        if v.is_empty() {
+            continue;
+        }
+        match k.as_ref() {
+            "csrf" => csrf = Some(v),
+            "approve" => approve = Some(true),
+            "reject" => approve = Some(false),
+            _ => {}
+        }
+    }
+    let csrf = CsrfToken::verify(req, csrf.as_deref());
+    if !csrf.valid {
+        let noti = [Notification::Error {
+            name: "Access request",
+            para: "csrf",
+            help: "../../help/#access_request",
+        }];
+        return request_get(req, res, key, Some(&noti)).await;
+    }
+    let mut view = match AccessRequestView::fetch(req, key).await? {
+        Some(view) => view,
+        None => return not_found(),
+    };
+    if let Some(approve) = approve {
+        let _ = view.decide(req, approve).await?;
+    }
+    redirect(req, res, "/app/access_requests/", false, true, true)
+}
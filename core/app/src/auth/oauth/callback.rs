@@ -5,13 +5,14 @@ use core_common::{
         method::Method,
         response::Response,
     },
-    objects::User,
+    objects::{Event, EventDetails, User},
     sec::{Auth, OAuth2, PreAuth},
     web::{
         get_query_parameters, invalid_method, redirect, AppError, Request,
         ResponseType, TemplateEngine,
     },
 };
+use std::borrow::Cow;
 
 /// Servers the oauth callback route.
 ///
@@ -26,7 +27,8 @@ where
     for<'a> D: Database
         + FetchByUid<PreAuth, User<'a>, D>
         + Create<PreAuth, User<'a>, D>
-        + Save<PreAuth, User<'a>, D>,
+        + Save<PreAuth, User<'a>, D>
+        + Create<PreAuth, Event<'a>, D>,
     T: TemplateEngine,
     R: Request<A, D, T>,
 {
@@ -45,7 +47,8 @@ where
     for<'a> D: Database
         + FetchByUid<PreAuth, User<'a>, D>
         + Create<PreAuth, User<'a>, D>
-        + Save<PreAuth, User<'a>, D>,
+        + Save<PreAuth, User<'a>, D>
+        + Create<PreAuth, Event<'a>, D>,
     T: TemplateEngine,
     R: Request<A, D, T>,
 {
@@ -64,7 +67,19 @@ where
         if Some(&state[..]) == state_cookie {
             let client = &req.get_base_data().oauth;
             let token_result = client.get_token(code.to_string()).await?;
-            let (cookies, _) = client.handle_token(&token_result, req).await?;
+            let (cookies, auth) = client.handle_token(&token_result, req).await?;
+            if let Some((auth, _)) = auth {
+                let db = req.get_database();
+                let event = Event::new(
+                    Cow::Owned(db.generate_id()?),
+                    Some(Cow::Borrowed(auth.get_id())),
+                    EventDetails::Login {
+                        uid: Cow::Borrowed(auth.get_uid()),
+                    },
+                    Some(Cow::Borrowed(auth.get_id())),
+                );
+                db.create(&event, &PreAuth)?;
+            }
             if let Some(header) = res.headers_mut() {
                 for cookie in cookies {
                     let value = HeaderValue::from_str(&cookie)
@@ -0,0 +1,175 @@
+use core_common::{
+    database::{BreakGlassStore, Create, Database},
+    http::{
+        method::Method,
+        response::{self, Response},
+    },
+    objects::Event,
+    sec::{Auth, CsrfToken},
+    url::form_urlencoded,
+    web::{
+        get_query_parameters, invalid_method, not_found, redirect, route_at,
+        serve_template, AppError, BaseContainer, Notification, Request,
+        ResponseType, TemplateEngine,
+    },
+};
+use core_views::{BreakGlassReportView, BreakGlassView};
+use std::borrow::Cow;
+
+/// Serves the emergency access route. Admins review emergency access taken
+/// by users, users may see their own emergency access
+///
+/// # Errors
+/// Fails when the communication with the database fails
+#[inline]
+#[allow(single_use_lifetimes)]
+pub async fn index<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    path: &[String],
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a> D: Database + BreakGlassStore + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(clippy::wildcard_enum_match_arm)]
+    match route_at(path, 3) {
+        Some("") => index_method(req, res).await,
+        Some(key) => break_glass_method(req, res, key).await,
+        _ => not_found(),
+    }
+}
+
+#[inline]
+async fn index_method<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    D: Database + BreakGlassStore,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    if !req.get_auth().is_admin() {
+        return not_found();
+    }
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => index_get(req, res).await,
+        _ => invalid_method(&[Method::GET]),
+    }
+}
+
+async fn index_get<A, D, T, R>(
+    req: &R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    D: Database + BreakGlassStore,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let unreviewed = get_query_parameters(req)
+        .any(|(k, v)| k.as_ref() == "unreviewed" && v == "1");
+    let view = BreakGlassReportView::fetch(req, unreviewed).await?;
+    let user = req.get_auth().get_user_container();
+    let url = req.get_uri().path();
+    let container = BaseContainer {
+        base: Cow::Borrowed("../"),
+        user,
+        ..BaseContainer::new(req.get_base_view(), &view, &(), url)
+    };
+    serve_template(req, res, "site_break_glass_report", &container)
+}
+
+#[inline]
+#[allow(single_use_lifetimes)]
+async fn break_glass_method<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    key: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a> D: Database + BreakGlassStore + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => break_glass_get(req, res, key, None).await,
+        Method::POST => break_glass_post(req, res, key).await,
+        _ => invalid_method(&[Method::GET, Method::POST]),
+    }
+}
+
+async fn break_glass_get<A, D, T, R>(
+    req: &R,
+    mut res: response::Builder,
+    key: &str,
+    noti: Option<&[Notification<'_>]>,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    D: Database + BreakGlassStore,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let view = match BreakGlassView::fetch(req, key).await? {
+        Some(view) => view,
+        None => return not_found(),
+    };
+    let user = req.get_auth().get_user_container();
+    let url = req.get_uri().path();
+    let csrf_token = CsrfToken::from(req);
+    let csrf = csrf_token.generate(req, &mut res)?;
+    let container = BaseContainer {
+        csrf: Some(csrf),
+        base: Cow::Borrowed("../../"),
+        user,
+        noti,
+        ..BaseContainer::new(req.get_base_view(), &view, &(), url)
+    };
+    serve_template(req, res, "site_break_glass", &container)
+}
+
+#[allow(single_use_lifetimes)]
+async fn break_glass_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    key: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a> D: Database + BreakGlassStore + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let csrf = form_urlencoded::parse(&bytes).find_map(|(k, v)| {
+        if k.as_ref() == "csrf" && !v.is_empty() {
+            Some(v)
+        } else {
+            None
+        }
+    });
+    let csrf = CsrfToken::verify(req, csrf.as_deref());
+    if !csrf.valid {
+        let noti = [Notification::Error {
+            name: "Emergency access review",
+            para: "csrf",
+            help: "../../help/#break_glass",
+        }];
+        return break_glass_get(req, res, key, Some(&noti)).await;
+    }
+    let mut view = match BreakGlassView::fetch(req, key).await? {
+        Some(view) if view.can_review => view,
+        _ => return not_found(),
+    };
+    let _ = view.review(req).await?;
+    redirect(req, res, "/app/break_glass/", false, true, true)
+}
@@ -0,0 +1,119 @@
+use core_common::{
+    database::{Database, FetchAll, FetchById},
+    http::{
+        method::Method,
+        response::{self, Response},
+    },
+    objects::{Entity, Event, EventFilter},
+    sec::Auth,
+    url::form_urlencoded,
+    web::{
+        get_query_parameters, invalid_method, not_found, route_at, serve_download,
+        serve_json, serve_template, AppError, BaseContainer, Request, ResponseType,
+        TemplateEngine,
+    },
+};
+use core_views::EventListView;
+use std::borrow::Cow;
+
+/// Serves the events route
+///
+/// # Errors
+/// Fails when the communication with the database fails
+#[inline]
+#[allow(single_use_lifetimes)]
+pub async fn index<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    path: &[String],
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>
+        + FetchById<'b, A, Entity<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(clippy::wildcard_enum_match_arm)]
+    match route_at(path, 3) {
+        Some("") => index_method(req, res).await,
+        _ => not_found(),
+    }
+}
+
+#[inline]
+#[allow(single_use_lifetimes)]
+async fn index_method<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>
+        + FetchById<'b, A, Entity<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => index_get(req, res).await,
+        _ => invalid_method(&[Method::GET]),
+    }
+}
+
+#[allow(single_use_lifetimes)]
+async fn index_get<A, D, T, R>(
+    req: &R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>
+        + FetchById<'b, A, Entity<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let uri = req.get_uri();
+    let filter = uri
+        .query()
+        .map(str::as_bytes)
+        .map(form_urlencoded::parse)
+        .map_or_else(EventFilter::default, EventFilter::from);
+    let (page, format) =
+        get_query_parameters(req).fold((1, None), |acc, (k, v)| match k.as_ref() {
+            "page" => (v.parse().unwrap_or(1), acc.1),
+            "format" => (acc.0, Some(v)),
+            _ => acc,
+        });
+    match format.as_deref() {
+        Some("csv") => {
+            let events = EventListView::fetch_all(req, &filter).await?;
+            let csv = EventListView::to_csv(&events);
+            serve_download(res, "text/csv; charset=UTF-8", "events.csv", csv)
+        }
+        Some("json") => {
+            let events = EventListView::fetch_all(req, &filter).await?;
+            serve_json(
+                res.header(
+                    "Content-Disposition",
+                    "attachment; filename=\"events.json\"",
+                ),
+                &events,
+            )
+        }
+        _ => {
+            let view = EventListView::fetch(req, &filter, page).await?;
+            let user = req.get_auth().get_user_container();
+            let url = req.get_uri().path();
+            let container = BaseContainer {
+                base: Cow::Borrowed("../"),
+                user,
+                ..BaseContainer::new(req.get_base_view(), &view.0, &filter, url)
+            };
+            serve_template(req, res, "site_events", &container)
+        }
+    }
+}
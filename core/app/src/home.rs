@@ -0,0 +1,174 @@
+use core_common::{
+    database::{
+        AccessRequestStore, ApiTokenStore, Create, Database, FetchAll,
+        NotificationStore,
+    },
+    http::{
+        method::Method,
+        response::{self, Response},
+    },
+    objects::{Event, NotificationPreferences, PublicKey, PublicKeyFilter},
+    sec::{Auth, CsrfToken},
+    types::Id,
+    url::form_urlencoded,
+    web::{
+        get_query_parameters, invalid_method, not_found, redirect_home,
+        serve_download, serve_template, unauthorized, AppError, BaseContainer,
+        Notification, Request, ResponseType, TemplateEngine,
+    },
+};
+use core_views::HomeView;
+use std::borrow::Cow;
+
+/// Serves the start page showing the keys of the current user, keys which
+/// are about to expire and pending access requests. `POST` creates or
+/// revokes an api token of the current user or saves the notification
+/// preferences
+///
+/// # Errors
+/// Fails when the communication with the database fails
+#[inline]
+#[allow(single_use_lifetimes)]
+pub async fn index<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + AccessRequestStore
+        + ApiTokenStore
+        + NotificationStore
+        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => index_get(req, res, None, CsrfToken::from(req)).await,
+        Method::POST => index_post(req, res).await,
+        _ => invalid_method(&[Method::GET, Method::POST]),
+    }
+}
+
+#[allow(single_use_lifetimes)]
+async fn index_get<A, D, T, R>(
+    req: &R,
+    mut res: response::Builder,
+    noti: Option<&[Notification<'_>]>,
+    csrf_token: CsrfToken,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + AccessRequestStore
+        + ApiTokenStore
+        + NotificationStore
+        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let (page, expiring_page) =
+        get_query_parameters(req).fold((1, 1), |acc, (k, v)| match k.as_ref() {
+            "ppage" => (v.parse().unwrap_or(1), acc.1),
+            "epage" => (acc.0, v.parse().unwrap_or(1)),
+            _ => acc,
+        });
+    let view = HomeView::fetch(req, page, expiring_page).await?;
+    let user = req.get_auth().get_user_container();
+    let url = req.get_uri().path();
+    let csrf = csrf_token.generate(req, &mut res)?;
+    let container = BaseContainer {
+        csrf: Some(csrf),
+        base: Cow::Borrowed(""),
+        user,
+        noti,
+        ..BaseContainer::new(req.get_base_view(), &view, &(), url)
+    };
+    serve_template(req, res, "site_home", &container)
+}
+
+#[allow(single_use_lifetimes)]
+async fn index_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + AccessRequestStore
+        + ApiTokenStore
+        + NotificationStore
+        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let mut csrf = None;
+    let mut api_token = false;
+    let mut revoke_token = None;
+    let mut notifications = false;
+    let mut preferences = NotificationPreferences {
+        key_add: false,
+        access: false,
+        key_expiring: false,
+        ..NotificationPreferences::new(Cow::Borrowed(req.get_auth().get_id()))
+    };
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        if v.is_empty() {
+            continue;
+        }
+        match k.as_ref() {
+            "csrf" => csrf = Some(v),
+            "api_token" => api_token = true,
+            "revoke_token" => revoke_token = Id::from_string(&v).ok(),
+            "notifications" => notifications = true,
+            "key_add" => preferences.key_add = true,
+            "access" => preferences.access = true,
+            "key_expiring" => preferences.key_expiring = true,
+            _ => {}
+        }
+    }
+    let csrf = CsrfToken::verify(req, csrf.as_deref());
+    if !csrf.valid {
+        let noti = if notifications {
+            [Notification::Error {
+                name: "Notifications",
+                para: "csrf",
+                help: "help/#notifications",
+            }]
+        } else {
+            [Notification::Error {
+                name: "Api token",
+                para: "csrf",
+                help: "help/#api_token",
+            }]
+        };
+        return index_get(req, res, Some(&noti), csrf).await;
+    }
+    if notifications {
+        HomeView::save_notifications(req, preferences).await?;
+        return redirect_home(req, res, false);
+    }
+    if let Some(token_id) = revoke_token {
+        if !HomeView::revoke_api_token(req, &token_id).await? {
+            return not_found();
+        }
+        return redirect_home(req, res, false);
+    }
+    if !api_token {
+        return index_get(req, res, None, csrf).await;
+    }
+    // a leaked api token must not be able to renew itself
+    if req.get_auth().is_api_token() {
+        return unauthorized();
+    }
+    match HomeView::create_api_token(req).await? {
+        Some(token) => {
+            serve_download(res, "text/plain; charset=UTF-8", "api_token", token)
+        }
+        None => not_found(),
+    }
+}
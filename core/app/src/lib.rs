@@ -52,9 +52,22 @@
     clippy::module_name_repetitions
 )]
 
+/// Contains routes for access requests
+pub mod access_requests;
 /// Routes for authentication
 pub mod auth;
+/// Contains routes for the emergency access report
+pub mod break_glass;
+/// Contains routes for the audit log
+pub mod events;
+/// Contains the route of the start page
+pub mod home;
 /// Contains routes for public key access
 pub mod public_keys;
+/// Contains routes for access recertification campaigns
+pub mod recertifications;
 /// Contains routes for static file access
 pub mod rstatic;
+/// Contains routes for server administration
+pub mod servers;
+pub mod webhooks;
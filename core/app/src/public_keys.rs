@@ -1,19 +1,32 @@
 use core_common::{
-    database::{Create, Database, Delete, FetchAll, FetchById, FetchByUid},
+    chrono::Utc,
+    database::{
+        CertificateStore, Create, Database, Delete, FetchAll, FetchAllFor,
+        FetchById, FetchByUid, KeyCount, LoginStore, Purge, Restore,
+    },
     http::{
         method::Method,
         response::{self, Response},
     },
-    objects::{Entity, PublicKey, PublicKeyFilter, User},
+    log,
+    objects::{
+        Entity, Event, EventFilter, KeyImportSummary, PublicKey, PublicKeyFilter,
+        Server, ServerFilter, User,
+    },
     sec::{Auth, CsrfToken},
-    types::Id,
+    types::{CertificateType, Id},
     url::form_urlencoded,
     web::{
-        invalid_method, not_found, redirect, route_at, serve_template, AppError,
-        BaseContainer, Notification, Request, ResponseType, TemplateEngine,
+        get_query_parameters, invalid_method, not_found, redirect, route_at,
+        serve_download, serve_template, AppError, BaseContainer, Notification,
+        Request, ResponseType, TemplateEngine,
     },
 };
-use core_views::{PublicKeyListView, PublicKeyView};
+use core_views::{
+    CertificateAuthorityView, CompromisedKeyReportView, DeactivatedKeyListView,
+    KeyPolicyReportView, KnownHostsView, PublicKeyListView, PublicKeyView,
+    SshConfigView,
+};
 use std::borrow::Cow;
 
 /// Serves the public keys route
@@ -31,17 +44,35 @@ where
     A: Auth,
     for<'a, 'b, 'c> D: Database
         + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + KeyCount
         + FetchByUid<A, User<'a>, D>
         + FetchById<'b, A, PublicKey<'a>, D>
         + FetchById<'b, A, Entity<'a>, D>
         + Create<A, PublicKey<'a>, D>
-        + Delete<A, PublicKey<'a>, D>,
+        + Delete<A, PublicKey<'a>, D>
+        + Restore<A, PublicKey<'a>, D>
+        + Purge<A, PublicKey<'a>, D>
+        + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>
+        + CertificateStore
+        + FetchAllFor<A, Server<'a>, ServerFilter<'c>, D>
+        + LoginStore
+        + Create<A, Event<'a>, D>,
     T: TemplateEngine,
     R: Request<A, D, T>,
 {
     #[allow(clippy::wildcard_enum_match_arm)]
     match route_at(path, 3) {
         Some("") => index_method(req, res).await,
+        Some("policy") => policy_method(req, res).await,
+        Some("deactivated") => deactivated_method(req, res).await,
+        Some("compromised") => compromised_method(req, res).await,
+        Some("ca") => ca_method(req, res, path).await,
+        Some("known_hosts") if route_at(path, 4) == Some("") => {
+            known_hosts_method(req, res).await
+        }
+        Some("ssh_config") if route_at(path, 4) == Some("") => {
+            ssh_config_method(req, res).await
+        }
         Some(key) => key_method(req, res, key).await,
         _ => not_found(),
     }
@@ -57,6 +88,7 @@ where
     A: Auth,
     for<'a, 'b, 'c> D: Database
         + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + KeyCount
         + FetchByUid<A, User<'a>, D>
         + Create<A, PublicKey<'a>, D>,
     T: TemplateEngine,
@@ -113,38 +145,293 @@ where
     A: Auth,
     for<'a, 'b, 'c> D: Database
         + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + KeyCount
         + FetchByUid<A, User<'a>, D>
         + Create<A, PublicKey<'a>, D>,
     T: TemplateEngine,
     R: Request<A, D, T>,
 {
     let bytes = req.body_as_bytes().await?;
-    let body =
-        form_urlencoded::parse(&bytes).fold((None, None, None), |acc, (k, v)| {
-            if v.is_empty() {
-                return acc;
-            }
-            match k.as_ref() {
-                "data" => (Some(v), acc.1, acc.2),
-                "uid" => (acc.0, Some(v), acc.2),
-                "csrf" => (acc.0, acc.1, Some(v)),
-                _ => acc,
-            }
-        });
-    let (body, csrf) = ((body.0, body.1), CsrfToken::verify(req, body.2.as_deref()));
+    let mut data = None;
+    let mut expires = None;
+    let mut uid = None;
+    let mut csrf = None;
+    let mut import = false;
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        if v.is_empty() {
+            continue;
+        }
+        match k.as_ref() {
+            "data" => data = Some(v),
+            "expires" => expires = Some(v),
+            "uid" => uid = Some(v),
+            "csrf" => csrf = Some(v),
+            "import" => import = true,
+            _ => {}
+        }
+    }
+    let csrf = CsrfToken::verify(req, csrf.as_deref());
     let db = req.get_database();
     let auth = req.get_auth();
-    let body = if let (data, Some(uid)) = body {
-        let user = db.fetch_by_uid(&uid, auth)?;
-        (data, user)
-    } else {
-        (body.0, None)
+    let user = match uid {
+        Some(uid) => db.fetch_by_uid(&uid, auth)?,
+        None => None,
     };
+    if import {
+        let para = match (data.as_ref(), user.as_ref()) {
+            _ if !csrf.valid => "csrf",
+            (Some(data), Some(user)) => {
+                let summary =
+                    PublicKeyListView::import(req, data, &user.entity_id).await?;
+                return import_get(req, res, &summary, csrf);
+            }
+            (None, _) => "Public Key Data",
+            (_, None) => "Uid",
+        };
+        let noti = [Notification::Error {
+            name: "Public Key",
+            para,
+            help: "../help/#pubkey_err",
+        }];
+        return index_get(req, res, Some(&noti), csrf).await;
+    }
     let noti =
-        PublicKeyListView::create(req, body.0, body.1.as_ref(), &csrf).await?;
+        PublicKeyListView::create(req, data, expires, user.as_ref(), &csrf).await?;
     index_get(req, res, Some(&noti), csrf).await
 }
 
+fn import_get<A, D, T, R>(
+    req: &R,
+    mut res: response::Builder,
+    summary: &KeyImportSummary,
+    csrf_token: CsrfToken,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    D: Database,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let user = req.get_auth().get_user_container();
+    let url = req.get_uri().path();
+    let csrf = csrf_token.generate(req, &mut res)?;
+    let container = BaseContainer {
+        csrf: Some(csrf),
+        base: Cow::Borrowed("../"),
+        user,
+        ..BaseContainer::new(req.get_base_view(), summary, &(), url)
+    };
+    serve_template(req, res, "site_keyimport", &container)
+}
+
+#[inline]
+#[allow(single_use_lifetimes)]
+async fn policy_method<A, D, T, R>(
+    req: &mut R,
+    mut res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D:
+        Database + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    if !req.get_auth().is_admin() {
+        return not_found();
+    }
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => {}
+        _ => return invalid_method(&[Method::GET]),
+    }
+    let view = KeyPolicyReportView::fetch(req).await?;
+    let user = req.get_auth().get_user_container();
+    let url = req.get_uri().path();
+    let csrf_token = CsrfToken::from(req);
+    let csrf = csrf_token.generate(req, &mut res)?;
+    let container = BaseContainer {
+        csrf: Some(csrf),
+        base: Cow::Borrowed("../../"),
+        user,
+        ..BaseContainer::new(req.get_base_view(), &view, &(), url)
+    };
+    serve_template(req, res, "site_keypolicy", &container)
+}
+
+#[inline]
+#[allow(single_use_lifetimes)]
+async fn compromised_method<A, D, T, R>(
+    req: &mut R,
+    mut res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    if !req.get_auth().is_admin() {
+        return not_found();
+    }
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => {}
+        _ => return invalid_method(&[Method::GET]),
+    }
+    let page = get_query_parameters(req)
+        .find_map(|(k, v)| {
+            if k.as_ref() == "page" {
+                v.parse().ok()
+            } else {
+                None
+            }
+        })
+        .unwrap_or(1);
+    let view = CompromisedKeyReportView::fetch(req, page).await?;
+    let user = req.get_auth().get_user_container();
+    let url = req.get_uri().path();
+    let csrf_token = CsrfToken::from(req);
+    let csrf = csrf_token.generate(req, &mut res)?;
+    let container = BaseContainer {
+        csrf: Some(csrf),
+        base: Cow::Borrowed("../../"),
+        user,
+        ..BaseContainer::new(req.get_base_view(), &view, &(), url)
+    };
+    serve_template(req, res, "site_compromisedkeys", &container)
+}
+
+#[inline]
+#[allow(single_use_lifetimes)]
+async fn deactivated_method<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + KeyCount
+        + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>
+        + FetchById<'b, A, Entity<'a>, D>
+        + Restore<A, PublicKey<'a>, D>
+        + Purge<A, PublicKey<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    if !req.get_auth().is_admin() {
+        return not_found();
+    }
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => deactivated_get(req, res, None, CsrfToken::from(req)).await,
+        Method::POST => deactivated_post(req, res).await,
+        _ => invalid_method(&[Method::GET, Method::POST]),
+    }
+}
+
+#[allow(single_use_lifetimes)]
+async fn deactivated_get<A, D, T, R>(
+    req: &R,
+    mut res: response::Builder,
+    noti: Option<&[Notification<'_>]>,
+    csrf_token: CsrfToken,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>
+        + FetchById<'b, A, Entity<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let page = get_query_parameters(req)
+        .find_map(|(k, v)| {
+            if k.as_ref() == "page" {
+                v.parse().ok()
+            } else {
+                None
+            }
+        })
+        .unwrap_or(1);
+    let view = DeactivatedKeyListView::fetch(req, page).await?;
+    let user = req.get_auth().get_user_container();
+    let url = req.get_uri().path();
+    let csrf = csrf_token.generate(req, &mut res)?;
+    let container = BaseContainer {
+        csrf: Some(csrf),
+        base: Cow::Borrowed("../../"),
+        user,
+        noti,
+        ..BaseContainer::new(req.get_base_view(), &view.0, &(), url)
+    };
+    serve_template(req, res, "site_deactivatedkeys", &container)
+}
+
+#[allow(single_use_lifetimes)]
+async fn deactivated_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + KeyCount
+        + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>
+        + FetchById<'b, A, Entity<'a>, D>
+        + Restore<A, PublicKey<'a>, D>
+        + Purge<A, PublicKey<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let mut ids = Vec::new();
+    let mut action = None;
+    let mut csrf = None;
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        if v.is_empty() {
+            continue;
+        }
+        match k.as_ref() {
+            "id" => ids.extend(Id::from_string(v.as_ref()).ok()),
+            "action" => action = Some(v),
+            "csrf" => csrf = Some(v),
+            _ => {}
+        }
+    }
+    let csrf = CsrfToken::verify(req, csrf.as_deref());
+    if !csrf.valid {
+        let noti = [Notification::Error {
+            name: "Public Key",
+            para: "csrf",
+            help: "../../help/#pubkey_err",
+        }];
+        return deactivated_get(req, res, Some(&noti), csrf).await;
+    }
+    match action.as_deref() {
+        Some("restore") => {
+            if let Some(noti) = DeactivatedKeyListView::restore(req, &ids).await? {
+                return deactivated_get(req, res, Some(&noti), csrf).await;
+            }
+        }
+        Some("purge") => req.get_database().purge(&ids, req.get_auth())?,
+        _ => {
+            let noti = [Notification::Error {
+                name: "Public Key",
+                para: "action",
+                help: "../../help/#pubkey_err",
+            }];
+            return deactivated_get(req, res, Some(&noti), csrf).await;
+        }
+    }
+    redirect(req, res, "/app/publickeys/deactivated/", false, true, true)
+}
+
 #[inline]
 #[allow(single_use_lifetimes)]
 async fn key_method<A, D, T, R>(
@@ -160,7 +447,9 @@ where
         + FetchById<'b, A, PublicKey<'a>, D>
         + FetchById<'b, A, Entity<'a>, D>
         + Create<A, PublicKey<'a>, D>
-        + Delete<A, PublicKey<'a>, D>,
+        + Delete<A, PublicKey<'a>, D>
+        + CertificateStore
+        + Create<A, Event<'a>, D>,
     T: TemplateEngine,
     R: Request<A, D, T>,
 {
@@ -192,6 +481,49 @@ where
         Some(view) => view,
         None => return not_found(),
     };
+    let format = get_query_parameters(req).find_map(|(k, v)| {
+        if k.as_ref() == "format" {
+            Some(v)
+        } else {
+            None
+        }
+    });
+    let public_key = &view.public_key;
+    match format.as_deref() {
+        // expired keys must no longer be deployed
+        Some(_) if public_key.is_expired(Utc::now().naive_utc()) => {
+            return not_found();
+        }
+        Some("openssh") => {
+            let filename = format!("{}.pub", public_key.id);
+            return serve_download(
+                res,
+                "text/plain; charset=UTF-8",
+                &filename,
+                public_key.to_plain(),
+            );
+        }
+        Some("rfc4716") => {
+            let filename = format!("{}.ssh2.pub", public_key.id);
+            return serve_download(
+                res,
+                "text/plain; charset=UTF-8",
+                &filename,
+                public_key.to_rfc4716(),
+            );
+        }
+        Some("pem") => {
+            let filename = format!("{}.pem", public_key.id);
+            return match public_key.to_pem() {
+                Some(pem) => {
+                    serve_download(res, "application/x-pem-file", &filename, pem)
+                }
+                None => not_found(),
+            };
+        }
+        Some(_) => return not_found(),
+        None => {}
+    }
     let user = req.get_auth().get_user_container();
     let url = req.get_uri().path();
     let csrf_token = CsrfToken::from(req);
@@ -218,19 +550,28 @@ where
         + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
         + FetchById<'b, A, PublicKey<'a>, D>
         + FetchById<'b, A, Entity<'a>, D>
-        + Delete<A, PublicKey<'a>, D>,
+        + Delete<A, PublicKey<'a>, D>
+        + CertificateStore
+        + Create<A, Event<'a>, D>,
     T: TemplateEngine,
     R: Request<A, D, T>,
 {
     let bytes = req.body_as_bytes().await?;
-    let csrf = form_urlencoded::parse(&bytes).find_map(|(k, v)| {
-        if k.as_ref() == "csrf" && !v.is_empty() {
-            Some(v)
-        } else {
-            None
+    let mut csrf = None;
+    let mut certificate = false;
+    let mut validity = None;
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        if v.is_empty() {
+            continue;
         }
-    });
-    let csrf = CsrfToken::verify(req, csrf.as_ref().map(AsRef::as_ref));
+        match k.as_ref() {
+            "csrf" => csrf = Some(v),
+            "certificate" => certificate = true,
+            "validity" => validity = Some(v),
+            _ => {}
+        }
+    }
+    let csrf = CsrfToken::verify(req, csrf.as_deref());
     if !csrf.valid {
         let noti = [Notification::Error {
             name: "Public Key",
@@ -239,6 +580,9 @@ where
         }];
         return key_get(req, res, key, Some(&noti)).await;
     }
+    if certificate {
+        return certificate_post(req, res, key, validity.as_deref()).await;
+    }
     let id = match Id::from_string(key) {
         Err(_) => {
             return not_found();
@@ -250,3 +594,172 @@ where
     db.delete(&[id], auth)?;
     redirect(req, res, "/app/publickeys/", false, true, true)
 }
+
+#[allow(single_use_lifetimes)]
+async fn certificate_post<A, D, T, R>(
+    req: &R,
+    res: response::Builder,
+    key: &str,
+    validity: Option<&str>,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + FetchById<'b, A, PublicKey<'a>, D>
+        + FetchById<'b, A, Entity<'a>, D>
+        + CertificateStore
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let view = match PublicKeyView::fetch(req, key).await? {
+        Some(view) if view.is_owner || req.get_auth().is_admin() => view,
+        _ => return not_found(),
+    };
+    let validity = match validity.map(str::parse) {
+        None => None,
+        Some(Ok(validity)) => Some(validity),
+        Some(Err(_)) => {
+            let noti = [Notification::Error {
+                name: "Public Key",
+                para: "Certificate validity",
+                help: "../../help/#pubkey_err",
+            }];
+            return key_get(req, res, key, Some(&noti)).await;
+        }
+    };
+    match view.issue_certificate(req, validity).await? {
+        Ok(certificate) => {
+            let filename = format!("{}-cert.pub", view.public_key.id);
+            serve_download(
+                res,
+                "text/plain; charset=UTF-8",
+                &filename,
+                certificate.line,
+            )
+        }
+        Err(err) => {
+            log::info!("Unable to issue certificate: {}", err);
+            let noti = [Notification::Error {
+                name: "Public Key",
+                para: err.para(),
+                help: "../../help/#pubkey_err",
+            }];
+            key_get(req, res, key, Some(&noti)).await
+        }
+    }
+}
+
+#[inline]
+#[allow(single_use_lifetimes)]
+async fn ca_method<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    path: &[String],
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'c> D: Database
+        + CertificateStore
+        + FetchAllFor<A, Server<'a>, ServerFilter<'c>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(clippy::wildcard_enum_match_arm)]
+    let (type_, filename) = match route_at(path, 4) {
+        Some("") => (CertificateType::User, "user_ca.pub"),
+        Some("known_hosts") => (CertificateType::Host, "known_hosts"),
+        _ => return not_found(),
+    };
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => match CertificateAuthorityView::fetch(req, type_).await? {
+            Ok(authority) => {
+                let content = match type_ {
+                    CertificateType::User => authority.0.public_key.into_owned(),
+                    CertificateType::Host => {
+                        let view = KnownHostsView::fetch(req).await?;
+                        match authority.to_known_hosts(&view.servers, false) {
+                            Ok(content) => content,
+                            Err(err) => {
+                                log::error!("Unable to create known_hosts: {}", err);
+                                return not_found();
+                            }
+                        }
+                    }
+                };
+                serve_download(res, "text/plain; charset=UTF-8", filename, content)
+            }
+            Err(err) => {
+                log::error!("Unable to create certificate authority: {}", err);
+                not_found()
+            }
+        },
+        _ => invalid_method(&[Method::GET]),
+    }
+}
+
+#[inline]
+#[allow(single_use_lifetimes)]
+async fn known_hosts_method<A, D, T, R>(
+    req: &R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'c> D: Database
+        + CertificateStore
+        + FetchAllFor<A, Server<'a>, ServerFilter<'c>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => {
+            let hashed =
+                get_query_parameters(req).any(|(k, v)| k == "hashed" && v == "1");
+            let view = KnownHostsView::fetch(req).await?;
+            match view.to_file(hashed) {
+                Ok(file) => serve_download(
+                    res,
+                    "text/plain; charset=UTF-8",
+                    "known_hosts",
+                    file,
+                ),
+                Err(err) => {
+                    log::error!("Unable to create known_hosts: {}", err);
+                    not_found()
+                }
+            }
+        }
+        _ => invalid_method(&[Method::GET]),
+    }
+}
+
+#[inline]
+#[allow(single_use_lifetimes)]
+async fn ssh_config_method<A, D, T, R>(
+    req: &R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    D: Database + LoginStore,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => {
+            let view = SshConfigView::fetch(req).await?;
+            serve_download(
+                res,
+                "text/plain; charset=UTF-8",
+                "ssh_config",
+                view.to_file(),
+            )
+        }
+        _ => invalid_method(&[Method::GET]),
+    }
+}
@@ -0,0 +1,245 @@
+use core_common::{
+    database::{AccessRequestStore, Create, Database, RecertificationStore},
+    http::{
+        method::Method,
+        response::{self, Response},
+    },
+    objects::Event,
+    sec::{Auth, CsrfToken},
+    types::RecertificationDecision,
+    url::form_urlencoded,
+    web::{
+        get_query_parameters, invalid_method, not_found, redirect, route_at,
+        serve_download, serve_template, AppError, BaseContainer, Notification,
+        Request, ResponseType, TemplateEngine,
+    },
+};
+use core_views::{RecertificationListView, RecertificationView};
+use std::borrow::Cow;
+
+/// Serves the recertification route. Admins start and close campaigns,
+/// server admins decide whether access to their servers is kept
+///
+/// # Errors
+/// Fails when the communication with the database fails
+#[inline]
+#[allow(single_use_lifetimes)]
+pub async fn index<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    path: &[String],
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a> D: Database
+        + AccessRequestStore
+        + RecertificationStore
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(clippy::wildcard_enum_match_arm)]
+    match route_at(path, 3) {
+        Some("") => index_method(req, res).await,
+        Some(key) => campaign_method(req, res, key).await,
+        _ => not_found(),
+    }
+}
+
+#[inline]
+#[allow(single_use_lifetimes)]
+async fn index_method<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a> D: Database + RecertificationStore + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => index_get(req, res, None, CsrfToken::from(req)).await,
+        Method::POST if req.get_auth().is_admin() => index_post(req, res).await,
+        Method::POST => not_found(),
+        _ => invalid_method(&[Method::GET, Method::POST]),
+    }
+}
+
+async fn index_get<A, D, T, R>(
+    req: &R,
+    mut res: response::Builder,
+    noti: Option<&[Notification<'_>]>,
+    csrf_token: CsrfToken,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    D: Database + RecertificationStore,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let view = RecertificationListView::fetch(req).await?;
+    let user = req.get_auth().get_user_container();
+    let url = req.get_uri().path();
+    let csrf = csrf_token.generate(req, &mut res)?;
+    let container = BaseContainer {
+        csrf: Some(csrf),
+        base: Cow::Borrowed("../"),
+        user,
+        noti,
+        ..BaseContainer::new(req.get_base_view(), &view, &(), url)
+    };
+    serve_template(req, res, "site_recertifications", &container)
+}
+
+#[allow(single_use_lifetimes)]
+async fn index_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a> D: Database + RecertificationStore + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let mut name = None;
+    let mut csrf = None;
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        if v.is_empty() {
+            continue;
+        }
+        match k.as_ref() {
+            "name" => name = Some(v),
+            "csrf" => csrf = Some(v),
+            _ => {}
+        }
+    }
+    let csrf = CsrfToken::verify(req, csrf.as_deref());
+    let noti = RecertificationListView::create(req, name, &csrf).await?;
+    index_get(req, res, Some(&noti), csrf).await
+}
+
+#[inline]
+#[allow(single_use_lifetimes)]
+async fn campaign_method<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    key: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a> D: Database
+        + AccessRequestStore
+        + RecertificationStore
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => campaign_get(req, res, key, None).await,
+        Method::POST => campaign_post(req, res, key).await,
+        _ => invalid_method(&[Method::GET, Method::POST]),
+    }
+}
+
+async fn campaign_get<A, D, T, R>(
+    req: &R,
+    mut res: response::Builder,
+    key: &str,
+    noti: Option<&[Notification<'_>]>,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    D: Database + RecertificationStore,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let view = match RecertificationView::fetch(req, key).await? {
+        Some(view) => view,
+        None => return not_found(),
+    };
+    let csv =
+        get_query_parameters(req).any(|(k, v)| k.as_ref() == "format" && v == "csv");
+    if csv {
+        return serve_download(
+            res,
+            "text/csv; charset=UTF-8",
+            "recertification.csv",
+            view.to_csv(),
+        );
+    }
+    let user = req.get_auth().get_user_container();
+    let url = req.get_uri().path();
+    let csrf_token = CsrfToken::from(req);
+    let csrf = csrf_token.generate(req, &mut res)?;
+    let container = BaseContainer {
+        csrf: Some(csrf),
+        base: Cow::Borrowed("../../"),
+        user,
+        noti,
+        ..BaseContainer::new(req.get_base_view(), &view, &(), url)
+    };
+    serve_template(req, res, "site_recertification", &container)
+}
+
+#[allow(single_use_lifetimes)]
+async fn campaign_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    key: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a> D: Database
+        + AccessRequestStore
+        + RecertificationStore
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let mut item = None;
+    let mut decision = None;
+    let mut close = false;
+    let mut csrf = None;
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        if v.is_empty() {
+            continue;
+        }
+        match k.as_ref() {
+            "item" => item = Some(v),
+            "keep" => decision = Some(RecertificationDecision::Keep),
+            "revoke" => decision = Some(RecertificationDecision::Revoke),
+            "close" => close = true,
+            "csrf" => csrf = Some(v),
+            _ => {}
+        }
+    }
+    let csrf = CsrfToken::verify(req, csrf.as_deref());
+    if !csrf.valid {
+        let noti = [Notification::Error {
+            name: "Recertification",
+            para: "csrf",
+            help: "../../help/#recertification",
+        }];
+        return campaign_get(req, res, key, Some(&noti)).await;
+    }
+    let mut view = match RecertificationView::fetch(req, key).await? {
+        Some(view) => view,
+        None => return not_found(),
+    };
+    if close {
+        if !view.can_close {
+            return not_found();
+        }
+        let _ = view.close(req).await?;
+    } else if let (Some(item), Some(decision)) = (item, decision) {
+        let _ = view.decide(req, &item, decision).await?;
+    }
+    let url = format!("/app/recertifications/{}/", view.progress.campaign.id);
+    redirect(req, res, &url, false, true, true)
+}
@@ -0,0 +1,707 @@
+use core_common::{
+    database::{
+        AccessRequestStore, ContactStore, Create, Database, Delete, FetchAll,
+        FetchById, Save, ServerAdminStore, ServerSettingsStore, ServerTokenStore,
+    },
+    http::{
+        method::Method,
+        response::{self, Response},
+    },
+    log,
+    objects::{
+        Event, Server, ServerAction, ServerFilter, ServerNote, ServerNoteFilter,
+    },
+    sec::{Auth, CsrfToken},
+    url::form_urlencoded,
+    web::{
+        get_query_parameters, invalid_method, not_found, redirect, route_at,
+        serve_download, serve_template, AppError, BaseContainer, Notification,
+        Request, ResponseType, TemplateEngine,
+    },
+};
+use core_views::{ContactForm, ServerForm, ServerListView, ServerView};
+use std::{borrow::Cow, convert::TryFrom};
+
+/// Serves the servers route. Admins create servers, admins and server
+/// admins manage administrators, accounts and access of a server
+///
+/// # Errors
+/// Fails when the communication with the database fails
+#[inline]
+#[allow(single_use_lifetimes)]
+pub async fn index<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    path: &[String],
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + AccessRequestStore
+        + ContactStore
+        + ServerAdminStore
+        + ServerSettingsStore
+        + ServerTokenStore
+        + FetchAll<'b, A, Server<'a>, ServerFilter<'c>, D>
+        + FetchById<'b, A, Server<'a>, D>
+        + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>
+        + Create<A, Server<'a>, D>
+        + Create<A, ServerNote<'a>, D>
+        + Save<A, ServerNote<'a>, D>
+        + Delete<A, ServerNote<'a>, D>
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(clippy::wildcard_enum_match_arm)]
+    match route_at(path, 3) {
+        Some("") => index_method(req, res).await,
+        Some(key) => server_method(req, res, key, path).await,
+        _ => not_found(),
+    }
+}
+
+#[inline]
+#[allow(single_use_lifetimes)]
+async fn index_method<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + ServerAdminStore
+        + FetchAll<'b, A, Server<'a>, ServerFilter<'c>, D>
+        + Create<A, Server<'a>, D>
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => index_get(req, res, None, CsrfToken::from(req)).await,
+        Method::POST if req.get_auth().is_admin() => index_post(req, res).await,
+        Method::POST => not_found(),
+        _ => invalid_method(&[Method::GET, Method::POST]),
+    }
+}
+
+#[allow(single_use_lifetimes)]
+async fn index_get<A, D, T, R>(
+    req: &R,
+    mut res: response::Builder,
+    noti: Option<&[Notification<'_>]>,
+    csrf_token: CsrfToken,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database + FetchAll<'b, A, Server<'a>, ServerFilter<'c>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let uri = req.get_uri();
+    let filter = uri
+        .query()
+        .map(str::as_bytes)
+        .map(form_urlencoded::parse)
+        .map_or_else(ServerFilter::default, ServerFilter::from);
+    let page = get_query_parameters(req)
+        .find_map(|(k, v)| {
+            if k.as_ref() == "page" {
+                v.parse().ok()
+            } else {
+                None
+            }
+        })
+        .unwrap_or(1);
+    let view = ServerListView::fetch(req, &filter, page).await?;
+    let user = req.get_auth().get_user_container();
+    let url = req.get_uri().path();
+    let csrf = csrf_token.generate(req, &mut res)?;
+    let container = BaseContainer {
+        csrf: Some(csrf),
+        base: Cow::Borrowed("../"),
+        user,
+        noti,
+        ..BaseContainer::new(req.get_base_view(), &view.0, &filter, url)
+    };
+    serve_template(req, res, "site_servers", &container)
+}
+
+#[allow(single_use_lifetimes)]
+async fn index_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + ServerAdminStore
+        + FetchAll<'b, A, Server<'a>, ServerFilter<'c>, D>
+        + Create<A, Server<'a>, D>
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let mut form = ServerForm::default();
+    let mut csrf = None;
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        if v.is_empty() {
+            continue;
+        }
+        match k.as_ref() {
+            "hostname" => form.hostname = Some(v),
+            "port" => form.port = Some(v),
+            "key_management" => form.key_management = Some(v),
+            "admin" => form.admin = Some(v),
+            "require_security_key" => form.require_security_key = true,
+            "csrf" => csrf = Some(v),
+            _ => {}
+        }
+    }
+    let csrf = CsrfToken::verify(req, csrf.as_deref());
+    let noti = ServerListView::create(req, form, &csrf).await?;
+    index_get(req, res, Some(&noti), csrf).await
+}
+
+#[inline]
+#[allow(single_use_lifetimes)]
+async fn server_method<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    key: &str,
+    path: &[String],
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + AccessRequestStore
+        + ContactStore
+        + ServerAdminStore
+        + ServerSettingsStore
+        + ServerTokenStore
+        + FetchById<'b, A, Server<'a>, D>
+        + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>
+        + Create<A, ServerNote<'a>, D>
+        + Save<A, ServerNote<'a>, D>
+        + Delete<A, ServerNote<'a>, D>
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(clippy::wildcard_enum_match_arm)]
+    let action = match route_at(path, 4) {
+        Some("") => {
+            #[allow(indirect_structural_match)]
+            return match *req.get_method() {
+                Method::GET => server_get(req, res, key, "../../", None).await,
+                _ => invalid_method(&[Method::GET]),
+            };
+        }
+        Some(action) if route_at(path, 5) == Some("") => {
+            match ServerAction::try_from(action) {
+                Ok(action) => action,
+                Err(()) => return not_found(),
+            }
+        }
+        _ => return not_found(),
+    };
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::POST => match action {
+            ServerAction::Admins => admins_post(req, res, key).await,
+            ServerAction::Accounts => accounts_post(req, res, key).await,
+            ServerAction::Access => access_post(req, res, key).await,
+            ServerAction::Notes => notes_post(req, res, key).await,
+            ServerAction::Contact => contact_post(req, res, key).await,
+            ServerAction::SecurityKey => security_key_post(req, res, key).await,
+            ServerAction::Token => token_post(req, res, key).await,
+        },
+        _ => invalid_method(&[Method::POST]),
+    }
+}
+
+#[allow(single_use_lifetimes)]
+async fn server_get<A, D, T, R>(
+    req: &R,
+    mut res: response::Builder,
+    key: &str,
+    base: &'static str,
+    noti: Option<&[Notification<'_>]>,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + AccessRequestStore
+        + ServerAdminStore
+        + FetchById<'b, A, Server<'a>, D>
+        + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let view = match ServerView::fetch(req, key).await? {
+        Some(view) => view,
+        None => return not_found(),
+    };
+    let user = req.get_auth().get_user_container();
+    let url = req.get_uri().path();
+    let csrf_token = CsrfToken::from(req);
+    let csrf = csrf_token.generate(req, &mut res)?;
+    let container = BaseContainer {
+        csrf: Some(csrf),
+        base: Cow::Borrowed(base),
+        user,
+        noti,
+        ..BaseContainer::new(req.get_base_view(), &view, &(), url)
+    };
+    serve_template(req, res, "site_server", &container)
+}
+
+/// Renders the server page below the route of an action, with the given
+/// notifications
+#[allow(single_use_lifetimes)]
+async fn action_get<A, D, T, R>(
+    req: &R,
+    res: response::Builder,
+    key: &str,
+    noti: &[Notification<'_>],
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + AccessRequestStore
+        + ServerAdminStore
+        + FetchById<'b, A, Server<'a>, D>
+        + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    server_get(req, res, key, "../../../", Some(noti)).await
+}
+
+/// Renders the server page with an error about an invalid csrf token
+#[allow(single_use_lifetimes)]
+async fn csrf_error<A, D, T, R>(
+    req: &R,
+    res: response::Builder,
+    key: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + AccessRequestStore
+        + ServerAdminStore
+        + FetchById<'b, A, Server<'a>, D>
+        + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let noti = [Notification::Error {
+        name: "Server",
+        para: "csrf",
+        help: "../../../help/#server_admin",
+    }];
+    action_get(req, res, key, &noti).await
+}
+
+/// Redirects back to the server page after a successful action
+#[allow(single_use_lifetimes)]
+fn server_redirect<A, D, T, R>(
+    req: &R,
+    res: response::Builder,
+    view: &ServerView<'_>,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    D: Database,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let url = format!("/app/servers/{}/", view.server.id);
+    redirect(req, res, &url, false, true, true)
+}
+
+#[allow(single_use_lifetimes)]
+async fn admins_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    key: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + AccessRequestStore
+        + ServerAdminStore
+        + FetchById<'b, A, Server<'a>, D>
+        + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let mut user_name = None;
+    let mut delete_admin = None;
+    let mut csrf = None;
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        if v.is_empty() {
+            continue;
+        }
+        match k.as_ref() {
+            "user_name" => user_name = Some(v),
+            "delete_admin" => delete_admin = Some(v),
+            "csrf" => csrf = Some(v),
+            _ => {}
+        }
+    }
+    if !CsrfToken::verify(req, csrf.as_deref()).valid {
+        return csrf_error(req, res, key).await;
+    }
+    let mut view = match ServerView::fetch(req, key).await? {
+        Some(view)
+            if ServerAction::Admins
+                .is_permitted(req.get_auth().is_admin(), view.can_manage) =>
+        {
+            view
+        }
+        _ => return not_found(),
+    };
+    if let Some(user_name) = user_name {
+        let noti = view.add_admin(req, &user_name).await?;
+        return action_get(req, res, key, &noti).await;
+    }
+    if let Some(delete_admin) = delete_admin {
+        let _ = view.remove_admin(req, &delete_admin).await?;
+    }
+    server_redirect(req, res, &view)
+}
+
+#[allow(single_use_lifetimes)]
+async fn accounts_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    key: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + AccessRequestStore
+        + ServerAdminStore
+        + FetchById<'b, A, Server<'a>, D>
+        + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let mut account_name = None;
+    let mut csrf = None;
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        if v.is_empty() {
+            continue;
+        }
+        match k.as_ref() {
+            "account_name" => account_name = Some(v),
+            "csrf" => csrf = Some(v),
+            _ => {}
+        }
+    }
+    if !CsrfToken::verify(req, csrf.as_deref()).valid {
+        return csrf_error(req, res, key).await;
+    }
+    let mut view = match ServerView::fetch(req, key).await? {
+        Some(view)
+            if ServerAction::Accounts
+                .is_permitted(req.get_auth().is_admin(), view.can_manage) =>
+        {
+            view
+        }
+        _ => return not_found(),
+    };
+    match account_name {
+        Some(account_name) => {
+            let noti = view.add_account(req, &account_name).await?;
+            action_get(req, res, key, &noti).await
+        }
+        None => server_redirect(req, res, &view),
+    }
+}
+
+#[allow(single_use_lifetimes)]
+async fn access_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    key: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + AccessRequestStore
+        + ServerAdminStore
+        + FetchById<'b, A, Server<'a>, D>
+        + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let mut account = None;
+    let mut name = None;
+    let mut source = None;
+    let mut csrf = None;
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        if v.is_empty() {
+            continue;
+        }
+        match k.as_ref() {
+            "account" => account = Some(v),
+            "name" => name = Some(v),
+            "source" => source = Some(v),
+            "csrf" => csrf = Some(v),
+            _ => {}
+        }
+    }
+    if !CsrfToken::verify(req, csrf.as_deref()).valid {
+        return csrf_error(req, res, key).await;
+    }
+    let mut view = match ServerView::fetch(req, key).await? {
+        Some(view)
+            if ServerAction::Access
+                .is_permitted(req.get_auth().is_admin(), view.can_manage) =>
+        {
+            view
+        }
+        _ => return not_found(),
+    };
+    if let (Some(account), Some(name)) = (&account, name) {
+        let noti = view.grant(req, account, &name).await?;
+        return action_get(req, res, key, &noti).await;
+    }
+    if let (Some(account), Some(source)) = (account, source) {
+        let _ = view.revoke(req, &account, &source).await?;
+    }
+    server_redirect(req, res, &view)
+}
+
+#[allow(single_use_lifetimes)]
+async fn notes_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    key: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + AccessRequestStore
+        + ServerAdminStore
+        + FetchById<'b, A, Server<'a>, D>
+        + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>
+        + Create<A, ServerNote<'a>, D>
+        + Save<A, ServerNote<'a>, D>
+        + Delete<A, ServerNote<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let mut note = None;
+    let mut add_note = false;
+    let mut edit_note = None;
+    let mut delete_note = None;
+    let mut csrf = None;
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        if v.is_empty() {
+            continue;
+        }
+        match k.as_ref() {
+            "note" => note = Some(v),
+            "add_note" => add_note = true,
+            "edit_note" => edit_note = Some(v),
+            "delete_note" => delete_note = Some(v),
+            "csrf" => csrf = Some(v),
+            _ => {}
+        }
+    }
+    if !CsrfToken::verify(req, csrf.as_deref()).valid {
+        return csrf_error(req, res, key).await;
+    }
+    let mut view = match ServerView::fetch(req, key).await? {
+        Some(view)
+            if ServerAction::Notes
+                .is_permitted(req.get_auth().is_admin(), view.can_manage) =>
+        {
+            view
+        }
+        _ => return not_found(),
+    };
+    let saved = if add_note {
+        view.add_note(req, note.as_deref().unwrap_or_default())
+            .await?
+    } else if let (Some(edit_note), Some(note)) = (&edit_note, &note) {
+        view.edit_note(req, edit_note, note).await?
+    } else {
+        if let Some(delete_note) = delete_note {
+            let _ = view.delete_note(req, &delete_note).await?;
+        }
+        true
+    };
+    if saved {
+        return server_redirect(req, res, &view);
+    }
+    let noti = [Notification::Error {
+        name: "Note",
+        para: "note",
+        help: "../../../help/#server_note",
+    }];
+    action_get(req, res, key, &noti).await
+}
+
+#[allow(single_use_lifetimes)]
+async fn contact_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    key: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + AccessRequestStore
+        + ServerAdminStore
+        + FetchById<'b, A, Server<'a>, D>
+        + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>
+        + ContactStore
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let mut contact = ContactForm::default();
+    let mut csrf = None;
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        if v.is_empty() {
+            continue;
+        }
+        match k.as_ref() {
+            "recipients" => contact.recipients = Some(v),
+            "hide_recipients" => contact.hide_recipients = true,
+            "subject" => contact.subject = Some(v),
+            "body" => contact.body = Some(v),
+            "csrf" => csrf = Some(v),
+            _ => {}
+        }
+    }
+    if !CsrfToken::verify(req, csrf.as_deref()).valid {
+        return csrf_error(req, res, key).await;
+    }
+    let view = match ServerView::fetch(req, key).await? {
+        Some(view)
+            if ServerAction::Contact
+                .is_permitted(req.get_auth().is_admin(), view.can_manage) =>
+        {
+            view
+        }
+        _ => return not_found(),
+    };
+    match view.contact(req, contact).await? {
+        Some(noti) => action_get(req, res, key, &noti).await,
+        None => server_redirect(req, res, &view),
+    }
+}
+
+#[allow(single_use_lifetimes)]
+async fn security_key_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    key: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + AccessRequestStore
+        + ServerAdminStore
+        + FetchById<'b, A, Server<'a>, D>
+        + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>
+        + ServerSettingsStore
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let mut required = false;
+    let mut csrf = None;
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        match k.as_ref() {
+            "require_security_key" => required = v == "required",
+            "csrf" => csrf = Some(v),
+            _ => {}
+        }
+    }
+    if !CsrfToken::verify(req, csrf.as_deref()).valid {
+        return csrf_error(req, res, key).await;
+    }
+    let mut view = match ServerView::fetch(req, key).await? {
+        Some(view)
+            if ServerAction::SecurityKey
+                .is_permitted(req.get_auth().is_admin(), view.can_manage) =>
+        {
+            view
+        }
+        _ => return not_found(),
+    };
+    let _ = view.set_require_security_key(req, required).await?;
+    server_redirect(req, res, &view)
+}
+
+#[allow(single_use_lifetimes)]
+async fn token_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    key: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + AccessRequestStore
+        + ServerAdminStore
+        + FetchById<'b, A, Server<'a>, D>
+        + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>
+        + ServerTokenStore
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let mut create = false;
+    let mut csrf = None;
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        match k.as_ref() {
+            "server_token" => create = v == "create",
+            "csrf" => csrf = Some(v),
+            _ => {}
+        }
+    }
+    if !CsrfToken::verify(req, csrf.as_deref()).valid {
+        return csrf_error(req, res, key).await;
+    }
+    let view = match ServerView::fetch(req, key).await? {
+        Some(view)
+            if ServerAction::Token
+                .is_permitted(req.get_auth().is_admin(), view.can_manage) =>
+        {
+            view
+        }
+        _ => return not_found(),
+    };
+    if !create {
+        let _ = view.revoke_token(req).await?;
+        return server_redirect(req, res, &view);
+    }
+    match view.create_token(req).await? {
+        Ok(token) => {
+            serve_download(res, "text/plain; charset=UTF-8", "server_token", token)
+        }
+        Err(err) => {
+            log::error!("Unable to create server token: {}", err);
+            not_found()
+        }
+    }
+}
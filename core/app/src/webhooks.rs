@@ -0,0 +1,256 @@
+use core_common::{
+    database::{Create, Database, Delete, FetchAll, FetchById},
+    http::{
+        method::Method,
+        response::{self, Response},
+    },
+    objects::{Webhook, WebhookDelivery, WebhookDeliveryFilter, WebhookFilter},
+    sec::{Auth, CsrfToken},
+    types::Id,
+    url::form_urlencoded,
+    web::{
+        get_query_parameters, invalid_method, not_found, redirect, route_at,
+        serve_template, AppError, BaseContainer, Notification, Request,
+        ResponseType, TemplateEngine,
+    },
+};
+use core_views::{WebhookForm, WebhookListView, WebhookView};
+use std::borrow::Cow;
+
+/// Serves the webhooks route. Only available to admins
+///
+/// # Errors
+/// Fails when the communication with the database fails
+#[inline]
+#[allow(single_use_lifetimes)]
+pub async fn index<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    path: &[String],
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, Webhook<'a>, WebhookFilter<'c>, D>
+        + FetchAll<'b, A, WebhookDelivery<'a>, WebhookDeliveryFilter<'c>, D>
+        + FetchById<'b, A, Webhook<'a>, D>
+        + Create<A, Webhook<'a>, D>
+        + Delete<A, Webhook<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    if !req.get_auth().is_admin() {
+        return not_found();
+    }
+    #[allow(clippy::wildcard_enum_match_arm)]
+    match route_at(path, 3) {
+        Some("") => index_method(req, res).await,
+        Some(key) => webhook_method(req, res, key).await,
+        _ => not_found(),
+    }
+}
+
+#[inline]
+#[allow(single_use_lifetimes)]
+async fn index_method<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, Webhook<'a>, WebhookFilter<'c>, D>
+        + Create<A, Webhook<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => index_get(req, res, None, CsrfToken::from(req)).await,
+        Method::POST => index_post(req, res).await,
+        _ => invalid_method(&[Method::GET, Method::POST]),
+    }
+}
+
+#[allow(single_use_lifetimes)]
+async fn index_get<A, D, T, R>(
+    req: &R,
+    mut res: response::Builder,
+    noti: Option<&[Notification<'_>]>,
+    csrf_token: CsrfToken,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database + FetchAll<'b, A, Webhook<'a>, WebhookFilter<'c>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let uri = req.get_uri();
+    let filter = uri
+        .query()
+        .map(str::as_bytes)
+        .map(form_urlencoded::parse)
+        .map_or_else(WebhookFilter::default, WebhookFilter::from);
+    let view = WebhookListView::fetch(req, &filter).await?;
+    let user = req.get_auth().get_user_container();
+    let url = req.get_uri().path();
+    let csrf = csrf_token.generate(req, &mut res)?;
+    let container = BaseContainer {
+        csrf: Some(csrf),
+        base: Cow::Borrowed("../"),
+        user,
+        noti,
+        ..BaseContainer::new(req.get_base_view(), &view.0, &filter, url)
+    };
+    serve_template(req, res, "site_webhooks", &container)
+}
+
+#[allow(single_use_lifetimes)]
+async fn index_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, Webhook<'a>, WebhookFilter<'c>, D>
+        + Create<A, Webhook<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let mut form = WebhookForm::default();
+    let mut csrf = None;
+    for (k, v) in form_urlencoded::parse(&bytes) {
+        if v.is_empty() {
+            continue;
+        }
+        match k.as_ref() {
+            "name" => form.name = Some(v),
+            "url" => form.url = Some(v),
+            "secret" => form.secret = Some(v),
+            "actions" => form.actions = Some(v),
+            "csrf" => csrf = Some(v),
+            _ => {}
+        }
+    }
+    let csrf = CsrfToken::verify(req, csrf.as_deref());
+    let noti = WebhookListView::create(req, form, &csrf).await?;
+    index_get(req, res, Some(&noti), csrf).await
+}
+
+#[inline]
+#[allow(single_use_lifetimes)]
+async fn webhook_method<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    key: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, WebhookDelivery<'a>, WebhookDeliveryFilter<'c>, D>
+        + FetchById<'b, A, Webhook<'a>, D>
+        + Delete<A, Webhook<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    #[allow(indirect_structural_match)]
+    match *req.get_method() {
+        Method::GET => webhook_get(req, res, key, None).await,
+        Method::POST => webhook_post(req, res, key).await,
+        _ => invalid_method(&[Method::GET, Method::POST]),
+    }
+}
+
+#[allow(single_use_lifetimes)]
+async fn webhook_get<A, D, T, R>(
+    req: &R,
+    mut res: response::Builder,
+    key: &str,
+    noti: Option<&[Notification<'_>]>,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, WebhookDelivery<'a>, WebhookDeliveryFilter<'c>, D>
+        + FetchById<'b, A, Webhook<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let uri = req.get_uri();
+    let filter = uri
+        .query()
+        .map(str::as_bytes)
+        .map(form_urlencoded::parse)
+        .map_or_else(WebhookDeliveryFilter::default, WebhookDeliveryFilter::from);
+    let page = get_query_parameters(req)
+        .find_map(|(k, v)| {
+            if k.as_ref() == "page" {
+                v.parse().ok()
+            } else {
+                None
+            }
+        })
+        .unwrap_or(1);
+    let view = match WebhookView::fetch(req, key, &filter, page).await? {
+        Some(view) => view,
+        None => return not_found(),
+    };
+    let user = req.get_auth().get_user_container();
+    let url = req.get_uri().path();
+    let csrf_token = CsrfToken::from(req);
+    let csrf = csrf_token.generate(req, &mut res)?;
+    let container = BaseContainer {
+        csrf: Some(csrf),
+        base: Cow::Borrowed("../../"),
+        user,
+        noti,
+        ..BaseContainer::new(req.get_base_view(), &view, &filter, url)
+    };
+    serve_template(req, res, "site_webhook", &container)
+}
+
+#[allow(single_use_lifetimes)]
+async fn webhook_post<A, D, T, R>(
+    req: &mut R,
+    res: response::Builder,
+    key: &str,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchAll<'b, A, WebhookDelivery<'a>, WebhookDeliveryFilter<'c>, D>
+        + FetchById<'b, A, Webhook<'a>, D>
+        + Delete<A, Webhook<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let bytes = req.body_as_bytes().await?;
+    let csrf = form_urlencoded::parse(&bytes).find_map(|(k, v)| {
+        if k.as_ref() == "csrf" && !v.is_empty() {
+            Some(v)
+        } else {
+            None
+        }
+    });
+    let csrf = CsrfToken::verify(req, csrf.as_ref().map(AsRef::as_ref));
+    if !csrf.valid {
+        let noti = [Notification::Error {
+            name: "Webhook",
+            para: "csrf",
+            help: "../../help/#webhook_err",
+        }];
+        return webhook_get(req, res, key, Some(&noti)).await;
+    }
+    let id = match Id::from_string(key) {
+        Err(_) => {
+            return not_found();
+        }
+        Ok(id) => id,
+    };
+    let db = req.get_database();
+    let auth = req.get_auth();
+    db.delete(&[id], auth)?;
+    redirect(req, res, "/app/webhooks/", false, true, true)
+}
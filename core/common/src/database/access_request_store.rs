@@ -0,0 +1,76 @@
+use crate::{
+    database::{Database, DbResult},
+    objects::{AccessRequest, AccessRequestEntry, ServerLogin},
+    types::Id,
+};
+
+/// Stores requests for access to server accounts and creates the access
+/// grants once they are approved. Permission checks must be done by the
+/// caller.
+pub trait AccessRequestStore: Database {
+    /// Fetches the named account on the server with the given hostname.
+    /// Returns `Ok(None)` if there is no such account.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_account(
+        &self,
+        hostname: &str,
+        account: &str,
+    ) -> DbResult<Option<ServerLogin<'static>>, Self>;
+
+    /// Stores a new request.
+    ///
+    /// # Errors
+    /// Fails on
+    /// * Connection Errors
+    /// * `DatabaseError::NonUnique` if the entity was granted access to the
+    ///   account or a request of the entity for the account is pending
+    fn create_request(&self, request: &AccessRequest<'_>) -> DbResult<(), Self>;
+
+    /// Fetches the request with the given id.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_request(
+        &self,
+        id: &Id,
+    ) -> DbResult<Option<AccessRequestEntry<'static>>, Self>;
+
+    /// Fetches all requests made by the entity, newest first.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_requests(
+        &self,
+        source_id: &Id,
+    ) -> DbResult<Vec<AccessRequestEntry<'static>>, Self>;
+
+    /// Fetches the pending requests for accounts on servers the entity
+    /// administers, oldest first. Returns every pending request if no
+    /// entity is given.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_pending(
+        &self,
+        approver_id: Option<&Id>,
+    ) -> DbResult<Vec<AccessRequestEntry<'static>>, Self>;
+
+    /// Checks whether the entity administers the server. Either the entity
+    /// or one of its groups is a server admin, or the entity is admin of a
+    /// group which is server admin.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn is_approver(&self, server_id: &Id, entity_id: &Id) -> DbResult<bool, Self>;
+
+    /// Stores the decision on a request. Approved requests grant the
+    /// requesting entity access to the account and queue a resync of it in
+    /// the same transaction. Returns `Ok(false)` if the request was decided
+    /// concurrently.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn decide_request(&self, request: &AccessRequest<'_>) -> DbResult<bool, Self>;
+}
@@ -0,0 +1,36 @@
+use crate::{
+    chrono::NaiveDateTime,
+    database::{Database, DbResult},
+    objects::AccessTransition,
+};
+
+/// Access to time-bound grants which started or ended. Used by the
+/// schedule worker which runs without a user and therefore bypasses
+/// permission checks.
+pub trait AccessSchedule: Database {
+    /// Fetches grants which started or ended before the given date and
+    /// for which no resync was queued since.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_transitions(
+        &self,
+        before: NaiveDateTime,
+        limit: usize,
+    ) -> DbResult<Vec<AccessTransition<'static>>, Self>;
+
+    /// Queues a resync of the server account the grant refers to and marks
+    /// the transition as handled. A grant which ended before `now` is
+    /// recorded as `AccessRevoke` event, so the end of time-bound and
+    /// emergency access is audited and notified like a revocation by an
+    /// administrator. All writes happen in one transaction, an end is
+    /// never recorded twice or lost
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn process_transition(
+        &self,
+        transition: &AccessTransition<'_>,
+        now: NaiveDateTime,
+    ) -> DbResult<(), Self>;
+}
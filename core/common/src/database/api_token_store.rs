@@ -0,0 +1,38 @@
+use crate::{
+    database::{Database, DbResult},
+    objects::ApiToken,
+    types::Id,
+};
+
+/// Stores the api tokens of users. Permission checks must be done by the
+/// caller.
+pub trait ApiTokenStore: Database {
+    /// Stores a newly created api token.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn create_api_token(&self, token: &ApiToken<'_>) -> DbResult<(), Self>;
+
+    /// Fetches the api token with the given id. Returns `Ok(None)` if the
+    /// token was revoked or is no longer valid.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_api_token(&self, id: &Id) -> DbResult<Option<ApiToken<'static>>, Self>;
+
+    /// Fetches the valid api tokens of the user, newest first.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_api_tokens(
+        &self,
+        entity_id: &Id,
+    ) -> DbResult<Vec<ApiToken<'static>>, Self>;
+
+    /// Revokes the api token of the user. Returns `Ok(false)` if the user
+    /// has no such token.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn revoke_api_token(&self, id: &Id, entity_id: &Id) -> DbResult<bool, Self>;
+}
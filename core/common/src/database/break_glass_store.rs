@@ -0,0 +1,58 @@
+use crate::{
+    database::{Database, DbResult},
+    objects::{BreakGlass, BreakGlassEntry},
+    types::Id,
+};
+
+/// Stores emergency access to server accounts. Permission checks must be
+/// done by the caller.
+pub trait BreakGlassStore: Database {
+    /// Stores the emergency access along with a grant of its own, which lasts
+    /// for its duration, and queues a resync of the account. Existing grants
+    /// of the entity are not changed.
+    ///
+    /// # Errors
+    /// Fails on
+    /// * Connection Errors
+    /// * `DatabaseError::NonUnique` if the entity already has access to the
+    ///   account
+    fn create_break_glass(&self, break_glass: &BreakGlass<'_>)
+        -> DbResult<(), Self>;
+
+    /// Checks whether the entity or one of its groups was granted access to
+    /// the account before, expired grants included. Emergency access does
+    /// not count.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn had_access(&self, entity_id: &Id, account_id: &Id) -> DbResult<bool, Self>;
+
+    /// Fetches the emergency access with the given id.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_break_glass(
+        &self,
+        id: &Id,
+    ) -> DbResult<Option<BreakGlassEntry<'static>>, Self>;
+
+    /// Fetches emergency accesses for the break-glass report, newest first.
+    /// Only returns those which were not reviewed yet if `unreviewed` is set.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_break_glass_report(
+        &self,
+        unreviewed: bool,
+    ) -> DbResult<Vec<BreakGlassEntry<'static>>, Self>;
+
+    /// Stores the review of an emergency access. Returns `Ok(false)` if it
+    /// was reviewed concurrently.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn review_break_glass(
+        &self,
+        break_glass: &BreakGlass<'_>,
+    ) -> DbResult<bool, Self>;
+}
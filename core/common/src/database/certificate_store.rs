@@ -0,0 +1,81 @@
+use crate::{
+    database::{Database, DbResult},
+    objects::{
+        AuthorizedPrincipals, CertificateAuthority, CertificatePrincipal,
+        HostCertificate,
+    },
+    types::{CertificateType, Id},
+};
+
+/// Stores the certificate authorities and provides the information required
+/// to issue certificates. Permission checks must be done by the caller.
+pub trait CertificateStore: Database {
+    /// Fetches the authority signing certificates of the given type.
+    /// Returns `Ok(None)` if no authority was created yet.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_authority(
+        &self,
+        type_: CertificateType,
+    ) -> DbResult<Option<CertificateAuthority<'static>>, Self>;
+
+    /// Stores a new authority. Only one authority per type may exist.
+    ///
+    /// # Errors
+    /// Fails on
+    /// * Connection Errors
+    /// * Unique Constraints
+    fn create_authority(
+        &self,
+        authority: &CertificateAuthority<'_>,
+    ) -> DbResult<(), Self>;
+
+    /// Fetches the principals of the named server accounts the entity or
+    /// one of its groups has access to together with the options of the
+    /// access grants.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_principals(
+        &self,
+        entity_id: &Id,
+    ) -> DbResult<Vec<CertificatePrincipal<'static>>, Self>;
+
+    /// Fetches the principals accepted by every named account of the
+    /// server. Accounts nobody has access to are returned with an empty
+    /// list so that previously delivered principals get removed.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_authorized_principals(
+        &self,
+        server_id: &Id,
+    ) -> DbResult<Vec<AuthorizedPrincipals<'static>>, Self>;
+
+    /// Fetches the host certificate most recently issued for the server.
+    /// Returns `Ok(None)` if no certificate was issued yet.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_host_certificate(
+        &self,
+        server_id: &Id,
+    ) -> DbResult<Option<HostCertificate<'static>>, Self>;
+
+    /// Stores the host certificate of a server, replacing the previous one.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn save_host_certificate(
+        &self,
+        certificate: &HostCertificate<'_>,
+    ) -> DbResult<(), Self>;
+
+    /// Stores the host key of a server in OpenSSH format. The caller must
+    /// ensure it matches the fingerprint of the server.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn save_host_key(&self, server_id: &Id, host_key: &str) -> DbResult<(), Self>;
+}
@@ -0,0 +1,32 @@
+use crate::{
+    database::{Database, DbResult},
+    objects::Contact,
+    types::{ContactRecipients, Id},
+};
+
+/// Resolves the users which are reached by mail. Permission checks must be
+/// done by the caller.
+pub trait ContactStore: Database {
+    /// Fetches the user with the given id. Returns `Ok(None)` if there is
+    /// no such user or the user has no email.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn fetch_contact(
+        &self,
+        entity_id: &Id,
+    ) -> DbResult<Option<Contact<'static>>, Self>;
+
+    /// Fetches the users reached when contacting the server, ordered by
+    /// uid. Groups are resolved to their members, including nested groups.
+    /// Only active access is taken into account and users without email
+    /// are skipped.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn fetch_server_contacts(
+        &self,
+        server_id: &Id,
+        recipients: ContactRecipients,
+    ) -> DbResult<Vec<Contact<'static>>, Self>;
+}
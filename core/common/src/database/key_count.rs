@@ -0,0 +1,16 @@
+use crate::{
+    database::{Database, DbResult},
+    types::Id,
+};
+
+/// Counts the keys of an owner for the key policy. Permission checks must
+/// be done by the caller.
+pub trait KeyCount: Database {
+    /// Counts the active keys of the entity, expired ones included. Reads
+    /// from the primary, so a key which was just added is counted before
+    /// the next one is written.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn count_keys(&self, entity_id: &Id) -> DbResult<usize, Self>;
+}
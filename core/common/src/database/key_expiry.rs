@@ -0,0 +1,27 @@
+use crate::{
+    chrono::NaiveDateTime,
+    database::{Database, DbResult},
+    objects::PublicKey,
+};
+
+/// Access to keys which are about to expire. Used by the expiry worker
+/// which runs without a user and therefore bypasses permission checks.
+pub trait KeyExpiry: Database {
+    /// Fetches active keys which expire before the given date and whose
+    /// owner was not reminded yet.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_expiring(
+        &self,
+        before: NaiveDateTime,
+        limit: usize,
+    ) -> DbResult<Vec<PublicKey<'static>>, Self>;
+
+    /// Marks the owner of the key as reminded and records a
+    /// `KeyExpiring` event
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn remind_expiring(&self, key: &PublicKey<'_>) -> DbResult<(), Self>;
+}
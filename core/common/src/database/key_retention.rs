@@ -0,0 +1,21 @@
+use crate::{
+    chrono::NaiveDateTime,
+    database::{Database, DbResult},
+};
+
+/// Permanently removes deleted keys once the retention period is over.
+/// Used by the retention worker which runs without a user and therefore
+/// bypasses permission checks.
+pub trait KeyRetention: Database {
+    /// Permanently removes at most `limit` keys which were deleted before
+    /// the given date and records a `KeyPurge` event for each of them.
+    /// Returns the number of removed keys.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn purge_deactivated(
+        &self,
+        before: NaiveDateTime,
+        limit: usize,
+    ) -> DbResult<usize, Self>;
+}
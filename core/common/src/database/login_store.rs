@@ -0,0 +1,20 @@
+use crate::{
+    database::{Database, DbResult},
+    objects::ServerLogin,
+    types::Id,
+};
+
+/// Provides the server accounts users can log into. Permission checks must
+/// be done by the caller.
+pub trait LoginStore: Database {
+    /// Fetches the named server accounts the entity or one of its groups
+    /// has access to together with their server, ordered by hostname and
+    /// account name.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_logins(
+        &self,
+        entity_id: &Id,
+    ) -> DbResult<Vec<ServerLogin<'static>>, Self>;
+}
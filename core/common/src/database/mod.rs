@@ -1,5 +1,11 @@
 //! This module contains various types for database interaction
 
+mod access_request_store;
+mod access_schedule;
+mod api_token_store;
+mod break_glass_store;
+mod certificate_store;
+mod contact_store;
 mod create;
 #[allow(clippy::module_inception)]
 mod database;
@@ -11,14 +17,36 @@ mod fetch_all_for;
 mod fetch_by_id;
 mod fetch_by_uid;
 mod fetch_first;
+mod key_count;
+mod key_expiry;
+mod key_retention;
+mod login_store;
+mod notification_queue;
+mod notification_store;
+mod purge;
+mod recertification_store;
+mod restore;
 mod save;
+mod server_admin_store;
+mod server_settings_store;
+mod server_token_store;
+mod webhook_queue;
 
 /// Result type for Database Communication
 pub type DbResult<T, D> = Result<T, DatabaseError<D>>;
 
 pub use self::{
+    access_request_store::AccessRequestStore, access_schedule::AccessSchedule,
+    api_token_store::ApiTokenStore, break_glass_store::BreakGlassStore,
+    certificate_store::CertificateStore, contact_store::ContactStore,
     create::Create, database::Database, database_error::DatabaseError,
     db_list::DbList, delete::Delete, fetch_all::FetchAll,
     fetch_all_for::FetchAllFor, fetch_by_id::FetchById, fetch_by_uid::FetchByUid,
-    fetch_first::FetchFirst, save::Save,
+    fetch_first::FetchFirst, key_count::KeyCount, key_expiry::KeyExpiry,
+    key_retention::KeyRetention, login_store::LoginStore,
+    notification_queue::NotificationQueue, notification_store::NotificationStore,
+    purge::Purge, recertification_store::RecertificationStore, restore::Restore,
+    save::Save, server_admin_store::ServerAdminStore,
+    server_settings_store::ServerSettingsStore,
+    server_token_store::ServerTokenStore, webhook_queue::WebhookQueue,
 };
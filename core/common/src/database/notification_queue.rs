@@ -0,0 +1,26 @@
+use crate::{
+    database::{Database, DbResult},
+    objects::NotificationDelivery,
+};
+
+/// Persistent queue of mail notifications. Used by the notification worker
+/// which runs without a user and therefore bypasses permission checks.
+pub trait NotificationQueue: Database {
+    /// Fetches notifications which are pending and due
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_due_notifications(
+        &self,
+        limit: usize,
+    ) -> DbResult<Vec<NotificationDelivery<'static>>, Self>;
+
+    /// Stores the result of a delivery attempt
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn finish_notification(
+        &self,
+        delivery: &NotificationDelivery<'_>,
+    ) -> DbResult<(), Self>;
+}
@@ -0,0 +1,29 @@
+use crate::{
+    database::{Database, DbResult},
+    objects::NotificationPreferences,
+    types::Id,
+};
+
+/// Stores the notification preferences of users. Permission checks must be
+/// done by the caller.
+pub trait NotificationStore: Database {
+    /// Fetches the preferences of the user with the given id. Returns
+    /// preferences enabling every notification if the user did not store
+    /// any.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn fetch_notification_preferences(
+        &self,
+        entity_id: &Id,
+    ) -> DbResult<NotificationPreferences<'static>, Self>;
+
+    /// Stores the preferences, replacing the previous ones of the user
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn save_notification_preferences(
+        &self,
+        preferences: &NotificationPreferences<'_>,
+    ) -> DbResult<(), Self>;
+}
@@ -0,0 +1,17 @@
+use crate::{
+    database::{Database, DbResult},
+    sec::Auth,
+    types::Id,
+};
+
+/// Allows permanently removing deleted objects
+pub trait Purge<A: Auth, T, D: Database>: Sized {
+    /// Permanently removes one or more deleted objects. Objects which
+    /// were not deleted before are left untouched.
+    /// Does not error if objects do not exist
+    ///
+    /// # Errors
+    /// Fails on
+    /// * Connection Errors
+    fn purge(&self, ids: &[Id], auth: &A) -> DbResult<(), D>;
+}
@@ -0,0 +1,90 @@
+use crate::{
+    database::{Database, DbResult},
+    objects::{
+        Recertification, RecertificationEntry, RecertificationItem,
+        RecertificationProgress,
+    },
+    types::Id,
+};
+
+/// Stores access recertification campaigns and removes the revoked access
+/// when a campaign is closed. Permission checks must be done by the caller.
+pub trait RecertificationStore: Database {
+    /// Stores a new campaign together with an item for every access grant
+    /// to a server account which has not ended and a single item for every
+    /// member of a group with such a grant. Returns the number of items.
+    ///
+    /// # Errors
+    /// Fails on
+    /// * Connection Errors
+    /// * `DatabaseError::NonUnique` if another campaign is still open
+    fn create_campaign(&self, campaign: &Recertification<'_>)
+        -> DbResult<u64, Self>;
+
+    /// Fetches every campaign with its progress, newest first.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_campaigns(
+        &self,
+    ) -> DbResult<Vec<RecertificationProgress<'static>>, Self>;
+
+    /// Fetches the campaign with the given id and its progress.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_campaign(
+        &self,
+        id: &Id,
+    ) -> DbResult<Option<RecertificationProgress<'static>>, Self>;
+
+    /// Fetches the items of the campaign ordered by server. Only returns
+    /// the access items of servers the entity administers, see
+    /// `AccessRequestStore::is_approver`, and the membership items of groups
+    /// it administers if one is given.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_items(
+        &self,
+        campaign_id: &Id,
+        reviewer_id: Option<&Id>,
+    ) -> DbResult<Vec<RecertificationEntry<'static>>, Self>;
+
+    /// Fetches the item with the given id.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_item(
+        &self,
+        id: &Id,
+    ) -> DbResult<Option<RecertificationEntry<'static>>, Self>;
+
+    /// Stores the decision on an item. Returns `Ok(false)` if the campaign
+    /// was closed concurrently.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn decide_item(&self, item: &RecertificationItem<'_>) -> DbResult<bool, Self>;
+
+    /// Checks whether the entity or one of its groups administers the group.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn is_group_admin(&self, group_id: &Id, entity_id: &Id) -> DbResult<bool, Self>;
+
+    /// Closes the campaign, removes every access grant and group
+    /// membership marked for revocation and queues a resync of the
+    /// affected accounts. Grants and memberships which another item of the
+    /// campaign marks to be kept are not removed, see
+    /// `RecertificationItem::revocations`. Everything is stored in one
+    /// transaction. Returns the items which were actually removed or
+    /// `Ok(None)` if the campaign was closed concurrently.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn close_campaign(
+        &self,
+        campaign: &Recertification<'_>,
+    ) -> DbResult<Option<Vec<RecertificationItem<'static>>>, Self>;
+}
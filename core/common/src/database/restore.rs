@@ -0,0 +1,16 @@
+use crate::{
+    database::{Database, DbResult},
+    sec::Auth,
+    types::Id,
+};
+
+/// Allows restoring deleted objects
+pub trait Restore<A: Auth, T, D: Database>: Sized {
+    /// Restores one or more previously deleted objects.
+    /// Does not error if objects do not exist or were not deleted
+    ///
+    /// # Errors
+    /// Fails on
+    /// * Connection Errors
+    fn restore(&self, ids: &[Id], auth: &A) -> DbResult<(), D>;
+}
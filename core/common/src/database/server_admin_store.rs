@@ -0,0 +1,103 @@
+use crate::{
+    database::{Database, DbResult},
+    objects::{Entity, ServerAccount},
+    types::Id,
+};
+
+/// Manages the administrators and the accounts of a server. Permission
+/// checks must be done by the caller.
+pub trait ServerAdminStore: Database {
+    /// Looks up the user with the given uid or, if there is none, the group
+    /// with the given name.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn fetch_entity_by_name(
+        &self,
+        name: &str,
+    ) -> DbResult<Option<Entity<'static>>, Self>;
+
+    /// Fetches the users and groups which administer the server, ordered
+    /// by name.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn fetch_server_admins(
+        &self,
+        server_id: &Id,
+    ) -> DbResult<Vec<Entity<'static>>, Self>;
+
+    /// Makes the entity administrator of the server.
+    ///
+    /// # Errors
+    /// Fails on
+    /// * Connection Errors
+    /// * `DatabaseError::NonUnique` if the entity already administers the
+    ///   server
+    fn add_server_admin(&self, server_id: &Id, entity_id: &Id)
+        -> DbResult<(), Self>;
+
+    /// Removes the entity from the administrators of the server. Returns
+    /// `Ok(false)` if it was no administrator.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn remove_server_admin(
+        &self,
+        server_id: &Id,
+        entity_id: &Id,
+    ) -> DbResult<bool, Self>;
+
+    /// Fetches the named accounts of the server together with every entity
+    /// which has access to them, ordered by name.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_server_accounts(
+        &self,
+        server_id: &Id,
+    ) -> DbResult<Vec<ServerAccount<'static>>, Self>;
+
+    /// Creates an account on the server and queues its sync.
+    ///
+    /// # Errors
+    /// Fails on
+    /// * Connection Errors
+    /// * `DatabaseError::NonUnique` if the server already has an account
+    ///   with the name
+    fn create_server_account(
+        &self,
+        server_id: &Id,
+        account_id: &Id,
+        name: &str,
+    ) -> DbResult<(), Self>;
+
+    /// Grants the entity unlimited access to the account of the server and
+    /// queues a resync of the account.
+    ///
+    /// # Errors
+    /// Fails on
+    /// * Connection Errors
+    /// * `DatabaseError::NonUnique` if the entity already has access to the
+    ///   account
+    fn grant_server_access(
+        &self,
+        server_id: &Id,
+        account_id: &Id,
+        source_id: &Id,
+        granted_by: &Id,
+    ) -> DbResult<(), Self>;
+
+    /// Revokes access of the entity to the account of the server and queues
+    /// a resync of the account. Returns `Ok(false)` if the entity had no
+    /// access.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn revoke_server_access(
+        &self,
+        server_id: &Id,
+        account_id: &Id,
+        source_id: &Id,
+    ) -> DbResult<bool, Self>;
+}
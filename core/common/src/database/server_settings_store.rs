@@ -0,0 +1,20 @@
+use crate::{
+    database::{Database, DbResult},
+    types::Id,
+};
+
+/// Manages the settings of a server which change the content of its
+/// accounts. Permission checks must be done by the caller.
+pub trait ServerSettingsStore: Database {
+    /// Changes whether the server only accepts keys backed by a hardware
+    /// security key and queues a resync of all its accounts. Returns
+    /// `Ok(false)` if the server does not exist.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn set_require_security_key(
+        &self,
+        server_id: &Id,
+        required: bool,
+    ) -> DbResult<bool, Self>;
+}
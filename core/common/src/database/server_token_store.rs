@@ -0,0 +1,31 @@
+use crate::{
+    database::{Database, DbResult},
+    objects::Server,
+    types::Id,
+};
+
+/// Manages the tokens servers authenticate with at the api. Only the digest
+/// of a token is stored. Permission checks must be done by the caller.
+pub trait ServerTokenStore: Database {
+    /// Replaces the digest of the token the server authenticates with at
+    /// the api. `None` revokes the current token. Returns `Ok(false)` if
+    /// the server had no token to revoke.
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn save_server_token(
+        &self,
+        server_id: &Id,
+        digest: Option<&[u8]>,
+    ) -> DbResult<bool, Self>;
+
+    /// Fetches the server if its current token has the given digest.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_server_by_token(
+        &self,
+        server_id: &Id,
+        digest: &[u8],
+    ) -> DbResult<Option<Server<'static>>, Self>;
+}
@@ -0,0 +1,26 @@
+use crate::{
+    database::{Database, DbResult},
+    objects::{Webhook, WebhookDelivery},
+};
+
+/// Persistent queue of webhook deliveries. Used by the delivery worker
+/// which runs without a user and therefore bypasses permission checks.
+pub trait WebhookQueue: Database {
+    /// Fetches deliveries which are pending and due together with their
+    /// webhook. Deliveries of inactive webhooks are not returned. Returned
+    /// deliveries are claimed and not returned again, even to other
+    /// instances, until they are finished or the claim times out.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    fn fetch_due(
+        &self,
+        limit: usize,
+    ) -> DbResult<Vec<(Webhook<'static>, WebhookDelivery<'static>)>, Self>;
+
+    /// Stores the result of a delivery attempt
+    ///
+    /// # Errors
+    /// Fails on connection errors.
+    fn finish_delivery(&self, delivery: &WebhookDelivery<'_>) -> DbResult<(), Self>;
+}
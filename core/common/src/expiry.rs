@@ -0,0 +1,157 @@
+//! Reminds owners of public keys which are about to expire. Every key is
+//! reminded once when it enters the warning period of the key policy. The
+//! reminder is recorded as `KeyExpiring` event and therefore also
+//! delivered to webhooks.
+
+use crate::{
+    chrono::Utc,
+    database::{DatabaseError, KeyExpiry},
+    objects::KeyPolicy,
+    worker::run_periodic,
+};
+use std::{sync::Arc, time::Duration};
+
+/// Number of keys processed at once
+const EXPIRY_BATCH: usize = 50;
+
+/// Worker which periodically records reminders for expiring keys
+#[derive(Debug)]
+pub struct ExpiryWorker<D: KeyExpiry> {
+    db: Arc<D>,
+    policy: KeyPolicy,
+    interval: Duration,
+}
+
+impl<D: KeyExpiry> ExpiryWorker<D> {
+    /// Creates a new worker which checks for expiring keys in the given
+    /// interval. The warning period is taken from the policy.
+    #[must_use]
+    #[inline]
+    pub fn new(db: Arc<D>, policy: KeyPolicy, interval: Duration) -> Self {
+        Self {
+            db,
+            policy,
+            interval,
+        }
+    }
+
+    /// Checks for expiring keys until the application stops
+    #[inline]
+    pub async fn run(self) {
+        let worker = &self;
+        run_periodic(self.interval, "expiring keys", || async move {
+            worker.process()
+        })
+        .await;
+    }
+
+    /// Records a reminder for every key which expires within the warning
+    /// period. Returns the number of reminded keys.
+    ///
+    /// # Errors
+    /// Fails on database errors
+    #[inline]
+    pub fn process(&self) -> Result<usize, DatabaseError<D>> {
+        let before = self.policy.warning_date(Utc::now().naive_utc());
+        let keys = self.db.fetch_expiring(before, EXPIRY_BATCH)?;
+        for key in &keys {
+            self.db.remind_expiring(key)?;
+        }
+        Ok(keys.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExpiryWorker;
+    use crate::{
+        chrono::{Duration as ChronoDuration, NaiveDateTime, Utc},
+        database::{DbResult, KeyExpiry},
+        objects::{KeyPolicy, PublicKey},
+        types::Id,
+        worker::tests::TestDb,
+    };
+    use std::{borrow::Cow, sync::Arc, time::Duration};
+
+    /// Keys and whether their owner was reminded
+    type Keys = Vec<(PublicKey<'static>, bool)>;
+
+    impl KeyExpiry for TestDb<Keys> {
+        fn fetch_expiring(
+            &self,
+            before: NaiveDateTime,
+            _limit: usize,
+        ) -> DbResult<Vec<PublicKey<'static>>, Self> {
+            let keys = self.data.lock().expect("Poisoned lock");
+            Ok(keys
+                .iter()
+                .filter(|(key, reminded)| {
+                    !reminded && key.expires.map_or(false, |e| e < before)
+                })
+                .map(|(key, _)| key.clone())
+                .collect())
+        }
+
+        fn remind_expiring(&self, key: &PublicKey<'_>) -> DbResult<(), Self> {
+            let mut keys = self.data.lock().expect("Poisoned lock");
+            for (k, reminded) in keys.iter_mut() {
+                if k.id == key.id {
+                    *reminded = true;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn key(id: &str, expires: Option<NaiveDateTime>) -> PublicKey<'static> {
+        let id = Id::from_string(id).expect("Invalid Id");
+        PublicKey {
+            id: Cow::Owned(id.clone()),
+            entity_id: Cow::Owned(id),
+            type_: Cow::Borrowed("ssh-ed25519"),
+            keydata: Cow::Borrowed(""),
+            comment: None,
+            keysize: Some(256),
+            fingerprint_md5: None,
+            fingerprint_sha256: None,
+            randomart_md5: None,
+            randomart_sha256: None,
+            upload_date: None,
+            application: None,
+            expires,
+            deactivated: None,
+        }
+    }
+
+    #[test]
+    fn test_process() {
+        let now = Utc::now().naive_utc();
+        let db = Arc::new(TestDb::new(vec![
+            (
+                key(
+                    "00bd8c06-daf7-47e6-8c96-8d467587b6dc",
+                    Some(now + ChronoDuration::days(3)),
+                ),
+                false,
+            ),
+            (
+                key(
+                    "c6efb44e-9b67-4dc0-a31b-6482476ed8b7",
+                    Some(now + ChronoDuration::days(60)),
+                ),
+                false,
+            ),
+            (key("5e1fc1f4-7cb1-4e10-9d4a-f7ac3c4d0b8e", None), false),
+        ]));
+        let worker = ExpiryWorker::new(
+            Arc::clone(&db),
+            KeyPolicy::default(),
+            Duration::from_secs(1),
+        );
+        assert_eq!(worker.process().expect("Unable to process"), 1);
+        assert_eq!(worker.process().expect("Unable to process"), 0);
+        let keys = db.data.lock().expect("Poisoned lock");
+        let reminded: Vec<bool> = keys.iter().map(|(_, r)| *r).collect();
+        assert_eq!(reminded, vec![true, false, false]);
+    }
+}
@@ -53,10 +53,17 @@
 )]
 
 pub mod database;
+pub mod expiry;
+pub mod mail;
+pub mod notification;
 pub mod objects;
+pub mod retention;
+pub mod schedule;
 pub mod sec;
 pub mod types;
 pub mod web;
+pub mod webhook;
+mod worker;
 
 /// Rexporting `async_trait` for lower libraries
 pub mod async_trait {
@@ -88,6 +95,11 @@ pub mod log {
     pub use ::log::*;
 }
 
+/// Rexporting `ring` for lower libraries
+pub mod ring {
+    pub use ::ring::*;
+}
+
 /// Rexporting `serde` for lower libraries
 pub mod serde {
     pub use ::serde::*;
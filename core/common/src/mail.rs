@@ -0,0 +1,363 @@
+//! Sends mails through a configurable SMTP relay. The SMTP client blocks,
+//! so mails are handed to the blocking thread pool of tokio and all mails
+//! of a batch share a single connection.
+
+use crate::{
+    objects::Contact,
+    tokio::task::{self, JoinError},
+};
+use lettre::{
+    smtp::{
+        authentication::Credentials, error::Error as SmtpError,
+        ConnectionReuseParameters,
+    },
+    ClientSecurity, ClientTlsParameters, SendableEmail, SmtpClient, Transport,
+};
+use lettre_email::{error::Error as EmailError, EmailBuilder, Mailbox};
+use native_tls::{Error as TlsError, TlsConnector};
+use std::{convert::TryFrom, error, fmt, time::Duration};
+
+/// How the connection to the SMTP relay is secured
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    /// Plain text connection. Only meant for relays on the same host or
+    /// local test sinks
+    None,
+    /// Upgrades the connection using `STARTTLS`. Fails if the relay does
+    /// not support it
+    StartTls,
+    /// Uses TLS right from the start
+    Tls,
+}
+
+impl TryFrom<&str> for SmtpSecurity {
+    type Error = ();
+
+    #[inline]
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "none" => Ok(Self::None),
+            "starttls" => Ok(Self::StartTls),
+            "tls" => Ok(Self::Tls),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Settings of the SMTP relay
+#[derive(Debug, Clone)]
+pub struct MailConfig {
+    /// Host of the relay
+    pub host: String,
+    /// Port of the relay
+    pub port: u16,
+    /// How the connection is secured
+    pub security: SmtpSecurity,
+    /// Username used to authenticate. No authentication if `None`
+    pub username: Option<String>,
+    /// Password used to authenticate
+    pub password: Option<String>,
+    /// Address mails are sent from
+    pub from_address: String,
+    /// Name shown next to the sender address
+    pub from_name: String,
+    /// Time to wait for the relay to answer a command
+    pub timeout: Duration,
+}
+
+/// A plain text mail to one or more users
+#[derive(Debug, Clone, Copy)]
+pub struct Mail<'a> {
+    /// The users receiving the mail
+    pub recipients: &'a [Contact<'a>],
+    /// Sends the mail as blind copy so that recipients do not see each other
+    pub hide_recipients: bool,
+    /// Replies go to this user instead of the sender address
+    pub reply_to: Option<&'a Contact<'a>>,
+    /// The subject of the mail
+    pub subject: &'a str,
+    /// The text of the mail
+    pub body: &'a str,
+}
+
+/// Error which may occur when sending mails
+#[derive(Debug)]
+pub enum MailError {
+    /// The mail could not be built, usually because of an invalid address
+    Build(EmailError),
+    /// Unable to setup TLS
+    Tls(TlsError),
+    /// The relay is unreachable or rejected a mail
+    Smtp(SmtpError),
+    /// The task sending the mails failed
+    Task(JoinError),
+}
+
+impl fmt::Display for MailError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Build(err) => write!(f, "Unable to build mail: {}", err),
+            Self::Tls(err) => write!(f, "Unable to setup TLS: {}", err),
+            Self::Smtp(err) => write!(f, "Unable to send mail: {}", err),
+            Self::Task(err) => write!(f, "Unable to run mail delivery: {}", err),
+        }
+    }
+}
+
+impl error::Error for MailError {}
+
+impl From<EmailError> for MailError {
+    #[inline]
+    fn from(err: EmailError) -> Self {
+        Self::Build(err)
+    }
+}
+
+impl From<TlsError> for MailError {
+    #[inline]
+    fn from(err: TlsError) -> Self {
+        Self::Tls(err)
+    }
+}
+
+impl From<SmtpError> for MailError {
+    #[inline]
+    fn from(err: SmtpError) -> Self {
+        Self::Smtp(err)
+    }
+}
+
+impl From<JoinError> for MailError {
+    #[inline]
+    fn from(err: JoinError) -> Self {
+        Self::Task(err)
+    }
+}
+
+/// Sends mails through the configured relay
+#[derive(Debug, Clone)]
+pub struct Mailer {
+    config: MailConfig,
+}
+
+impl Mailer {
+    /// Creates a new mailer. No connection is made until mails are sent
+    #[must_use]
+    #[inline]
+    pub const fn new(config: MailConfig) -> Self {
+        Self { config }
+    }
+
+    /// Sends a single mail
+    ///
+    /// # Errors
+    /// Fails if an address is invalid or the relay is unreachable or
+    /// rejects the mail
+    #[inline]
+    pub async fn send(&self, mail: &Mail<'_>) -> Result<(), MailError> {
+        self.send_all(&[*mail]).await
+    }
+
+    /// Sends all mails using a single connection. Stops at the first
+    /// failure
+    ///
+    /// # Errors
+    /// Fails if an address is invalid or the relay is unreachable or
+    /// rejects a mail
+    #[inline]
+    pub async fn send_all(&self, mails: &[Mail<'_>]) -> Result<(), MailError> {
+        let emails = mails
+            .iter()
+            .map(|mail| self.build(mail))
+            .collect::<Result<Vec<_>, _>>()?;
+        if emails.is_empty() {
+            return Ok(());
+        }
+        let config = self.config.clone();
+        task::spawn_blocking(move || deliver(&config, emails)).await?
+    }
+
+    fn build(&self, mail: &Mail<'_>) -> Result<SendableEmail, MailError> {
+        let from = mailbox(&self.config.from_address, Some(&self.config.from_name));
+        let mut builder = EmailBuilder::new()
+            .from(from.clone())
+            .subject(mail.subject)
+            .text(mail.body);
+        if mail.hide_recipients {
+            builder = builder.to(from);
+        }
+        for contact in mail.recipients {
+            let recipient = mailbox(&contact.email, contact.name.as_deref());
+            builder = if mail.hide_recipients {
+                builder.bcc(recipient)
+            } else {
+                builder.to(recipient)
+            };
+        }
+        if let Some(contact) = mail.reply_to {
+            builder =
+                builder.reply_to(mailbox(&contact.email, contact.name.as_deref()));
+        }
+        Ok(builder.build()?.into())
+    }
+}
+
+fn mailbox(address: &str, name: Option<&str>) -> Mailbox {
+    match name {
+        Some(name) if !name.is_empty() => {
+            Mailbox::new_with_name(name.to_string(), address.to_string())
+        }
+        _ => Mailbox::new(address.to_string()),
+    }
+}
+
+fn deliver(
+    config: &MailConfig,
+    emails: Vec<SendableEmail>,
+) -> Result<(), MailError> {
+    let security = match config.security {
+        SmtpSecurity::None => ClientSecurity::None,
+        SmtpSecurity::StartTls => ClientSecurity::Required(tls_parameters(config)?),
+        SmtpSecurity::Tls => ClientSecurity::Wrapper(tls_parameters(config)?),
+    };
+    let mut client = SmtpClient::new((config.host.as_str(), config.port), security)?
+        .timeout(Some(config.timeout))
+        .connection_reuse(ConnectionReuseParameters::ReuseUnlimited);
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        client =
+            client.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    let mut transport = client.transport();
+    let res = emails
+        .into_iter()
+        .try_for_each(|email| transport.send(email).map(|_| ()));
+    transport.close();
+    res.map_err(MailError::Smtp)
+}
+
+fn tls_parameters(config: &MailConfig) -> Result<ClientTlsParameters, MailError> {
+    let connector = TlsConnector::new()?;
+    Ok(ClientTlsParameters::new(config.host.clone(), connector))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Mail, MailConfig, Mailer, SmtpSecurity};
+    use crate::{objects::Contact, types::Id};
+    use std::{
+        borrow::Cow,
+        convert::TryFrom,
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+        time::Duration,
+    };
+
+    /// Starts a SMTP sink which accepts a single connection and returns
+    /// the recipients of the envelope and the transmitted data
+    fn sink() -> (u16, thread::JoinHandle<(Vec<String>, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Unable to bind");
+        let port = listener.local_addr().expect("Unable to get address").port();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("Unable to accept");
+            let mut reader =
+                BufReader::new(stream.try_clone().expect("Unable to clone"));
+            let mut recipients = Vec::new();
+            let mut data = String::new();
+            stream
+                .write_all(b"220 localhost ESMTP\r\n")
+                .expect("Unable to greet");
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap_or_default() > 0 {
+                let command = line.to_ascii_uppercase();
+                let reply: &[u8] = if command.starts_with("EHLO") {
+                    b"250 localhost\r\n"
+                } else if command.starts_with("RCPT TO:") {
+                    recipients.push(line.trim_end().to_string());
+                    b"250 OK\r\n"
+                } else if command.starts_with("DATA") {
+                    stream
+                        .write_all(b"354 Go ahead\r\n")
+                        .expect("Unable to reply");
+                    let mut body = String::new();
+                    while reader.read_line(&mut body).unwrap_or_default() > 0 {
+                        if body.ends_with("\r\n.\r\n") {
+                            break;
+                        }
+                    }
+                    data.push_str(&body);
+                    b"250 OK\r\n"
+                } else if command.starts_with("QUIT") {
+                    stream.write_all(b"221 Bye\r\n").expect("Unable to reply");
+                    break;
+                } else {
+                    b"250 OK\r\n"
+                };
+                stream.write_all(reply).expect("Unable to reply");
+                line.clear();
+            }
+            (recipients, data)
+        });
+        (port, handle)
+    }
+
+    fn mailer(port: u16) -> Mailer {
+        Mailer::new(MailConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+            security: SmtpSecurity::None,
+            username: None,
+            password: None,
+            from_address: "ska@example.com".to_string(),
+            from_name: "SSH Key Authority".to_string(),
+            timeout: Duration::from_secs(5),
+        })
+    }
+
+    fn contact<'a>(id: &'a Id, uid: &'a str, email: &'a str) -> Contact<'a> {
+        Contact {
+            entity_id: Cow::Borrowed(id),
+            uid: Cow::Borrowed(uid),
+            name: None,
+            email: Cow::Borrowed(email),
+        }
+    }
+
+    #[test]
+    fn test_security() {
+        assert_eq!(SmtpSecurity::try_from("none"), Ok(SmtpSecurity::None));
+        assert_eq!(
+            SmtpSecurity::try_from("starttls"),
+            Ok(SmtpSecurity::StartTls)
+        );
+        assert_eq!(SmtpSecurity::try_from("tls"), Ok(SmtpSecurity::Tls));
+        assert_eq!(SmtpSecurity::try_from("ssl"), Err(()));
+    }
+
+    #[tokio::test]
+    async fn test_send_hidden_recipients() {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        let recipients = [
+            contact(&id, "alice", "alice@example.com"),
+            contact(&id, "bob", "bob@example.com"),
+        ];
+        let (port, handle) = sink();
+        let mail = Mail {
+            recipients: &recipients,
+            hide_recipients: true,
+            reply_to: None,
+            subject: "Maintenance",
+            body: "Reboot on Monday",
+        };
+        mailer(port).send(&mail).await.expect("Unable to send");
+
+        let (envelope, data) = handle.join().expect("Sink failed");
+        assert!(envelope.iter().any(|v| v.contains("alice@example.com")));
+        assert!(envelope.iter().any(|v| v.contains("bob@example.com")));
+        assert!(data.contains("Subject: Maintenance"));
+        assert!(data.contains("Reboot on Monday"));
+        assert!(!data.contains("bob@example.com"));
+    }
+}
@@ -0,0 +1,422 @@
+//! Notifies users by mail about changes concerning them. Notifications are
+//! queued by the database when the event is recorded, taking the
+//! preferences of the users into account, and sent by the
+//! `NotificationWorker`. Every mail is rendered from the template
+//! `mail_<action>` of its event. The first line of the rendered text is
+//! used as subject, the remaining text as body.
+
+use crate::{
+    chrono::{NaiveDateTime, Utc},
+    database::{ContactStore, DatabaseError, NotificationQueue},
+    log,
+    mail::{Mail, Mailer},
+    objects::{Contact, EventDetails, NotificationDelivery},
+    serde::Serialize,
+    types::{DeliveryStatus, NotificationType, SyncStatusType},
+    web::TemplateEngine,
+    webhook::retry_delay,
+    worker::run_periodic,
+};
+use std::{borrow::Cow, slice, sync::Arc, time::Duration};
+
+/// Maximum number of attempts before a notification is marked as failed
+pub const MAX_ATTEMPTS: i32 = 5;
+
+/// Number of notifications processed at once
+const NOTIFICATION_BATCH: usize = 50;
+
+/// Returns the type of notification sent for the event. `None` if users
+/// are not notified about it
+#[must_use]
+#[inline]
+pub fn notification_type(details: &EventDetails<'_>) -> Option<NotificationType> {
+    #[allow(clippy::wildcard_enum_match_arm)]
+    match *details {
+        EventDetails::KeyAdd { .. } => Some(NotificationType::KeyAdd),
+        EventDetails::AccessGrant { .. }
+        | EventDetails::AccessRevoke { .. }
+        | EventDetails::AccessRequestCreate { .. } => Some(NotificationType::Access),
+        EventDetails::SyncResult {
+            status: SyncStatusType::SyncFailure,
+            ..
+        } => Some(NotificationType::SyncFailure),
+        EventDetails::KeyExpiring { .. } => Some(NotificationType::KeyExpiring),
+        _ => None,
+    }
+}
+
+/// Splits a rendered mail into subject and body. Blank lines between
+/// subject and body are removed
+#[must_use]
+#[inline]
+pub fn split_mail(text: &str) -> (&str, &str) {
+    let mut parts = text.trim_start().splitn(2, '\n');
+    let subject = parts.next().unwrap_or_default().trim();
+    let body = parts
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches(|c| c == '\r' || c == '\n');
+    (subject, body)
+}
+
+/// Data passed to the mail templates
+#[derive(Debug, Serialize)]
+struct MailData<'a> {
+    recipient: &'a Contact<'a>,
+    details: &'a EventDetails<'a>,
+    target: Option<&'a str>,
+    date: Option<NaiveDateTime>,
+}
+
+/// Worker which renders and sends queued notifications
+#[derive(Debug)]
+pub struct NotificationWorker<D, T>
+where
+    D: NotificationQueue + ContactStore,
+    T: TemplateEngine,
+{
+    db: Arc<D>,
+    templates: Arc<T>,
+    mailer: Mailer,
+    interval: Duration,
+}
+
+impl<D, T> NotificationWorker<D, T>
+where
+    D: NotificationQueue + ContactStore,
+    T: TemplateEngine,
+{
+    /// Creates a new worker which checks the queue in the given interval
+    #[must_use]
+    #[inline]
+    pub fn new(
+        db: Arc<D>,
+        templates: Arc<T>,
+        mailer: Mailer,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            db,
+            templates,
+            mailer,
+            interval,
+        }
+    }
+
+    /// Processes the queue until the application stops
+    #[inline]
+    pub async fn run(self) {
+        run_periodic(self.interval, "notifications", || self.process()).await;
+    }
+
+    /// Attempts all due notifications once. Returns the number of
+    /// processed notifications.
+    ///
+    /// # Errors
+    /// Fails on database errors
+    #[inline]
+    pub async fn process(&self) -> Result<usize, DatabaseError<D>> {
+        let due = self.db.fetch_due_notifications(NOTIFICATION_BATCH)?;
+        let count = due.len();
+        for mut delivery in due {
+            self.attempt(&mut delivery).await;
+            self.db.finish_notification(&delivery)?;
+        }
+        Ok(count)
+    }
+
+    async fn attempt(&self, delivery: &mut NotificationDelivery<'_>) {
+        delivery.attempts = delivery.attempts.saturating_add(1);
+        let contact = match self.db.fetch_contact(&delivery.entity_id) {
+            Ok(Some(contact)) => Ok(contact),
+            Ok(None) => {
+                delivery.status = DeliveryStatus::Failed;
+                delivery.next_attempt = None;
+                delivery.error = Some(Cow::Borrowed("User has no email"));
+                return;
+            }
+            Err(err) => Err(err.to_string()),
+        };
+        let res = match contact {
+            Ok(contact) => self.send(delivery, &contact).await,
+            Err(err) => Err(err),
+        };
+        match res {
+            Ok(()) => {
+                delivery.status = DeliveryStatus::Delivered;
+                delivery.next_attempt = None;
+                delivery.error = None;
+                return;
+            }
+            Err(err) => delivery.error = Some(Cow::Owned(err)),
+        }
+        if delivery.attempts >= MAX_ATTEMPTS {
+            log::warn!(
+                "Giving up notification {} of {}: {}",
+                delivery.id,
+                delivery.entity_id,
+                delivery.error.as_deref().unwrap_or_default()
+            );
+            delivery.status = DeliveryStatus::Failed;
+            delivery.next_attempt = None;
+        } else {
+            delivery.next_attempt = Utc::now()
+                .naive_utc()
+                .checked_add_signed(retry_delay(delivery.attempts));
+        }
+    }
+
+    async fn send(
+        &self,
+        delivery: &NotificationDelivery<'_>,
+        contact: &Contact<'_>,
+    ) -> Result<(), String> {
+        let name = format!("mail_{}", delivery.details.action());
+        let data = MailData {
+            recipient: contact,
+            details: &delivery.details,
+            target: delivery.target.as_deref(),
+            date: delivery.date,
+        };
+        let text = self
+            .templates
+            .render(&name, &data)
+            .map_err(|err| err.to_string())?;
+        let (subject, body) = split_mail(&text);
+        let mail = Mail {
+            recipients: slice::from_ref(contact),
+            hide_recipients: false,
+            reply_to: None,
+            subject,
+            body,
+        };
+        self.mailer.send(&mail).await.map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{notification_type, split_mail, NotificationWorker};
+    use crate::{
+        database::{ContactStore, DbResult, NotificationQueue},
+        mail::{MailConfig, Mailer, SmtpSecurity},
+        objects::{Contact, EventDetails, NotificationDelivery},
+        serde::Serialize,
+        types::{
+            ContactRecipients, DeliveryStatus, Id, NotificationType, SyncStatusType,
+        },
+        web::{RenderError, TemplateEngine},
+        worker::tests::TestDb,
+    };
+    use std::{
+        borrow::Cow,
+        fmt,
+        net::TcpListener,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    #[derive(Debug)]
+    struct TestQueue {
+        email: Option<&'static str>,
+        deliveries: Vec<NotificationDelivery<'static>>,
+    }
+
+    impl ContactStore for TestDb<TestQueue> {
+        fn fetch_contact(
+            &self,
+            entity_id: &Id,
+        ) -> DbResult<Option<Contact<'static>>, Self> {
+            let email = self.data.lock().expect("Poisoned lock").email;
+            Ok(email.map(|email| Contact {
+                entity_id: Cow::Owned(entity_id.clone()),
+                uid: Cow::Borrowed("alice"),
+                name: None,
+                email: Cow::Borrowed(email),
+            }))
+        }
+
+        fn fetch_server_contacts(
+            &self,
+            _server_id: &Id,
+            _recipients: ContactRecipients,
+        ) -> DbResult<Vec<Contact<'static>>, Self> {
+            Ok(Vec::new())
+        }
+    }
+
+    impl NotificationQueue for TestDb<TestQueue> {
+        fn fetch_due_notifications(
+            &self,
+            _limit: usize,
+        ) -> DbResult<Vec<NotificationDelivery<'static>>, Self> {
+            let data = self.data.lock().expect("Poisoned lock");
+            Ok(data
+                .deliveries
+                .iter()
+                .filter(|d| d.status == DeliveryStatus::Pending)
+                .cloned()
+                .collect())
+        }
+
+        fn finish_notification(
+            &self,
+            delivery: &NotificationDelivery<'_>,
+        ) -> DbResult<(), Self> {
+            let mut data = self.data.lock().expect("Poisoned lock");
+            for d in &mut data.deliveries {
+                if d.id == delivery.id {
+                    d.status = delivery.status;
+                    d.attempts = delivery.attempts;
+                    d.next_attempt = delivery.next_attempt;
+                    d.error =
+                        delivery.error.as_ref().map(|e| Cow::Owned(e.to_string()));
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct TestTemplates {
+        rendered: Mutex<Vec<String>>,
+    }
+
+    impl TemplateEngine for TestTemplates {
+        type TemplateError = fmt::Error;
+
+        fn render<T: Serialize>(
+            &self,
+            name: &str,
+            _data: &T,
+        ) -> Result<String, RenderError<Self>> {
+            let mut rendered = self.rendered.lock().expect("Poisoned lock");
+            rendered.push(name.to_string());
+            Ok("Key added\n\nA key was added to your account".to_string())
+        }
+    }
+
+    type TestWorker = NotificationWorker<TestDb<TestQueue>, TestTemplates>;
+
+    fn worker(
+        email: Option<&'static str>,
+    ) -> (Arc<TestDb<TestQueue>>, Arc<TestTemplates>, TestWorker) {
+        let id = Id::from_string("c6efb44e-9b67-4dc0-a31b-6482476ed8b7")
+            .expect("Invalid Id");
+        let delivery = NotificationDelivery {
+            id: Cow::Owned(id.clone()),
+            entity_id: Cow::Owned(id.clone()),
+            event_id: Cow::Owned(id.clone()),
+            details: EventDetails::KeyAdd {
+                key_id: Cow::Owned(id),
+                fingerprint: None,
+            },
+            target: None,
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt: None,
+            error: None,
+            date: None,
+        };
+        let db = Arc::new(TestDb::new(TestQueue {
+            email,
+            deliveries: vec![delivery],
+        }));
+        // bind and release a port so that the relay is unreachable
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|v| v.local_addr())
+            .expect("Unable to bind")
+            .port();
+        let mailer = Mailer::new(MailConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+            security: SmtpSecurity::None,
+            username: None,
+            password: None,
+            from_address: "ska@example.com".to_string(),
+            from_name: "SSH Key Authority".to_string(),
+            timeout: Duration::from_secs(1),
+        });
+        let templates = Arc::new(TestTemplates::default());
+        let worker = NotificationWorker::new(
+            Arc::clone(&db),
+            Arc::clone(&templates),
+            mailer,
+            Duration::from_secs(1),
+        );
+        (db, templates, worker)
+    }
+
+    #[test]
+    fn test_notification_type() {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        let grant = EventDetails::AccessRevoke {
+            source_id: Cow::Borrowed(&id),
+            dest_id: Cow::Borrowed(&id),
+        };
+        assert_eq!(notification_type(&grant), Some(NotificationType::Access));
+        let request = EventDetails::AccessRequestCreate {
+            request_id: Cow::Borrowed(&id),
+            dest_id: Cow::Borrowed(&id),
+            reason: None,
+        };
+        assert_eq!(notification_type(&request), Some(NotificationType::Access));
+        let failure = EventDetails::SyncResult {
+            status: SyncStatusType::SyncFailure,
+            message: None,
+        };
+        assert_eq!(
+            notification_type(&failure),
+            Some(NotificationType::SyncFailure)
+        );
+        let success = EventDetails::SyncResult {
+            status: SyncStatusType::SyncSuccess,
+            message: None,
+        };
+        assert_eq!(notification_type(&success), None);
+        let login = EventDetails::Login {
+            uid: Cow::Borrowed("alice"),
+        };
+        assert_eq!(notification_type(&login), None);
+    }
+
+    #[test]
+    fn test_split_mail() {
+        assert_eq!(
+            split_mail("\nKey added \r\n\r\nHello\nBye\n"),
+            ("Key added", "Hello\nBye\n")
+        );
+        assert_eq!(split_mail("Key added"), ("Key added", ""));
+    }
+
+    #[tokio::test]
+    async fn test_process_without_email() {
+        let (db, templates, worker) = worker(None);
+        assert_eq!(worker.process().await.expect("Process failed"), 1);
+
+        let data = db.data.lock().expect("Poisoned lock");
+        let delivery = data.deliveries.first().expect("Missing delivery");
+        assert_eq!(delivery.status, DeliveryStatus::Failed);
+        assert_eq!(delivery.attempts, 1);
+        assert!(delivery.next_attempt.is_none());
+        assert!(templates.rendered.lock().expect("Poisoned lock").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_process_unreachable_relay() {
+        let (db, templates, worker) = worker(Some("alice@example.com"));
+        assert_eq!(worker.process().await.expect("Process failed"), 1);
+
+        let data = db.data.lock().expect("Poisoned lock");
+        let delivery = data.deliveries.first().expect("Missing delivery");
+        assert_eq!(delivery.status, DeliveryStatus::Pending);
+        assert_eq!(delivery.attempts, 1);
+        assert!(delivery.next_attempt.is_some());
+        assert!(delivery.error.is_some());
+        assert_eq!(
+            *templates.rendered.lock().expect("Poisoned lock"),
+            vec!["mail_key_add".to_string()]
+        );
+    }
+}
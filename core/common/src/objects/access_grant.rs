@@ -0,0 +1,199 @@
+use crate::{
+    chrono::{Duration, NaiveDate, NaiveDateTime},
+    serde::Serialize,
+    types::Id,
+};
+use std::{borrow::Cow, cmp};
+
+/// The period in which an access grant is effective. Both ends are
+/// optional, a grant without any is effective forever
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Serialize)]
+pub struct AccessValidity {
+    /// The date and time from which on access is granted
+    pub valid_from: Option<NaiveDateTime>,
+    /// The date and time until which access is granted
+    pub valid_until: Option<NaiveDateTime>,
+}
+
+impl AccessValidity {
+    /// Parses a date in the format `%Y-%m-%dT%H:%M` as sent by
+    /// `datetime-local` inputs. A plain `%Y-%m-%d` refers to the start of
+    /// the day. Times are in UTC
+    #[must_use]
+    #[inline]
+    pub fn parse_date(value: &str) -> Option<NaiveDateTime> {
+        let value = value.trim();
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+            .or_else(|_| {
+                NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                    .map(|date| date.and_hms(0, 0, 0))
+            })
+            .ok()
+    }
+
+    /// Checks whether the period ends after it starts
+    #[must_use]
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        match (self.valid_from, self.valid_until) {
+            (Some(from), Some(until)) => from < until,
+            _ => true,
+        }
+    }
+
+    /// Checks whether access is granted at the given date
+    #[must_use]
+    #[inline]
+    pub fn is_active(&self, now: NaiveDateTime) -> bool {
+        self.valid_from.map_or(true, |from| from <= now)
+            && self.valid_until.map_or(true, |until| now < until)
+    }
+
+    /// Checks whether access ended at the given date. A period without an
+    /// end never ends
+    #[must_use]
+    #[inline]
+    pub fn has_ended(&self, now: NaiveDateTime) -> bool {
+        self.valid_until.map_or(false, |until| until <= now)
+    }
+
+    /// The time left until access ends. Returns `None` if access does not
+    /// end and a zero duration if it already ended
+    #[must_use]
+    #[inline]
+    pub fn remaining(&self, now: NaiveDateTime) -> Option<Duration> {
+        self.valid_until
+            .map(|until| cmp::max(until - now, Duration::zero()))
+    }
+
+    /// Combines the periods of two grants to the same account. Access is
+    /// granted as long as any of the grants is effective, so the earliest
+    /// start and the latest end win
+    #[must_use]
+    #[inline]
+    pub fn union(self, other: Self) -> Self {
+        let valid_from = match (self.valid_from, other.valid_from) {
+            (Some(a), Some(b)) => Some(cmp::min(a, b)),
+            _ => None,
+        };
+        let valid_until = match (self.valid_until, other.valid_until) {
+            (Some(a), Some(b)) => Some(cmp::max(a, b)),
+            _ => None,
+        };
+        Self {
+            valid_from,
+            valid_until,
+        }
+    }
+}
+
+/// A time-bound access grant which started or ended and whose server
+/// account needs to be synced again
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct AccessTransition<'a> {
+    /// The id of the access grant
+    pub access_id: Cow<'a, Id>,
+    /// The entity which is granted access
+    pub source_id: Cow<'a, Id>,
+    /// The server the account belongs to
+    pub server_id: Cow<'a, Id>,
+    /// The server account the grant refers to
+    pub account_id: Cow<'a, Id>,
+    /// The period in which the grant is effective
+    pub validity: AccessValidity,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AccessValidity;
+    use crate::chrono::{Duration, NaiveDate};
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(
+            AccessValidity::parse_date("2020-11-24T08:30"),
+            Some(NaiveDate::from_ymd(2020, 11, 24).and_hms(8, 30, 0))
+        );
+        assert_eq!(
+            AccessValidity::parse_date(" 2020-11-24 "),
+            Some(NaiveDate::from_ymd(2020, 11, 24).and_hms(0, 0, 0))
+        );
+        assert_eq!(AccessValidity::parse_date("24.11.2020"), None);
+    }
+
+    #[test]
+    fn test_is_active() {
+        let from = NaiveDate::from_ymd(2020, 11, 24).and_hms(8, 0, 0);
+        let until = NaiveDate::from_ymd(2020, 11, 25).and_hms(8, 0, 0);
+        let validity = AccessValidity {
+            valid_from: Some(from),
+            valid_until: Some(until),
+        };
+        assert!(validity.is_valid());
+        assert!(!validity.is_active(from - Duration::seconds(1)));
+        assert!(validity.is_active(from));
+        assert!(validity.is_active(until - Duration::seconds(1)));
+        assert!(!validity.is_active(until));
+        assert!(AccessValidity::default().is_active(until));
+
+        let reversed = AccessValidity {
+            valid_from: Some(until),
+            valid_until: Some(from),
+        };
+        assert!(!reversed.is_valid());
+    }
+
+    #[test]
+    fn test_has_ended() {
+        let until = NaiveDate::from_ymd(2020, 11, 25).and_hms(8, 0, 0);
+        let validity = AccessValidity {
+            valid_from: None,
+            valid_until: Some(until),
+        };
+        assert!(!validity.has_ended(until - Duration::seconds(1)));
+        assert!(validity.has_ended(until));
+        assert!(!AccessValidity::default().has_ended(until));
+    }
+
+    #[test]
+    fn test_remaining() {
+        let until = NaiveDate::from_ymd(2020, 11, 25).and_hms(8, 0, 0);
+        let validity = AccessValidity {
+            valid_from: None,
+            valid_until: Some(until),
+        };
+        assert_eq!(
+            validity.remaining(until - Duration::hours(3)),
+            Some(Duration::hours(3))
+        );
+        assert_eq!(
+            validity.remaining(until + Duration::hours(3)),
+            Some(Duration::zero())
+        );
+        assert_eq!(AccessValidity::default().remaining(until), None);
+    }
+
+    #[test]
+    fn test_union() {
+        let day = |d| NaiveDate::from_ymd(2020, 11, d).and_hms(0, 0, 0);
+        let a = AccessValidity {
+            valid_from: Some(day(1)),
+            valid_until: Some(day(10)),
+        };
+        let b = AccessValidity {
+            valid_from: Some(day(5)),
+            valid_until: Some(day(20)),
+        };
+        assert_eq!(
+            a.union(b),
+            AccessValidity {
+                valid_from: Some(day(1)),
+                valid_until: Some(day(20)),
+            }
+        );
+        assert_eq!(
+            a.union(AccessValidity::default()),
+            AccessValidity::default()
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{
+    chrono::NaiveDateTime,
+    objects::{AccessValidity, ServerLogin},
+    serde::Serialize,
+    types::{AccessRequestStatus, Id},
+};
+use std::borrow::Cow;
+
+/// A request of an entity for access to a server account
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct AccessRequest<'a> {
+    /// The id which uniquely identifies the request
+    pub id: Cow<'a, Id>,
+    /// The entity which requests access
+    pub source_id: Cow<'a, Id>,
+    /// The server account access is requested to
+    pub dest_id: Cow<'a, Id>,
+    /// Why access is required
+    pub reason: Option<Cow<'a, str>>,
+    /// The period for which access is requested
+    pub validity: AccessValidity,
+    /// Whether the request was decided and how
+    pub status: AccessRequestStatus,
+    /// The date and time when access was requested
+    pub request_date: Option<NaiveDateTime>,
+    /// The entity which decided the request. `None` if the request is
+    /// pending or was approved automatically
+    pub decided_by: Option<Cow<'a, Id>>,
+    /// The date and time when the request was decided
+    pub decision_date: Option<NaiveDateTime>,
+}
+
+impl<'a> AccessRequest<'a> {
+    /// Records the decision on a pending request. Returns `false` without
+    /// changing the request if it was already decided
+    #[inline]
+    pub fn decide(
+        &mut self,
+        approve: bool,
+        decided_by: Option<Cow<'a, Id>>,
+        now: NaiveDateTime,
+    ) -> bool {
+        if self.status != AccessRequestStatus::Pending {
+            return false;
+        }
+        self.status = if approve {
+            AccessRequestStatus::Approved
+        } else {
+            AccessRequestStatus::Rejected
+        };
+        self.decided_by = decided_by;
+        self.decision_date = Some(now);
+        true
+    }
+}
+
+/// An access request together with the information required to decide on
+/// it
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct AccessRequestEntry<'a> {
+    /// The request itself
+    pub request: AccessRequest<'a>,
+    /// The uid of the requesting user. `None` if the requesting entity is
+    /// not a user
+    pub requester: Option<Cow<'a, str>>,
+    /// The server account access is requested to
+    pub login: ServerLogin<'a>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AccessRequest;
+    use crate::{
+        chrono::NaiveDate,
+        objects::AccessValidity,
+        types::{AccessRequestStatus, Id},
+    };
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_decide() {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        let mut request = AccessRequest {
+            id: Cow::Borrowed(&id),
+            source_id: Cow::Borrowed(&id),
+            dest_id: Cow::Borrowed(&id),
+            reason: None,
+            validity: AccessValidity::default(),
+            status: AccessRequestStatus::Pending,
+            request_date: None,
+            decided_by: None,
+            decision_date: None,
+        };
+        let now = NaiveDate::from_ymd(2020, 11, 17).and_hms(12, 0, 0);
+        assert!(request.decide(false, Some(Cow::Borrowed(&id)), now));
+        assert_eq!(request.status, AccessRequestStatus::Rejected);
+        assert_eq!(request.decided_by.as_deref(), Some(&id));
+        assert_eq!(request.decision_date, Some(now));
+
+        let later = NaiveDate::from_ymd(2020, 11, 18).and_hms(12, 0, 0);
+        assert!(!request.decide(true, None, later));
+        assert_eq!(request.status, AccessRequestStatus::Rejected);
+        assert_eq!(request.decision_date, Some(now));
+    }
+}
@@ -0,0 +1,16 @@
+use crate::{chrono::NaiveDateTime, serde::Serialize, types::Id};
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Hash, Serialize)]
+/// Defines an api token a user created. The signed token only carries the
+/// id, so removing the token revokes it
+pub struct ApiToken<'a> {
+    /// The id which uniquely identifies the token
+    pub id: Cow<'a, Id>,
+    /// The user the token authenticates
+    pub entity_id: Cow<'a, Id>,
+    /// The date and time after which the token is rejected
+    pub valid_until: NaiveDateTime,
+    /// The date and time when the token was created
+    pub created: NaiveDateTime,
+}
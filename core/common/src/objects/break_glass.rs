@@ -0,0 +1,155 @@
+use crate::{
+    chrono::{Duration, NaiveDateTime},
+    objects::{AccessValidity, ServerLogin},
+    serde::Serialize,
+    types::Id,
+};
+use std::borrow::Cow;
+
+/// Shortest emergency access in hours
+pub const BREAK_GLASS_MIN_HOURS: i64 = 1;
+/// Longest emergency access in hours
+pub const BREAK_GLASS_MAX_HOURS: i64 = 4;
+
+/// Emergency access to a server account which an entity took without
+/// approval. Access starts immediately, ends after a few hours and is
+/// reviewed after the fact
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct BreakGlass<'a> {
+    /// The id which uniquely identifies the emergency access
+    pub id: Cow<'a, Id>,
+    /// The entity which took emergency access
+    pub source_id: Cow<'a, Id>,
+    /// The server account access was taken to
+    pub dest_id: Cow<'a, Id>,
+    /// Why emergency access was required
+    pub reason: Cow<'a, str>,
+    /// The date and time when access started
+    pub start_date: NaiveDateTime,
+    /// The date and time when access ends
+    pub valid_until: NaiveDateTime,
+    /// The entity which reviewed the emergency access
+    pub reviewed_by: Option<Cow<'a, Id>>,
+    /// The date and time of the review
+    pub review_date: Option<NaiveDateTime>,
+}
+
+impl<'a> BreakGlass<'a> {
+    /// Creates an emergency access which starts now and lasts for the
+    /// given duration
+    #[must_use]
+    #[inline]
+    pub fn new(
+        id: Cow<'a, Id>,
+        source_id: Cow<'a, Id>,
+        dest_id: Cow<'a, Id>,
+        reason: Cow<'a, str>,
+        duration: Duration,
+        now: NaiveDateTime,
+    ) -> Self {
+        Self {
+            id,
+            source_id,
+            dest_id,
+            reason,
+            start_date: now,
+            valid_until: now + duration,
+            reviewed_by: None,
+            review_date: None,
+        }
+    }
+
+    /// Parses the duration of an emergency access given in hours. Returns
+    /// `None` if it is outside of `BREAK_GLASS_MIN_HOURS` and
+    /// `BREAK_GLASS_MAX_HOURS`
+    #[must_use]
+    #[inline]
+    pub fn parse_duration(value: &str) -> Option<Duration> {
+        value
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .filter(|hours| {
+                (BREAK_GLASS_MIN_HOURS..=BREAK_GLASS_MAX_HOURS).contains(hours)
+            })
+            .map(Duration::hours)
+    }
+
+    /// The period in which access is granted
+    #[must_use]
+    #[inline]
+    pub fn validity(&self) -> AccessValidity {
+        AccessValidity {
+            valid_from: Some(self.start_date),
+            valid_until: Some(self.valid_until),
+        }
+    }
+
+    /// Records the review. Returns `false` without changing the emergency
+    /// access if it was already reviewed
+    #[inline]
+    pub fn review(&mut self, reviewed_by: Cow<'a, Id>, now: NaiveDateTime) -> bool {
+        if self.reviewed_by.is_some() || self.review_date.is_some() {
+            return false;
+        }
+        self.reviewed_by = Some(reviewed_by);
+        self.review_date = Some(now);
+        true
+    }
+}
+
+/// An emergency access together with the information required to review
+/// it
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct BreakGlassEntry<'a> {
+    /// The emergency access itself
+    pub break_glass: BreakGlass<'a>,
+    /// The uid of the user which took emergency access. `None` if the
+    /// entity is not a user
+    pub requester: Option<Cow<'a, str>>,
+    /// The server account access was taken to
+    pub login: ServerLogin<'a>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BreakGlass;
+    use crate::{
+        chrono::{Duration, NaiveDate},
+        types::Id,
+    };
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(BreakGlass::parse_duration("1"), Some(Duration::hours(1)));
+        assert_eq!(BreakGlass::parse_duration(" 4 "), Some(Duration::hours(4)));
+        assert_eq!(BreakGlass::parse_duration("0"), None);
+        assert_eq!(BreakGlass::parse_duration("5"), None);
+        assert_eq!(BreakGlass::parse_duration("two"), None);
+    }
+
+    #[test]
+    fn test_review() {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        let now = NaiveDate::from_ymd(2020, 12, 1).and_hms(3, 0, 0);
+        let mut break_glass = BreakGlass::new(
+            Cow::Borrowed(&id),
+            Cow::Borrowed(&id),
+            Cow::Borrowed(&id),
+            Cow::Borrowed("Database outage"),
+            Duration::hours(2),
+            now,
+        );
+        let validity = break_glass.validity();
+        assert!(validity.is_active(now));
+        assert!(!validity.is_active(now + Duration::hours(2)));
+
+        let later = now + Duration::days(1);
+        assert!(break_glass.review(Cow::Borrowed(&id), later));
+        assert_eq!(break_glass.review_date, Some(later));
+        assert!(!break_glass.review(Cow::Borrowed(&id), later + Duration::days(1)));
+        assert_eq!(break_glass.review_date, Some(later));
+    }
+}
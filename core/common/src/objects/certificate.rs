@@ -0,0 +1,884 @@
+use crate::{
+    base64,
+    chrono::{Duration, NaiveDateTime},
+    objects::{key_format, PublicKey},
+    ring::{
+        rand::{SecureRandom, SystemRandom},
+        signature::{Ed25519KeyPair, KeyPair},
+    },
+    sec::{decrypt, encrypt, SecretError},
+    serde::Serialize,
+    types::{AccessOption, CertificateType, Id},
+};
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashSet},
+    convert::TryFrom,
+    error, fmt,
+};
+
+/// Purpose used to derive the key which encrypts the private keys
+const CA_SECRET_PURPOSE: &str = "certificate-authority";
+/// Key type of the certificate authorities
+const CA_KEY_TYPE: &str = "ssh-ed25519";
+/// Suffix openssh adds to the key type of certificates
+const CERT_SUFFIX: &str = "-cert-v01@openssh.com";
+/// Time a certificate is valid before it was issued to tolerate clock skew
+const CLOCK_SKEW_MINUTES: i64 = 5;
+/// Default number of hours a certificate is valid
+pub const DEFAULT_CERTIFICATE_VALIDITY: u32 = 8;
+/// Default maximum number of hours a certificate may be valid
+pub const DEFAULT_CERTIFICATE_MAX_VALIDITY: u32 = 24;
+/// Default number of days a host certificate is valid
+pub const DEFAULT_HOST_CERTIFICATE_VALIDITY: u32 = 30;
+
+/// Error while issuing a certificate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertificateError {
+    /// The public key cannot be parsed
+    InvalidKey,
+    /// The public key is expired
+    Expired,
+    /// The owner of the public key has no access to any server account
+    NoPrincipals,
+    /// The owner of the public key only has access to server accounts which
+    /// require keys backed by a hardware security key
+    SecurityKeyRequired,
+    /// The host key does not match the fingerprint stored for the server
+    UnknownHostKey,
+    /// The access grants use different values for the given option. A
+    /// certificate can only contain one
+    ConflictingOption(AccessOption),
+    /// The private key of the authority cannot be decrypted or used
+    Secret(SecretError),
+    /// Unable to generate or use a signing key
+    Crypto,
+}
+
+impl CertificateError {
+    /// Name of the parameter which prevents the issuance. Used for
+    /// notifications
+    #[must_use]
+    #[inline]
+    pub const fn para(&self) -> &'static str {
+        match self {
+            Self::InvalidKey => "Public Key Data",
+            Self::Expired => "Expiry date",
+            Self::NoPrincipals => "Access",
+            Self::SecurityKeyRequired => "Key type",
+            Self::UnknownHostKey => "Host key",
+            Self::ConflictingOption(_) => "Access options",
+            Self::Secret(_) | Self::Crypto => "Certificate authority",
+        }
+    }
+}
+
+impl fmt::Display for CertificateError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidKey => write!(f, "Public key cannot be certified"),
+            Self::Expired => write!(f, "Public key is expired"),
+            Self::NoPrincipals => write!(f, "No access to any server account"),
+            Self::SecurityKeyRequired => write!(
+                f,
+                "Server accounts only accept keys backed by a security key"
+            ),
+            Self::UnknownHostKey => {
+                write!(f, "Host key does not match the server fingerprint")
+            }
+            Self::ConflictingOption(option) => {
+                write!(f, "Access grants use different {:?} options", option)
+            }
+            Self::Secret(err) => err.fmt(f),
+            Self::Crypto => write!(f, "Unable to create signing key"),
+        }
+    }
+}
+
+impl error::Error for CertificateError {}
+
+impl From<SecretError> for CertificateError {
+    #[inline]
+    fn from(err: SecretError) -> Self {
+        Self::Secret(err)
+    }
+}
+
+/// Limits the validity of issued certificates
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize)]
+pub struct CertificatePolicy {
+    /// Number of hours a certificate is valid if not requested otherwise
+    pub default_validity: u32,
+    /// Maximum number of hours a certificate may be valid
+    pub max_validity: u32,
+    /// Number of days a host certificate is valid
+    pub host_validity: u32,
+}
+
+impl Default for CertificatePolicy {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            default_validity: DEFAULT_CERTIFICATE_VALIDITY,
+            max_validity: DEFAULT_CERTIFICATE_MAX_VALIDITY,
+            host_validity: DEFAULT_HOST_CERTIFICATE_VALIDITY,
+        }
+    }
+}
+
+impl CertificatePolicy {
+    /// Returns the validity period for a certificate issued now. The
+    /// requested number of hours is limited to the maximum validity and
+    /// the expiry date of the key
+    ///
+    /// # Errors
+    /// Fails if the key is already expired
+    #[inline]
+    pub fn validity(
+        &self,
+        key: &PublicKey<'_>,
+        requested: Option<u32>,
+        now: NaiveDateTime,
+    ) -> Result<(NaiveDateTime, NaiveDateTime), CertificateError> {
+        if key.is_expired(now) {
+            return Err(CertificateError::Expired);
+        }
+        let hours = requested
+            .unwrap_or(self.default_validity)
+            .min(self.max_validity)
+            .max(1);
+        let mut valid_before = now + Duration::hours(i64::from(hours));
+        if let Some(expires) = key.expires {
+            valid_before = valid_before.min(expires);
+        }
+        Ok((now - Duration::minutes(CLOCK_SKEW_MINUTES), valid_before))
+    }
+
+    /// Returns the validity period for a host certificate issued now
+    #[must_use]
+    #[inline]
+    pub fn validity_for_host(
+        &self,
+        now: NaiveDateTime,
+    ) -> (NaiveDateTime, NaiveDateTime) {
+        let days = i64::from(self.host_validity.max(1));
+        (
+            now - Duration::minutes(CLOCK_SKEW_MINUTES),
+            now + Duration::days(days),
+        )
+    }
+}
+
+/// Returns the principal granting access to an account on a server.
+/// Principals include the hostname so that a certificate only grants access
+/// to the accounts its owner has access to and not to accounts of the same
+/// name on other servers
+#[must_use]
+#[inline]
+pub fn principal_name(account: &str, hostname: &str) -> String {
+    format!("{}@{}", account, hostname)
+}
+
+/// A server account an entity has access to together with the options of
+/// the access grant
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+pub struct CertificatePrincipal<'a> {
+    /// Principal of the server account, see `principal_name`
+    pub name: Cow<'a, str>,
+    /// Options of the access grant
+    pub options: Vec<(AccessOption, Option<Cow<'a, str>>)>,
+    /// Whether the server only accepts keys backed by a hardware security
+    /// key
+    pub require_security_key: bool,
+    /// The time the access grant ends, if it is time-bound
+    pub valid_until: Option<NaiveDateTime>,
+}
+
+/// The principals accepted for a server account. Servers using
+/// `KeyManagement::Certificates` receive this list as
+/// `AuthorizedPrincipalsFile` instead of an `authorized_keys` file
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+pub struct AuthorizedPrincipals<'a> {
+    /// Name of the server account
+    pub account: Cow<'a, str>,
+    /// Principals accepted for the account. Empty if nobody has access
+    pub principals: Vec<Cow<'a, str>>,
+}
+
+impl AuthorizedPrincipals<'_> {
+    /// Renders the list in the `AuthorizedPrincipalsFile` format
+    #[must_use]
+    #[inline]
+    pub fn to_file(&self) -> String {
+        self.principals
+            .iter()
+            .map(|principal| format!("{}\n", principal))
+            .collect()
+    }
+}
+
+/// The host certificate most recently issued for a server
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+pub struct HostCertificate<'a> {
+    /// The server the certificate was issued for
+    pub server_id: Cow<'a, Id>,
+    /// The serial number of the certificate
+    pub serial: u64,
+    /// The certificate in the openssh `authorized_keys` format
+    pub certificate: Cow<'a, str>,
+    /// The time from which the certificate is valid
+    pub valid_after: NaiveDateTime,
+    /// The time until which the certificate is valid
+    pub valid_before: NaiveDateTime,
+}
+
+impl HostCertificate<'_> {
+    /// Whether the certificate should be renewed. This is the case once
+    /// less than a quarter of its validity period remains
+    #[must_use]
+    #[inline]
+    pub fn needs_renewal(&self, now: NaiveDateTime) -> bool {
+        let remaining = (self.valid_before - self.valid_after) / 4;
+        now >= self.valid_before - remaining
+    }
+}
+
+/// Content of a certificate which is about to be signed
+#[derive(Debug, Clone)]
+pub struct CertificateRequest<'a> {
+    /// The public key to certify
+    pub public_key: &'a PublicKey<'a>,
+    /// Identifier logged by the server when the certificate is used
+    pub key_id: String,
+    /// Names the certificate is valid for. User names for user
+    /// certificates and host names for host certificates
+    pub principals: Vec<String>,
+    /// The time from which the certificate is valid
+    pub valid_after: NaiveDateTime,
+    /// The time until which the certificate is valid
+    pub valid_before: NaiveDateTime,
+    /// Critical options sorted by name
+    pub critical_options: Vec<(&'static str, String)>,
+    /// Extensions sorted by name
+    pub extensions: Vec<&'static str>,
+}
+
+impl<'a> CertificateRequest<'a> {
+    /// Creates a user certificate request for the given grants. The
+    /// restrictions of all grants are combined so that the certificate is
+    /// never less restrictive than one of them. Environment options are
+    /// ignored as certificates cannot set environment variables. Grants of
+    /// servers which require security keys are skipped for other keys. The
+    /// certificate expires once the first of the included grants ends
+    ///
+    /// # Errors
+    /// Fails if there are no grants or the grants use different commands
+    /// or source addresses
+    #[inline]
+    pub fn for_user(
+        public_key: &'a PublicKey<'a>,
+        key_id: String,
+        grants: &[CertificatePrincipal<'_>],
+        valid_after: NaiveDateTime,
+        valid_before: NaiveDateTime,
+    ) -> Result<Self, CertificateError> {
+        let security_key = public_key.is_security_key();
+        let (grants, skipped): (Vec<_>, Vec<_>) = grants
+            .iter()
+            .partition(|grant| security_key || !grant.require_security_key);
+        let principals: BTreeSet<String> =
+            grants.iter().map(|grant| grant.name.to_string()).collect();
+        if principals.is_empty() {
+            return Err(if skipped.is_empty() {
+                CertificateError::NoPrincipals
+            } else {
+                CertificateError::SecurityKeyRequired
+            });
+        }
+        let valid_before = grants
+            .iter()
+            .filter_map(|grant| grant.valid_until)
+            .fold(valid_before, Ord::min);
+        let mut restrictions = HashSet::new();
+        let mut command = BTreeSet::new();
+        let mut from = BTreeSet::new();
+        for (option, value) in grants.iter().flat_map(|grant| &grant.options) {
+            match option {
+                AccessOption::Command => {
+                    command.extend(value.as_deref().map(String::from));
+                }
+                AccessOption::From => {
+                    from.extend(value.as_deref().map(String::from));
+                }
+                AccessOption::Environment => {}
+                AccessOption::NoAgentForwarding
+                | AccessOption::NoPortForwarding
+                | AccessOption::NoPty
+                | AccessOption::NoX11Forwarding
+                | AccessOption::NoUserRc => {
+                    let _ = restrictions.insert(*option);
+                }
+            }
+        }
+        let mut critical_options = Vec::new();
+        for (name, option, values) in &[
+            ("force-command", AccessOption::Command, command),
+            ("source-address", AccessOption::From, from),
+        ] {
+            let mut values = values.iter();
+            if let Some(value) = values.next() {
+                if values.next().is_some() {
+                    return Err(CertificateError::ConflictingOption(*option));
+                }
+                critical_options.push((*name, value.clone()));
+            }
+        }
+        let extensions = [
+            ("permit-X11-forwarding", AccessOption::NoX11Forwarding),
+            ("permit-agent-forwarding", AccessOption::NoAgentForwarding),
+            ("permit-port-forwarding", AccessOption::NoPortForwarding),
+            ("permit-pty", AccessOption::NoPty),
+            ("permit-user-rc", AccessOption::NoUserRc),
+        ]
+        .iter()
+        .filter(|(_, option)| !restrictions.contains(option))
+        .map(|(name, _)| *name)
+        .collect();
+        Ok(Self {
+            public_key,
+            key_id,
+            principals: principals.into_iter().collect(),
+            valid_after,
+            valid_before,
+            critical_options,
+            extensions,
+        })
+    }
+
+    /// Creates a request for a host certificate. Host certificates contain
+    /// neither options nor extensions
+    #[must_use]
+    #[inline]
+    pub fn for_host(
+        public_key: &'a PublicKey<'a>,
+        key_id: String,
+        principals: Vec<String>,
+        valid_after: NaiveDateTime,
+        valid_before: NaiveDateTime,
+    ) -> Self {
+        Self {
+            public_key,
+            key_id,
+            principals,
+            valid_after,
+            valid_before,
+            critical_options: Vec::new(),
+            extensions: Vec::new(),
+        }
+    }
+}
+
+/// A certificate issued by a `CertificateAuthority`
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+pub struct SignedCertificate {
+    /// The serial number of the certificate
+    pub serial: u64,
+    /// The certificate in the openssh `authorized_keys` format
+    pub line: String,
+}
+
+/// An openssh certificate authority. The private key is stored encrypted
+/// with the app secret
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct CertificateAuthority<'a> {
+    /// The id which uniquely identifies the authority
+    pub id: Cow<'a, Id>,
+    /// The type of certificates the authority signs
+    pub type_: CertificateType,
+    /// The public key in the openssh `authorized_keys` format
+    pub public_key: Cow<'a, str>,
+    /// The encrypted private key
+    #[serde(skip)]
+    pub private_key: Cow<'a, [u8]>,
+    /// The time when the authority was created
+    pub created: Option<NaiveDateTime>,
+}
+
+impl<'a> CertificateAuthority<'a> {
+    /// Generates a new ed25519 authority
+    ///
+    /// # Errors
+    /// Fails if no random data is available
+    #[inline]
+    pub fn generate(
+        id: Cow<'a, Id>,
+        type_: CertificateType,
+        app_secret: &[u8; 32],
+    ) -> Result<Self, CertificateError> {
+        let rng = SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng)
+            .map_err(|_| CertificateError::Crypto)?;
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref())
+            .map_err(|_| CertificateError::Crypto)?;
+        let comment = match type_ {
+            CertificateType::User => "ssh-key-manager-user-ca",
+            CertificateType::Host => "ssh-key-manager-host-ca",
+        };
+        let public_key = format!(
+            "{} {} {}",
+            CA_KEY_TYPE,
+            base64::encode(&ca_blob(&pair)?),
+            comment
+        );
+        let private_key = encrypt(app_secret, CA_SECRET_PURPOSE, pkcs8.as_ref())?;
+        Ok(Self {
+            id,
+            type_,
+            public_key: Cow::Owned(public_key),
+            private_key: Cow::Owned(private_key),
+            created: None,
+        })
+    }
+
+    /// Signs the request using a random serial number
+    ///
+    /// # Errors
+    /// Fails if the private key cannot be decrypted or the public key of
+    /// the request cannot be parsed
+    #[inline]
+    pub fn sign(
+        &self,
+        app_secret: &[u8; 32],
+        request: &CertificateRequest<'_>,
+    ) -> Result<SignedCertificate, CertificateError> {
+        let pkcs8 = decrypt(app_secret, CA_SECRET_PURPOSE, &self.private_key)?;
+        let pair = Ed25519KeyPair::from_pkcs8(&pkcs8)
+            .map_err(|_| CertificateError::Crypto)?;
+        let key = request.public_key;
+        let blob = base64::decode(key.keydata.as_bytes())
+            .map_err(|_| CertificateError::InvalidKey)?;
+        let mut fields = blob.as_slice();
+        if key_format::read_string(&mut fields) != Some(key.type_.as_bytes()) {
+            return Err(CertificateError::InvalidKey);
+        }
+        let cert_type = match key.type_.strip_suffix("@openssh.com") {
+            Some(type_) => format!("{}{}", type_, CERT_SUFFIX),
+            None => format!("{}{}", key.type_, CERT_SUFFIX),
+        };
+        let rng = SystemRandom::new();
+        let mut nonce = [0_u8; 32];
+        let mut serial = [0_u8; 8];
+        rng.fill(&mut nonce).map_err(|_| CertificateError::Crypto)?;
+        rng.fill(&mut serial)
+            .map_err(|_| CertificateError::Crypto)?;
+
+        let mut cert = Vec::new();
+        write(&mut cert, cert_type.as_bytes())?;
+        write(&mut cert, &nonce)?;
+        cert.extend_from_slice(fields);
+        cert.extend_from_slice(&serial);
+        let type_: u32 = match self.type_ {
+            CertificateType::User => 1,
+            CertificateType::Host => 2,
+        };
+        cert.extend_from_slice(&type_.to_be_bytes());
+        write(&mut cert, request.key_id.as_bytes())?;
+        let mut principals = Vec::new();
+        for principal in &request.principals {
+            write(&mut principals, principal.as_bytes())?;
+        }
+        write(&mut cert, &principals)?;
+        cert.extend_from_slice(&timestamp(request.valid_after).to_be_bytes());
+        cert.extend_from_slice(&timestamp(request.valid_before).to_be_bytes());
+        let mut options = Vec::new();
+        for (name, value) in &request.critical_options {
+            let mut data = Vec::new();
+            write(&mut data, value.as_bytes())?;
+            write(&mut options, name.as_bytes())?;
+            write(&mut options, &data)?;
+        }
+        write(&mut cert, &options)?;
+        let mut extensions = Vec::new();
+        for name in &request.extensions {
+            write(&mut extensions, name.as_bytes())?;
+            write(&mut extensions, &[])?;
+        }
+        write(&mut cert, &extensions)?;
+        write(&mut cert, &[])?;
+        write(&mut cert, &ca_blob(&pair)?)?;
+        let mut signature = Vec::new();
+        write(&mut signature, CA_KEY_TYPE.as_bytes())?;
+        write(&mut signature, pair.sign(&cert).as_ref())?;
+        write(&mut cert, &signature)?;
+
+        let mut line = format!("{} {}", cert_type, base64::encode(&cert));
+        if let Some(ref comment) = key.comment {
+            line.push(' ');
+            line.push_str(comment);
+        }
+        Ok(SignedCertificate {
+            serial: u64::from_be_bytes(serial),
+            line,
+        })
+    }
+}
+
+/// Writes an ssh string
+fn write(buf: &mut Vec<u8>, value: &[u8]) -> Result<(), CertificateError> {
+    key_format::write_string(buf, value).ok_or(CertificateError::InvalidKey)
+}
+
+/// Returns the public key blob of the authority
+fn ca_blob(pair: &Ed25519KeyPair) -> Result<Vec<u8>, CertificateError> {
+    let mut blob = Vec::new();
+    write(&mut blob, CA_KEY_TYPE.as_bytes())?;
+    write(&mut blob, pair.public_key().as_ref())?;
+    Ok(blob)
+}
+
+/// Seconds since the epoch. Dates before the epoch are mapped to 0
+fn timestamp(date: NaiveDateTime) -> u64 {
+    u64::try_from(date.timestamp()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        principal_name, AuthorizedPrincipals, CertificateAuthority,
+        CertificateError, CertificatePolicy, CertificatePrincipal,
+        CertificateRequest, HostCertificate,
+    };
+    use crate::{
+        base64,
+        chrono::{Duration, NaiveDate},
+        objects::{key_format, PublicKey},
+        ring::signature::{UnparsedPublicKey, ED25519},
+        sec::SecretError,
+        types::{AccessOption, CertificateType, Id},
+    };
+    use std::borrow::Cow;
+
+    const KEYDATA: &str =
+        "AAAAC3NzaC1lZDI1NTE5AAAAIBs4kQzLi3HMJyW11u3cCAgbEBqJ2ZmI+KsKc1fH1uPB";
+
+    fn key() -> PublicKey<'static> {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        PublicKey {
+            id: Cow::Owned(id.clone()),
+            entity_id: Cow::Owned(id),
+            type_: Cow::Borrowed("ssh-ed25519"),
+            keydata: Cow::Borrowed(KEYDATA),
+            comment: Some(Cow::Borrowed("laptop")),
+            keysize: Some(256),
+            fingerprint_md5: None,
+            fingerprint_sha256: None,
+            randomart_md5: None,
+            randomart_sha256: None,
+            upload_date: None,
+            application: None,
+            expires: None,
+            deactivated: None,
+        }
+    }
+
+    fn grant(
+        name: &'static str,
+        options: &[(AccessOption, Option<&'static str>)],
+    ) -> CertificatePrincipal<'static> {
+        CertificatePrincipal {
+            name: Cow::Borrowed(name),
+            options: options
+                .iter()
+                .map(|(option, value)| (*option, value.map(Cow::Borrowed)))
+                .collect(),
+            require_security_key: false,
+            valid_until: None,
+        }
+    }
+
+    fn next<'a>(reader: &mut &'a [u8]) -> &'a [u8] {
+        key_format::read_string(reader).expect("Missing field")
+    }
+
+    #[test]
+    fn test_principals_file() {
+        let principal = principal_name("deploy", "web1.example.com");
+        assert_eq!(principal, "deploy@web1.example.com");
+        let mut principals = AuthorizedPrincipals {
+            account: Cow::Borrowed("deploy"),
+            principals: vec![Cow::Owned(principal)],
+        };
+        assert_eq!(principals.to_file(), "deploy@web1.example.com\n");
+        principals.principals.clear();
+        assert_eq!(principals.to_file(), "");
+    }
+
+    #[test]
+    fn test_options() {
+        let key = key();
+        let date = NaiveDate::from_ymd(2020, 10, 13).and_hms(12, 0, 0);
+        let grants = [
+            grant("root", &[(AccessOption::NoPty, None)]),
+            grant(
+                "deploy",
+                &[
+                    (AccessOption::Command, Some("/usr/bin/deploy")),
+                    (AccessOption::NoAgentForwarding, None),
+                    (AccessOption::Environment, Some("A=B")),
+                ],
+            ),
+            grant("root", &[]),
+        ];
+        let request =
+            CertificateRequest::for_user(&key, "id".into(), &grants, date, date)
+                .expect("Request");
+        assert_eq!(request.principals, vec!["deploy", "root"]);
+        assert_eq!(
+            request.critical_options,
+            vec![("force-command", "/usr/bin/deploy".into())]
+        );
+        assert_eq!(
+            request.extensions,
+            vec![
+                "permit-X11-forwarding",
+                "permit-port-forwarding",
+                "permit-user-rc"
+            ]
+        );
+
+        let conflict = [
+            grant("a", &[(AccessOption::From, Some("10.0.0.0/8"))]),
+            grant("b", &[(AccessOption::From, Some("192.168.0.0/16"))]),
+        ];
+        assert_eq!(
+            CertificateRequest::for_user(&key, "id".into(), &conflict, date, date)
+                .err(),
+            Some(CertificateError::ConflictingOption(AccessOption::From))
+        );
+        assert_eq!(
+            CertificateRequest::for_user(&key, "id".into(), &[], date, date).err(),
+            Some(CertificateError::NoPrincipals)
+        );
+    }
+
+    #[test]
+    fn test_security_key_required() {
+        let mut key = key();
+        let date = NaiveDate::from_ymd(2020, 10, 13).and_hms(12, 0, 0);
+        let mut root = grant("root", &[]);
+        root.require_security_key = true;
+        let grants = [root.clone(), grant("deploy", &[])];
+        let request =
+            CertificateRequest::for_user(&key, "id".into(), &grants, date, date)
+                .expect("Request");
+        assert_eq!(request.principals, vec!["deploy"]);
+        assert_eq!(
+            CertificateRequest::for_user(&key, "id".into(), &[root], date, date)
+                .err(),
+            Some(CertificateError::SecurityKeyRequired)
+        );
+
+        key.type_ = Cow::Borrowed("sk-ssh-ed25519@openssh.com");
+        let request =
+            CertificateRequest::for_user(&key, "id".into(), &grants, date, date)
+                .expect("Request");
+        assert_eq!(request.principals, vec!["deploy", "root"]);
+    }
+
+    #[test]
+    fn test_grant_validity() {
+        let key = key();
+        let after = NaiveDate::from_ymd(2020, 10, 13).and_hms(12, 0, 0);
+        let before = after + Duration::hours(8);
+        let mut temporary = grant("deploy", &[]);
+        temporary.valid_until = Some(after + Duration::hours(2));
+        let grants = [grant("root", &[]), temporary.clone()];
+        let request =
+            CertificateRequest::for_user(&key, "id".into(), &grants, after, before)
+                .expect("Request");
+        assert_eq!(request.valid_before, after + Duration::hours(2));
+
+        temporary.valid_until = Some(before + Duration::hours(2));
+        let request = CertificateRequest::for_user(
+            &key,
+            "id".into(),
+            &[temporary],
+            after,
+            before,
+        )
+        .expect("Request");
+        assert_eq!(request.valid_before, before);
+    }
+
+    #[test]
+    fn test_validity() {
+        let policy = CertificatePolicy {
+            default_validity: 8,
+            max_validity: 24,
+            host_validity: 30,
+        };
+        let now = NaiveDate::from_ymd(2020, 10, 13).and_hms(12, 0, 0);
+        let mut key = key();
+        let (after, before) = policy.validity(&key, None, now).expect("Validity");
+        assert_eq!(after, now - Duration::minutes(5));
+        assert_eq!(before, now + Duration::hours(8));
+        let (_, before) = policy.validity(&key, Some(48), now).expect("Validity");
+        assert_eq!(before, now + Duration::hours(24));
+        key.expires = Some(now + Duration::hours(2));
+        let (_, before) = policy.validity(&key, None, now).expect("Validity");
+        assert_eq!(before, now + Duration::hours(2));
+        key.expires = Some(now - Duration::hours(2));
+        assert_eq!(
+            policy.validity(&key, None, now),
+            Err(CertificateError::Expired)
+        );
+        let (after, before) = policy.validity_for_host(now);
+        assert_eq!(after, now - Duration::minutes(5));
+        assert_eq!(before, now + Duration::days(30));
+    }
+
+    #[test]
+    fn test_host_renewal() {
+        let id = Id::from_string("1ef4a2b8-4e1b-4a5f-9d0e-7c7b1b0d3a11")
+            .expect("Invalid Id");
+        let issued = NaiveDate::from_ymd(2020, 10, 1).and_hms(0, 0, 0);
+        let certificate = HostCertificate {
+            server_id: Cow::Owned(id),
+            serial: 1,
+            certificate: Cow::Borrowed(""),
+            valid_after: issued,
+            valid_before: issued + Duration::days(40),
+        };
+        assert!(!certificate.needs_renewal(issued + Duration::days(29)));
+        assert!(certificate.needs_renewal(issued + Duration::days(30)));
+        assert!(certificate.needs_renewal(issued + Duration::days(41)));
+    }
+
+    #[test]
+    fn test_sign() {
+        let secret = [3_u8; 32];
+        let id = Id::from_string("1ef4a2b8-4e1b-4a5f-9d0e-7c7b1b0d3a11")
+            .expect("Invalid Id");
+        let ca = CertificateAuthority::generate(
+            Cow::Owned(id),
+            CertificateType::User,
+            &secret,
+        )
+        .expect("Generate");
+        assert!(ca.public_key.starts_with("ssh-ed25519 "));
+
+        let key = key();
+        let after = NaiveDate::from_ymd(2020, 10, 13).and_hms(12, 0, 0);
+        let before = after + Duration::hours(8);
+        let grants = [grant("root", &[(AccessOption::NoPty, None)])];
+        let request = CertificateRequest::for_user(
+            &key,
+            "alice".into(),
+            &grants,
+            after,
+            before,
+        )
+        .expect("Request");
+        assert_eq!(
+            ca.sign(&[4_u8; 32], &request).map(|_| ()),
+            Err(CertificateError::Secret(SecretError::Invalid))
+        );
+        let signed = ca.sign(&secret, &request).expect("Sign");
+        let mut parts = signed.line.split(' ');
+        assert_eq!(parts.next(), Some("ssh-ed25519-cert-v01@openssh.com"));
+        let cert = base64::decode(parts.next().expect("Missing data"))
+            .expect("Invalid base64");
+        assert_eq!(parts.next(), Some("laptop"));
+
+        let mut reader = cert.as_slice();
+        assert_eq!(next(&mut reader), b"ssh-ed25519-cert-v01@openssh.com");
+        assert_eq!(next(&mut reader).len(), 32);
+        let subject = base64::decode(KEYDATA).expect("Invalid base64");
+        assert_eq!(next(&mut reader), subject.get(19..).expect("Short key"));
+        assert_eq!(reader.get(..8), Some(&signed.serial.to_be_bytes()[..]));
+        reader = reader.get(8..).expect("Missing serial");
+        assert_eq!(reader.get(..4), Some(&[0_u8, 0, 0, 1][..]));
+        reader = reader.get(4..).expect("Missing type");
+        assert_eq!(next(&mut reader), b"alice");
+        let mut principals = next(&mut reader);
+        assert_eq!(next(&mut principals), b"root");
+        assert!(principals.is_empty());
+        let valid_after = after.timestamp().to_be_bytes();
+        assert_eq!(reader.get(..8), Some(&valid_after[..]));
+        reader = reader.get(16..).expect("Missing validity");
+        assert!(next(&mut reader).is_empty());
+        let mut extensions = next(&mut reader);
+        let mut names = Vec::new();
+        while !extensions.is_empty() {
+            names.push(next(&mut extensions).to_vec());
+            assert!(next(&mut extensions).is_empty());
+        }
+        assert!(!names.contains(&b"permit-pty".to_vec()));
+        assert_eq!(names.len(), 4);
+        assert!(next(&mut reader).is_empty());
+        let mut ca_key = next(&mut reader);
+        assert_eq!(next(&mut ca_key), b"ssh-ed25519");
+        let ca_key = next(&mut ca_key).to_vec();
+
+        let message = cert
+            .len()
+            .checked_sub(reader.len())
+            .and_then(|len| cert.get(..len))
+            .expect("Missing signature");
+        let mut signature = next(&mut reader);
+        assert!(reader.is_empty());
+        assert_eq!(next(&mut signature), b"ssh-ed25519");
+        let signature = next(&mut signature);
+        assert!(UnparsedPublicKey::new(&ED25519, ca_key)
+            .verify(message, signature)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_sign_host() {
+        let secret = [3_u8; 32];
+        let id = Id::from_string("1ef4a2b8-4e1b-4a5f-9d0e-7c7b1b0d3a11")
+            .expect("Invalid Id");
+        let ca = CertificateAuthority::generate(
+            Cow::Owned(id),
+            CertificateType::Host,
+            &secret,
+        )
+        .expect("Generate");
+        assert!(ca.public_key.ends_with(" ssh-key-manager-host-ca"));
+
+        let key = key();
+        let after = NaiveDate::from_ymd(2020, 10, 13).and_hms(12, 0, 0);
+        let request = CertificateRequest::for_host(
+            &key,
+            "web1.example.com".into(),
+            vec!["web1.example.com".into(), "10.0.0.1".into()],
+            after,
+            after + Duration::days(30),
+        );
+        let signed = ca.sign(&secret, &request).expect("Sign");
+        let cert = signed
+            .line
+            .split(' ')
+            .nth(1)
+            .and_then(|data| base64::decode(data).ok())
+            .expect("Invalid certificate");
+
+        let mut reader = cert.as_slice();
+        let _ = next(&mut reader);
+        let _ = next(&mut reader);
+        let _ = next(&mut reader);
+        reader = reader.get(8..).expect("Missing serial");
+        assert_eq!(reader.get(..4), Some(&[0_u8, 0, 0, 2][..]));
+        reader = reader.get(4..).expect("Missing type");
+        assert_eq!(next(&mut reader), b"web1.example.com");
+        let mut principals = next(&mut reader);
+        assert_eq!(next(&mut principals), b"web1.example.com");
+        assert_eq!(next(&mut principals), b"10.0.0.1");
+        reader = reader.get(16..).expect("Missing validity");
+        assert!(next(&mut reader).is_empty());
+        assert!(next(&mut reader).is_empty());
+    }
+}
@@ -0,0 +1,15 @@
+use crate::{serde::Serialize, types::Id};
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Hash, Serialize)]
+/// A user who can be reached by mail
+pub struct Contact<'a> {
+    /// The id of the user
+    pub entity_id: Cow<'a, Id>,
+    /// The uid of the user
+    pub uid: Cow<'a, str>,
+    /// The name of the user
+    pub name: Option<Cow<'a, str>>,
+    /// The email of the user
+    pub email: Cow<'a, str>,
+}
@@ -1,9 +1,17 @@
 use crate::{
-    chrono::NaiveDateTime,
-    serde::Serialize,
-    types::{EventTypes, Id},
+    chrono::{NaiveDate, NaiveDateTime},
+    serde::{Deserialize, Serialize},
+    serde_json::{self, Value},
+    types::{
+        AccessRequestStatus, ContactRecipients, EventSeverity, EventTypes, Id,
+        SyncStatusType,
+    },
 };
-use std::borrow::Cow;
+use std::{borrow::Cow, convert::TryFrom};
+
+/// Version written into the serialized `EventDetails`. Must be increased
+/// whenever a variant changes in an incompatible way.
+pub const EVENT_DETAILS_VERSION: u64 = 1;
 
 /// Defines an Event which description an Action take by a user
 /// inside the Application
@@ -16,11 +24,510 @@ pub struct Event<'a> {
     /// The date and time when the change was registered
     pub date: Option<NaiveDateTime>,
     /// Further details about the change
-    pub details: Cow<'a, str>,
+    pub details: EventDetails<'a>,
     /// The type of the object the change was made on
     pub type_: EventTypes,
     /// The id of the object the change was made on
     pub object_id: Option<Cow<'a, Id>>,
+    /// How urgently the event should be looked at
+    pub severity: EventSeverity,
+}
+
+impl<'a> Event<'a> {
+    /// Creates a new Event. The type and the severity of the event are
+    /// derived from the details
+    #[must_use]
+    #[inline]
+    pub fn new(
+        id: Cow<'a, Id>,
+        actor_id: Option<Cow<'a, Id>>,
+        details: EventDetails<'a>,
+        object_id: Option<Cow<'a, Id>>,
+    ) -> Self {
+        Self {
+            id,
+            actor_id,
+            date: None,
+            type_: details.event_type(),
+            severity: details.severity(),
+            details,
+            object_id,
+        }
+    }
+}
+
+/// Typed information about the action an Event describes
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum EventDetails<'a> {
+    /// A public key was added to an entity
+    KeyAdd {
+        /// Id of the public key
+        key_id: Cow<'a, Id>,
+        /// Md5 fingerprint of the public key
+        fingerprint: Option<Cow<'a, str>>,
+    },
+    /// A public key was removed from an entity
+    KeyRemove {
+        /// Id of the public key
+        key_id: Cow<'a, Id>,
+        /// Md5 fingerprint of the public key
+        fingerprint: Option<Cow<'a, str>>,
+    },
+    /// The owner of a public key was reminded that it expires soon
+    KeyExpiring {
+        /// Id of the public key
+        key_id: Cow<'a, Id>,
+        /// Md5 fingerprint of the public key
+        fingerprint: Option<Cow<'a, str>>,
+        /// The time when the key expires
+        expires: NaiveDateTime,
+    },
+    /// A previously removed public key was restored
+    KeyRestore {
+        /// Id of the public key
+        key_id: Cow<'a, Id>,
+        /// Md5 fingerprint of the public key
+        fingerprint: Option<Cow<'a, str>>,
+    },
+    /// A removed public key was permanently deleted
+    KeyPurge {
+        /// Id of the public key
+        key_id: Cow<'a, Id>,
+        /// Md5 fingerprint of the public key
+        fingerprint: Option<Cow<'a, str>>,
+    },
+    /// A public key was uploaded which is already owned by another
+    /// entity. Signals a private key shared between users
+    KeyCollision {
+        /// Sha256 fingerprint of the public key
+        fingerprint: Cow<'a, str>,
+        /// The entity which owns the existing key
+        owner_id: Cow<'a, Id>,
+    },
+    /// A certificate was issued for a public key
+    CertificateIssue {
+        /// Id of the public key
+        key_id: Cow<'a, Id>,
+        /// Md5 fingerprint of the public key
+        fingerprint: Option<Cow<'a, str>>,
+        /// Serial number of the certificate
+        serial: u64,
+        /// Names the certificate is valid for
+        principals: Vec<Cow<'a, str>>,
+        /// The time from which the certificate is valid
+        valid_after: NaiveDateTime,
+        /// The time until which the certificate is valid
+        valid_before: NaiveDateTime,
+    },
+    /// A host certificate was issued for a server
+    HostCertificateIssue {
+        /// Hostname of the server
+        hostname: Cow<'a, str>,
+        /// Md5 fingerprint of the host key
+        fingerprint: Option<Cow<'a, str>>,
+        /// Serial number of the certificate
+        serial: u64,
+        /// Names the certificate is valid for
+        principals: Vec<Cow<'a, str>>,
+        /// The time from which the certificate is valid
+        valid_after: NaiveDateTime,
+        /// The time until which the certificate is valid
+        valid_before: NaiveDateTime,
+    },
+    /// A server was created
+    ServerCreate {
+        /// Hostname of the server
+        hostname: Cow<'a, str>,
+    },
+    /// A server was deleted
+    ServerDelete {
+        /// Hostname of the server if known
+        hostname: Option<Cow<'a, str>>,
+    },
+    /// A user or group was made administrator of a server
+    ServerAdminAdd {
+        /// The new administrator
+        entity_id: Cow<'a, Id>,
+    },
+    /// A user or group is no longer administrator of a server
+    ServerAdminRemove {
+        /// The former administrator
+        entity_id: Cow<'a, Id>,
+    },
+    /// A note was added to a server
+    ServerNoteAdd {
+        /// The id of the note
+        note_id: Cow<'a, Id>,
+        /// The text of the note
+        note: Cow<'a, str>,
+    },
+    /// A note of a server was changed
+    ServerNoteEdit {
+        /// The id of the note
+        note_id: Cow<'a, Id>,
+        /// The text before the change
+        previous: Cow<'a, str>,
+        /// The text after the change
+        note: Cow<'a, str>,
+    },
+    /// A note was removed from a server
+    ServerNoteDelete {
+        /// The id of the note
+        note_id: Cow<'a, Id>,
+        /// The text of the removed note
+        note: Cow<'a, str>,
+    },
+    /// The users of a server were contacted by mail
+    ServerContact {
+        /// Who received the mail
+        recipients: ContactRecipients,
+        /// The subject of the mail
+        subject: Cow<'a, str>,
+        /// The number of users the mail was sent to
+        sent: u64,
+    },
+    /// The security key requirement of a server was changed
+    ServerSecurityKey {
+        /// Whether only keys backed by a hardware security key are accepted
+        required: bool,
+    },
+    /// The token a server authenticates with at the api was created or
+    /// revoked
+    ServerToken {
+        /// Whether the token was revoked without creating a new one
+        revoked: bool,
+    },
+    /// An account on a server is now managed
+    AccountCreate {
+        /// The entity id of the account
+        account_id: Cow<'a, Id>,
+        /// The name of the account
+        name: Cow<'a, str>,
+    },
+    /// Access was granted to an entity
+    AccessGrant {
+        /// The entity which received access
+        source_id: Cow<'a, Id>,
+        /// The entity which access was granted to
+        dest_id: Cow<'a, Id>,
+    },
+    /// Access was revoked from an entity
+    AccessRevoke {
+        /// The entity which lost access
+        source_id: Cow<'a, Id>,
+        /// The entity which access was revoked from
+        dest_id: Cow<'a, Id>,
+    },
+    /// Access to a server account was requested
+    AccessRequestCreate {
+        /// Id of the request
+        request_id: Cow<'a, Id>,
+        /// The server account access is requested to
+        dest_id: Cow<'a, Id>,
+        /// Why access is required
+        reason: Option<Cow<'a, str>>,
+    },
+    /// A request for access to a server account was approved or rejected
+    AccessRequestDecide {
+        /// Id of the request
+        request_id: Cow<'a, Id>,
+        /// The entity which requested access
+        source_id: Cow<'a, Id>,
+        /// The server account access was requested to
+        dest_id: Cow<'a, Id>,
+        /// The decision
+        status: AccessRequestStatus,
+    },
+    /// Emergency access to a server account was taken without approval
+    BreakGlass {
+        /// Id of the emergency access
+        break_glass_id: Cow<'a, Id>,
+        /// The server account access was taken to
+        dest_id: Cow<'a, Id>,
+        /// Why emergency access was required
+        reason: Cow<'a, str>,
+        /// The time until which access is granted
+        valid_until: NaiveDateTime,
+    },
+    /// An emergency access was reviewed
+    BreakGlassReview {
+        /// Id of the emergency access
+        break_glass_id: Cow<'a, Id>,
+        /// The entity which took emergency access
+        source_id: Cow<'a, Id>,
+    },
+    /// An access recertification campaign was started
+    RecertificationStart {
+        /// Id of the campaign
+        campaign_id: Cow<'a, Id>,
+        /// Name of the campaign
+        name: Cow<'a, str>,
+        /// Number of grants and memberships to review
+        items: u64,
+    },
+    /// An access recertification campaign was closed
+    RecertificationClose {
+        /// Id of the campaign
+        campaign_id: Cow<'a, Id>,
+        /// Number of removed grants and memberships
+        revoked: u64,
+    },
+    /// An entity was added to a group
+    MemberAdd {
+        /// The entity added to the group
+        member_id: Cow<'a, Id>,
+    },
+    /// An entity was removed from a group
+    MemberRemove {
+        /// The entity removed from the group
+        member_id: Cow<'a, Id>,
+    },
+    /// A server or server account was synchronised
+    SyncResult {
+        /// Resulting status of the synchronisation
+        status: SyncStatusType,
+        /// Further information about the result
+        message: Option<Cow<'a, str>>,
+    },
+    /// A user logged in
+    Login {
+        /// The uid used to log in
+        uid: Cow<'a, str>,
+    },
+    /// A user created a token to access the api
+    ApiTokenCreate {
+        /// The id of the token
+        token_id: Cow<'a, Id>,
+        /// The time until which the token is valid
+        valid_until: NaiveDateTime,
+    },
+    /// A user revoked one of their api tokens
+    ApiTokenRevoke {
+        /// The id of the token
+        token_id: Cow<'a, Id>,
+    },
+    /// Details which could not be mapped to one of the other variants.
+    /// Mostly events migrated from the php version of the application.
+    Legacy {
+        /// The action of the event if there was one
+        legacy_action: Option<String>,
+        /// The value of the event if there was one
+        value: Option<String>,
+        /// The details as stored in the database
+        raw: String,
+    },
+}
+
+impl EventDetails<'_> {
+    /// Names of all actions which are stored in the serialized details
+    pub const ACTIONS: [&'static str; 33] = [
+        "key_add",
+        "key_remove",
+        "key_expiring",
+        "key_restore",
+        "key_purge",
+        "key_collision",
+        "certificate_issue",
+        "host_certificate_issue",
+        "server_create",
+        "server_delete",
+        "server_admin_add",
+        "server_admin_remove",
+        "server_note_add",
+        "server_note_edit",
+        "server_note_delete",
+        "server_contact",
+        "server_security_key",
+        "server_token",
+        "account_create",
+        "access_grant",
+        "access_revoke",
+        "access_request_create",
+        "access_request_decide",
+        "break_glass",
+        "break_glass_review",
+        "recertification_start",
+        "recertification_close",
+        "member_add",
+        "member_remove",
+        "sync_result",
+        "login",
+        "api_token_create",
+        "api_token_revoke",
+    ];
+
+    /// Returns which object type the event id refers to. Legacy events
+    /// are always classified as `EventTypes::Entity`. Use the stored type
+    /// when reading them from the database instead.
+    #[must_use]
+    #[inline]
+    pub fn event_type(&self) -> EventTypes {
+        match self {
+            Self::HostCertificateIssue { .. }
+            | Self::ServerCreate { .. }
+            | Self::ServerDelete { .. }
+            | Self::ServerAdminAdd { .. }
+            | Self::ServerAdminRemove { .. }
+            | Self::ServerNoteAdd { .. }
+            | Self::ServerNoteEdit { .. }
+            | Self::ServerNoteDelete { .. }
+            | Self::ServerContact { .. }
+            | Self::ServerSecurityKey { .. }
+            | Self::ServerToken { .. }
+            | Self::AccountCreate { .. }
+            | Self::SyncResult { .. } => EventTypes::Server,
+            Self::KeyAdd { .. }
+            | Self::KeyRemove { .. }
+            | Self::KeyExpiring { .. }
+            | Self::KeyRestore { .. }
+            | Self::KeyPurge { .. }
+            | Self::KeyCollision { .. }
+            | Self::CertificateIssue { .. }
+            | Self::AccessGrant { .. }
+            | Self::AccessRevoke { .. }
+            | Self::AccessRequestCreate { .. }
+            | Self::AccessRequestDecide { .. }
+            | Self::BreakGlass { .. }
+            | Self::BreakGlassReview { .. }
+            | Self::RecertificationStart { .. }
+            | Self::RecertificationClose { .. }
+            | Self::MemberAdd { .. }
+            | Self::MemberRemove { .. }
+            | Self::Login { .. }
+            | Self::ApiTokenCreate { .. }
+            | Self::ApiTokenRevoke { .. }
+            | Self::Legacy { .. } => EventTypes::Entity,
+        }
+    }
+
+    /// Returns how urgently the event should be looked at. Emergency access
+    /// and key collisions are of high severity
+    #[must_use]
+    #[inline]
+    pub fn severity(&self) -> EventSeverity {
+        #[allow(clippy::wildcard_enum_match_arm)]
+        match *self {
+            Self::BreakGlass { .. } | Self::KeyCollision { .. } => {
+                EventSeverity::High
+            }
+            _ => EventSeverity::Normal,
+        }
+    }
+
+    /// Returns the name of the action as used in the serialized details
+    #[must_use]
+    #[inline]
+    pub fn action(&self) -> &str {
+        match *self {
+            Self::KeyAdd { .. } => "key_add",
+            Self::KeyRemove { .. } => "key_remove",
+            Self::KeyExpiring { .. } => "key_expiring",
+            Self::KeyRestore { .. } => "key_restore",
+            Self::KeyPurge { .. } => "key_purge",
+            Self::KeyCollision { .. } => "key_collision",
+            Self::CertificateIssue { .. } => "certificate_issue",
+            Self::HostCertificateIssue { .. } => "host_certificate_issue",
+            Self::ServerCreate { .. } => "server_create",
+            Self::ServerDelete { .. } => "server_delete",
+            Self::ServerAdminAdd { .. } => "server_admin_add",
+            Self::ServerAdminRemove { .. } => "server_admin_remove",
+            Self::ServerNoteAdd { .. } => "server_note_add",
+            Self::ServerNoteEdit { .. } => "server_note_edit",
+            Self::ServerNoteDelete { .. } => "server_note_delete",
+            Self::ServerContact { .. } => "server_contact",
+            Self::ServerSecurityKey { .. } => "server_security_key",
+            Self::ServerToken { .. } => "server_token",
+            Self::AccountCreate { .. } => "account_create",
+            Self::AccessGrant { .. } => "access_grant",
+            Self::AccessRevoke { .. } => "access_revoke",
+            Self::AccessRequestCreate { .. } => "access_request_create",
+            Self::AccessRequestDecide { .. } => "access_request_decide",
+            Self::BreakGlass { .. } => "break_glass",
+            Self::BreakGlassReview { .. } => "break_glass_review",
+            Self::RecertificationStart { .. } => "recertification_start",
+            Self::RecertificationClose { .. } => "recertification_close",
+            Self::MemberAdd { .. } => "member_add",
+            Self::MemberRemove { .. } => "member_remove",
+            Self::SyncResult { .. } => "sync_result",
+            Self::Login { .. } => "login",
+            Self::ApiTokenCreate { .. } => "api_token_create",
+            Self::ApiTokenRevoke { .. } => "api_token_revoke",
+            Self::Legacy {
+                ref legacy_action, ..
+            } => legacy_action.as_deref().unwrap_or("legacy"),
+        }
+    }
+
+    /// Serializes the details into json including the version tag
+    #[must_use]
+    #[inline]
+    pub fn to_json(&self) -> String {
+        if let Self::Legacy { ref raw, .. } = *self {
+            return raw.clone();
+        }
+        let mut value = serde_json::to_value(self).unwrap_or(Value::Null);
+        if let Value::Object(ref mut map) = value {
+            let _ = map.insert("version".into(), EVENT_DETAILS_VERSION.into());
+        }
+        value.to_string()
+    }
+
+    /// Parses details stored in the database. Never fails: details which
+    /// neither match the current format nor a known legacy format are
+    /// returned as `EventDetails::Legacy`.
+    #[must_use]
+    #[inline]
+    pub fn parse(raw: &str) -> EventDetails<'static> {
+        let value = match serde_json::from_str::<Value>(raw) {
+            Ok(value @ Value::Object(_)) => value,
+            _ => {
+                return EventDetails::Legacy {
+                    legacy_action: None,
+                    value: None,
+                    raw: raw.into(),
+                }
+            }
+        };
+        let version = value.get("version").and_then(Value::as_u64);
+        let action = value.get("action").and_then(Value::as_str);
+        let text = value.get("value").and_then(Value::as_str);
+        let parsed = match (version, action) {
+            (Some(EVENT_DETAILS_VERSION), _) => {
+                serde_json::from_value::<EventDetails<'static>>(value.clone()).ok()
+            }
+            (None, Some("Pubkey add")) | (None, Some("Pubkey remove")) => value
+                .get("id")
+                .and_then(Value::as_str)
+                .and_then(|id| Id::from_string(id).ok())
+                .map(|key_id| {
+                    let key_id = Cow::Owned(key_id);
+                    let fingerprint = text.map(|v| Cow::Owned(v.into()));
+                    if action == Some("Pubkey add") {
+                        EventDetails::KeyAdd {
+                            key_id,
+                            fingerprint,
+                        }
+                    } else {
+                        EventDetails::KeyRemove {
+                            key_id,
+                            fingerprint,
+                        }
+                    }
+                }),
+            (None, Some("Server add")) => {
+                text.map(|hostname| EventDetails::ServerCreate {
+                    hostname: Cow::Owned(hostname.into()),
+                })
+            }
+            _ => None,
+        };
+        parsed.unwrap_or_else(|| EventDetails::Legacy {
+            legacy_action: action.map(Into::into),
+            value: text.map(Into::into),
+            raw: raw.into(),
+        })
+    }
 }
 
 /// Provides fields to filter when searching for multiple
@@ -29,8 +536,258 @@ pub struct Event<'a> {
 pub struct EventFilter<'a> {
     /// The entity who did the change must equal to this id
     pub actor_id: Option<Cow<'a, Id>>,
-    /// The Event information must be like
+    /// The Event information must contain this text
     pub details: Option<Cow<'a, str>>,
     /// The object the operation was made on must equal
     pub object_id: Option<Cow<'a, Id>>,
+    /// The type of the object must equal
+    pub type_: Option<EventTypes>,
+    /// The action stored inside the Event information must equal. Only
+    /// the names in `EventDetails::ACTIONS` are accepted
+    pub action: Option<Cow<'a, str>>,
+    /// The Event must be registered on or after this date
+    pub date_ge: Option<NaiveDateTime>,
+    /// The Event must be registered on or before this date
+    pub date_le: Option<NaiveDateTime>,
+}
+
+impl Default for EventFilter<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            actor_id: None,
+            details: None,
+            object_id: None,
+            type_: None,
+            action: None,
+            date_ge: None,
+            date_le: None,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = (Cow<'a, str>, Cow<'a, str>)>> From<I>
+    for EventFilter<'a>
+{
+    #[inline]
+    fn from(iter: I) -> Self {
+        let mut filter = Self::default();
+        for (key, val) in iter {
+            if val.is_empty() {
+                continue;
+            }
+            match key.as_ref() {
+                "actor" => {
+                    filter.actor_id = Id::from_string(val).ok().map(Cow::Owned);
+                }
+                "object" => {
+                    filter.object_id = Id::from_string(val).ok().map(Cow::Owned);
+                }
+                "type" => {
+                    filter.type_ = EventTypes::try_from(val.as_ref()).ok();
+                }
+                "action" => {
+                    filter.action = EventDetails::ACTIONS
+                        .iter()
+                        .find(|&&action| action == val)
+                        .map(|&action| Cow::Borrowed(action));
+                }
+                "details" => {
+                    filter.details = Some(val);
+                }
+                "date-from" => {
+                    filter.date_ge = NaiveDate::parse_from_str(&val, "%Y-%m-%d")
+                        .ok()
+                        .map(|date| date.and_hms(0, 0, 0));
+                }
+                "date-to" => {
+                    filter.date_le = NaiveDate::parse_from_str(&val, "%Y-%m-%d")
+                        .ok()
+                        .map(|date| date.and_hms(23, 59, 59));
+                }
+                _ => {}
+            }
+        }
+        filter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EventDetails, EventFilter, EVENT_DETAILS_VERSION};
+    use crate::{
+        chrono::NaiveDate,
+        types::{ContactRecipients, EventSeverity, EventTypes, Id, SyncStatusType},
+    };
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_roundtrip() {
+        let details = EventDetails::SyncResult {
+            status: SyncStatusType::SyncFailure,
+            message: Some(Cow::Borrowed("Connection refused")),
+        };
+        let json = details.to_json();
+        assert!(json.contains(&format!("\"version\":{}", EVENT_DETAILS_VERSION)));
+        assert!(json.contains("\"action\":\"sync_result\""));
+        assert_eq!(details.action(), "sync_result");
+        assert!(EventDetails::ACTIONS
+            .iter()
+            .any(|&action| action == details.action()));
+        assert_eq!(EventDetails::parse(&json), details);
+        assert_eq!(details.event_type(), EventTypes::Server);
+        assert_eq!(details.severity(), EventSeverity::Normal);
+    }
+
+    #[test]
+    fn test_action_filter() {
+        let filter = EventFilter::from(
+            vec![(Cow::Borrowed("action"), Cow::Borrowed("key_add"))].into_iter(),
+        );
+        assert_eq!(filter.action, Some(Cow::Borrowed("key_add")));
+        let filter = EventFilter::from(
+            vec![(Cow::Borrowed("action"), Cow::Borrowed("%"))].into_iter(),
+        );
+        assert_eq!(filter.action, None);
+    }
+
+    #[test]
+    fn test_break_glass() {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        let details = EventDetails::BreakGlass {
+            break_glass_id: Cow::Borrowed(&id),
+            dest_id: Cow::Borrowed(&id),
+            reason: Cow::Borrowed("Database outage"),
+            valid_until: NaiveDate::from_ymd(2020, 12, 1).and_hms(12, 0, 0),
+        };
+        assert_eq!(details.action(), "break_glass");
+        assert_eq!(details.severity(), EventSeverity::High);
+        assert_eq!(EventDetails::parse(&details.to_json()), details);
+    }
+
+    #[test]
+    fn test_recertification() {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        let details = EventDetails::RecertificationClose {
+            campaign_id: Cow::Borrowed(&id),
+            revoked: 3,
+        };
+        assert_eq!(details.action(), "recertification_close");
+        assert_eq!(details.event_type(), EventTypes::Entity);
+        assert_eq!(EventDetails::parse(&details.to_json()), details);
+    }
+
+    #[test]
+    fn test_server_admin() {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        let details = EventDetails::ServerAdminAdd {
+            entity_id: Cow::Borrowed(&id),
+        };
+        assert_eq!(details.action(), "server_admin_add");
+        assert_eq!(details.event_type(), EventTypes::Server);
+        assert_eq!(EventDetails::parse(&details.to_json()), details);
+
+        let details = EventDetails::AccountCreate {
+            account_id: Cow::Borrowed(&id),
+            name: Cow::Borrowed("deploy"),
+        };
+        assert_eq!(details.action(), "account_create");
+        assert_eq!(EventDetails::parse(&details.to_json()), details);
+    }
+
+    #[test]
+    fn test_server_note() {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        let details = EventDetails::ServerNoteEdit {
+            note_id: Cow::Borrowed(&id),
+            previous: Cow::Borrowed("Reboot on Monday"),
+            note: Cow::Borrowed("Reboot on Tuesday"),
+        };
+        assert_eq!(details.action(), "server_note_edit");
+        assert_eq!(details.event_type(), EventTypes::Server);
+        assert_eq!(EventDetails::parse(&details.to_json()), details);
+    }
+
+    #[test]
+    fn test_server_contact() {
+        let details = EventDetails::ServerContact {
+            recipients: ContactRecipients::RootUsers,
+            subject: Cow::Borrowed("Maintenance on Monday"),
+            sent: 3,
+        };
+        assert_eq!(details.action(), "server_contact");
+        assert_eq!(details.event_type(), EventTypes::Server);
+        assert_eq!(EventDetails::parse(&details.to_json()), details);
+
+        let details = EventDetails::ServerSecurityKey { required: true };
+        assert_eq!(details.action(), "server_security_key");
+        assert_eq!(details.event_type(), EventTypes::Server);
+        assert_eq!(EventDetails::parse(&details.to_json()), details);
+
+        let details = EventDetails::ServerToken { revoked: false };
+        assert_eq!(details.action(), "server_token");
+        assert_eq!(details.event_type(), EventTypes::Server);
+        assert_eq!(EventDetails::parse(&details.to_json()), details);
+    }
+
+    #[test]
+    fn test_api_token() {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        let details = EventDetails::ApiTokenCreate {
+            token_id: Cow::Borrowed(&id),
+            valid_until: NaiveDate::from_ymd(2021, 3, 1).and_hms(12, 0, 0),
+        };
+        assert_eq!(details.action(), "api_token_create");
+        assert_eq!(details.event_type(), EventTypes::Entity);
+        assert_eq!(EventDetails::parse(&details.to_json()), details);
+
+        let details = EventDetails::ApiTokenRevoke {
+            token_id: Cow::Borrowed(&id),
+        };
+        assert_eq!(details.action(), "api_token_revoke");
+        assert_eq!(details.event_type(), EventTypes::Entity);
+        assert_eq!(EventDetails::parse(&details.to_json()), details);
+    }
+
+    #[test]
+    fn test_parse_unversioned_key_add() {
+        let raw = r#"{"action":"Pubkey add","value":"a1:b2","id":"00bd8c06-daf7-47e6-8c96-8d467587b6dc"}"#;
+        let key_id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        assert_eq!(
+            EventDetails::parse(raw),
+            EventDetails::KeyAdd {
+                key_id: Cow::Owned(key_id),
+                fingerprint: Some(Cow::Borrowed("a1:b2")),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_legacy() {
+        let raw = r#"{"action":"Member add","value":"user:jdoe"}"#;
+        assert_eq!(
+            EventDetails::parse(raw),
+            EventDetails::Legacy {
+                legacy_action: Some("Member add".into()),
+                value: Some("user:jdoe".into()),
+                raw: raw.into(),
+            }
+        );
+        let raw = "Sync failed";
+        assert_eq!(
+            EventDetails::parse(raw),
+            EventDetails::Legacy {
+                legacy_action: None,
+                value: None,
+                raw: raw.into(),
+            }
+        );
+        assert_eq!(EventDetails::parse(raw).to_json(), raw);
+    }
 }
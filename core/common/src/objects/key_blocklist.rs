@@ -0,0 +1,226 @@
+use crate::{
+    objects::PublicKey,
+    types::{FingerprintMd5, FingerprintSha256},
+};
+use std::{borrow::Cow, collections::HashSet, error, fmt};
+
+/// Number of bytes of a sha256 fingerprint
+const SHA256_LEN: usize = 32;
+/// Number of bytes of a md5 fingerprint
+const MD5_LEN: usize = 16;
+/// Number of trailing md5 bytes stored in the Debian `openssh-blacklist`
+/// files
+const MD5_SUFFIX_LEN: usize = 10;
+
+/// Error while reading a blocklist file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBlocklistError {
+    /// Number of the line containing the invalid entry, starting at 1
+    pub line: usize,
+}
+
+impl fmt::Display for KeyBlocklistError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid fingerprint in line {}", self.line)
+    }
+}
+
+impl error::Error for KeyBlocklistError {}
+
+/// Fingerprints of known compromised keys. Keys matching one of the
+/// fingerprints are rejected when they are parsed
+#[derive(Debug, Clone, Default)]
+pub struct KeyBlocklist {
+    sha256: HashSet<Vec<u8>>,
+    md5: HashSet<Vec<u8>>,
+    md5_suffix: HashSet<Vec<u8>>,
+}
+
+impl KeyBlocklist {
+    /// Adds the fingerprints of a blocklist file. Every line contains one
+    /// fingerprint which may be followed by a description. Empty lines and
+    /// lines starting with `#` are skipped. The following formats are
+    /// supported:
+    /// * sha256 fingerprints as printed by `ssh-keygen -l`, e.g.
+    ///   `SHA256:nThbg6kXUpJWGl7E1IGOCspRomTxdCARLviKw6E5SY8`
+    /// * md5 fingerprints, e.g. `MD5:c1:b1:30:29:d7:b8:de:6c:97:77:10:d7:46:41:63:87`
+    /// * truncated md5 fingerprints of the Debian `openssh-blacklist`
+    ///   package, e.g. `0000ab1d9c3b0a7bb4ba`
+    ///
+    /// Returns the number of added fingerprints
+    ///
+    /// # Errors
+    /// Fails on the first line which is not a fingerprint
+    #[inline]
+    pub fn extend(&mut self, text: &str) -> Result<usize, KeyBlocklistError> {
+        let mut count: usize = 0;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let entry = line.split_whitespace().next().unwrap_or_default();
+            let error = KeyBlocklistError {
+                line: number.saturating_add(1),
+            };
+            if let Some(sha256) = entry.strip_prefix("SHA256:") {
+                let fingerprint =
+                    FingerprintSha256::from_string(Cow::Borrowed(sha256))
+                        .map_err(|_| error)?;
+                if fingerprint.get_bytes().len() != SHA256_LEN {
+                    return Err(error);
+                }
+                let _ = self.sha256.insert(fingerprint.get_bytes().to_vec());
+            } else {
+                let md5 = entry.strip_prefix("MD5:").unwrap_or(entry);
+                if !md5.chars().all(|c| c == ':' || c.is_ascii_hexdigit()) {
+                    return Err(error);
+                }
+                let fingerprint = FingerprintMd5::from_string(Cow::Borrowed(md5));
+                let bytes = fingerprint.get_bytes().to_vec();
+                let _ = match bytes.len() {
+                    MD5_LEN => self.md5.insert(bytes),
+                    MD5_SUFFIX_LEN => self.md5_suffix.insert(bytes),
+                    _ => return Err(error),
+                };
+            }
+            count = count.saturating_add(1);
+        }
+        Ok(count)
+    }
+
+    /// Number of fingerprints in the blocklist
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.sha256
+            .len()
+            .saturating_add(self.md5.len())
+            .saturating_add(self.md5_suffix.len())
+    }
+
+    /// Whether the blocklist contains no fingerprints
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether one of the fingerprints of the key is blocked
+    #[must_use]
+    #[inline]
+    pub fn contains(&self, key: &PublicKey<'_>) -> bool {
+        if let Some(ref sha256) = key.fingerprint_sha256 {
+            if self.sha256.contains(sha256.get_bytes()) {
+                return true;
+            }
+        }
+        if let Some(ref md5) = key.fingerprint_md5 {
+            let bytes = md5.get_bytes();
+            if self.md5.contains(bytes) {
+                return true;
+            }
+            let suffix = bytes
+                .len()
+                .checked_sub(MD5_SUFFIX_LEN)
+                .and_then(|start| bytes.get(start..));
+            if let Some(suffix) = suffix {
+                return self.md5_suffix.contains(suffix);
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeyBlocklist, KeyBlocklistError};
+    use crate::{
+        objects::{PublicKey, PublicKeyConversionError},
+        types::{FingerprintMd5, FingerprintSha256, Id},
+        worker::tests::TestDb,
+    };
+    use std::borrow::Cow;
+
+    fn key(md5: &str, sha256: &str) -> PublicKey<'static> {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        PublicKey {
+            id: Cow::Owned(id.clone()),
+            entity_id: Cow::Owned(id),
+            type_: Cow::Borrowed("ssh-rsa"),
+            keydata: Cow::Borrowed(""),
+            comment: None,
+            keysize: Some(2048),
+            fingerprint_md5: Some(Cow::Owned(FingerprintMd5::from_string(
+                Cow::Owned(md5.into()),
+            ))),
+            fingerprint_sha256: FingerprintSha256::from_string(Cow::Owned(
+                sha256.into(),
+            ))
+            .ok()
+            .map(Cow::Owned),
+            randomart_md5: None,
+            randomart_sha256: None,
+            upload_date: None,
+            application: None,
+            expires: None,
+            deactivated: None,
+        }
+    }
+
+    #[test]
+    fn test_blocklist() {
+        let mut blocklist = KeyBlocklist::default();
+        assert!(blocklist.is_empty());
+        let text = "# Leaked keys\n\
+            SHA256:nThbg6kXUpJWGl7E1IGOCspRomTxdCARLviKw6E5SY8 build server\n\
+            \n\
+            MD5:c1:b1:30:29:d7:b8:de:6c:97:77:10:d7:46:41:63:87\n\
+            # Keys 0 - 32767\n\
+            de6c977710d746416387\n";
+        assert_eq!(blocklist.extend(text), Ok(3));
+        assert_eq!(blocklist.len(), 3);
+
+        let sha256 = "nThbg6kXUpJWGl7E1IGOCspRomTxdCARLviKw6E5SY8";
+        let other_sha256 = "47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU";
+        let md5 = "c1:b1:30:29:d7:b8:de:6c:97:77:10:d7:46:41:63:87";
+        let other_md5 = "00:11:22:33:44:55:66:77:88:99:aa:bb:cc:dd:ee:ff";
+        let suffix_md5 = "ff:ff:ff:ff:ff:ff:de:6c:97:77:10:d7:46:41:63:87";
+        assert!(blocklist.contains(&key(other_md5, sha256)));
+        assert!(blocklist.contains(&key(md5, other_sha256)));
+        assert!(blocklist.contains(&key(suffix_md5, other_sha256)));
+        assert!(!blocklist.contains(&key(other_md5, other_sha256)));
+
+        assert_eq!(
+            blocklist.extend("SHA256:nThbg6kXUpJWGl7E1IGOCspRomTxdCARLviKw6E5SY8\nnot a fingerprint"),
+            Err(KeyBlocklistError { line: 2 })
+        );
+        assert_eq!(
+            blocklist.extend("00:11:22"),
+            Err(KeyBlocklistError { line: 1 })
+        );
+    }
+
+    #[test]
+    fn test_parse_compromised() {
+        let key_str = r"ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABgQDS6o9i9w5eFXEUcQMhOvupIhPFdb1evYoYPmTDSkoejpZF+u7PHfPanSXc/95UbsOuBLENgIGnr/1gN9Vvok/XqZc+UnODyAKztdGx8za9Zhxe3BBxs1R1UJ5Ri5U+WQkvedUYJs2vvl67ZkMFOV49gILjHD8Lq43lU3pyPupmKtq3dRzCyFQk9smx4eyW9vWaPgKHHMeFvoO2coMg9vF06vuFb5H/KqEO58GYgy45Zc+sePOWA6i4z9uBWQyTUzpHrT8TpQABunIfx6KGwyt+7y8LzCbsks7R/HE67PNJz9bb7lBXraqBMFfFhciiHDgnppt8BY/MCeF7OLcsyhztaBJyz6v04c4jiHX32FfsL8w57fPU9paCj6RnSbCcB4hrsuqpCnAEClLSBhrFa/3agucst7VP6Z+pabzh+lNjuwWh9FR7/zB3sBNhQDpMwJyuOcwLKj+uThZGfzIpRSIfUK7WX2msCqlgCnP7ELkinj8fETXEFg1mL66VgpYuFHM= testkey";
+        let db = TestDb::new(());
+        let entity_id = Id::from_string("c6efb44e-9b67-4dc0-a31b-6482476ed8b7")
+            .expect("Invalid Id");
+        let mut blocklist = KeyBlocklist::default();
+        assert!(PublicKey::parse(key_str, &entity_id, &db, &blocklist).is_ok());
+
+        let _ = blocklist
+            .extend("b8:1d:cb:18:d9:7d:f6:c8:c9:1b:e6:e9:7d:b5:32:4e:b8:1d")
+            .expect_err("Fingerprint is too long");
+        let _ = blocklist
+            .extend("c9:1b:e6:e9:7d:b5:32:4e:b8:1d")
+            .expect("Unable to extend blocklist");
+        assert!(matches!(
+            PublicKey::parse(key_str, &entity_id, &db, &blocklist),
+            Err(PublicKeyConversionError::Compromised)
+        ));
+    }
+}
@@ -0,0 +1,302 @@
+//! Conversion between the OpenSSH, RFC4716 and PEM public key formats
+
+use std::convert::TryFrom;
+
+const RFC4716_BEGIN: &str = "---- BEGIN SSH2 PUBLIC KEY ----";
+const RFC4716_END: &str = "---- END SSH2 PUBLIC KEY ----";
+const PEM_BEGIN: &str = "-----BEGIN PUBLIC KEY-----";
+const PEM_END: &str = "-----END PUBLIC KEY-----";
+const PEM_RSA_BEGIN: &str = "-----BEGIN RSA PUBLIC KEY-----";
+const PEM_RSA_END: &str = "-----END RSA PUBLIC KEY-----";
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_NULL: u8 = 0x05;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+
+const OID_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+const OID_EC: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+const OID_ED25519: &[u8] = &[0x2b, 0x65, 0x70];
+/// Curve names used by openssh and their oids
+const CURVES: [(&str, &[u8]); 3] = [
+    (
+        "nistp256",
+        &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07],
+    ),
+    ("nistp384", &[0x2b, 0x81, 0x04, 0x00, 0x22]),
+    ("nistp521", &[0x2b, 0x81, 0x04, 0x00, 0x23]),
+];
+
+/// Converts a key in the RFC4716 or PEM format into the openssh
+/// `authorized_keys` format. The comment of RFC4716 keys is returned
+/// separately as it may contain whitespace. Returns `None` if the data is
+/// in neither format or malformed
+pub(crate) fn to_openssh(data: &str) -> Option<(String, Option<String>)> {
+    let data = data.trim();
+    let (blob, comment) = if data.starts_with(RFC4716_BEGIN) {
+        parse_rfc4716(data)?
+    } else if data.starts_with(PEM_BEGIN) {
+        (spki_to_blob(&pem_body(data, PEM_BEGIN, PEM_END)?)?, None)
+    } else if data.starts_with(PEM_RSA_BEGIN) {
+        let der = pem_body(data, PEM_RSA_BEGIN, PEM_RSA_END)?;
+        (pkcs1_to_blob(&der)?, None)
+    } else {
+        return None;
+    };
+    let type_ =
+        String::from_utf8(read_string(&mut blob.as_slice())?.to_vec()).ok()?;
+    Some((format!("{} {}", type_, base64::encode(&blob)), comment))
+}
+
+/// Converts a base64 encoded openssh key into the RFC4716 format
+pub(crate) fn to_rfc4716(keydata: &str, comment: Option<&str>) -> String {
+    let mut text = String::from(RFC4716_BEGIN);
+    text.push('\n');
+    if let Some(comment) = comment {
+        let header = format!("Comment: \"{}\"", comment.replace('"', "\\\""));
+        let chars: Vec<char> = header.chars().collect();
+        let mut lines = chars.chunks(70).peekable();
+        while let Some(line) = lines.next() {
+            text.extend(line);
+            if lines.peek().is_some() {
+                text.push('\\');
+            }
+            text.push('\n');
+        }
+    }
+    push_wrapped(&mut text, keydata, 70);
+    text.push_str(RFC4716_END);
+    text.push('\n');
+    text
+}
+
+/// Converts a base64 encoded openssh key into a PEM encoded
+/// `SubjectPublicKeyInfo`. Returns `None` for key types without a PEM
+/// representation
+pub(crate) fn to_pem(keydata: &str) -> Option<String> {
+    let blob = base64::decode(keydata).ok()?;
+    let der = blob_to_spki(&blob)?;
+    let mut text = String::from(PEM_BEGIN);
+    text.push('\n');
+    push_wrapped(&mut text, &base64::encode(&der), 64);
+    text.push_str(PEM_END);
+    text.push('\n');
+    Some(text)
+}
+
+fn push_wrapped(text: &mut String, data: &str, width: usize) {
+    let mut rest = data;
+    while !rest.is_empty() {
+        let (line, tail) = rest.split_at(width.min(rest.len()));
+        text.push_str(line);
+        text.push('\n');
+        rest = tail;
+    }
+}
+
+fn parse_rfc4716(data: &str) -> Option<(Vec<u8>, Option<String>)> {
+    let lines = data.lines().map(str::trim).skip(1);
+    let mut comment = None;
+    let mut body = String::new();
+    let mut header = String::new();
+    let mut in_header = false;
+    for line in lines {
+        if line == RFC4716_END {
+            let blob = base64::decode(&body).ok()?;
+            return Some((blob, comment));
+        }
+        if in_header || (body.is_empty() && line.contains(':')) {
+            in_header = line.ends_with('\\');
+            header.push_str(line.trim_end_matches('\\'));
+            if !in_header {
+                let mut parts = header.splitn(2, ':');
+                let tag = parts.next().unwrap_or_default().trim();
+                let value = parts.next().unwrap_or_default().trim();
+                if tag.eq_ignore_ascii_case("Comment") {
+                    let value = value
+                        .strip_prefix('"')
+                        .and_then(|v| v.strip_suffix('"'))
+                        .unwrap_or(value);
+                    comment = Some(value.replace("\\\"", "\""));
+                }
+                header.clear();
+            }
+        } else {
+            body.push_str(line);
+        }
+    }
+    None
+}
+
+fn pem_body(data: &str, begin: &str, end: &str) -> Option<Vec<u8>> {
+    let body: String = data
+        .strip_prefix(begin)?
+        .trim_end()
+        .strip_suffix(end)?
+        .split_whitespace()
+        .collect();
+    base64::decode(&body).ok()
+}
+
+/// Reads a length prefixed string in the ssh wire format
+pub(crate) fn read_string<'a>(reader: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = reader.get(..4)?;
+    let len = u32::from_be_bytes([
+        *len.first()?,
+        *len.get(1)?,
+        *len.get(2)?,
+        *len.get(3)?,
+    ]);
+    let end = usize::try_from(len).ok()?.checked_add(4)?;
+    let value = reader.get(4..end)?;
+    *reader = reader.get(end..)?;
+    Some(value)
+}
+
+pub(crate) fn write_string(buf: &mut Vec<u8>, value: &[u8]) -> Option<()> {
+    buf.extend_from_slice(&u32::try_from(value.len()).ok()?.to_be_bytes());
+    buf.extend_from_slice(value);
+    Some(())
+}
+
+/// Strips leading zeros of an unsigned big endian integer and adds a zero
+/// byte if the highest bit is set. Used for ssh mpints and der integers
+fn positive_integer(value: &[u8]) -> Vec<u8> {
+    let start = value.iter().position(|b| *b != 0).unwrap_or(value.len());
+    let value = value.get(start..).unwrap_or_default();
+    let mut result = Vec::with_capacity(value.len().saturating_add(1));
+    if value.first().map_or(false, |b| b & 0x80 != 0) {
+        result.push(0);
+    }
+    result.extend_from_slice(value);
+    result
+}
+
+fn read_tlv<'a>(reader: &mut &'a [u8]) -> Option<(u8, &'a [u8])> {
+    let tag = *reader.first()?;
+    let first = *reader.get(1)?;
+    let (len, header) = if first < 0x80 {
+        (usize::from(first), 2_usize)
+    } else {
+        let count = usize::from(first & 0x7f);
+        if count == 0 || count > 4 {
+            return None;
+        }
+        let bytes = reader.get(2..count.checked_add(2)?)?;
+        let len = bytes
+            .iter()
+            .fold(0_usize, |acc, b| (acc << 8) | usize::from(*b));
+        (len, count.checked_add(2)?)
+    };
+    let end = header.checked_add(len)?;
+    let value = reader.get(header..end)?;
+    *reader = reader.get(end..)?;
+    Some((tag, value))
+}
+
+fn read_expected<'a>(reader: &mut &'a [u8], tag: u8) -> Option<&'a [u8]> {
+    match read_tlv(reader)? {
+        (t, value) if t == tag => Some(value),
+        _ => None,
+    }
+}
+
+fn write_tlv(buf: &mut Vec<u8>, tag: u8, value: &[u8]) -> Option<()> {
+    buf.push(tag);
+    let len = value.len();
+    if len < 0x80 {
+        buf.push(u8::try_from(len).ok()?);
+    } else {
+        let bytes = u32::try_from(len).ok()?.to_be_bytes();
+        let start = bytes.iter().position(|b| *b != 0).unwrap_or(3);
+        let bytes = bytes.get(start..)?;
+        buf.push(0x80 | u8::try_from(bytes.len()).ok()?);
+        buf.extend_from_slice(bytes);
+    }
+    buf.extend_from_slice(value);
+    Some(())
+}
+
+fn pkcs1_to_blob(der: &[u8]) -> Option<Vec<u8>> {
+    let mut reader = der;
+    let mut seq = read_expected(&mut reader, TAG_SEQUENCE)?;
+    let n = read_expected(&mut seq, TAG_INTEGER)?;
+    let e = read_expected(&mut seq, TAG_INTEGER)?;
+    let mut blob = Vec::new();
+    write_string(&mut blob, b"ssh-rsa")?;
+    write_string(&mut blob, &positive_integer(e))?;
+    write_string(&mut blob, &positive_integer(n))?;
+    Some(blob)
+}
+
+fn spki_to_blob(der: &[u8]) -> Option<Vec<u8>> {
+    let mut reader = der;
+    let mut spki = read_expected(&mut reader, TAG_SEQUENCE)?;
+    let mut algorithm = read_expected(&mut spki, TAG_SEQUENCE)?;
+    let oid = read_expected(&mut algorithm, TAG_OID)?;
+    let key = read_expected(&mut spki, TAG_BIT_STRING)?;
+    // the first byte contains the number of unused bits
+    let key = match key.split_first()? {
+        (0, key) => key,
+        _ => return None,
+    };
+
+    let mut blob = Vec::new();
+    if oid == OID_RSA {
+        return pkcs1_to_blob(key);
+    } else if oid == OID_ED25519 {
+        write_string(&mut blob, b"ssh-ed25519")?;
+        write_string(&mut blob, key)?;
+    } else if oid == OID_EC {
+        let curve = read_expected(&mut algorithm, TAG_OID)?;
+        let (name, _) = CURVES.iter().find(|(_, oid)| *oid == curve)?;
+        write_string(&mut blob, format!("ecdsa-sha2-{}", name).as_bytes())?;
+        write_string(&mut blob, name.as_bytes())?;
+        write_string(&mut blob, key)?;
+    } else {
+        return None;
+    }
+    Some(blob)
+}
+
+fn blob_to_spki(blob: &[u8]) -> Option<Vec<u8>> {
+    let mut reader = blob;
+    let type_ = read_string(&mut reader)?;
+    let mut algorithm = Vec::new();
+    let mut key = vec![0];
+    match type_ {
+        b"ssh-rsa" => {
+            let e = read_string(&mut reader)?;
+            let n = read_string(&mut reader)?;
+            let mut integers = Vec::new();
+            write_tlv(&mut integers, TAG_INTEGER, &positive_integer(n))?;
+            write_tlv(&mut integers, TAG_INTEGER, &positive_integer(e))?;
+            write_tlv(&mut key, TAG_SEQUENCE, &integers)?;
+            write_tlv(&mut algorithm, TAG_OID, OID_RSA)?;
+            write_tlv(&mut algorithm, TAG_NULL, &[])?;
+        }
+        b"ssh-ed25519" => {
+            key.extend_from_slice(read_string(&mut reader)?);
+            write_tlv(&mut algorithm, TAG_OID, OID_ED25519)?;
+        }
+        _ => {
+            let name = read_string(&mut reader)?;
+            let (_, curve) = CURVES.iter().find(|(n, _)| n.as_bytes() == name)?;
+            if type_
+                != format!("ecdsa-sha2-{}", String::from_utf8_lossy(name)).as_bytes()
+            {
+                return None;
+            }
+            key.extend_from_slice(read_string(&mut reader)?);
+            write_tlv(&mut algorithm, TAG_OID, OID_EC)?;
+            write_tlv(&mut algorithm, TAG_OID, curve)?;
+        }
+    }
+    let mut spki = Vec::new();
+    write_tlv(&mut spki, TAG_SEQUENCE, &algorithm)?;
+    write_tlv(&mut spki, TAG_BIT_STRING, &key)?;
+    let mut der = Vec::new();
+    write_tlv(&mut der, TAG_SEQUENCE, &spki)?;
+    Some(der)
+}
@@ -0,0 +1,205 @@
+use crate::{serde::Serialize, types::AccessOption};
+
+/// Prefixes of the key types known to openssh. Used to tell options apart
+/// from the key type
+const KEY_TYPE_PREFIXES: [&str; 3] = ["ssh-", "ecdsa-", "sk-"];
+
+/// An option preceding a key in an `authorized_keys` file
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+pub struct KeyOption {
+    /// The access option the openssh option maps to
+    pub option: AccessOption,
+    /// The value of the option, e.g. the command of `command="..."`
+    pub value: Option<String>,
+}
+
+/// A single line of an `authorized_keys` file split into its options and
+/// the key itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorizedKeysLine<'a> {
+    /// Options which map to an `AccessOption`
+    pub options: Vec<KeyOption>,
+    /// Options without an `AccessOption` equivalent
+    pub unknown_options: Vec<String>,
+    /// The key in the format `type data [comment]`
+    pub key: &'a str,
+}
+
+impl<'a> AuthorizedKeysLine<'a> {
+    /// Splits a line of an `authorized_keys` file. Returns `None` for empty
+    /// lines and comments
+    #[inline]
+    #[must_use]
+    pub fn parse(line: &'a str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        if KEY_TYPE_PREFIXES
+            .iter()
+            .any(|prefix| line.starts_with(prefix))
+        {
+            return Some(Self {
+                options: Vec::new(),
+                unknown_options: Vec::new(),
+                key: line,
+            });
+        }
+
+        let mut options = Vec::new();
+        let mut unknown_options = Vec::new();
+        let mut current = String::new();
+        let mut quoted = false;
+        let mut escaped = false;
+        let mut end = line.len();
+        for (pos, c) in line.char_indices() {
+            if escaped {
+                current.push(c);
+                escaped = false;
+                continue;
+            }
+            match c {
+                '\\' if quoted => escaped = true,
+                '"' => quoted = !quoted,
+                ',' if !quoted => {
+                    Self::push_option(&current, &mut options, &mut unknown_options);
+                    current.clear();
+                }
+                c if c.is_whitespace() && !quoted => {
+                    end = pos;
+                    break;
+                }
+                c => current.push(c),
+            }
+        }
+        Self::push_option(&current, &mut options, &mut unknown_options);
+        Some(Self {
+            options,
+            unknown_options,
+            key: line.get(end..).unwrap_or_default().trim(),
+        })
+    }
+
+    fn push_option(
+        option: &str,
+        options: &mut Vec<KeyOption>,
+        unknown_options: &mut Vec<String>,
+    ) {
+        if option.is_empty() {
+            return;
+        }
+        let mut parts = option.splitn(2, '=');
+        let name = parts.next().unwrap_or_default();
+        let value = parts.next().map(String::from);
+        match AccessOption::from_openssh(name) {
+            Some(option) => options.push(KeyOption { option, value }),
+            None => unknown_options.push(name.into()),
+        }
+    }
+}
+
+/// Outcome of importing a single line
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Serialize)]
+pub enum ImportStatus {
+    /// The key was added
+    Imported,
+    /// A key with the same fingerprint already exists
+    Duplicate,
+    /// The line does not contain a valid or allowed key
+    Invalid,
+}
+
+/// Result of importing a single line of an `authorized_keys` file
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+pub struct KeyImportLine {
+    /// The line number, starting at 1
+    pub line: usize,
+    /// The outcome of the import
+    pub status: ImportStatus,
+    /// The sha256 fingerprint of the key if it could be parsed
+    pub fingerprint: Option<String>,
+    /// The comment of the key
+    pub comment: Option<String>,
+    /// Options which map to an `AccessOption`
+    pub options: Vec<KeyOption>,
+    /// Options without an `AccessOption` equivalent
+    pub unknown_options: Vec<String>,
+    /// Why the key was not imported
+    pub message: Option<String>,
+}
+
+/// Summary of a bulk import
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize)]
+pub struct KeyImportSummary {
+    /// Number of imported keys
+    pub imported: usize,
+    /// Number of keys which already existed
+    pub duplicate: usize,
+    /// Number of lines which could not be imported
+    pub invalid: usize,
+    /// Number of lines with options. Options are not stored with the key,
+    /// they have to be configured on the access grants instead
+    pub discarded_options: usize,
+    /// Result of every non empty line
+    pub lines: Vec<KeyImportLine>,
+}
+
+impl KeyImportSummary {
+    /// Adds the result of a line and updates the counters
+    #[inline]
+    pub fn push(&mut self, line: KeyImportLine) {
+        let counter = match line.status {
+            ImportStatus::Imported => &mut self.imported,
+            ImportStatus::Duplicate => &mut self.duplicate,
+            ImportStatus::Invalid => &mut self.invalid,
+        };
+        *counter = counter.saturating_add(1);
+        if !line.options.is_empty() || !line.unknown_options.is_empty() {
+            self.discarded_options = self.discarded_options.saturating_add(1);
+        }
+        self.lines.push(line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuthorizedKeysLine, KeyOption};
+    use crate::types::AccessOption;
+
+    #[test]
+    fn test_parse_plain() {
+        let line = AuthorizedKeysLine::parse("ssh-ed25519 AAAA test@host")
+            .expect("Unable to parse line");
+        assert!(line.options.is_empty());
+        assert_eq!(line.key, "ssh-ed25519 AAAA test@host");
+        assert_eq!(AuthorizedKeysLine::parse("  # comment"), None);
+        assert_eq!(AuthorizedKeysLine::parse("   "), None);
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let line = AuthorizedKeysLine::parse(
+            r#"from="10.0.0.1,10.0.0.2",command="echo \"hi there\"",no-pty,restrict ssh-rsa AAAA test"#,
+        )
+        .expect("Unable to parse line");
+        assert_eq!(
+            line.options,
+            vec![
+                KeyOption {
+                    option: AccessOption::From,
+                    value: Some("10.0.0.1,10.0.0.2".into()),
+                },
+                KeyOption {
+                    option: AccessOption::Command,
+                    value: Some(r#"echo "hi there""#.into()),
+                },
+                KeyOption {
+                    option: AccessOption::NoPty,
+                    value: None,
+                },
+            ]
+        );
+        assert_eq!(line.unknown_options, vec![String::from("restrict")]);
+        assert_eq!(line.key, "ssh-rsa AAAA test");
+    }
+}
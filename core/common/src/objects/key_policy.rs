@@ -0,0 +1,385 @@
+use crate::{
+    chrono::{Duration, NaiveDateTime},
+    objects::{PublicKey, SK_ECDSA, SK_ED25519},
+    serde::Serialize,
+};
+use std::{error, fmt};
+
+/// Key type which is never accepted regardless of the configuration
+pub const BANNED_KEY_TYPE: &str = "ssh-dss";
+/// Number of days before the expiry in which owners are reminded
+pub const DEFAULT_EXPIRY_WARNING: u32 = 14;
+
+/// A key type allowed by the policy
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+pub struct KeyTypeRule {
+    /// The openssh key type, e.g. `ssh-ed25519`
+    pub type_: String,
+    /// The minimum key size in bits. `None` if every size is accepted
+    pub min_size: Option<i32>,
+}
+
+/// Restricts which public keys may be uploaded
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+pub struct KeyPolicy {
+    /// The key types which are allowed
+    pub allowed: Vec<KeyTypeRule>,
+    /// Maximum number of keys a single user may own. `None` if unlimited
+    pub max_keys: Option<usize>,
+    /// Number of days after which uploaded keys expire. `None` if keys
+    /// only expire when requested by their owner
+    pub max_age: Option<u32>,
+    /// Number of days before the expiry in which keys are considered to
+    /// be expiring soon
+    pub expiry_warning: u32,
+    /// Number of days deleted keys are kept before they are removed
+    /// permanently. `None` if deleted keys are kept forever
+    pub retention: Option<u32>,
+}
+
+impl Default for KeyPolicy {
+    #[inline]
+    fn default() -> Self {
+        let rule = |type_: &str, min_size| KeyTypeRule {
+            type_: type_.into(),
+            min_size,
+        };
+        Self {
+            allowed: vec![
+                rule("ssh-ed25519", None),
+                rule("ecdsa-sha2-nistp256", None),
+                rule("ecdsa-sha2-nistp384", None),
+                rule("ecdsa-sha2-nistp521", None),
+                rule("ssh-rsa", Some(3072)),
+                rule(SK_ED25519, None),
+                rule(SK_ECDSA, None),
+            ],
+            max_keys: None,
+            max_age: None,
+            expiry_warning: DEFAULT_EXPIRY_WARNING,
+            retention: None,
+        }
+    }
+}
+
+/// Error while parsing the key policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyPolicyError {
+    /// The minimum size of a key type is not a number
+    InvalidSize(String),
+    /// The banned `ssh-dss` key type was allowed
+    Banned,
+    /// No key type is allowed
+    Empty,
+}
+
+impl fmt::Display for KeyPolicyError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSize(rule) => {
+                write!(f, "Invalid minimum key size in '{}'", rule)
+            }
+            Self::Banned => {
+                write!(f, "Key type {} cannot be allowed", BANNED_KEY_TYPE)
+            }
+            Self::Empty => write!(f, "At least one key type must be allowed"),
+        }
+    }
+}
+
+impl error::Error for KeyPolicyError {}
+
+/// Reason why a key is rejected by the policy
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+pub enum PolicyViolation {
+    /// The key type is banned
+    Banned(String),
+    /// The key type is not part of the allowed types
+    TypeNotAllowed(String),
+    /// The key is smaller than the minimum size of its type
+    TooSmall {
+        /// The key type
+        type_: String,
+        /// Size of the key. `None` if unknown
+        size: Option<i32>,
+        /// Minimum size of the key type
+        min_size: i32,
+    },
+    /// The user already owns the maximum number of keys
+    TooManyKeys(usize),
+}
+
+impl PolicyViolation {
+    /// Name of the parameter which violates the policy. Used for
+    /// notifications
+    #[must_use]
+    #[inline]
+    pub const fn para(&self) -> &'static str {
+        match self {
+            Self::Banned(_) | Self::TypeNotAllowed(_) => "Key type",
+            Self::TooSmall { .. } => "Key size",
+            Self::TooManyKeys(_) => "Number of keys",
+        }
+    }
+}
+
+impl fmt::Display for PolicyViolation {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Banned(type_) => write!(f, "Key type {} is banned", type_),
+            Self::TypeNotAllowed(type_) => {
+                write!(f, "Key type {} is not allowed", type_)
+            }
+            Self::TooSmall {
+                type_,
+                size,
+                min_size,
+            } => match size {
+                Some(size) => write!(
+                    f,
+                    "Key of type {} has {} bits but requires at least {} bits",
+                    type_, size, min_size
+                ),
+                None => write!(
+                    f,
+                    "Key of type {} requires at least {} bits",
+                    type_, min_size
+                ),
+            },
+            Self::TooManyKeys(max) => {
+                write!(f, "Users may not own more than {} keys", max)
+            }
+        }
+    }
+}
+
+impl KeyPolicy {
+    /// Parses a comma separated list of allowed key types. Every type may
+    /// be followed by `:<bits>` to require a minimum size, e.g.
+    /// `ssh-ed25519,ssh-rsa:3072`.
+    ///
+    /// # Errors
+    /// Fails if a size is not a number, no type is given or `ssh-dss`
+    /// is allowed
+    #[inline]
+    pub fn parse(
+        types: &str,
+        max_keys: Option<usize>,
+    ) -> Result<Self, KeyPolicyError> {
+        let mut allowed = Vec::new();
+        for rule in types.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+            let mut parts = rule.splitn(2, ':');
+            let type_ = parts.next().unwrap_or_default().trim();
+            if type_ == BANNED_KEY_TYPE {
+                return Err(KeyPolicyError::Banned);
+            }
+            let min_size = match parts.next() {
+                Some(size) => Some(
+                    size.trim()
+                        .parse()
+                        .map_err(|_| KeyPolicyError::InvalidSize(rule.into()))?,
+                ),
+                None => None,
+            };
+            allowed.push(KeyTypeRule {
+                type_: type_.into(),
+                min_size,
+            });
+        }
+        if allowed.is_empty() {
+            return Err(KeyPolicyError::Empty);
+        }
+        Ok(Self {
+            allowed,
+            max_keys,
+            ..Self::default()
+        })
+    }
+
+    /// Returns when a key uploaded at `now` expires. The expiry requested
+    /// by the owner is shortened to the maximum age if necessary
+    #[must_use]
+    #[inline]
+    pub fn expiry(
+        &self,
+        requested: Option<NaiveDateTime>,
+        now: NaiveDateTime,
+    ) -> Option<NaiveDateTime> {
+        let max = self
+            .max_age
+            .and_then(|days| now.checked_add_signed(Duration::days(days.into())));
+        match (requested, max) {
+            (Some(requested), Some(max)) => Some(requested.min(max)),
+            (requested, max) => requested.or(max),
+        }
+    }
+
+    /// Returns the date until which expiring keys are considered to be
+    /// expiring soon
+    #[must_use]
+    #[inline]
+    pub fn warning_date(&self, now: NaiveDateTime) -> NaiveDateTime {
+        now.checked_add_signed(Duration::days(self.expiry_warning.into()))
+            .unwrap_or(now)
+    }
+
+    /// Returns the date before which deleted keys are removed permanently.
+    /// `None` if deleted keys are kept forever
+    #[must_use]
+    #[inline]
+    pub fn purge_date(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        self.retention
+            .and_then(|days| now.checked_sub_signed(Duration::days(days.into())))
+    }
+
+    /// Checks whether the key type and size is allowed
+    ///
+    /// # Errors
+    /// Returns the reason why the key is rejected
+    #[inline]
+    pub fn check(&self, key: &PublicKey<'_>) -> Result<(), PolicyViolation> {
+        if key.type_ == BANNED_KEY_TYPE {
+            return Err(PolicyViolation::Banned(key.type_.to_string()));
+        }
+        let rule = self
+            .allowed
+            .iter()
+            .find(|rule| rule.type_ == key.type_)
+            .ok_or_else(|| PolicyViolation::TypeNotAllowed(key.type_.to_string()))?;
+        match rule.min_size {
+            Some(min_size) if key.keysize.map_or(true, |size| size < min_size) => {
+                Err(PolicyViolation::TooSmall {
+                    type_: key.type_.to_string(),
+                    size: key.keysize,
+                    min_size,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks whether a user owning the given number of keys may add
+    /// another one
+    ///
+    /// # Errors
+    /// Returns `TooManyKeys` if the maximum is reached
+    #[inline]
+    pub fn check_count(&self, existing: usize) -> Result<(), PolicyViolation> {
+        match self.max_keys {
+            Some(max) if existing >= max => Err(PolicyViolation::TooManyKeys(max)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeyPolicy, KeyPolicyError, PolicyViolation};
+    use crate::{
+        chrono::NaiveDate,
+        objects::{PublicKey, SK_ED25519},
+        types::Id,
+    };
+    use std::borrow::Cow;
+
+    fn key(type_: &str, keysize: i32) -> PublicKey<'static> {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        PublicKey {
+            id: Cow::Owned(id.clone()),
+            entity_id: Cow::Owned(id),
+            type_: Cow::Owned(type_.into()),
+            keydata: Cow::Borrowed(""),
+            comment: None,
+            keysize: Some(keysize),
+            fingerprint_md5: None,
+            fingerprint_sha256: None,
+            randomart_md5: None,
+            randomart_sha256: None,
+            upload_date: None,
+            application: None,
+            expires: None,
+            deactivated: None,
+        }
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = KeyPolicy::default();
+        assert_eq!(policy.check(&key("ssh-ed25519", 256)), Ok(()));
+        assert_eq!(policy.check(&key("ssh-rsa", 4096)), Ok(()));
+        assert_eq!(policy.check(&key(SK_ED25519, 256)), Ok(()));
+        assert_eq!(
+            policy.check(&key("ssh-rsa", 1024)),
+            Err(PolicyViolation::TooSmall {
+                type_: "ssh-rsa".into(),
+                size: Some(1024),
+                min_size: 3072,
+            })
+        );
+        assert_eq!(
+            policy.check(&key("ssh-dss", 1024)),
+            Err(PolicyViolation::Banned("ssh-dss".into()))
+        );
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        let policy = KeyPolicy::parse("ssh-ed25519, ssh-rsa:4096", Some(2))
+            .expect("Unable to parse policy");
+        assert_eq!(
+            policy.check(&key("ecdsa-sha2-nistp256", 256)),
+            Err(PolicyViolation::TypeNotAllowed(
+                "ecdsa-sha2-nistp256".into()
+            ))
+        );
+        assert!(policy.check(&key("ssh-rsa", 3072)).is_err());
+        assert_eq!(policy.check_count(1), Ok(()));
+        assert_eq!(policy.check_count(2), Err(PolicyViolation::TooManyKeys(2)));
+        assert_eq!(
+            KeyPolicy::parse("ssh-dss", None),
+            Err(KeyPolicyError::Banned)
+        );
+        assert!(KeyPolicy::parse("ssh-rsa:big", None).is_err());
+        assert_eq!(KeyPolicy::parse(" , ", None), Err(KeyPolicyError::Empty));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let now = NaiveDate::from_ymd(2020, 1, 1).and_hms(0, 0, 0);
+        let soon = NaiveDate::from_ymd(2020, 2, 1).and_hms(0, 0, 0);
+        let late = NaiveDate::from_ymd(2022, 1, 1).and_hms(0, 0, 0);
+        let policy = KeyPolicy::default();
+        assert_eq!(policy.expiry(None, now), None);
+        assert_eq!(policy.expiry(Some(late), now), Some(late));
+        assert_eq!(
+            policy.warning_date(now),
+            NaiveDate::from_ymd(2020, 1, 15).and_hms(0, 0, 0)
+        );
+
+        let policy = KeyPolicy {
+            max_age: Some(366),
+            ..KeyPolicy::default()
+        };
+        let max = NaiveDate::from_ymd(2021, 1, 1).and_hms(0, 0, 0);
+        assert_eq!(policy.expiry(None, now), Some(max));
+        assert_eq!(policy.expiry(Some(soon), now), Some(soon));
+        assert_eq!(policy.expiry(Some(late), now), Some(max));
+    }
+
+    #[test]
+    fn test_purge_date() {
+        let now = NaiveDate::from_ymd(2020, 3, 1).and_hms(0, 0, 0);
+        assert_eq!(KeyPolicy::default().purge_date(now), None);
+        let policy = KeyPolicy {
+            retention: Some(60),
+            ..KeyPolicy::default()
+        };
+        assert_eq!(
+            policy.purge_date(now),
+            Some(NaiveDate::from_ymd(2020, 1, 1).and_hms(0, 0, 0))
+        );
+    }
+}
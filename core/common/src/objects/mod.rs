@@ -1,17 +1,65 @@
 //! This module contains various database objects
 
+mod access_grant;
+mod access_request;
+mod api_token;
+mod break_glass;
+mod certificate;
+mod contact;
 mod entity;
 mod event;
 mod group;
+mod key_blocklist;
+mod key_format;
+mod key_import;
+mod key_policy;
+mod notification;
 mod public_key;
+mod recertification;
 mod server;
+mod server_note;
 mod user;
+mod webhook;
 
 pub use self::{
+    access_grant::{AccessTransition, AccessValidity},
+    access_request::{AccessRequest, AccessRequestEntry},
+    api_token::ApiToken,
+    break_glass::{
+        BreakGlass, BreakGlassEntry, BREAK_GLASS_MAX_HOURS, BREAK_GLASS_MIN_HOURS,
+    },
+    certificate::{
+        principal_name, AuthorizedPrincipals, CertificateAuthority,
+        CertificateError, CertificatePolicy, CertificatePrincipal,
+        CertificateRequest, HostCertificate, SignedCertificate,
+        DEFAULT_CERTIFICATE_MAX_VALIDITY, DEFAULT_CERTIFICATE_VALIDITY,
+        DEFAULT_HOST_CERTIFICATE_VALIDITY,
+    },
+    contact::Contact,
     entity::Entity,
-    event::{Event, EventFilter},
+    event::{Event, EventDetails, EventFilter, EVENT_DETAILS_VERSION},
     group::{Group, GroupFilter},
-    public_key::{PublicKey, PublicKeyConversionError, PublicKeyFilter},
-    server::{Server, ServerFilter},
+    key_blocklist::{KeyBlocklist, KeyBlocklistError},
+    key_import::{
+        AuthorizedKeysLine, ImportStatus, KeyImportLine, KeyImportSummary, KeyOption,
+    },
+    key_policy::{
+        KeyPolicy, KeyPolicyError, KeyTypeRule, PolicyViolation, BANNED_KEY_TYPE,
+        DEFAULT_EXPIRY_WARNING,
+    },
+    notification::{NotificationDelivery, NotificationPreferences},
+    public_key::{
+        PublicKey, PublicKeyConversionError, PublicKeyFilter, SECURITY_KEY_TYPES,
+        SK_ECDSA, SK_ED25519,
+    },
+    recertification::{
+        Recertification, RecertificationEntry, RecertificationItem,
+        RecertificationProgress,
+    },
+    server::{
+        Server, ServerAccess, ServerAccount, ServerAction, ServerFilter, ServerLogin,
+    },
+    server_note::{ServerNote, ServerNoteFilter},
     user::{User, UserFilter},
+    webhook::{Webhook, WebhookDelivery, WebhookDeliveryFilter, WebhookFilter},
 };
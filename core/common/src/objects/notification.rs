@@ -0,0 +1,97 @@
+use crate::{
+    chrono::NaiveDateTime,
+    objects::EventDetails,
+    serde::Serialize,
+    types::{DeliveryStatus, Id, NotificationType},
+};
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize)]
+/// Which notifications a user receives by mail. Users without stored
+/// preferences receive every notification
+pub struct NotificationPreferences<'a> {
+    /// The user the preferences belong to
+    pub entity_id: Cow<'a, Id>,
+    /// Notify when a public key is added to the account
+    pub key_add: bool,
+    /// Notify when access to a server account is granted or revoked
+    pub access: bool,
+    /// Notify when the synchronisation of an administered server fails.
+    /// Not offered to users yet, as no sync records a `SyncResult` event
+    pub sync_failure: bool,
+    /// Notify when a public key is about to expire
+    pub key_expiring: bool,
+}
+
+impl<'a> NotificationPreferences<'a> {
+    /// Creates preferences which enable every notification
+    #[must_use]
+    #[inline]
+    pub const fn new(entity_id: Cow<'a, Id>) -> Self {
+        Self {
+            entity_id,
+            key_add: true,
+            access: true,
+            sync_failure: true,
+            key_expiring: true,
+        }
+    }
+
+    /// Whether the user wants to be notified about the given type
+    #[must_use]
+    #[inline]
+    pub const fn is_enabled(&self, type_: NotificationType) -> bool {
+        match type_ {
+            NotificationType::KeyAdd => self.key_add,
+            NotificationType::Access => self.access,
+            NotificationType::SyncFailure => self.sync_failure,
+            NotificationType::KeyExpiring => self.key_expiring,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, Serialize)]
+/// A notification about an event queued for or sent to a user
+pub struct NotificationDelivery<'a> {
+    /// The id which uniquely identifies the delivery
+    pub id: Cow<'a, Id>,
+    /// The user who is notified
+    pub entity_id: Cow<'a, Id>,
+    /// The event the user is notified about
+    pub event_id: Cow<'a, Id>,
+    /// Details of the event
+    pub details: EventDetails<'a>,
+    /// The server or server account the event refers to, e.g.
+    /// `root@example.com`. `None` if the event does not refer to one
+    pub target: Option<Cow<'a, str>>,
+    /// The current status of the delivery
+    pub status: DeliveryStatus,
+    /// Number of attempts made so far
+    pub attempts: i32,
+    /// The time of the next attempt
+    pub next_attempt: Option<NaiveDateTime>,
+    /// The error of the last attempt
+    pub error: Option<Cow<'a, str>>,
+    /// The time the notification was queued
+    pub date: Option<NaiveDateTime>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NotificationPreferences;
+    use crate::types::{Id, NotificationType};
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_is_enabled() {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        let mut preferences = NotificationPreferences::new(Cow::Owned(id));
+        assert!(preferences.is_enabled(NotificationType::KeyAdd));
+        assert!(preferences.is_enabled(NotificationType::SyncFailure));
+
+        preferences.access = false;
+        assert!(!preferences.is_enabled(NotificationType::Access));
+        assert!(preferences.is_enabled(NotificationType::KeyExpiring));
+    }
+}
@@ -1,13 +1,23 @@
 use crate::{
-    chrono::NaiveDateTime,
+    chrono::{NaiveDate, NaiveDateTime},
     database::{Database, DatabaseError},
+    objects::{key_format, AuthorizedKeysLine, KeyBlocklist},
+    ring::digest::{digest, SHA256},
     serde::Serialize,
     types::Id,
     types::{FingerprintMd5, FingerprintSha256},
 };
 use bishop::{BishopArt, DrawingOptions};
+use md5::{Digest, Md5};
 use std::{borrow::Cow, convert::TryFrom};
 
+/// Key type of ed25519 keys backed by a FIDO/U2F security key
+pub const SK_ED25519: &str = "sk-ssh-ed25519@openssh.com";
+/// Key type of ecdsa keys backed by a FIDO/U2F security key
+pub const SK_ECDSA: &str = "sk-ecdsa-sha2-nistp256@openssh.com";
+/// Key types backed by a FIDO/U2F security key
+pub const SECURITY_KEY_TYPES: [&str; 2] = [SK_ED25519, SK_ECDSA];
+
 /// Conversion Error happening when trying to parse an invalid public key
 #[derive(Debug)]
 pub enum PublicKeyConversionError<D: Database> {
@@ -15,6 +25,10 @@ pub enum PublicKeyConversionError<D: Database> {
     DatabaseError(DatabaseError<D>),
     /// OpenSSH was unable to parse the key
     OpenSshError(openssh_keys::errors::Error),
+    /// The key claims to be a security key but its data is malformed
+    InvalidSecurityKey,
+    /// The key is known to be compromised
+    Compromised,
 }
 
 #[derive(Debug, Clone, Hash, Serialize)]
@@ -42,9 +56,31 @@ pub struct PublicKey<'a> {
     pub randomart_sha256: Option<Cow<'a, str>>,
     /// The time when the user uploaded the key
     pub upload_date: Option<NaiveDateTime>,
+    /// The application string of security keys, usually `ssh:`
+    pub application: Option<Cow<'a, str>>,
+    /// The time after which the key must no longer be used. `None` if
+    /// the key never expires
+    pub expires: Option<NaiveDateTime>,
+    /// The time when the key was deleted. `None` for active keys
+    pub deactivated: Option<NaiveDateTime>,
 }
 
 impl<'a> PublicKey<'a> {
+    #[must_use]
+    #[inline]
+    /// Whether the key is backed by a FIDO/U2F security key
+    pub fn is_security_key(&self) -> bool {
+        SECURITY_KEY_TYPES.contains(&self.type_.as_ref())
+    }
+
+    #[must_use]
+    #[inline]
+    /// Whether the key is expired at the given time. Expired keys must not
+    /// be written into `authorized_keys` files
+    pub fn is_expired(&self, now: NaiveDateTime) -> bool {
+        self.expires.map_or(false, |expires| expires <= now)
+    }
+
     #[must_use]
     #[inline]
     /// Converts the key to its openssh authorized keys representation
@@ -57,25 +93,68 @@ impl<'a> PublicKey<'a> {
         )
     }
 
+    #[must_use]
+    #[inline]
+    /// Converts the key to the RFC4716 format used by PuTTY and other
+    /// SSH2 implementations
+    pub fn to_rfc4716(&self) -> String {
+        key_format::to_rfc4716(&self.keydata, self.comment.as_deref())
+    }
+
+    #[must_use]
+    #[inline]
+    /// Converts the key to a PEM encoded PKCS8 public key. Returns `None`
+    /// for security keys which have no PEM representation
+    pub fn to_pem(&self) -> Option<String> {
+        key_format::to_pem(&self.keydata)
+    }
+
+    /// Parses an expiry date in the format `%Y-%m-%d`. Keys expire at the
+    /// end of the given day
+    #[must_use]
+    #[inline]
+    pub fn parse_expiry(value: &str) -> Option<NaiveDateTime> {
+        NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+            .ok()
+            .map(|date| date.and_hms(23, 59, 59))
+    }
+
     /// Tries to parse a `PublicKey` from String. String must be in the openssh
-    /// `authorized_keys` format. This method does not save the public key to the
-    /// database.
+    /// `authorized_keys`, the RFC4716 or the PEM format. This method does not
+    /// save the public key to the database.
     ///
     /// # Errors
-    /// Fails if key is not in the openssh `authorized_keys` format or when the
-    /// database cannot create an uuid
+    /// Fails if key is not in one of the supported formats, when its
+    /// fingerprint is part of the blocklist or when the database cannot
+    /// create an uuid
     #[inline]
     pub fn parse<D: Database>(
         data: &str,
         owner: &'a Id,
         db: &D,
+        blocklist: &KeyBlocklist,
     ) -> Result<Self, PublicKeyConversionError<D>> {
-        openssh_keys::PublicKey::parse(data)
-            .map_err(PublicKeyConversionError::OpenSshError)
-            .and_then(|key| {
-                Self::from_openssh(&key, owner, db)
-                    .map_err(PublicKeyConversionError::DatabaseError)
-            })
+        let converted = key_format::to_openssh(data);
+        let data = converted.as_ref().map_or(data, |(key, _)| key.as_str());
+        let mut key = if let Some((type_, rest)) = SecurityKey::find(data) {
+            let key = SecurityKey::parse(type_, rest)
+                .ok_or(PublicKeyConversionError::InvalidSecurityKey)?;
+            Self::from_security_key(key, owner, db)
+                .map_err(PublicKeyConversionError::DatabaseError)?
+        } else {
+            let key = openssh_keys::PublicKey::parse(data)
+                .map_err(PublicKeyConversionError::OpenSshError)?;
+            Self::from_openssh(&key, owner, db)
+                .map_err(PublicKeyConversionError::DatabaseError)?
+        };
+        // openssh-keys only keeps the first word of a comment
+        if let Some((_, Some(comment))) = converted {
+            key.comment = Some(Cow::Owned(comment));
+        }
+        if blocklist.contains(&key) {
+            return Err(PublicKeyConversionError::Compromised);
+        }
+        Ok(key)
     }
 
     fn from_openssh<D: Database>(
@@ -96,13 +175,14 @@ impl<'a> PublicKey<'a> {
             FingerprintSha256::from_string(Cow::Owned(value.fingerprint()))
                 .ok()
                 .map(Cow::Owned);
+        let top_text = format!("{} {}", value.keytype(), value.size());
         let randomart_md5 = fingerprint_md5
             .as_ref()
-            .map(|f| Self::create_randomart_md5(value, f))
+            .map(|f| Self::create_randomart_md5(top_text.clone(), f))
             .map(Cow::Owned);
         let randomart_sha256 = fingerprint_sha256
             .as_ref()
-            .map(|f| Self::create_randomart_sha256(value, f))
+            .map(|f| Self::create_randomart_sha256(top_text, f))
             .map(Cow::Owned);
 
         Ok(Self {
@@ -117,14 +197,53 @@ impl<'a> PublicKey<'a> {
             randomart_md5,
             randomart_sha256,
             upload_date: None,
+            application: None,
+            expires: None,
+            deactivated: None,
+        })
+    }
+
+    fn from_security_key<D: Database>(
+        value: SecurityKey,
+        owner: &'a Id,
+        db: &D,
+    ) -> Result<Self, DatabaseError<D>> {
+        let id = Cow::Owned(db.generate_id()?);
+        let entity_id = Cow::Borrowed(owner);
+        let fingerprint_md5 = FingerprintMd5::from_bytes(Cow::Owned(
+            Md5::digest(&value.blob).to_vec(),
+        ));
+        let fingerprint_sha256 = FingerprintSha256::from_bytes(Cow::Owned(
+            digest(&SHA256, &value.blob).as_ref().to_vec(),
+        ));
+        let top_text = format!("{} {}", value.short_name(), SecurityKey::SIZE);
+        let randomart_md5 =
+            Self::create_randomart_md5(top_text.clone(), &fingerprint_md5);
+        let randomart_sha256 =
+            Self::create_randomart_sha256(top_text, &fingerprint_sha256);
+
+        Ok(Self {
+            id,
+            entity_id,
+            type_: Cow::Borrowed(value.type_),
+            keydata: Cow::Owned(base64::encode(&value.blob)),
+            comment: value.comment.map(Cow::Owned),
+            keysize: Some(SecurityKey::SIZE),
+            fingerprint_md5: Some(Cow::Owned(fingerprint_md5)),
+            fingerprint_sha256: Some(Cow::Owned(fingerprint_sha256)),
+            randomart_md5: Some(Cow::Owned(randomart_md5)),
+            randomart_sha256: Some(Cow::Owned(randomart_sha256)),
+            upload_date: None,
+            application: Some(Cow::Owned(value.application)),
+            expires: None,
+            deactivated: None,
         })
     }
 
     fn create_randomart_md5(
-        pk: &openssh_keys::PublicKey,
+        top_text: String,
         fingerprint: &FingerprintMd5<'_>,
     ) -> String {
-        let top_text = format!("{} {}", pk.keytype(), pk.size());
         let options = DrawingOptions {
             top_text,
             bottom_text: "MD5".into(),
@@ -138,10 +257,9 @@ impl<'a> PublicKey<'a> {
     }
 
     fn create_randomart_sha256(
-        pk: &openssh_keys::PublicKey,
+        top_text: String,
         fingerprint: &FingerprintSha256<'_>,
     ) -> String {
-        let top_text = format!("{} {}", pk.keytype(), pk.size());
         let options = DrawingOptions {
             top_text,
             bottom_text: "SHA256".into(),
@@ -155,6 +273,82 @@ impl<'a> PublicKey<'a> {
     }
 }
 
+/// A FIDO/U2F security key which openssh-keys is unable to parse
+#[derive(Debug)]
+struct SecurityKey {
+    type_: &'static str,
+    blob: Vec<u8>,
+    application: String,
+    comment: Option<String>,
+}
+
+impl SecurityKey {
+    /// Both supported security key types use 256 bit keys
+    const SIZE: i32 = 256;
+
+    /// Looks for a security key type at the key type position of an
+    /// `authorized_keys` line, i.e. after any options. Returns the type and
+    /// everything following it
+    fn find(data: &str) -> Option<(&'static str, &str)> {
+        let line = AuthorizedKeysLine::parse(data)?;
+        let mut parts = line.key.splitn(2, char::is_whitespace);
+        let type_ = parts.next()?;
+        let rest = parts.next()?;
+        SECURITY_KEY_TYPES
+            .iter()
+            .find(|&&v| v == type_)
+            .map(|&v| (v, rest))
+    }
+
+    /// Parses the base64 encoded key and comment following the type
+    fn parse(type_: &'static str, rest: &str) -> Option<Self> {
+        let mut parts = rest.trim().splitn(2, char::is_whitespace);
+        let blob = base64::decode(parts.next()?).ok()?;
+        let comment = parts
+            .next()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(String::from);
+
+        let mut reader = blob.as_slice();
+        if key_format::read_string(&mut reader)? != type_.as_bytes() {
+            return None;
+        }
+        let key_len = if type_ == SK_ED25519 {
+            32
+        } else {
+            if key_format::read_string(&mut reader)? != b"nistp256" {
+                return None;
+            }
+            65
+        };
+        if key_format::read_string(&mut reader)?.len() != key_len {
+            return None;
+        }
+        let application =
+            String::from_utf8(key_format::read_string(&mut reader)?.to_vec())
+                .ok()?;
+        if !reader.is_empty() {
+            return None;
+        }
+        Some(Self {
+            type_,
+            blob,
+            application,
+            comment,
+        })
+    }
+
+    /// The name openssh uses in the randomart header
+    fn short_name(&self) -> &'static str {
+        if self.type_ == SK_ED25519 {
+            "ED25519-SK"
+        } else {
+            "ECDSA-SK"
+        }
+    }
+}
+
 #[derive(Debug, Clone, Hash, Serialize)]
 /// Provides fields to filter when searching for multiple
 /// objects
@@ -173,6 +367,18 @@ pub struct PublicKeyFilter<'a> {
     pub fingerprint_md5: Option<Cow<'a, FingerprintMd5<'a>>>,
     /// The sha256 fingerprint must be equal to this value
     pub fingerprint_sha256: Option<Cow<'a, FingerprintSha256<'a>>>,
+    /// Whether the key must or must not be backed by a security key
+    pub security_key: Option<bool>,
+    /// The key must expire before this date
+    pub expires_before: Option<NaiveDateTime>,
+    /// The key must expire after this date
+    pub expires_after: Option<NaiveDateTime>,
+    /// Whether to include expired keys. They are left out by default as
+    /// they must no longer be used
+    pub expired: bool,
+    /// Whether to search deleted instead of active keys. Only honored
+    /// for admins
+    pub deactivated: bool,
 }
 
 impl Default for PublicKeyFilter<'_> {
@@ -186,6 +392,11 @@ impl Default for PublicKeyFilter<'_> {
             keysize_le: None,
             fingerprint_md5: None,
             fingerprint_sha256: None,
+            security_key: None,
+            expires_before: None,
+            expires_after: None,
+            expired: false,
+            deactivated: false,
         }
     }
 }
@@ -213,6 +424,27 @@ impl<'a, I: Iterator<Item = (Cow<'a, str>, Cow<'a, str>)>> From<I>
                 "keysize-max" => {
                     filter.keysize_le = val.parse::<i32>().ok();
                 }
+                "security-key" => {
+                    filter.security_key = val.parse::<bool>().ok();
+                }
+                "expires-before" => {
+                    filter.expires_before =
+                        NaiveDate::parse_from_str(&val, "%Y-%m-%d")
+                            .ok()
+                            .map(|date| date.and_hms(0, 0, 0));
+                }
+                "expires-after" => {
+                    filter.expires_after =
+                        NaiveDate::parse_from_str(&val, "%Y-%m-%d")
+                            .ok()
+                            .map(|date| date.and_hms(23, 59, 59));
+                }
+                "expired" => {
+                    filter.expired = val.parse::<bool>().unwrap_or(false);
+                }
+                "deactivated" => {
+                    filter.deactivated = val.parse::<bool>().unwrap_or(false);
+                }
                 "fingerprint" => {
                     filter.fingerprint_md5 =
                         Some(Cow::Owned(FingerprintMd5::from_string(val.clone())));
@@ -228,9 +460,11 @@ impl<'a, I: Iterator<Item = (Cow<'a, str>, Cow<'a, str>)>> From<I>
 
 #[cfg(test)]
 mod tests {
-    use super::{PublicKey, PublicKeyFilter};
+    use super::{PublicKey, PublicKeyConversionError, PublicKeyFilter};
     use crate::{
+        chrono::NaiveDate,
         database::{Database, DatabaseError},
+        objects::KeyBlocklist,
         types::{FingerprintMd5, FingerprintSha256, Id},
     };
     use std::borrow::Cow;
@@ -288,8 +522,9 @@ mod tests {
         let id = db.generate_id().expect("Unable to generate id");
 
         let entity_id = Id::from_string(owner_id).expect("Invalid Id");
-        let key =
-            PublicKey::parse(key_str, &entity_id, &db).expect("Unable to parse key");
+        let blocklist = KeyBlocklist::default();
+        let key = PublicKey::parse(key_str, &entity_id, &db, &blocklist)
+            .expect("Unable to parse key");
 
         assert_eq!(key.id, Cow::Owned(id));
         assert_eq!(key.entity_id, Cow::Borrowed(&entity_id));
@@ -307,9 +542,123 @@ mod tests {
         assert_eq!(key.randomart_md5, Some(random_art_md5.into()));
         assert_eq!(key.randomart_sha256, Some(random_art_sha256.into()));
         assert_eq!(key.upload_date, None);
+        assert_eq!(key.application, None);
+        assert!(!key.is_security_key());
         assert_eq!(key.to_plain(), key_str);
     }
 
+    #[test]
+    fn expiry() {
+        let key_str = r"ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAICJYFFs98Fs9qm/BTK3J7/lOXZzKKqf/Fh33WidjECBG";
+        let entity_id = Id::from_string("c6efb44e-9b67-4dc0-a31b-6482476ed8b7")
+            .expect("Invalid Id");
+        let key =
+            PublicKey::parse(key_str, &entity_id, &TestDb, &KeyBlocklist::default())
+                .expect("Unable to parse key");
+
+        let now = NaiveDate::from_ymd(2020, 10, 1).and_hms(12, 0, 0);
+        assert!(!key.is_expired(now));
+        let key = PublicKey {
+            expires: Some(NaiveDate::from_ymd(2020, 10, 1).and_hms(0, 0, 0)),
+            ..key
+        };
+        assert!(key.is_expired(now));
+        assert!(!key.is_expired(NaiveDate::from_ymd(2020, 9, 30).and_hms(0, 0, 0)));
+        assert_eq!(
+            PublicKey::parse_expiry("2020-10-01"),
+            Some(NaiveDate::from_ymd(2020, 10, 1).and_hms(23, 59, 59))
+        );
+        assert_eq!(PublicKey::parse_expiry("tomorrow"), None);
+    }
+
+    #[test]
+    fn parse_security_key() {
+        let owner_id = r"c6efb44e-9b67-4dc0-a31b-6482476ed8b7";
+        let keydata = r"AAAAGnNrLXNzaC1lZDI1NTE5QG9wZW5zc2guY29tAAAAIAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8gAAAABHNzaDo=";
+        let key_str = format!("sk-ssh-ed25519@openssh.com {} yubikey", keydata);
+        let fingerprint_md5 = FingerprintMd5::from_string(
+            r"04:dc:9b:b0:a6:43:8e:07:e2:b4:5c:dc:ff:08:95:c5".into(),
+        );
+        let fingerprint_sha256 = FingerprintSha256::from_string(Cow::Borrowed(
+            r"nX0hpi5mKbTIJ10yBq5/sLQhiI1Y0RBAqN0drDdgJWs",
+        ))
+        .expect("Unable to convert fingerprint");
+
+        let db = TestDb;
+        let entity_id = Id::from_string(owner_id).expect("Invalid Id");
+        let key = PublicKey::parse(
+            &format!("no-touch-required {}", key_str),
+            &entity_id,
+            &db,
+            &KeyBlocklist::default(),
+        )
+        .expect("Unable to parse key");
+
+        assert_eq!(key.type_, "sk-ssh-ed25519@openssh.com");
+        assert_eq!(key.keydata, keydata);
+        assert_eq!(key.comment, Some("yubikey".into()));
+        assert_eq!(key.keysize, Some(256));
+        assert_eq!(key.application, Some("ssh:".into()));
+        assert_eq!(key.fingerprint_md5, Some(Cow::Owned(fingerprint_md5)));
+        assert_eq!(key.fingerprint_sha256, Some(Cow::Owned(fingerprint_sha256)));
+        assert!(key.is_security_key());
+        assert_eq!(key.to_plain(), key_str);
+
+        let truncated =
+            "sk-ssh-ed25519@openssh.com AAAAGnNrLXNzaC1lZDI1NTE5QG9wZW5zc2guY29t";
+        assert!(matches!(
+            PublicKey::parse(truncated, &entity_id, &db, &KeyBlocklist::default()),
+            Err(PublicKeyConversionError::InvalidSecurityKey)
+        ));
+
+        let key_str = format!(
+            "ssh-ed25519 {} migrated from {} key",
+            r"AAAAC3NzaC1lZDI1NTE5AAAAICJYFFs98Fs9qm/BTK3J7/lOXZzKKqf/Fh33WidjECBG",
+            "sk-ssh-ed25519@openssh.com"
+        );
+        let key =
+            PublicKey::parse(&key_str, &entity_id, &db, &KeyBlocklist::default())
+                .expect("Unable to parse key");
+        assert_eq!(key.type_, "ssh-ed25519");
+        assert!(!key.is_security_key());
+    }
+
+    #[test]
+    fn parse_formats() {
+        let owner_id = r"c6efb44e-9b67-4dc0-a31b-6482476ed8b7";
+        let keydata =
+            r"AAAAC3NzaC1lZDI1NTE5AAAAICJYFFs98Fs9qm/BTK3J7/lOXZzKKqf/Fh33WidjECBG";
+        let rfc4716 = r#"---- BEGIN SSH2 PUBLIC KEY ----
+Comment: "256-bit ED25519, converted by root@vm from OpenSSH"
+AAAAC3NzaC1lZDI1NTE5AAAAICJYFFs98Fs9qm/BTK3J7/lOXZzKKqf/Fh33WidjECBG
+---- END SSH2 PUBLIC KEY ----
+"#;
+        let pem = r"-----BEGIN PUBLIC KEY-----
+MCowBQYDK2VwAyEAIlgUWz3wWz2qb8FMrcnv+U5dnMoqp/8WHfdaJ2MQIEY=
+-----END PUBLIC KEY-----
+";
+
+        let db = TestDb;
+        let entity_id = Id::from_string(owner_id).expect("Invalid Id");
+        let key =
+            PublicKey::parse(rfc4716, &entity_id, &db, &KeyBlocklist::default())
+                .expect("Unable to parse RFC4716 key");
+        assert_eq!(key.type_, "ssh-ed25519");
+        assert_eq!(key.keydata, keydata);
+        assert_eq!(
+            key.comment,
+            Some("256-bit ED25519, converted by root@vm from OpenSSH".into())
+        );
+        assert_eq!(key.to_rfc4716(), rfc4716);
+        assert_eq!(key.to_pem().as_deref(), Some(pem));
+
+        let key = PublicKey::parse(pem, &entity_id, &db, &KeyBlocklist::default())
+            .expect("Unable to parse PEM key");
+        assert_eq!(key.type_, "ssh-ed25519");
+        assert_eq!(key.keydata, keydata);
+        assert_eq!(key.comment, None);
+    }
+
     #[test]
     fn filter() {
         let type_ = "test_type";
@@ -324,6 +673,11 @@ mod tests {
             ("keysize-min", keysize_min),
             ("other2", "other2"),
             ("keysize-max", kesize_max),
+            ("security-key", "true"),
+            ("expires-before", "2021-01-31"),
+            ("expires-after", "invalid"),
+            ("expired", "true"),
+            ("deactivated", "true"),
             ("fingerprint", fingerprint_md5),
             ("other3", "other3"),
         ]
@@ -336,6 +690,14 @@ mod tests {
         assert_eq!(filter.comment, Some(comment.into()));
         assert_eq!(filter.keysize_ge, Some(20));
         assert_eq!(filter.keysize_le, Some(2000));
+        assert_eq!(filter.security_key, Some(true));
+        assert_eq!(
+            filter.expires_before,
+            Some(NaiveDate::from_ymd(2021, 1, 31).and_hms(0, 0, 0))
+        );
+        assert_eq!(filter.expires_after, None);
+        assert!(filter.expired);
+        assert!(filter.deactivated);
         assert_eq!(
             filter.fingerprint_md5,
             Some(Cow::Owned(FingerprintMd5::from_string(
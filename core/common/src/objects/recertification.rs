@@ -0,0 +1,304 @@
+use crate::{
+    chrono::NaiveDateTime,
+    serde::Serialize,
+    types::{Id, RecertificationDecision, RecertificationKind},
+};
+use std::{borrow::Cow, collections::HashSet};
+
+/// A campaign in which server admins review who can log in where. Access
+/// which is marked for revocation is removed when the campaign is closed
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct Recertification<'a> {
+    /// The id which uniquely identifies the campaign
+    pub id: Cow<'a, Id>,
+    /// Name of the campaign, e.g. the quarter it covers
+    pub name: Cow<'a, str>,
+    /// The entity which started the campaign
+    pub started_by: Option<Cow<'a, Id>>,
+    /// The date and time when the campaign was started
+    pub start_date: Option<NaiveDateTime>,
+    /// The entity which closed the campaign
+    pub closed_by: Option<Cow<'a, Id>>,
+    /// The date and time when the campaign was closed. `None` while the
+    /// campaign is open
+    pub close_date: Option<NaiveDateTime>,
+}
+
+impl<'a> Recertification<'a> {
+    /// Creates a new open campaign
+    #[must_use]
+    #[inline]
+    pub fn new(
+        id: Cow<'a, Id>,
+        name: Cow<'a, str>,
+        started_by: Option<Cow<'a, Id>>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            started_by,
+            start_date: None,
+            closed_by: None,
+            close_date: None,
+        }
+    }
+
+    /// Checks whether decisions may still be made
+    #[must_use]
+    #[inline]
+    pub fn is_open(&self) -> bool {
+        self.close_date.is_none()
+    }
+
+    /// Records the closing of the campaign. Returns `false` without
+    /// changing the campaign if it was already closed
+    #[inline]
+    pub fn close(&mut self, closed_by: Cow<'a, Id>, now: NaiveDateTime) -> bool {
+        if !self.is_open() {
+            return false;
+        }
+        self.closed_by = Some(closed_by);
+        self.close_date = Some(now);
+        true
+    }
+}
+
+/// A single access which is reviewed during a campaign. The access is
+/// copied when the campaign starts, so later changes do not affect it
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct RecertificationItem<'a> {
+    /// The id which uniquely identifies the item
+    pub id: Cow<'a, Id>,
+    /// The campaign the item belongs to
+    pub campaign_id: Cow<'a, Id>,
+    /// The server whose admins review the item
+    pub server_id: Cow<'a, Id>,
+    /// The entity which has access, or the member of the group
+    pub source_id: Cow<'a, Id>,
+    /// The server account access was granted to, or the group
+    pub dest_id: Cow<'a, Id>,
+    /// Whether the item is an access grant or a group membership
+    pub kind: RecertificationKind,
+    /// The decision of the server admins. `None` until decided
+    pub decision: Option<RecertificationDecision>,
+    /// The entity which decided
+    pub decided_by: Option<Cow<'a, Id>>,
+    /// The date and time of the decision
+    pub decision_date: Option<NaiveDateTime>,
+}
+
+impl<'a> RecertificationItem<'a> {
+    /// Records the decision on the item. A decision may be changed until
+    /// the campaign is closed. Returns `false` if the item already has the
+    /// same decision
+    #[inline]
+    pub fn decide(
+        &mut self,
+        decision: RecertificationDecision,
+        decided_by: Cow<'a, Id>,
+        now: NaiveDateTime,
+    ) -> bool {
+        if self.decision == Some(decision) {
+            return false;
+        }
+        self.decision = Some(decision);
+        self.decided_by = Some(decided_by);
+        self.decision_date = Some(now);
+        true
+    }
+
+    /// Returns the items marked for revocation. An access or membership
+    /// which another item marks to be kept is left out, the reviewers did
+    /// not agree on it
+    #[must_use]
+    #[inline]
+    pub fn revocations(items: &[Self]) -> Vec<Self> {
+        let kept: HashSet<(RecertificationKind, &Id, &Id)> = items
+            .iter()
+            .filter(|v| v.decision == Some(RecertificationDecision::Keep))
+            .map(|v| (v.kind, &*v.source_id, &*v.dest_id))
+            .collect();
+        items
+            .iter()
+            .filter(|v| v.decision == Some(RecertificationDecision::Revoke))
+            .filter(|v| !kept.contains(&(v.kind, &*v.source_id, &*v.dest_id)))
+            .cloned()
+            .collect()
+    }
+}
+
+/// An item together with the names required to decide on it
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct RecertificationEntry<'a> {
+    /// The item itself
+    pub item: RecertificationItem<'a>,
+    /// The hostname of the server
+    pub hostname: Cow<'a, str>,
+    /// The uid of the user or the name of the group which has access.
+    /// `None` if the entity is neither or was deleted
+    pub source_name: Option<Cow<'a, str>>,
+    /// The name of the server account or of the group. `None` if it was
+    /// deleted
+    pub dest_name: Option<Cow<'a, str>>,
+}
+
+/// A campaign together with the number of decided items
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct RecertificationProgress<'a> {
+    /// The campaign itself
+    pub campaign: Recertification<'a>,
+    /// Number of items in the campaign
+    pub total: u64,
+    /// Number of items with a decision
+    pub decided: u64,
+    /// Number of items marked for revocation
+    pub revoked: u64,
+}
+
+impl RecertificationProgress<'_> {
+    /// Checks whether every item was decided
+    #[must_use]
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.decided >= self.total
+    }
+
+    /// Share of decided items in percent. An empty campaign is complete
+    #[must_use]
+    #[inline]
+    pub fn percent(&self) -> u64 {
+        self.decided
+            .saturating_mul(100)
+            .checked_div(self.total)
+            .unwrap_or(100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Recertification, RecertificationItem, RecertificationProgress};
+    use crate::{
+        chrono::NaiveDate,
+        types::{Id, RecertificationDecision, RecertificationKind},
+    };
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_close() {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        let mut campaign =
+            Recertification::new(Cow::Borrowed(&id), Cow::Borrowed("Q4 2020"), None);
+        assert!(campaign.is_open());
+        let now = NaiveDate::from_ymd(2020, 12, 8).and_hms(12, 0, 0);
+        assert!(campaign.close(Cow::Borrowed(&id), now));
+        assert!(!campaign.is_open());
+        assert!(!campaign.close(Cow::Borrowed(&id), now));
+        assert_eq!(campaign.close_date, Some(now));
+    }
+
+    #[test]
+    fn test_decide() {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        let mut item = RecertificationItem {
+            id: Cow::Borrowed(&id),
+            campaign_id: Cow::Borrowed(&id),
+            server_id: Cow::Borrowed(&id),
+            source_id: Cow::Borrowed(&id),
+            dest_id: Cow::Borrowed(&id),
+            kind: RecertificationKind::Access,
+            decision: None,
+            decided_by: None,
+            decision_date: None,
+        };
+        let now = NaiveDate::from_ymd(2020, 12, 8).and_hms(12, 0, 0);
+        assert!(item.decide(RecertificationDecision::Keep, Cow::Borrowed(&id), now));
+        assert!(!item.decide(
+            RecertificationDecision::Keep,
+            Cow::Borrowed(&id),
+            now
+        ));
+        assert!(item.decide(
+            RecertificationDecision::Revoke,
+            Cow::Borrowed(&id),
+            now
+        ));
+        assert_eq!(item.decision, Some(RecertificationDecision::Revoke));
+    }
+
+    #[test]
+    fn test_revocations() {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        let other = Id::from_string("c6efb44e-9b67-4dc0-a31b-6482476ed8b7")
+            .expect("Invalid Id");
+        let item = |source_id: &Id, kind, decision| RecertificationItem {
+            id: Cow::Owned(id.clone()),
+            campaign_id: Cow::Owned(id.clone()),
+            server_id: Cow::Owned(id.clone()),
+            source_id: Cow::Owned(source_id.clone()),
+            dest_id: Cow::Owned(id.clone()),
+            kind,
+            decision,
+            decided_by: None,
+            decision_date: None,
+        };
+        let items = [
+            item(
+                &id,
+                RecertificationKind::Access,
+                Some(RecertificationDecision::Revoke),
+            ),
+            item(
+                &id,
+                RecertificationKind::Access,
+                Some(RecertificationDecision::Keep),
+            ),
+            item(
+                &id,
+                RecertificationKind::Membership,
+                Some(RecertificationDecision::Revoke),
+            ),
+            item(
+                &other,
+                RecertificationKind::Access,
+                Some(RecertificationDecision::Revoke),
+            ),
+            item(&other, RecertificationKind::Membership, None),
+        ];
+        let revoked = RecertificationItem::revocations(&items);
+        let revoked: Vec<_> = revoked
+            .iter()
+            .map(|v| (v.kind, v.source_id.as_ref()))
+            .collect();
+        assert_eq!(
+            revoked,
+            vec![
+                (RecertificationKind::Membership, &id),
+                (RecertificationKind::Access, &other),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_progress() {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        let campaign =
+            Recertification::new(Cow::Borrowed(&id), Cow::Borrowed("Q4 2020"), None);
+        let mut progress = RecertificationProgress {
+            campaign,
+            total: 8,
+            decided: 2,
+            revoked: 1,
+        };
+        assert_eq!(progress.percent(), 25);
+        assert!(!progress.is_complete());
+        progress.decided = 8;
+        assert!(progress.is_complete());
+        progress.total = 0;
+        progress.decided = 0;
+        assert_eq!(progress.percent(), 100);
+    }
+}
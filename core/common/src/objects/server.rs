@@ -1,8 +1,25 @@
 use crate::{
+    base64,
+    objects::{AccessValidity, Entity},
+    ring::{
+        hmac,
+        rand::{SecureRandom, SystemRandom},
+    },
+    sec::SecretError,
     serde::Serialize,
     types::{AuthorizationType, Id, KeyManagement, SyncStatusType},
 };
-use std::{borrow::Cow, convert::TryFrom};
+use std::{borrow::Cow, convert::TryFrom, fmt::Write};
+
+/// Port ssh uses if none is given. Other ports are part of the
+/// `known_hosts` pattern
+const DEFAULT_SSH_PORT: i32 = 22;
+/// Length of the salt of hashed `known_hosts` names, same as `ssh-keygen -H`
+const KNOWN_HOSTS_SALT_LEN: usize = 20;
+/// Characters which would change the meaning of a `ssh_config` value
+const SSH_CONFIG_SPECIAL: &[char] = &['"', '\'', '\\', '#', '='];
+/// Characters with a special meaning in `Host` patterns
+const SSH_CONFIG_PATTERN: &[char] = &['*', '?', '!', ','];
 
 #[derive(Debug, Clone, Hash, Serialize)]
 /// Defines the Server structure in the database
@@ -25,6 +42,244 @@ pub struct Server<'a> {
     pub rsa_key_fingerprint: Option<Cow<'a, str>>,
     /// The ssh port
     pub port: i32,
+    /// Whether only keys backed by a hardware security key may access
+    /// the server
+    pub require_security_key: bool,
+    /// The host key matching the fingerprint in OpenSSH format. Stored once
+    /// the server presented it
+    pub host_key: Option<Cow<'a, str>>,
+    /// The jump host used to reach the server, in the format expected by
+    /// `ProxyJump`
+    pub proxy_jump: Option<Cow<'a, str>>,
+}
+
+impl Server<'_> {
+    /// The names the server is reachable by as used in `known_hosts`.
+    /// Contains the hostname and the ip address if it differs. Both are
+    /// written as `[name]:port` if the server does not use port 22
+    #[must_use]
+    #[inline]
+    pub fn host_patterns(&self) -> Vec<String> {
+        let mut names = vec![&*self.hostname];
+        if let Some(ref ip_address) = self.ip_address {
+            if *ip_address != self.hostname {
+                names.push(ip_address);
+            }
+        }
+        names
+            .into_iter()
+            .map(|name| {
+                if self.port == DEFAULT_SSH_PORT {
+                    name.to_string()
+                } else {
+                    format!("[{}]:{}", name, self.port)
+                }
+            })
+            .collect()
+    }
+
+    /// Renders the host key as `known_hosts` entry. Hashed entries use a
+    /// separate line with its own salt for every name, like
+    /// `ssh-keygen -H` does. Returns `Ok(None)` if no host key is stored
+    ///
+    /// # Errors
+    /// Fails when no random data is available for the salt
+    #[inline]
+    pub fn to_known_hosts(
+        &self,
+        hashed: bool,
+    ) -> Result<Option<String>, SecretError> {
+        match self.host_key {
+            Some(ref host_key) => {
+                self.known_hosts_entry(None, host_key, hashed).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Renders a `known_hosts` entry binding the key to the names of the
+    /// server. The marker, e.g. `@cert-authority`, is put in front of
+    /// every line
+    ///
+    /// # Errors
+    /// Fails when no random data is available for the salt
+    #[inline]
+    pub fn known_hosts_entry(
+        &self,
+        marker: Option<&str>,
+        key: &str,
+        hashed: bool,
+    ) -> Result<String, SecretError> {
+        let marker =
+            marker.map_or_else(String::new, |marker| format!("{} ", marker));
+        let patterns = self.host_patterns();
+        if !hashed {
+            return Ok(format!("{}{} {}\n", marker, patterns.join(","), key));
+        }
+        let rng = SystemRandom::new();
+        let mut entry = String::new();
+        for pattern in patterns {
+            let mut salt = [0_u8; KNOWN_HOSTS_SALT_LEN];
+            rng.fill(&mut salt).map_err(|_| SecretError::Random)?;
+            let _ =
+                writeln!(entry, "{}{} {}", marker, hash_host(&pattern, &salt), key);
+        }
+        Ok(entry)
+    }
+}
+
+/// Hashes a `known_hosts` name with the given salt
+fn hash_host(name: &str, salt: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, salt);
+    let tag = hmac::sign(&key, name.as_bytes());
+    format!(
+        "|1|{}|{}",
+        base64::encode(salt),
+        base64::encode(tag.as_ref())
+    )
+}
+
+/// A named server account a user can log into
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct ServerLogin<'a> {
+    /// The server the account belongs to
+    pub server: Server<'a>,
+    /// The entity id of the account
+    pub account_id: Cow<'a, Id>,
+    /// The name of the account
+    pub account: Cow<'a, str>,
+    /// The period in which the user has access to the account. Unrestricted
+    /// if access is not time-bound or unknown
+    pub validity: AccessValidity,
+}
+
+impl ServerLogin<'_> {
+    /// The name used as `Host` in `ssh_config`. Consists of hostname and
+    /// account so that every account gets its own entry
+    #[must_use]
+    #[inline]
+    pub fn alias(&self) -> String {
+        format!("{}-{}", self.server.hostname, self.account)
+    }
+
+    /// Renders the login as `ssh_config` block. Returns `None` if the
+    /// hostname, the account or the jump host contain characters which
+    /// would change the meaning of the configuration
+    #[must_use]
+    #[inline]
+    pub fn to_ssh_config(&self) -> Option<String> {
+        let names = [&*self.server.hostname, &*self.account];
+        let proxy_jump = self.server.proxy_jump.as_deref();
+        let valid = names
+            .iter()
+            .all(|v| is_config_value(v) && !v.contains(SSH_CONFIG_PATTERN))
+            && proxy_jump.map_or(true, is_config_value);
+        if !valid {
+            return None;
+        }
+        let mut block = String::new();
+        let _ = writeln!(block, "Host {}", self.alias());
+        let _ = writeln!(block, "    HostName {}", self.server.hostname);
+        let _ = writeln!(block, "    Port {}", self.server.port);
+        let _ = writeln!(block, "    User {}", self.account);
+        if let Some(proxy_jump) = proxy_jump {
+            let _ = writeln!(block, "    ProxyJump {}", proxy_jump);
+        }
+        Some(block)
+    }
+}
+
+/// An account on a server together with every entity which has access
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct ServerAccount<'a> {
+    /// The entity id of the account
+    pub account_id: Cow<'a, Id>,
+    /// The name of the account
+    pub name: Cow<'a, str>,
+    /// The last synchronisation status of the account
+    pub sync_status: SyncStatusType,
+    /// The users, groups and server accounts which can log into the account
+    pub access: Vec<ServerAccess<'a>>,
+}
+
+/// An entity with access to a server account
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct ServerAccess<'a> {
+    /// The user, group or server account which has access
+    pub source: Entity<'a>,
+    /// The period in which the entity has access
+    pub validity: AccessValidity,
+}
+
+/// A change to a server, each of which is posted to its own route below the
+/// server page
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ServerAction {
+    /// Adds or removes an administrator
+    Admins,
+    /// Adds an account
+    Accounts,
+    /// Grants or revokes access to an account
+    Access,
+    /// Adds, edits or deletes a note
+    Notes,
+    /// Mails the users with access
+    Contact,
+    /// Changes whether only security keys are accepted
+    SecurityKey,
+    /// Creates or revokes the server token
+    Token,
+}
+
+impl ServerAction {
+    /// Whether only app administrators may perform the action. Server
+    /// administrators and approvers may only manage accounts and access
+    #[inline]
+    #[must_use]
+    pub const fn admin_only(self) -> bool {
+        match self {
+            Self::Accounts | Self::Access => false,
+            Self::Admins
+            | Self::Notes
+            | Self::Contact
+            | Self::SecurityKey
+            | Self::Token => true,
+        }
+    }
+
+    /// Whether a user may perform the action, given whether the user is an
+    /// app administrator and whether the user can manage the server
+    #[inline]
+    #[must_use]
+    pub const fn is_permitted(self, is_admin: bool, can_manage: bool) -> bool {
+        is_admin || (can_manage && !self.admin_only())
+    }
+}
+
+impl TryFrom<&str> for ServerAction {
+    type Error = ();
+
+    #[inline]
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "admins" => Ok(Self::Admins),
+            "accounts" => Ok(Self::Accounts),
+            "access" => Ok(Self::Access),
+            "notes" => Ok(Self::Notes),
+            "contact" => Ok(Self::Contact),
+            "security_key" => Ok(Self::SecurityKey),
+            "token" => Ok(Self::Token),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Whether the value can be written to `ssh_config` without quoting
+fn is_config_value(value: &str) -> bool {
+    !value.is_empty()
+        && !value.chars().any(|c| {
+            c.is_whitespace() || c.is_control() || SSH_CONFIG_SPECIAL.contains(&c)
+        })
 }
 
 #[derive(Debug, Clone, Hash, Serialize)]
@@ -105,3 +360,138 @@ impl<'a, I: Iterator<Item = (Cow<'a, str>, Cow<'a, str>)>> From<I>
         filter
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_host, Server, ServerAction, ServerLogin};
+    use crate::{
+        objects::AccessValidity,
+        types::{AuthorizationType, Id, KeyManagement, SyncStatusType},
+    };
+    use std::{borrow::Cow, convert::TryFrom};
+
+    fn server(port: i32) -> Server<'static> {
+        let id = Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+            .expect("Invalid Id");
+        Server {
+            id: Cow::Owned(id),
+            hostname: Cow::Borrowed("example.com"),
+            ip_address: Some(Cow::Borrowed("192.0.2.1")),
+            name: None,
+            key_management: KeyManagement::Keys,
+            authorization: AuthorizationType::Manual,
+            sync_status: SyncStatusType::SyncSuccess,
+            rsa_key_fingerprint: None,
+            port,
+            require_security_key: false,
+            host_key: Some(Cow::Borrowed("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5")),
+            proxy_jump: None,
+        }
+    }
+
+    #[test]
+    fn test_known_hosts() {
+        assert_eq!(
+            server(22).to_known_hosts(false),
+            Ok(Some(
+                "example.com,192.0.2.1 ssh-ed25519 AAAAC3NzaC1lZDI1NTE5\n".into()
+            ))
+        );
+        assert_eq!(
+            server(2222).host_patterns(),
+            vec!["[example.com]:2222", "[192.0.2.1]:2222"]
+        );
+
+        let hashed = server(22).to_known_hosts(true).expect("Random");
+        let hashed = hashed.expect("Host key");
+        assert_eq!(hashed.lines().count(), 2);
+        assert!(hashed.lines().all(|line| line.starts_with("|1|")
+            && line.ends_with(" ssh-ed25519 AAAAC3NzaC1lZDI1NTE5")));
+
+        let mut no_key = server(22);
+        no_key.host_key = None;
+        assert_eq!(no_key.to_known_hosts(false), Ok(None));
+
+        assert_eq!(
+            server(2222).known_hosts_entry(
+                Some("@cert-authority"),
+                "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5",
+                false
+            ),
+            Ok("@cert-authority [example.com]:2222,[192.0.2.1]:2222 \
+                ssh-ed25519 AAAAC3NzaC1lZDI1NTE5\n"
+                .into())
+        );
+    }
+
+    #[test]
+    fn test_hash_host() {
+        let salt: Vec<u8> = (0..20).collect();
+        assert_eq!(
+            hash_host("example.com", &salt),
+            "|1|AAECAwQFBgcICQoLDA0ODxAREhM=|nnUK16ANsXd3hL31YfAkGOluSjU="
+        );
+        assert_eq!(
+            hash_host("[example.com]:2222", &salt),
+            "|1|AAECAwQFBgcICQoLDA0ODxAREhM=|Wgcx+Fm+LmaWwC7rQ80eIf2uHe0="
+        );
+    }
+
+    #[test]
+    fn test_ssh_config() {
+        let account_id = Id::from_string("6f9619ff-8b86-d011-b42d-00c04fc964ff")
+            .expect("Invalid Id");
+        let mut login = ServerLogin {
+            server: server(2222),
+            account_id: Cow::Owned(account_id),
+            account: Cow::Borrowed("deploy"),
+            validity: AccessValidity::default(),
+        };
+        assert_eq!(
+            login.to_ssh_config().as_deref(),
+            Some(
+                "Host example.com-deploy\n    HostName example.com\n    \
+                 Port 2222\n    User deploy\n"
+            )
+        );
+
+        login.server.proxy_jump = Some(Cow::Borrowed("jump@bastion:2222"));
+        let config = login.to_ssh_config().expect("Valid config");
+        assert!(config.ends_with("    ProxyJump jump@bastion:2222\n"));
+
+        login.server.proxy_jump = Some(Cow::Borrowed("bastion\n    User root"));
+        assert_eq!(login.to_ssh_config(), None);
+        login.server.proxy_jump = None;
+        login.account = Cow::Borrowed("*");
+        assert_eq!(login.to_ssh_config(), None);
+    }
+
+    #[test]
+    fn test_action_permission() {
+        let admin_only = [
+            ServerAction::Admins,
+            ServerAction::Notes,
+            ServerAction::Contact,
+            ServerAction::SecurityKey,
+            ServerAction::Token,
+        ];
+        for action in &admin_only {
+            assert!(action.is_permitted(true, true));
+            assert!(action.is_permitted(true, false));
+            assert!(!action.is_permitted(false, true));
+            assert!(!action.is_permitted(false, false));
+        }
+        for action in &[ServerAction::Accounts, ServerAction::Access] {
+            assert!(action.is_permitted(true, false));
+            assert!(action.is_permitted(false, true));
+            assert!(!action.is_permitted(false, false));
+        }
+        assert_eq!(
+            ServerAction::try_from("security_key"),
+            Ok(ServerAction::SecurityKey)
+        );
+        assert_eq!(ServerAction::try_from("admins"), Ok(ServerAction::Admins));
+        assert_eq!(ServerAction::try_from(""), Err(()));
+        assert_eq!(ServerAction::try_from("Admins"), Err(()));
+    }
+}
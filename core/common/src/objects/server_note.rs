@@ -0,0 +1,34 @@
+use crate::{chrono::NaiveDateTime, serde::Serialize, types::Id};
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Hash, Serialize)]
+/// Defines a note admins left on a server
+pub struct ServerNote<'a> {
+    /// The id which uniquely identifies the note
+    pub id: Cow<'a, Id>,
+    /// The server the note belongs to
+    pub server_id: Cow<'a, Id>,
+    /// The entity who wrote the note. `None` if the author was removed
+    pub entity_id: Option<Cow<'a, Id>>,
+    /// The name of the author. Only set when fetched
+    pub author: Option<Cow<'a, str>>,
+    /// The date and time when the note was written
+    pub date: NaiveDateTime,
+    /// The text of the note
+    pub note: Cow<'a, str>,
+}
+
+#[derive(Debug, Clone, Hash, Serialize)]
+/// Provides fields to filter when searching for multiple
+/// objects
+pub struct ServerNoteFilter<'a> {
+    /// The note must belong to this server
+    pub server_id: Option<Cow<'a, Id>>,
+}
+
+impl Default for ServerNoteFilter<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self { server_id: None }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{
+    chrono::NaiveDateTime,
+    sec::{decrypt, encrypt, SecretError},
+    serde::Serialize,
+    types::{DeliveryStatus, Id},
+};
+use std::{borrow::Cow, convert::TryFrom};
+
+/// Purpose used to derive the key which encrypts the signing secrets
+const WEBHOOK_SECRET_PURPOSE: &str = "webhook-secret";
+
+#[derive(Debug, Clone, Hash, Serialize)]
+/// Defines an outbound webhook which receives events
+pub struct Webhook<'a> {
+    /// The id which uniquely identifies the webhook
+    pub id: Cow<'a, Id>,
+    /// The display name
+    pub name: Cow<'a, str>,
+    /// The url the events are posted to
+    pub url: Cow<'a, str>,
+    /// The encrypted secret used to sign the payload
+    #[serde(skip)]
+    pub secret: Cow<'a, [u8]>,
+    /// Comma separated list of event actions to send. All events
+    /// are sent if not set
+    pub actions: Option<Cow<'a, str>>,
+    /// Whether events are sent to the webhook
+    pub active: bool,
+}
+
+impl Webhook<'_> {
+    /// Encrypts the secret used to sign the payload, so that it can be
+    /// stored
+    ///
+    /// # Errors
+    /// Fails when no random data is available
+    #[inline]
+    pub fn encrypt_secret(
+        app_secret: &[u8; 32],
+        secret: &str,
+    ) -> Result<Vec<u8>, SecretError> {
+        encrypt(app_secret, WEBHOOK_SECRET_PURPOSE, secret.as_bytes())
+    }
+
+    /// Decrypts the secret used to sign the payload
+    ///
+    /// # Errors
+    /// Fails if the secret was not encrypted with the given app secret
+    #[inline]
+    pub fn signing_secret(
+        &self,
+        app_secret: &[u8; 32],
+    ) -> Result<Vec<u8>, SecretError> {
+        decrypt(app_secret, WEBHOOK_SECRET_PURPOSE, &self.secret)
+    }
+
+    /// Checks whether the given action is part of the comma separated
+    /// list of actions. An empty list matches every action.
+    #[must_use]
+    #[inline]
+    pub fn action_matches(actions: Option<&str>, action: &str) -> bool {
+        actions.map_or(true, |actions| {
+            let mut list = actions
+                .split(',')
+                .map(str::trim)
+                .filter(|a| !a.is_empty())
+                .peekable();
+            list.peek().is_none() || list.any(|a| a == action)
+        })
+    }
+
+    /// Checks whether events with the given action are sent to this webhook
+    #[must_use]
+    #[inline]
+    pub fn matches(&self, action: &str) -> bool {
+        self.active && Self::action_matches(self.actions.as_deref(), action)
+    }
+}
+
+#[derive(Debug, Clone, Hash, Serialize)]
+/// Provides fields to filter when searching for multiple
+/// objects
+pub struct WebhookFilter<'a> {
+    /// Name must be like this value
+    pub name: Option<Cow<'a, str>>,
+    /// Url must be like this value
+    pub url: Option<Cow<'a, str>>,
+}
+
+impl Default for WebhookFilter<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            name: None,
+            url: None,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = (Cow<'a, str>, Cow<'a, str>)>> From<I>
+    for WebhookFilter<'a>
+{
+    #[inline]
+    fn from(iter: I) -> Self {
+        let mut filter = Self::default();
+        for (key, val) in iter {
+            if val.is_empty() {
+                continue;
+            }
+            match key.as_ref() {
+                "name" => {
+                    filter.name = Some(val);
+                }
+                "url" => {
+                    filter.url = Some(val);
+                }
+                _ => {}
+            }
+        }
+        filter
+    }
+}
+
+#[derive(Debug, Clone, Hash, Serialize)]
+/// A single event queued for or delivered to a webhook
+pub struct WebhookDelivery<'a> {
+    /// The id which uniquely identifies the delivery
+    pub id: Cow<'a, Id>,
+    /// The webhook the event is sent to
+    pub webhook_id: Cow<'a, Id>,
+    /// The event which is sent
+    pub event_id: Cow<'a, Id>,
+    /// The json payload posted to the webhook
+    pub payload: Cow<'a, str>,
+    /// The current status of the delivery
+    pub status: DeliveryStatus,
+    /// Number of attempts made so far
+    pub attempts: i32,
+    /// The time of the next attempt
+    pub next_attempt: Option<NaiveDateTime>,
+    /// The http status code of the last attempt
+    pub response_code: Option<i32>,
+    /// The error of the last attempt
+    pub error: Option<Cow<'a, str>>,
+    /// The time the delivery was queued
+    pub date: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Hash, Serialize)]
+/// Provides fields to filter when searching for multiple
+/// objects
+pub struct WebhookDeliveryFilter<'a> {
+    /// The webhook must equal to this id
+    pub webhook_id: Option<Cow<'a, Id>>,
+    /// The status must equal to this value
+    pub status: Option<DeliveryStatus>,
+}
+
+impl Default for WebhookDeliveryFilter<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            webhook_id: None,
+            status: None,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = (Cow<'a, str>, Cow<'a, str>)>> From<I>
+    for WebhookDeliveryFilter<'a>
+{
+    #[inline]
+    fn from(iter: I) -> Self {
+        let mut filter = Self::default();
+        for (key, val) in iter {
+            if val.is_empty() {
+                continue;
+            }
+            if key.as_ref() == "status" {
+                filter.status = DeliveryStatus::try_from(val.as_ref()).ok();
+            }
+        }
+        filter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Webhook;
+    use crate::{sec::SecretError, types::Id};
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_signing_secret() {
+        let app_secret = [7_u8; 32];
+        let secret =
+            Webhook::encrypt_secret(&app_secret, "secret").expect("Encrypt");
+        assert_ne!(secret.get(12..18), Some(&b"secret"[..]));
+        let id = Id::from_string("c6efb44e-9b67-4dc0-a31b-6482476ed8b7")
+            .expect("Invalid Id");
+        let webhook = Webhook {
+            id: Cow::Owned(id),
+            name: Cow::Borrowed("test"),
+            url: Cow::Borrowed("https://example.com/hook"),
+            secret: Cow::Owned(secret),
+            actions: None,
+            active: true,
+        };
+        assert_eq!(webhook.signing_secret(&app_secret), Ok(b"secret".to_vec()));
+        assert_eq!(
+            webhook.signing_secret(&[8_u8; 32]),
+            Err(SecretError::Invalid)
+        );
+    }
+
+    #[test]
+    fn test_action_matches() {
+        assert!(Webhook::action_matches(None, "key_add"));
+        assert!(Webhook::action_matches(Some(""), "key_add"));
+        assert!(Webhook::action_matches(
+            Some("key_add, key_remove"),
+            "key_remove"
+        ));
+        assert!(!Webhook::action_matches(
+            Some("key_add,key_remove"),
+            "login"
+        ));
+    }
+}
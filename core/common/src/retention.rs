@@ -0,0 +1,114 @@
+//! Permanently removes deleted public keys once the retention period of
+//! the key policy is over, so revoked key material is not kept forever.
+//! Every removal is recorded as `KeyPurge` event.
+
+use crate::{
+    chrono::Utc,
+    database::{DatabaseError, KeyRetention},
+    objects::KeyPolicy,
+    worker::run_periodic,
+};
+use std::{sync::Arc, time::Duration};
+
+/// Number of keys removed at once
+const RETENTION_BATCH: usize = 50;
+
+/// Worker which periodically removes deleted keys
+#[derive(Debug)]
+pub struct RetentionWorker<D: KeyRetention> {
+    db: Arc<D>,
+    policy: KeyPolicy,
+    interval: Duration,
+}
+
+impl<D: KeyRetention> RetentionWorker<D> {
+    /// Creates a new worker which checks for deleted keys in the given
+    /// interval. The retention period is taken from the policy.
+    #[must_use]
+    #[inline]
+    pub fn new(db: Arc<D>, policy: KeyPolicy, interval: Duration) -> Self {
+        Self {
+            db,
+            policy,
+            interval,
+        }
+    }
+
+    /// Removes deleted keys until the application stops
+    #[inline]
+    pub async fn run(self) {
+        let worker = &self;
+        run_periodic(
+            self.interval,
+            "deleted keys",
+            || async move { worker.process() },
+        )
+        .await;
+    }
+
+    /// Removes keys which were deleted before the retention period.
+    /// Returns the number of removed keys.
+    ///
+    /// # Errors
+    /// Fails on database errors
+    #[inline]
+    pub fn process(&self) -> Result<usize, DatabaseError<D>> {
+        match self.policy.purge_date(Utc::now().naive_utc()) {
+            Some(before) => self.db.purge_deactivated(before, RETENTION_BATCH),
+            None => Ok(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RetentionWorker;
+    use crate::{
+        chrono::{Duration as ChronoDuration, NaiveDateTime, Utc},
+        database::{DbResult, KeyRetention},
+        objects::KeyPolicy,
+        worker::tests::TestDb,
+    };
+    use std::{sync::Arc, time::Duration};
+
+    /// Deletion dates of the deleted keys
+    type Deactivated = Vec<NaiveDateTime>;
+
+    impl KeyRetention for TestDb<Deactivated> {
+        fn purge_deactivated(
+            &self,
+            before: NaiveDateTime,
+            _limit: usize,
+        ) -> DbResult<usize, Self> {
+            let mut keys = self.data.lock().expect("Poisoned lock");
+            let count = keys.len();
+            keys.retain(|deactivated| *deactivated >= before);
+            Ok(count.saturating_sub(keys.len()))
+        }
+    }
+
+    #[test]
+    fn test_process() {
+        let now = Utc::now().naive_utc();
+        let db = Arc::new(TestDb::new(vec![
+            now - ChronoDuration::days(40),
+            now - ChronoDuration::days(10),
+        ]));
+        let worker = RetentionWorker::new(
+            Arc::clone(&db),
+            KeyPolicy::default(),
+            Duration::from_secs(1),
+        );
+        assert_eq!(worker.process().expect("Unable to process"), 0);
+
+        let policy = KeyPolicy {
+            retention: Some(30),
+            ..KeyPolicy::default()
+        };
+        let worker =
+            RetentionWorker::new(Arc::clone(&db), policy, Duration::from_secs(1));
+        assert_eq!(worker.process().expect("Unable to process"), 1);
+        assert_eq!(worker.process().expect("Unable to process"), 0);
+        assert_eq!(db.data.lock().expect("Poisoned lock").len(), 1);
+    }
+}
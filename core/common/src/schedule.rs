@@ -0,0 +1,194 @@
+//! Queues a resync of server accounts whose time-bound access grants
+//! started or ended, so the rendered `authorized_keys` follow the validity
+//! of the grants without waiting for an unrelated change. The end of a
+//! grant, including emergency access, is recorded as `AccessRevoke` event
+//! and therefore also notified and delivered to webhooks.
+
+use crate::{
+    chrono::Utc,
+    database::{AccessSchedule, DatabaseError},
+    worker::run_periodic,
+};
+use std::{sync::Arc, time::Duration};
+
+/// Number of grants processed at once
+const SCHEDULE_BATCH: usize = 50;
+
+/// Worker which periodically queues resyncs for started or ended grants
+#[derive(Debug)]
+pub struct AccessScheduleWorker<D: AccessSchedule> {
+    db: Arc<D>,
+    interval: Duration,
+}
+
+impl<D: AccessSchedule> AccessScheduleWorker<D> {
+    /// Creates a new worker which checks for started or ended grants in the
+    /// given interval
+    #[must_use]
+    #[inline]
+    pub fn new(db: Arc<D>, interval: Duration) -> Self {
+        Self { db, interval }
+    }
+
+    /// Checks for started or ended grants until the application stops
+    #[inline]
+    pub async fn run(self) {
+        let worker = &self;
+        run_periodic(self.interval, "started or ended grants", || async move {
+            worker.process()
+        })
+        .await;
+    }
+
+    /// Queues a resync for every grant which started or ended since the
+    /// last run and records the end of grants. Returns the number of
+    /// processed grants.
+    ///
+    /// # Errors
+    /// Fails on database errors
+    #[inline]
+    pub fn process(&self) -> Result<usize, DatabaseError<D>> {
+        let now = Utc::now().naive_utc();
+        let transitions = self.db.fetch_transitions(now, SCHEDULE_BATCH)?;
+        for transition in &transitions {
+            self.db.process_transition(transition, now)?;
+        }
+        Ok(transitions.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AccessScheduleWorker;
+    use crate::{
+        chrono::{Duration as ChronoDuration, NaiveDateTime, Utc},
+        database::{AccessSchedule, DbResult},
+        objects::{AccessTransition, AccessValidity},
+        types::Id,
+        worker::tests::TestDb,
+    };
+    use std::{borrow::Cow, sync::Arc, time::Duration};
+
+    #[derive(Debug)]
+    struct Grants {
+        /// Grants with the time of their last sync
+        grants: Vec<(AccessTransition<'static>, Option<NaiveDateTime>)>,
+        /// Accounts a sync was queued for
+        queued: Vec<Id>,
+        /// Grants whose end was recorded
+        ended: Vec<Id>,
+    }
+
+    impl AccessSchedule for TestDb<Grants> {
+        fn fetch_transitions(
+            &self,
+            before: NaiveDateTime,
+            _limit: usize,
+        ) -> DbResult<Vec<AccessTransition<'static>>, Self> {
+            let data = self.data.lock().expect("Poisoned lock");
+            Ok(data
+                .grants
+                .iter()
+                .filter(|(grant, synced)| {
+                    let validity = grant.validity;
+                    [validity.valid_from, validity.valid_until]
+                        .iter()
+                        .flatten()
+                        .any(|t| *t <= before && synced.map_or(true, |s| s < *t))
+                })
+                .map(|(grant, _)| grant.clone())
+                .collect())
+        }
+
+        fn process_transition(
+            &self,
+            transition: &AccessTransition<'_>,
+            now: NaiveDateTime,
+        ) -> DbResult<(), Self> {
+            let mut data = self.data.lock().expect("Poisoned lock");
+            for (grant, synced) in &mut data.grants {
+                if grant.access_id == transition.access_id {
+                    *synced = Some(now);
+                }
+            }
+            data.queued.push(transition.account_id.clone().into_owned());
+            if transition.validity.valid_until.map_or(false, |v| v <= now) {
+                data.ended.push(transition.access_id.clone().into_owned());
+            }
+            Ok(())
+        }
+    }
+
+    fn grant(id: &str, validity: AccessValidity) -> AccessTransition<'static> {
+        let id = Id::from_string(id).expect("Invalid Id");
+        AccessTransition {
+            access_id: Cow::Owned(id.clone()),
+            source_id: Cow::Owned(id.clone()),
+            server_id: Cow::Owned(id.clone()),
+            account_id: Cow::Owned(id),
+            validity,
+        }
+    }
+
+    #[test]
+    fn test_process() {
+        let now = Utc::now().naive_utc();
+        let ended = grant(
+            "00bd8c06-daf7-47e6-8c96-8d467587b6dc",
+            AccessValidity {
+                valid_from: Some(now - ChronoDuration::days(3)),
+                valid_until: Some(now - ChronoDuration::hours(1)),
+            },
+        );
+        let upcoming = grant(
+            "c6efb44e-9b67-4dc0-a31b-6482476ed8b7",
+            AccessValidity {
+                valid_from: Some(now + ChronoDuration::days(1)),
+                valid_until: None,
+            },
+        );
+        let permanent = grant(
+            "5e1fc1f4-7cb1-4e10-9d4a-f7ac3c4d0b8e",
+            AccessValidity::default(),
+        );
+        let db = Arc::new(TestDb::new(Grants {
+            grants: vec![
+                (ended.clone(), Some(now - ChronoDuration::days(2))),
+                (upcoming, None),
+                (permanent, None),
+            ],
+            queued: Vec::new(),
+            ended: Vec::new(),
+        }));
+        let worker =
+            AccessScheduleWorker::new(Arc::clone(&db), Duration::from_secs(1));
+        assert_eq!(worker.process().expect("Unable to process"), 1);
+        assert_eq!(worker.process().expect("Unable to process"), 0);
+        let data = db.data.lock().expect("Poisoned lock");
+        assert_eq!(data.queued, vec![ended.account_id.into_owned()]);
+        assert_eq!(data.ended, vec![ended.access_id.into_owned()]);
+    }
+
+    #[test]
+    fn test_process_started() {
+        let now = Utc::now().naive_utc();
+        let started = grant(
+            "00bd8c06-daf7-47e6-8c96-8d467587b6dc",
+            AccessValidity {
+                valid_from: Some(now - ChronoDuration::hours(1)),
+                valid_until: Some(now + ChronoDuration::hours(3)),
+            },
+        );
+        let db = Arc::new(TestDb::new(Grants {
+            grants: vec![(started.clone(), None)],
+            queued: Vec::new(),
+            ended: Vec::new(),
+        }));
+        let worker =
+            AccessScheduleWorker::new(Arc::clone(&db), Duration::from_secs(1));
+        assert_eq!(worker.process().expect("Unable to process"), 1);
+        let data = db.data.lock().expect("Poisoned lock");
+        assert_eq!(data.queued, vec![started.account_id.into_owned()]);
+        assert!(data.ended.is_empty());
+    }
+}
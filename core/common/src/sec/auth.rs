@@ -1,6 +1,6 @@
 use crate::{
     async_trait::async_trait,
-    database::{Create, Database, FetchByUid, Save},
+    database::{ApiTokenStore, Create, Database, FetchByUid, Save},
     http::response,
     objects::User,
     types::{Id, UserTypes},
@@ -30,6 +30,7 @@ pub trait Auth: Sized + Send + Sync + fmt::Debug {
     ) -> Option<Self>
     where
         for<'a> D: Database
+            + ApiTokenStore
             + FetchByUid<PreAuth, User<'a>, D>
             + Create<PreAuth, User<'a>, D>
             + Save<PreAuth, User<'a>, D>,
@@ -90,6 +91,29 @@ pub trait Auth: Sized + Send + Sync + fmt::Debug {
         D: Database,
         T: TemplateEngine,
         R: Request<Self, D, T>;
+
+    /// Creates a token which authenticates api requests send with the
+    /// `Authorization: Bearer` header until the given unix timestamp. The
+    /// token refers to the stored api token with the given id and is
+    /// rejected once that is revoked.
+    /// May return `Ok(None)` if api tokens are not supported
+    ///
+    /// # Errors
+    /// Fails on signing or encryption operations
+    fn get_api_token<D, T, R>(
+        &self,
+        req: &R,
+        token_id: &Id,
+        exp: u64,
+    ) -> Result<Option<String>, AppError<Self, D, T, R>>
+    where
+        D: Database,
+        T: TemplateEngine,
+        R: Request<Self, D, T>;
+
+    /// Returns `true` if the request was authenticated with an api token
+    /// instead of a login
+    fn is_api_token(&self) -> bool;
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -117,6 +141,7 @@ impl Auth for PreAuth {
     async fn authenticate<D, T, R>(_: &R, _: &mut response::Builder) -> Option<Self>
     where
         for<'a> D: Database
+            + ApiTokenStore
             + FetchByUid<PreAuth, User<'a>, D>
             + Create<PreAuth, User<'a>, D>
             + Save<PreAuth, User<'a>, D>,
@@ -186,4 +211,24 @@ impl Auth for PreAuth {
     {
         Ok(None)
     }
+
+    #[inline]
+    fn get_api_token<D, T, R>(
+        &self,
+        _: &R,
+        _: &Id,
+        _: u64,
+    ) -> Result<Option<String>, AppError<Self, D, T, R>>
+    where
+        D: Database,
+        T: TemplateEngine,
+        R: Request<Self, D, T>,
+    {
+        Ok(None)
+    }
+
+    #[inline]
+    fn is_api_token(&self) -> bool {
+        false
+    }
 }
@@ -3,9 +3,11 @@
 mod auth;
 mod csrf;
 mod oauth;
+mod secret;
 
 pub use self::{
     auth::{Auth, AuthMethod, PreAuth},
     csrf::CsrfToken,
     oauth::{OAuth2, OAuthError},
+    secret::{decrypt, encrypt, generate_token, token_digest, SecretError},
 };
@@ -0,0 +1,156 @@
+use crate::{
+    base64,
+    ring::{
+        aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN},
+        digest, hmac,
+        rand::{SecureRandom, SystemRandom},
+    },
+};
+use std::{error, fmt};
+
+/// Error while encrypting or decrypting a secret
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretError {
+    /// Unable to gather random data for the nonce
+    Random,
+    /// The data was not encrypted with the given secret and purpose or was
+    /// modified
+    Invalid,
+}
+
+impl fmt::Display for SecretError {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Random => write!(f, "Unable to gather random data"),
+            Self::Invalid => write!(f, "Secret is invalid or was modified"),
+        }
+    }
+}
+
+impl error::Error for SecretError {}
+
+/// Derives a separate key for every purpose so that data encrypted for one
+/// purpose cannot be decrypted for another one
+fn derive_key(
+    app_secret: &[u8; 32],
+    purpose: &str,
+) -> Result<LessSafeKey, SecretError> {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, app_secret);
+    let tag = hmac::sign(&key, purpose.as_bytes());
+    UnboundKey::new(&CHACHA20_POLY1305, tag.as_ref())
+        .map(LessSafeKey::new)
+        .map_err(|_| SecretError::Invalid)
+}
+
+/// Encrypts data using a key derived from the app secret and the purpose.
+/// The random nonce is prepended to the result.
+///
+/// # Errors
+/// Fails when no random data is available
+#[inline]
+pub fn encrypt(
+    app_secret: &[u8; 32],
+    purpose: &str,
+    data: &[u8],
+) -> Result<Vec<u8>, SecretError> {
+    let key = derive_key(app_secret, purpose)?;
+    let mut nonce = [0_u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| SecretError::Random)?;
+    let mut out = data.to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(purpose.as_bytes()),
+        &mut out,
+    )
+    .map_err(|_| SecretError::Invalid)?;
+    let mut result = nonce.to_vec();
+    result.extend_from_slice(&out);
+    Ok(result)
+}
+
+/// Decrypts data created by `encrypt` using the same app secret and purpose
+///
+/// # Errors
+/// Fails when the data was encrypted with a different app secret or
+/// purpose or when it was modified
+#[inline]
+pub fn decrypt(
+    app_secret: &[u8; 32],
+    purpose: &str,
+    data: &[u8],
+) -> Result<Vec<u8>, SecretError> {
+    let key = derive_key(app_secret, purpose)?;
+    let (nonce, data) = match (data.get(..NONCE_LEN), data.get(NONCE_LEN..)) {
+        (Some(nonce), Some(data)) => (nonce, data),
+        _ => return Err(SecretError::Invalid),
+    };
+    let nonce =
+        Nonce::try_assume_unique_for_key(nonce).map_err(|_| SecretError::Invalid)?;
+    let mut out = data.to_vec();
+    let len = key
+        .open_in_place(nonce, Aad::from(purpose.as_bytes()), &mut out)
+        .map_err(|_| SecretError::Invalid)?
+        .len();
+    out.truncate(len);
+    Ok(out)
+}
+
+/// Creates a random token used to authenticate at the api. Returns the
+/// token together with its digest. Only the digest must be stored
+///
+/// # Errors
+/// Fails when no random data is available
+#[inline]
+pub fn generate_token() -> Result<(String, Vec<u8>), SecretError> {
+    let mut data = [0_u8; 32];
+    SystemRandom::new()
+        .fill(&mut data)
+        .map_err(|_| SecretError::Random)?;
+    let token = base64::encode_config(&data, base64::URL_SAFE_NO_PAD);
+    let digest = token_digest(&token);
+    Ok((token, digest))
+}
+
+/// The digest under which a token created by `generate_token` is stored
+#[must_use]
+#[inline]
+pub fn token_digest(token: &str) -> Vec<u8> {
+    digest::digest(&digest::SHA256, token.as_bytes())
+        .as_ref()
+        .to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decrypt, encrypt, generate_token, token_digest, SecretError};
+
+    #[test]
+    fn test_roundtrip() {
+        let secret = [7_u8; 32];
+        let data = encrypt(&secret, "test", b"private key").expect("Encrypt");
+        assert_ne!(data.get(12..), Some(&b"private key"[..]));
+        assert_eq!(decrypt(&secret, "test", &data), Ok(b"private key".to_vec()));
+        assert_eq!(decrypt(&secret, "other", &data), Err(SecretError::Invalid));
+        assert_eq!(
+            decrypt(&[8_u8; 32], "test", &data),
+            Err(SecretError::Invalid)
+        );
+        assert_eq!(
+            decrypt(&secret, "test", b"short"),
+            Err(SecretError::Invalid)
+        );
+    }
+
+    #[test]
+    fn test_token() {
+        let (token, digest) = generate_token().expect("Token");
+        let (other, _) = generate_token().expect("Token");
+        assert_eq!(token.len(), 43);
+        assert_ne!(token, other);
+        assert_eq!(token_digest(&token), digest);
+        assert_ne!(token_digest(&other), digest);
+    }
+}
@@ -24,3 +24,23 @@ pub enum AccessOption {
     /// Disable the usage of the user rc file
     NoUserRc,
 }
+
+impl AccessOption {
+    /// Maps an option of the openssh `authorized_keys` format to its
+    /// `AccessOption`. Returns `None` for options without an equivalent
+    #[inline]
+    #[must_use]
+    pub fn from_openssh(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "command" => Some(Self::Command),
+            "from" => Some(Self::From),
+            "environment" => Some(Self::Environment),
+            "no-agent-forwarding" => Some(Self::NoAgentForwarding),
+            "no-port-forwarding" => Some(Self::NoPortForwarding),
+            "no-pty" => Some(Self::NoPty),
+            "no-x11-forwarding" => Some(Self::NoX11Forwarding),
+            "no-user-rc" => Some(Self::NoUserRc),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use crate::serde::{Deserialize, Serialize};
+use core_macros::EnumFrom;
+use std::convert::TryFrom;
+
+/// Status of a request for access to a server account
+#[derive(
+    Debug, Copy, Clone, Hash, EnumFrom, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum AccessRequestStatus {
+    /// The request waits for a decision of an administrator
+    Pending,
+    /// The request was approved and access was granted
+    Approved,
+    /// The request was rejected
+    Rejected,
+}
+
+impl Default for AccessRequestStatus {
+    #[inline]
+    fn default() -> Self {
+        Self::Pending
+    }
+}
@@ -0,0 +1,14 @@
+use crate::serde::{Deserialize, Serialize};
+use core_macros::EnumFrom;
+use std::convert::TryFrom;
+
+/// Type of an openssh certificate and of the authority signing it
+#[derive(
+    Debug, Copy, Clone, Hash, EnumFrom, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum CertificateType {
+    /// Certificate authenticating a user to a server
+    User,
+    /// Certificate authenticating a server to a user
+    Host,
+}
@@ -0,0 +1,16 @@
+use crate::serde::{Deserialize, Serialize};
+use core_macros::EnumFrom;
+use std::convert::TryFrom;
+
+/// Who receives a mail sent through the contact form of a server
+#[derive(
+    Debug, Copy, Clone, Hash, EnumFrom, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum ContactRecipients {
+    /// The administrators of the server
+    Admins,
+    /// Every user with access to the root account of the server
+    RootUsers,
+    /// Every user with access to any account of the server
+    Users,
+}
@@ -0,0 +1,23 @@
+use crate::serde::{Deserialize, Serialize};
+use core_macros::EnumFrom;
+use std::convert::TryFrom;
+
+/// Status of a webhook delivery
+#[derive(
+    Debug, Copy, Clone, Hash, EnumFrom, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum DeliveryStatus {
+    /// The delivery is queued and will be attempted or retried
+    Pending,
+    /// The receiver acknowledged the delivery
+    Delivered,
+    /// All attempts failed. The delivery will not be retried
+    Failed,
+}
+
+impl Default for DeliveryStatus {
+    #[inline]
+    fn default() -> Self {
+        Self::Pending
+    }
+}
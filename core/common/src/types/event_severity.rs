@@ -0,0 +1,21 @@
+use crate::serde::{Deserialize, Serialize};
+
+/// How urgently an event should be looked at. Derived from the details of
+/// the event and not stored in the database
+#[derive(
+    Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum EventSeverity {
+    /// Regular activity
+    Normal,
+    /// Activity which should be reviewed, e.g. security relevant actions
+    /// bypassing the usual approval
+    High,
+}
+
+impl Default for EventSeverity {
+    #[inline]
+    fn default() -> Self {
+        Self::Normal
+    }
+}
@@ -118,6 +118,14 @@ impl Hash for Id {
     }
 }
 
+impl fmt::Display for Id {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buffer = Uuid::encode_buffer();
+        write!(f, "{}", self.to_str(&mut buffer))
+    }
+}
+
 impl FromStr for Id {
     type Err = UuidConversionError;
     /// Tries to parse a string as uuid.
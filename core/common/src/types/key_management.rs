@@ -15,4 +15,8 @@ pub enum KeyManagement {
     Keys,
     /// Keys are managed by another system.
     Other,
+    /// Access is granted using certificates signed by the user certificate
+    /// authority. The server receives the authority and a list of
+    /// principals for every account instead of the keys
+    Certificates,
 }
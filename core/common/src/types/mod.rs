@@ -1,23 +1,39 @@
 //! This module contains various types for the database objects
 
 mod access_options;
+mod access_request_status;
 mod authorization_type;
+mod certificate_type;
+mod contact_recipients;
+mod delivery_status;
 mod entity_types;
+mod event_severity;
 mod event_types;
 mod fingerprint;
 mod id;
 mod key_management;
+mod notification_type;
+mod recertification_decision;
+mod recertification_kind;
 mod sync_status_type;
 mod user_types;
 
 pub use self::{
     access_options::AccessOption,
+    access_request_status::AccessRequestStatus,
     authorization_type::AuthorizationType,
+    certificate_type::CertificateType,
+    contact_recipients::ContactRecipients,
+    delivery_status::DeliveryStatus,
     entity_types::EntityTypes,
+    event_severity::EventSeverity,
     event_types::EventTypes,
     fingerprint::{FingerprintConversionError, FingerprintMd5, FingerprintSha256},
     id::Id,
     key_management::KeyManagement,
+    notification_type::NotificationType,
+    recertification_decision::RecertificationDecision,
+    recertification_kind::RecertificationKind,
     sync_status_type::SyncStatusType,
     user_types::UserTypes,
 };
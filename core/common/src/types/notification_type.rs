@@ -0,0 +1,18 @@
+use crate::serde::{Deserialize, Serialize};
+use core_macros::EnumFrom;
+use std::convert::TryFrom;
+
+/// Kind of change a user is notified about by mail
+#[derive(
+    Debug, Copy, Clone, Hash, EnumFrom, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum NotificationType {
+    /// A public key was added to the account of the user
+    KeyAdd,
+    /// The user gained or lost access to a server account
+    Access,
+    /// The synchronisation of a server administered by the user failed
+    SyncFailure,
+    /// A public key of the user is about to expire
+    KeyExpiring,
+}
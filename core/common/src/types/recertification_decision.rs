@@ -0,0 +1,15 @@
+use crate::serde::{Deserialize, Serialize};
+use core_macros::EnumFrom;
+use std::convert::TryFrom;
+
+/// Decision of a server admin on an access during a recertification
+/// campaign
+#[derive(
+    Debug, Copy, Clone, Hash, EnumFrom, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum RecertificationDecision {
+    /// The access is still required
+    Keep,
+    /// The access is removed when the campaign is closed
+    Revoke,
+}
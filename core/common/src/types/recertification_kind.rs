@@ -0,0 +1,14 @@
+use crate::serde::{Deserialize, Serialize};
+use core_macros::EnumFrom;
+use std::convert::TryFrom;
+
+/// What kind of access is reviewed during a recertification campaign
+#[derive(
+    Debug, Copy, Clone, Hash, EnumFrom, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum RecertificationKind {
+    /// An access grant to a server account
+    Access,
+    /// A membership in a group which has access to a server account
+    Membership,
+}
@@ -1,4 +1,10 @@
-use crate::{sec::OAuth2, serde::Serialize, types::Id};
+use crate::{
+    mail::Mailer,
+    objects::{CertificatePolicy, KeyBlocklist, KeyPolicy},
+    sec::OAuth2,
+    serde::Serialize,
+    types::Id,
+};
 use std::borrow::Cow;
 
 /// Basic Data container required for the base template
@@ -132,4 +138,17 @@ pub struct BaseData {
     pub app_secret: [u8; 32],
     /// `OAuth2` client
     pub oauth: OAuth2,
+    /// Policy which public keys must fulfill
+    pub key_policy: KeyPolicy,
+    /// Fingerprints of compromised public keys which are rejected
+    pub key_blocklist: KeyBlocklist,
+    /// Limits the validity of issued certificates
+    pub certificate_policy: CertificatePolicy,
+    /// Number of days api tokens are valid
+    pub api_token_validity: u32,
+    /// Server accounts, as `account@hostname`, anyone may take emergency
+    /// access to
+    pub break_glass_accounts: Vec<String>,
+    /// Sends mails. `None` if no SMTP relay is configured
+    pub mailer: Option<Mailer>,
 }
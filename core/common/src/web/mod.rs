@@ -11,8 +11,9 @@ pub use self::{
     request::Request,
     response_type::ResponseType,
     routes::{
-        invalid_method, not_found, redirect, redirect_home, serve_login,
-        serve_template, AppError,
+        invalid_method, not_found, redirect, redirect_home, serve_download,
+        serve_json, serve_json_status, serve_login, serve_template, unauthorized,
+        AppError,
     },
     server::{Server, ServerError},
     template_engine::{RenderError, TemplateEngine},
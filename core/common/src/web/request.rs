@@ -51,6 +51,9 @@ pub trait Request<A: Auth, D: Database, T: TemplateEngine>: Debug + Sized {
     /// Returns the user agent
     fn get_user_agent(&self) -> Option<&str>;
 
+    /// Returns the authorization header
+    fn get_authorization(&self) -> Option<&str>;
+
     /// Returns the current path as str
     fn get_uri(&self) -> &Uri;
 
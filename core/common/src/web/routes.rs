@@ -10,6 +10,7 @@ use crate::{
     },
     sec::{Auth, OAuthError},
     serde::Serialize,
+    serde_json,
     web::{self, BaseContainer, Request, ResponseType, TemplateEngine},
 };
 use std::{convert::TryFrom, error, fmt};
@@ -37,6 +38,8 @@ pub enum AppError<A: Auth, D: Database, T: TemplateEngine, R: Request<A, D, T>>
     RequestError(R::RequestError),
     /// Error while creating csrf
     CsrfError(csrf::CsrfError),
+    /// Error while serializing json
+    Json(serde_json::Error),
 }
 
 impl<A: Auth, D: Database, T: TemplateEngine, R: Request<A, D, T>> fmt::Display
@@ -71,6 +74,7 @@ impl<A: Auth, D: Database, T: TemplateEngine, R: Request<A, D, T>> fmt::Display
                 write!(f, "Error while trying load request: {}", err)
             }
             Self::CsrfError(err) => write!(f, "Error while creating csrf: {}", err),
+            Self::Json(err) => write!(f, "Error while serializing json: {}", err),
         }
     }
 }
@@ -95,6 +99,7 @@ impl<
             Self::AuthError(err) => Some(err),
             Self::RequestError(err) => Some(err),
             Self::CsrfError(err) => Some(err),
+            Self::Json(err) => Some(err),
         }
     }
 }
@@ -171,6 +176,15 @@ impl<A: Auth, D: Database, T: TemplateEngine, R: Request<A, D, T>>
     }
 }
 
+impl<A: Auth, D: Database, T: TemplateEngine, R: Request<A, D, T>>
+    From<serde_json::Error> for AppError<A, D, T, R>
+{
+    #[inline]
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
 /// Serves the login page. Should be used when the user is not authenticated
 /// and authentication is required
 ///
@@ -221,6 +235,85 @@ where
         .map_err(AppError::Http)
 }
 
+/// Serves the given value as json
+///
+/// # Errors
+/// Fails when the value could not be serialized
+#[inline]
+pub fn serve_json<A, D, T, R, S>(
+    res: response::Builder,
+    data: &S,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    D: Database,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+    S: Serialize,
+{
+    serve_json_status(res, StatusCode::OK, data)
+}
+
+/// Serves the given value as json using the given status code
+///
+/// # Errors
+/// Fails when the value could not be serialized
+#[inline]
+pub fn serve_json_status<A, D, T, R, S>(
+    res: response::Builder,
+    status: StatusCode,
+    data: &S,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    D: Database,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+    S: Serialize,
+{
+    let content = serde_json::to_string(data)?;
+    res.header("Content-Type", "application/json; charset=UTF-8")
+        .status(status)
+        .body(ResponseType::String(content))
+        .map_err(AppError::Http)
+}
+
+/// Serves the given content as file download with the given name
+///
+/// # Errors
+/// Fails when the reponse could not be created
+#[inline]
+pub fn serve_download<A, D, T, R>(
+    res: response::Builder,
+    content_type: &str,
+    filename: &str,
+    content: String,
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    D: Database,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let disposition = format!("attachment; filename=\"{}\"", filename);
+    res.header("Content-Type", content_type)
+        .header("Content-Disposition", disposition)
+        .status(StatusCode::OK)
+        .body(ResponseType::String(content))
+        .map_err(AppError::Http)
+}
+
+/// Serves the 401 response. Used by api routes instead of the login page
+///
+/// # Errors
+/// Fails when the reponse could not be created
+#[inline]
+pub fn unauthorized<A: Auth, D: Database, T: TemplateEngine, R: Request<A, D, T>>(
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>> {
+    let builder = Response::builder().status(StatusCode::UNAUTHORIZED);
+    builder.body(ResponseType::Empty).map_err(AppError::Http)
+}
+
 /// Serves the 404 page
 ///
 /// # Errors
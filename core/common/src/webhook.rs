@@ -0,0 +1,387 @@
+//! Delivers events queued by the database to outbound webhooks. Every
+//! request is signed with the secret of the webhook so that receivers are
+//! able to verify its origin. Secrets are stored encrypted with the app
+//! secret. Failed deliveries are retried with an exponential backoff until
+//! `MAX_ATTEMPTS` is reached.
+
+use crate::{
+    chrono::{Duration as ChronoDuration, Utc},
+    database::{DatabaseError, WebhookQueue},
+    log,
+    objects::{Event, Webhook, WebhookDelivery},
+    ring::hmac,
+    serde_json,
+    types::DeliveryStatus,
+    worker::run_periodic,
+};
+use reqwest::{header::CONTENT_TYPE, Client};
+use std::{borrow::Cow, convert::TryFrom, fmt::Write, sync::Arc, time::Duration};
+
+/// Maximum number of attempts before a delivery is marked as failed
+pub const MAX_ATTEMPTS: i32 = 8;
+/// Header containing the unix timestamp used for the signature
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+/// Header containing the signature in the form `sha256=<hex>`
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+/// Header containing the id of the delivery. Stays the same on retries
+/// and may be used by receivers to detect duplicates.
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+
+/// Delay before the first retry. Doubles with every further attempt
+const RETRY_BASE_SECS: i64 = 30;
+/// Number of deliveries processed at once
+const DELIVERY_BATCH: usize = 50;
+
+/// Signs the payload by calculating the hex encoded
+/// `HMAC-SHA256(secret, "<timestamp>.<payload>")`
+#[must_use]
+#[inline]
+pub fn sign(secret: &[u8], timestamp: i64, payload: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+    let mut ctx = hmac::Context::with_key(&key);
+    ctx.update(timestamp.to_string().as_bytes());
+    ctx.update(b".");
+    ctx.update(payload.as_bytes());
+    let tag = ctx.sign();
+    let mut hex = String::with_capacity(64);
+    for byte in tag.as_ref() {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    hex
+}
+
+/// Returns the time to wait after the given number of failed attempts
+#[must_use]
+#[inline]
+pub fn retry_delay(attempts: i32) -> ChronoDuration {
+    let exp = u32::try_from(attempts.saturating_sub(1)).unwrap_or_default();
+    let factor = 2_i64.checked_pow(exp).unwrap_or(i64::MAX);
+    ChronoDuration::seconds(RETRY_BASE_SECS.saturating_mul(factor))
+}
+
+/// Serializes the event into the json body posted to webhooks
+#[must_use]
+#[inline]
+pub fn payload(event: &Event<'_>) -> String {
+    serde_json::to_string(event).unwrap_or_default()
+}
+
+/// Worker which posts queued deliveries to their webhook
+#[derive(Debug)]
+pub struct WebhookWorker<D: WebhookQueue> {
+    db: Arc<D>,
+    client: Client,
+    interval: Duration,
+    app_secret: [u8; 32],
+}
+
+impl<D: WebhookQueue> WebhookWorker<D> {
+    /// Creates a new worker which checks the queue in the given interval.
+    /// Requests are aborted after the given timeout. The app secret
+    /// decrypts the secrets of the webhooks.
+    ///
+    /// # Errors
+    /// Fails if the http client cannot be initialized
+    #[inline]
+    pub fn new(
+        db: Arc<D>,
+        interval: Duration,
+        timeout: Duration,
+        app_secret: [u8; 32],
+    ) -> Result<Self, reqwest::Error> {
+        let client = Client::builder().timeout(timeout).build()?;
+        Ok(Self {
+            db,
+            client,
+            interval,
+            app_secret,
+        })
+    }
+
+    /// Processes the queue until the application stops
+    #[inline]
+    pub async fn run(self) {
+        run_periodic(self.interval, "webhook deliveries", || self.process()).await;
+    }
+
+    /// Attempts all due deliveries once. Returns the number of processed
+    /// deliveries.
+    ///
+    /// # Errors
+    /// Fails on database errors
+    #[inline]
+    pub async fn process(&self) -> Result<usize, DatabaseError<D>> {
+        let due = self.db.fetch_due(DELIVERY_BATCH)?;
+        let count = due.len();
+        for (webhook, mut delivery) in due {
+            self.attempt(&webhook, &mut delivery).await;
+            self.db.finish_delivery(&delivery)?;
+        }
+        Ok(count)
+    }
+
+    async fn attempt(
+        &self,
+        webhook: &Webhook<'_>,
+        delivery: &mut WebhookDelivery<'_>,
+    ) {
+        let secret = match webhook.signing_secret(&self.app_secret) {
+            Ok(secret) => secret,
+            Err(err) => {
+                log::error!(
+                    "Unable to decrypt secret of webhook {}: {}",
+                    webhook.name,
+                    err
+                );
+                delivery.attempts = delivery.attempts.saturating_add(1);
+                delivery.response_code = None;
+                delivery.error = Some(Cow::Owned(err.to_string()));
+                delivery.status = DeliveryStatus::Failed;
+                delivery.next_attempt = None;
+                return;
+            }
+        };
+        let timestamp = Utc::now().timestamp();
+        let signature = sign(&secret, timestamp, &delivery.payload);
+        let res = self
+            .client
+            .post(webhook.url.as_ref())
+            .header(CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, format!("sha256={}", signature))
+            .header(DELIVERY_HEADER, delivery.id.to_string())
+            .body(delivery.payload.to_string())
+            .send()
+            .await;
+        delivery.attempts = delivery.attempts.saturating_add(1);
+        match res {
+            Ok(res) => {
+                let status = res.status();
+                delivery.response_code = Some(i32::from(status.as_u16()));
+                if status.is_success() {
+                    delivery.status = DeliveryStatus::Delivered;
+                    delivery.next_attempt = None;
+                    delivery.error = None;
+                    return;
+                }
+                delivery.error =
+                    Some(Cow::Owned(format!("Receiver responded with {}", status)));
+            }
+            Err(err) => {
+                delivery.response_code = None;
+                delivery.error = Some(Cow::Owned(err.to_string()));
+            }
+        }
+        if delivery.attempts >= MAX_ATTEMPTS {
+            log::warn!(
+                "Giving up delivery {} to webhook {}: {}",
+                delivery.id,
+                webhook.name,
+                delivery.error.as_deref().unwrap_or_default()
+            );
+            delivery.status = DeliveryStatus::Failed;
+            delivery.next_attempt = None;
+        } else {
+            delivery.next_attempt = Utc::now()
+                .naive_utc()
+                .checked_add_signed(retry_delay(delivery.attempts));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sign, WebhookWorker, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+    use crate::{
+        database::{DbResult, WebhookQueue},
+        objects::{Webhook, WebhookDelivery},
+        types::{DeliveryStatus, Id},
+        worker::tests::TestDb,
+    };
+    use std::{
+        borrow::Cow,
+        io::{Read, Write},
+        net::TcpListener,
+        sync::Arc,
+        thread,
+        time::Duration,
+    };
+
+    const APP_SECRET: [u8; 32] = [7; 32];
+
+    #[derive(Debug)]
+    struct TestQueue {
+        url: String,
+        deliveries: Vec<WebhookDelivery<'static>>,
+    }
+
+    impl WebhookQueue for TestDb<TestQueue> {
+        fn fetch_due(
+            &self,
+            _limit: usize,
+        ) -> DbResult<Vec<(Webhook<'static>, WebhookDelivery<'static>)>, Self>
+        {
+            let id = self.generate_id()?;
+            let webhook = Webhook {
+                id: Cow::Owned(id),
+                name: Cow::Borrowed("test"),
+                url: Cow::Owned(
+                    self.data.lock().expect("Poisoned lock").url.clone(),
+                ),
+                secret: Cow::Owned(
+                    Webhook::encrypt_secret(&APP_SECRET, "secret")
+                        .expect("Unable to encrypt secret"),
+                ),
+                actions: None,
+                active: true,
+            };
+            let data = self.data.lock().expect("Poisoned lock");
+            Ok(data
+                .deliveries
+                .iter()
+                .filter(|d| d.status == DeliveryStatus::Pending)
+                .map(|d| (webhook.clone(), d.clone()))
+                .collect())
+        }
+
+        fn finish_delivery(
+            &self,
+            delivery: &WebhookDelivery<'_>,
+        ) -> DbResult<(), Self> {
+            let mut data = self.data.lock().expect("Poisoned lock");
+            data.deliveries.clear();
+            data.deliveries.push(WebhookDelivery {
+                id: Cow::Owned(delivery.id.as_ref().clone()),
+                webhook_id: Cow::Owned(delivery.webhook_id.as_ref().clone()),
+                event_id: Cow::Owned(delivery.event_id.as_ref().clone()),
+                payload: Cow::Owned(delivery.payload.to_string()),
+                status: delivery.status,
+                attempts: delivery.attempts,
+                next_attempt: delivery.next_attempt,
+                response_code: delivery.response_code,
+                error: delivery.error.as_ref().map(|e| Cow::Owned(e.to_string())),
+                date: delivery.date,
+            });
+            Ok(())
+        }
+    }
+
+    /// Starts a receiver which answers a single request with the given
+    /// status line and returns the raw request
+    fn receiver(status: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Unable to bind");
+        let addr = listener.local_addr().expect("Unable to get address");
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("Unable to accept");
+            stream
+                .set_read_timeout(Some(Duration::from_millis(500)))
+                .expect("Unable to set timeout");
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while let Ok(len) = stream.read(&mut buf) {
+                if len == 0 {
+                    break;
+                }
+                request.extend_from_slice(buf.get(..len).unwrap_or_default());
+                let text = String::from_utf8_lossy(&request);
+                if text.ends_with('}') {
+                    break;
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            );
+            stream
+                .write_all(response.as_bytes())
+                .expect("Unable to respond");
+            String::from_utf8_lossy(&request).into_owned()
+        });
+        (format!("http://{}/hook", addr), handle)
+    }
+
+    fn queue(url: String) -> Arc<TestDb<TestQueue>> {
+        let id = Id::from_string("c6efb44e-9b67-4dc0-a31b-6482476ed8b7")
+            .expect("Invalid Id");
+        let delivery = WebhookDelivery {
+            id: Cow::Owned(id.clone()),
+            webhook_id: Cow::Owned(id.clone()),
+            event_id: Cow::Owned(id),
+            payload: Cow::Borrowed(r#"{"action":"login"}"#),
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt: None,
+            response_code: None,
+            error: None,
+            date: None,
+        };
+        Arc::new(TestDb::new(TestQueue {
+            url,
+            deliveries: vec![delivery],
+        }))
+    }
+
+    fn header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+        request.lines().find_map(|line| {
+            let mut parts = line.splitn(2, ':');
+            let key = parts.next()?;
+            if key.eq_ignore_ascii_case(name) {
+                parts.next().map(str::trim)
+            } else {
+                None
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_delivery_signed() {
+        let (url, handle) = receiver("204 No Content");
+        let db = queue(url);
+        let worker = WebhookWorker::new(
+            Arc::clone(&db),
+            Duration::from_secs(1),
+            Duration::from_secs(5),
+            APP_SECRET,
+        )
+        .expect("Unable to create worker");
+        assert_eq!(worker.process().await.expect("Unable to process"), 1);
+
+        let request = handle.join().expect("Receiver failed");
+        assert!(request.starts_with("POST /hook"));
+        let timestamp: i64 = header(&request, TIMESTAMP_HEADER)
+            .and_then(|v| v.parse().ok())
+            .expect("Missing timestamp");
+        let expected = format!(
+            "sha256={}",
+            sign(b"secret", timestamp, r#"{"action":"login"}"#)
+        );
+        assert_eq!(header(&request, SIGNATURE_HEADER), Some(expected.as_str()));
+
+        let data = db.data.lock().expect("Poisoned lock");
+        let delivery = data.deliveries.first().expect("Missing delivery");
+        assert_eq!(delivery.status, DeliveryStatus::Delivered);
+        assert_eq!(delivery.response_code, Some(204));
+        assert_eq!(delivery.attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_delivery_retried() {
+        let (url, handle) = receiver("500 Internal Server Error");
+        let db = queue(url);
+        let worker = WebhookWorker::new(
+            Arc::clone(&db),
+            Duration::from_secs(1),
+            Duration::from_secs(5),
+            APP_SECRET,
+        )
+        .expect("Unable to create worker");
+        assert_eq!(worker.process().await.expect("Unable to process"), 1);
+        let _ = handle.join().expect("Receiver failed");
+
+        let data = db.data.lock().expect("Poisoned lock");
+        let delivery = data.deliveries.first().expect("Missing delivery");
+        assert_eq!(delivery.status, DeliveryStatus::Pending);
+        assert_eq!(delivery.response_code, Some(500));
+        assert!(delivery.next_attempt.is_some());
+    }
+}
@@ -0,0 +1,74 @@
+//! Loop shared by the background workers. A worker processes batches back
+//! to back while there is work left and waits for its interval otherwise.
+
+use crate::{log, tokio::time::delay_for};
+use std::{fmt::Display, future::Future, time::Duration};
+
+/// Calls `process` until the application stops. `process` returns the
+/// number of processed items, `items` names them in the log messages.
+/// Errors are logged and retried after the interval.
+#[inline]
+pub(crate) async fn run_periodic<F, R, E>(
+    interval: Duration,
+    items: &str,
+    mut process: F,
+) where
+    F: FnMut() -> R,
+    R: Future<Output = Result<usize, E>>,
+    E: Display,
+{
+    loop {
+        match process().await {
+            Ok(0) => delay_for(interval).await,
+            Ok(count) => log::info!("Processed {} {}", count, items),
+            Err(err) => {
+                log::error!("Unable to process {}: {}", items, err);
+                delay_for(interval).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::{
+        database::{Database, DatabaseError},
+        types::Id,
+    };
+    use std::{borrow::Cow, fmt, sync::Mutex};
+
+    /// Database of the worker tests. The stores a worker needs are
+    /// implemented on top of `data`
+    #[derive(Debug)]
+    pub(crate) struct TestDb<T> {
+        pub(crate) data: Mutex<T>,
+    }
+
+    impl<T> TestDb<T> {
+        pub(crate) fn new(data: T) -> Self {
+            Self {
+                data: Mutex::new(data),
+            }
+        }
+    }
+
+    impl<T: fmt::Debug + Send> Database for TestDb<T> {
+        type DatabaseError = fmt::Error;
+
+        fn generate_id(&self) -> Result<Id, DatabaseError<Self>> {
+            Ok(Id::from_string("00bd8c06-daf7-47e6-8c96-8d467587b6dc")
+                .expect("Invalid Id"))
+        }
+
+        fn fetch_permission_ids<'a>(
+            &self,
+            _entity_id: Cow<'a, Id>,
+        ) -> Result<Vec<Cow<'a, Id>>, DatabaseError<Self>> {
+            Ok(Vec::new())
+        }
+
+        fn migrate(&self) -> Result<(), DatabaseError<Self>> {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use core_common::{
+    chrono::Utc,
+    database::{AccessRequestStore, Create, Database, DatabaseError, LoginStore},
+    objects::{
+        AccessRequest, AccessRequestEntry, AccessValidity, Event, EventDetails,
+        ServerLogin,
+    },
+    sec::{Auth, CsrfToken},
+    serde::Serialize,
+    types::{AccessRequestStatus, AuthorizationType, Id},
+    web::{AppError, Notification, Request, TemplateEngine},
+};
+use std::borrow::Cow;
+
+/// Information required to request access to a server account
+#[derive(Debug, Clone, Default)]
+pub struct AccessRequestForm<'a> {
+    /// The hostname of the server
+    pub hostname: Option<Cow<'a, str>>,
+    /// The name of the account on the server
+    pub account: Option<Cow<'a, str>>,
+    /// Why access is required
+    pub reason: Option<Cow<'a, str>>,
+    /// When access should start, see `AccessValidity::parse_date`
+    pub valid_from: Option<Cow<'a, str>>,
+    /// When access should end, see `AccessValidity::parse_date`
+    pub valid_until: Option<Cow<'a, str>>,
+}
+
+/// The access of the current user, their requests and the requests waiting
+/// for their decision
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct AccessRequestListView<'a> {
+    /// Server accounts the current user has access to at the moment
+    pub access: Vec<ServerLogin<'a>>,
+    /// Requests made by the current user
+    pub requests: Vec<AccessRequestEntry<'a>>,
+    /// Pending requests the current user may decide. Admins see every
+    /// pending request
+    pub pending: Vec<AccessRequestEntry<'a>>,
+}
+
+impl AccessRequestListView<'_> {
+    /// Fetches the access and the requests of the current user and the
+    /// pending requests for servers the user administers
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    pub async fn fetch<A, D, T, R>(req: &R) -> Result<Self, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        D: Database + AccessRequestStore + LoginStore,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+        let access = db.fetch_logins(auth.get_id())?;
+        let requests = db.fetch_requests(auth.get_id())?;
+        let approver_id = if auth.is_admin() {
+            None
+        } else {
+            Some(auth.get_id())
+        };
+        let pending = db.fetch_pending(approver_id)?;
+        Ok(Self {
+            access,
+            requests,
+            pending,
+        })
+    }
+
+    /// Requests access to a server account for the current user. The
+    /// server admins decide on the request unless the server uses
+    /// `AuthorizationType::Automatic`, in which case access is granted
+    /// immediately. Every request is recorded as event
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes, clippy::needless_lifetimes)]
+    pub async fn create<'e, A, D, T, R>(
+        req: &R,
+        form: AccessRequestForm<'_>,
+        csrf: &CsrfToken,
+    ) -> Result<[Notification<'e>; 1], AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + AccessRequestStore + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        if !csrf.valid {
+            return Ok([Notification::Error {
+                name: "Access request",
+                para: "csrf",
+                help: "../help/#access_request",
+            }]);
+        }
+        let db = req.get_database();
+        let auth = req.get_auth();
+        let (hostname, account) = match (form.hostname, form.account) {
+            (Some(hostname), Some(account)) => (hostname, account),
+            (None, _) => {
+                return Ok([Notification::Error {
+                    name: "Access request",
+                    para: "Hostname",
+                    help: "../help/#access_request",
+                }])
+            }
+            (_, None) => {
+                return Ok([Notification::Error {
+                    name: "Access request",
+                    para: "Account",
+                    help: "../help/#access_request",
+                }])
+            }
+        };
+        let valid_from = form.valid_from.as_deref().map(AccessValidity::parse_date);
+        let valid_until =
+            form.valid_until.as_deref().map(AccessValidity::parse_date);
+        let validity = match (valid_from, valid_until) {
+            (Some(None), _) => {
+                return Ok([Notification::Error {
+                    name: "Access request",
+                    para: "Valid from",
+                    help: "../help/#access_request",
+                }])
+            }
+            (_, Some(None)) => {
+                return Ok([Notification::Error {
+                    name: "Access request",
+                    para: "Valid until",
+                    help: "../help/#access_request",
+                }])
+            }
+            (valid_from, valid_until) => AccessValidity {
+                valid_from: valid_from.flatten(),
+                valid_until: valid_until.flatten(),
+            },
+        };
+        let now = Utc::now().naive_utc();
+        if !validity.is_valid() || validity.valid_until.map_or(false, |v| v <= now) {
+            return Ok([Notification::Error {
+                name: "Access request",
+                para: "Valid until",
+                help: "../help/#access_request",
+            }]);
+        }
+        let login = match db.fetch_account(&hostname, &account)? {
+            Some(login) => login,
+            None => {
+                return Ok([Notification::Error {
+                    name: "Access request",
+                    para: "Account",
+                    help: "../help/#access_request",
+                }])
+            }
+        };
+        let mut request = AccessRequest {
+            id: Cow::Owned(db.generate_id()?),
+            source_id: Cow::Borrowed(auth.get_id()),
+            dest_id: login.account_id,
+            reason: form.reason,
+            validity,
+            status: AccessRequestStatus::Pending,
+            request_date: None,
+            decided_by: None,
+            decision_date: None,
+        };
+        match db.create_request(&request) {
+            Err(DatabaseError::NonUnique) => {
+                return Ok([Notification::Unique {
+                    name: "Access request",
+                    para: "account",
+                    help: "../help/#access_request",
+                }])
+            }
+            Err(err) => return Err(AppError::DatabaseError(err)),
+            Ok(()) => {}
+        }
+
+        let details = EventDetails::AccessRequestCreate {
+            request_id: Cow::Borrowed(&request.id),
+            dest_id: Cow::Borrowed(&request.dest_id),
+            reason: request.reason.as_deref().map(Cow::Borrowed),
+        };
+        let event = Event::new(
+            Cow::Owned(db.generate_id()?),
+            Some(Cow::Borrowed(auth.get_id())),
+            details,
+            Some(Cow::Borrowed(auth.get_id())),
+        );
+        db.create(&event, auth)?;
+
+        if login.server.authorization == AuthorizationType::Automatic
+            && request.decide(true, None, now)
+        {
+            let _ = record_decision(req, &request)?;
+        }
+        Ok([Notification::Info {
+            name: "Access request",
+            url: ".",
+            id: Cow::Owned(request.id.into_owned()),
+        }])
+    }
+}
+
+/// A single access request ready to be presented
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct AccessRequestView<'a> {
+    /// The request to show to the user
+    pub entry: AccessRequestEntry<'a>,
+    /// Whether the current user may approve or reject the request
+    pub can_decide: bool,
+}
+
+impl AccessRequestView<'_> {
+    /// Fetches the request with the given id. Only the requesting user,
+    /// the admins of the server and application admins may see it
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    pub async fn fetch<A, D, T, R>(
+        req: &R,
+        key: &str,
+    ) -> Result<Option<Self>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        D: Database + AccessRequestStore,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+        let id = match Id::from_string(key) {
+            Ok(id) => id,
+            Err(_) => return Ok(None),
+        };
+        let entry = match db.fetch_request(&id)? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let approver = auth.is_admin()
+            || db.is_approver(&entry.login.server.id, auth.get_id())?;
+        if !approver && *entry.request.source_id != *auth.get_id() {
+            return Ok(None);
+        }
+        let can_decide =
+            approver && entry.request.status == AccessRequestStatus::Pending;
+        Ok(Some(Self { entry, can_decide }))
+    }
+
+    /// Approves or rejects the request in the name of the current user.
+    /// Approval grants the requesting entity access to the account.
+    /// Returns `false` if the request was already decided
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(single_use_lifetimes)]
+    pub async fn decide<A, D, T, R>(
+        &mut self,
+        req: &R,
+        approve: bool,
+    ) -> Result<bool, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + AccessRequestStore + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        if !self.can_decide {
+            return Ok(false);
+        }
+        let auth = req.get_auth();
+        let decided_by = Cow::Owned(auth.get_id().clone());
+        let request = &mut self.entry.request;
+        if !request.decide(approve, Some(decided_by), Utc::now().naive_utc()) {
+            return Ok(false);
+        }
+        self.can_decide = false;
+        record_decision(req, request)
+    }
+}
+
+/// Stores the decision on the request and records it as event. Approved
+/// requests additionally record the access grant
+#[allow(single_use_lifetimes)]
+fn record_decision<A, D, T, R>(
+    req: &R,
+    request: &AccessRequest<'_>,
+) -> Result<bool, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'b> D: Database + AccessRequestStore + Create<A, Event<'b>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let db = req.get_database();
+    let auth = req.get_auth();
+    if !db.decide_request(request)? {
+        return Ok(false);
+    }
+    let details = EventDetails::AccessRequestDecide {
+        request_id: Cow::Borrowed(&request.id),
+        source_id: Cow::Borrowed(&request.source_id),
+        dest_id: Cow::Borrowed(&request.dest_id),
+        status: request.status,
+    };
+    let event = Event::new(
+        Cow::Owned(db.generate_id()?),
+        request.decided_by.as_deref().map(Cow::Borrowed),
+        details,
+        Some(Cow::Borrowed(&request.source_id)),
+    );
+    db.create(&event, auth)?;
+    if request.status == AccessRequestStatus::Approved {
+        let details = EventDetails::AccessGrant {
+            source_id: Cow::Borrowed(&request.source_id),
+            dest_id: Cow::Borrowed(&request.dest_id),
+        };
+        let event = Event::new(
+            Cow::Owned(db.generate_id()?),
+            request.decided_by.as_deref().map(Cow::Borrowed),
+            details,
+            Some(Cow::Borrowed(&request.source_id)),
+        );
+        db.create(&event, auth)?;
+    }
+    Ok(true)
+}
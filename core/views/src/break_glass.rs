@@ -0,0 +1,291 @@
+use core_common::{
+    chrono::Utc,
+    database::{
+        AccessRequestStore, BreakGlassStore, Create, Database, DatabaseError,
+    },
+    log,
+    objects::{BreakGlass, BreakGlassEntry, Event, EventDetails},
+    sec::{Auth, CsrfToken},
+    serde::Serialize,
+    types::Id,
+    web::{AppError, Notification, Request, TemplateEngine},
+};
+use std::borrow::Cow;
+
+/// Information required to take emergency access to a server account
+#[derive(Debug, Clone, Default)]
+pub struct BreakGlassForm<'a> {
+    /// The hostname of the server
+    pub hostname: Option<Cow<'a, str>>,
+    /// The name of the account on the server
+    pub account: Option<Cow<'a, str>>,
+    /// Why emergency access is required
+    pub reason: Option<Cow<'a, str>>,
+    /// How long access lasts, see `BreakGlass::parse_duration`
+    pub hours: Option<Cow<'a, str>>,
+}
+
+/// Every emergency access ready to be reviewed
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct BreakGlassReportView<'a> {
+    /// The emergency accesses, newest first
+    pub entries: Vec<BreakGlassEntry<'a>>,
+    /// Whether only emergency accesses without review are listed
+    pub unreviewed: bool,
+}
+
+impl BreakGlassReportView<'_> {
+    /// Fetches the emergency accesses, optionally only the ones without
+    /// review
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    pub async fn fetch<A, D, T, R>(
+        req: &R,
+        unreviewed: bool,
+    ) -> Result<Self, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        D: Database + BreakGlassStore,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let entries = req.get_database().fetch_break_glass_report(unreviewed)?;
+        Ok(Self {
+            entries,
+            unreviewed,
+        })
+    }
+}
+
+/// A single emergency access ready to be presented
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct BreakGlassView<'a> {
+    /// The emergency access to show to the user
+    pub entry: BreakGlassEntry<'a>,
+    /// Whether the current user may review the emergency access
+    pub can_review: bool,
+}
+
+impl BreakGlassView<'_> {
+    /// Takes emergency access to a server account for the current user.
+    /// Only accounts the user was granted access to before or which are
+    /// configured for emergency access may be taken. Access is granted
+    /// immediately without approval, the account is
+    /// synced and a high severity event is recorded so the access can be
+    /// reviewed after the fact
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes, clippy::needless_lifetimes)]
+    pub async fn create<'e, A, D, T, R>(
+        req: &R,
+        form: BreakGlassForm<'_>,
+        csrf: &CsrfToken,
+    ) -> Result<[Notification<'e>; 1], AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database
+            + AccessRequestStore
+            + BreakGlassStore
+            + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        if !csrf.valid {
+            return Ok([Notification::Error {
+                name: "Emergency access",
+                para: "csrf",
+                help: "../help/#break_glass",
+            }]);
+        }
+        let db = req.get_database();
+        let auth = req.get_auth();
+        let (hostname, account) = match (form.hostname, form.account) {
+            (Some(hostname), Some(account)) => (hostname, account),
+            (None, _) => {
+                return Ok([Notification::Error {
+                    name: "Emergency access",
+                    para: "Hostname",
+                    help: "../help/#break_glass",
+                }])
+            }
+            (_, None) => {
+                return Ok([Notification::Error {
+                    name: "Emergency access",
+                    para: "Account",
+                    help: "../help/#break_glass",
+                }])
+            }
+        };
+        let reason = match form.reason {
+            Some(reason) if !reason.trim().is_empty() => reason,
+            _ => {
+                return Ok([Notification::Error {
+                    name: "Emergency access",
+                    para: "Reason",
+                    help: "../help/#break_glass",
+                }])
+            }
+        };
+        let duration =
+            match form.hours.as_deref().and_then(BreakGlass::parse_duration) {
+                Some(duration) => duration,
+                None => {
+                    return Ok([Notification::Error {
+                        name: "Emergency access",
+                        para: "Duration",
+                        help: "../help/#break_glass",
+                    }])
+                }
+            };
+        let login = match db.fetch_account(&hostname, &account)? {
+            Some(login) => login,
+            None => {
+                return Ok([Notification::Error {
+                    name: "Emergency access",
+                    para: "Account",
+                    help: "../help/#break_glass",
+                }])
+            }
+        };
+        // only accounts the user was granted before or which are configured
+        // for emergency access may be taken
+        let name = format!("{}@{}", account, hostname);
+        let allowed = req
+            .get_base_data()
+            .break_glass_accounts
+            .iter()
+            .any(|v| *v == name);
+        if !allowed && !db.had_access(auth.get_id(), &login.account_id)? {
+            return Ok([Notification::Error {
+                name: "Emergency access",
+                para: "Account",
+                help: "../help/#break_glass",
+            }]);
+        }
+        let emergency = BreakGlass::new(
+            Cow::Owned(db.generate_id()?),
+            Cow::Borrowed(auth.get_id()),
+            login.account_id,
+            reason,
+            duration,
+            Utc::now().naive_utc(),
+        );
+        match db.create_break_glass(&emergency) {
+            Err(DatabaseError::NonUnique) => {
+                return Ok([Notification::Unique {
+                    name: "Emergency access",
+                    para: "account",
+                    help: "../help/#break_glass",
+                }])
+            }
+            Err(err) => return Err(AppError::DatabaseError(err)),
+            Ok(()) => {}
+        }
+        log::warn!(
+            "Emergency access to {}@{} taken by {}: {}",
+            account,
+            hostname,
+            auth.get_id(),
+            emergency.reason
+        );
+
+        let details = EventDetails::BreakGlass {
+            break_glass_id: Cow::Borrowed(&emergency.id),
+            dest_id: Cow::Borrowed(&emergency.dest_id),
+            reason: Cow::Borrowed(&*emergency.reason),
+            valid_until: emergency.valid_until,
+        };
+        let event = Event::new(
+            Cow::Owned(db.generate_id()?),
+            Some(Cow::Borrowed(auth.get_id())),
+            details,
+            Some(Cow::Borrowed(auth.get_id())),
+        );
+        db.create(&event, auth)?;
+        Ok([Notification::Info {
+            name: "Emergency access",
+            url: "../break_glass",
+            id: Cow::Owned(emergency.id.into_owned()),
+        }])
+    }
+
+    /// Fetches the emergency access with the given id. Only the user
+    /// which took it and application admins may see it
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    pub async fn fetch<A, D, T, R>(
+        req: &R,
+        key: &str,
+    ) -> Result<Option<Self>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        D: Database + BreakGlassStore,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let id = match Id::from_string(key) {
+            Ok(id) => id,
+            Err(_) => return Ok(None),
+        };
+        let auth = req.get_auth();
+        let entry = match req.get_database().fetch_break_glass(&id)? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        if !auth.is_admin() && *entry.break_glass.source_id != *auth.get_id() {
+            return Ok(None);
+        }
+        let can_review = auth.is_admin() && entry.break_glass.review_date.is_none();
+        Ok(Some(Self { entry, can_review }))
+    }
+
+    /// Marks the emergency access as reviewed by the current user. Returns
+    /// `false` if it was already reviewed or the user is no admin
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(single_use_lifetimes)]
+    pub async fn review<A, D, T, R>(
+        &mut self,
+        req: &R,
+    ) -> Result<bool, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + BreakGlassStore + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        if !self.can_review {
+            return Ok(false);
+        }
+        let db = req.get_database();
+        let auth = req.get_auth();
+        let emergency = &mut self.entry.break_glass;
+        let reviewed_by = Cow::Owned(auth.get_id().clone());
+        if !emergency.review(reviewed_by, Utc::now().naive_utc())
+            || !db.review_break_glass(emergency)?
+        {
+            return Ok(false);
+        }
+        self.can_review = false;
+        let details = EventDetails::BreakGlassReview {
+            break_glass_id: Cow::Borrowed(&emergency.id),
+            source_id: Cow::Borrowed(&emergency.source_id),
+        };
+        let event = Event::new(
+            Cow::Owned(db.generate_id()?),
+            Some(Cow::Borrowed(auth.get_id())),
+            details,
+            Some(Cow::Borrowed(&emergency.source_id)),
+        );
+        db.create(&event, auth)?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,354 @@
+use crate::PublicKeyView;
+use core_common::{
+    chrono::Utc,
+    database::{CertificateStore, Create, Database, DatabaseError},
+    log,
+    objects::{
+        AuthorizedPrincipals, CertificateAuthority, CertificateError,
+        CertificateRequest, Event, EventDetails, HostCertificate, PublicKey,
+        PublicKeyConversionError, Server, SignedCertificate,
+    },
+    sec::{Auth, SecretError},
+    serde::Serialize,
+    types::{CertificateType, FingerprintMd5, Id},
+    web::{AppError, Request, TemplateEngine},
+};
+use std::borrow::Cow;
+
+/// A certificate authority ready to be presented
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct CertificateAuthorityView<'a>(pub CertificateAuthority<'a>);
+
+impl CertificateAuthorityView<'_> {
+    /// Fetches the authority of the given type. The authority is generated
+    /// when it is used for the first time
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    pub async fn fetch<A, D, T, R>(
+        req: &R,
+        type_: CertificateType,
+    ) -> Result<Result<Self, CertificateError>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        D: Database + CertificateStore,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let db = req.get_database();
+        if let Some(authority) = db.fetch_authority(type_)? {
+            return Ok(Ok(Self(authority)));
+        }
+        let app_secret = &req.get_base_data().app_secret;
+        let id = Cow::Owned(db.generate_id()?);
+        let authority = match CertificateAuthority::generate(id, type_, app_secret) {
+            Ok(authority) => authority,
+            Err(err) => return Ok(Err(err)),
+        };
+        match db.create_authority(&authority) {
+            Ok(()) => {
+                log::info!("Created {:?} certificate authority", type_);
+                Ok(Ok(Self(authority)))
+            }
+            // created concurrently by another request
+            Err(DatabaseError::NonUnique) => db
+                .fetch_authority(type_)?
+                .map(|authority| Ok(Self(authority)))
+                .ok_or_else(|| AppError::DatabaseError(DatabaseError::NonUnique)),
+            Err(err) => Err(AppError::DatabaseError(err)),
+        }
+    }
+
+    /// Renders the authority as `known_hosts` entries trusting it for the
+    /// names of the given servers only
+    ///
+    /// # Errors
+    /// Fails when no random data is available to hash the hostnames
+    #[inline]
+    pub fn to_known_hosts(
+        &self,
+        servers: &[Server<'_>],
+        hashed: bool,
+    ) -> Result<String, SecretError> {
+        let mut file = String::new();
+        for server in servers {
+            file.push_str(&server.known_hosts_entry(
+                Some("@cert-authority"),
+                &self.0.public_key,
+                hashed,
+            )?);
+        }
+        Ok(file)
+    }
+}
+
+/// The host certificate of a server
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct HostCertificateView<'a> {
+    /// The certificate most recently issued. `None` if no certificate was
+    /// issued yet
+    pub certificate: Option<HostCertificate<'a>>,
+    /// Whether a new certificate should be requested
+    pub renew: bool,
+}
+
+impl HostCertificateView<'_> {
+    /// Fetches the host certificate of the server. Permission checks must
+    /// be done by the caller
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    pub async fn fetch<A, D, T, R>(
+        req: &R,
+        server_id: &Id,
+    ) -> Result<Self, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        D: Database + CertificateStore,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let db = req.get_database();
+        let certificate = db.fetch_host_certificate(server_id)?;
+        let now = Utc::now().naive_utc();
+        let renew = certificate
+            .as_ref()
+            .map_or(true, |certificate| certificate.needs_renewal(now));
+        Ok(Self { certificate, renew })
+    }
+
+    /// Issues a host certificate for the given host key. The key must match
+    /// the fingerprint stored for the server, so only the registered host
+    /// key gets certified. The key is stored for `known_hosts` exports.
+    /// The hostname and the ip address of the server are used as
+    /// principals. Every issued certificate is recorded as an event
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(single_use_lifetimes)]
+    pub async fn issue<A, D, T, R>(
+        req: &R,
+        server: &Server<'_>,
+        data: &str,
+    ) -> Result<
+        Result<HostCertificate<'static>, CertificateError>,
+        AppError<A, D, T, R>,
+    >
+    where
+        A: Auth,
+        for<'a> D: Database + CertificateStore + Create<A, Event<'a>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let db = req.get_database();
+        let auth = req.get_auth();
+        let base = req.get_base_data();
+        let key = match PublicKey::parse(data, &server.id, db, &base.key_blocklist) {
+            Ok(key) => key,
+            Err(PublicKeyConversionError::DatabaseError(err)) => {
+                return Err(AppError::DatabaseError(err));
+            }
+            Err(PublicKeyConversionError::OpenSshError(_))
+            | Err(PublicKeyConversionError::InvalidSecurityKey)
+            | Err(PublicKeyConversionError::Compromised) => {
+                return Ok(Err(CertificateError::InvalidKey));
+            }
+        };
+        let pinned = server
+            .rsa_key_fingerprint
+            .as_ref()
+            .map(|v| FingerprintMd5::from_string(Cow::Borrowed(v)));
+        let known =
+            pinned.map_or(false, |v| key.fingerprint_md5.as_deref() == Some(&v));
+        if !known {
+            return Ok(Err(CertificateError::UnknownHostKey));
+        }
+        let host_key = format!("{} {}", key.type_, key.keydata);
+        if server.host_key.as_deref() != Some(host_key.as_str()) {
+            db.save_host_key(&server.id, &host_key)?;
+        }
+
+        let mut principals = vec![server.hostname.to_string()];
+        if let Some(ref ip_address) = server.ip_address {
+            if *ip_address != server.hostname {
+                principals.push(ip_address.to_string());
+            }
+        }
+        let (valid_after, valid_before) = base
+            .certificate_policy
+            .validity_for_host(Utc::now().naive_utc());
+        let request = CertificateRequest::for_host(
+            &key,
+            server.hostname.to_string(),
+            principals,
+            valid_after,
+            valid_before,
+        );
+        let authority =
+            match CertificateAuthorityView::fetch(req, CertificateType::Host).await?
+            {
+                Ok(authority) => authority.0,
+                Err(err) => return Ok(Err(err)),
+            };
+        let signed = match authority.sign(&base.app_secret, &request) {
+            Ok(signed) => signed,
+            Err(err) => return Ok(Err(err)),
+        };
+        let certificate = HostCertificate {
+            server_id: Cow::Owned(server.id.clone().into_owned()),
+            serial: signed.serial,
+            certificate: Cow::Owned(signed.line),
+            valid_after,
+            valid_before,
+        };
+        db.save_host_certificate(&certificate)?;
+
+        let details = EventDetails::HostCertificateIssue {
+            hostname: Cow::Borrowed(&server.hostname),
+            fingerprint: key
+                .fingerprint_md5
+                .as_ref()
+                .map(|v| Cow::Owned(v.to_str().into_owned())),
+            serial: certificate.serial,
+            principals: request.principals.iter().map(Cow::from).collect(),
+            valid_after,
+            valid_before,
+        };
+        let event = Event::new(
+            Cow::Owned(db.generate_id()?),
+            Some(Cow::Borrowed(auth.get_id())),
+            details,
+            Some(Cow::Borrowed(&server.id)),
+        );
+        db.create(&event, auth)?;
+        Ok(Ok(certificate))
+    }
+}
+
+/// The files a server using certificate based key management requires
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct ServerCertificatesView<'a> {
+    /// The public key of the user certificate authority. Belongs into the
+    /// file configured as `TrustedUserCAKeys`
+    pub authority: Cow<'a, str>,
+    /// The principals of every account. Belong into the files configured
+    /// as `AuthorizedPrincipalsFile`
+    pub accounts: Vec<AuthorizedPrincipals<'a>>,
+}
+
+impl ServerCertificatesView<'_> {
+    /// Fetches the authority and the principals of the accounts on the
+    /// server. Permission checks must be done by the caller
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    pub async fn fetch<A, D, T, R>(
+        req: &R,
+        server_id: &Id,
+    ) -> Result<Result<Self, CertificateError>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        D: Database + CertificateStore,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let authority =
+            match CertificateAuthorityView::fetch(req, CertificateType::User).await?
+            {
+                Ok(authority) => authority.0.public_key,
+                Err(err) => return Ok(Err(err)),
+            };
+        let db = req.get_database();
+        let accounts = db.fetch_authorized_principals(server_id)?;
+        Ok(Ok(Self {
+            authority,
+            accounts,
+        }))
+    }
+}
+
+impl PublicKeyView<'_> {
+    /// Issues a user certificate for the public key. The principals and
+    /// restrictions are derived from the access grants of the key owner.
+    /// The validity is given in hours and limited by the certificate
+    /// policy, the expiry date of the key and the end of time-bound
+    /// grants. Every issued certificate is recorded as an event
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(single_use_lifetimes)]
+    pub async fn issue_certificate<A, D, T, R>(
+        &self,
+        req: &R,
+        validity: Option<u32>,
+    ) -> Result<Result<SignedCertificate, CertificateError>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'a> D: Database + CertificateStore + Create<A, Event<'a>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let db = req.get_database();
+        let auth = req.get_auth();
+        let base = req.get_base_data();
+        let key = &self.public_key;
+        let now = Utc::now().naive_utc();
+        let (valid_after, valid_before) =
+            match base.certificate_policy.validity(key, validity, now) {
+                Ok(validity) => validity,
+                Err(err) => return Ok(Err(err)),
+            };
+        let grants = db.fetch_principals(&key.entity_id)?;
+        let owner = self
+            .owner
+            .name
+            .as_ref()
+            .map_or_else(|| key.entity_id.to_string(), ToString::to_string);
+        let key_id = format!("{}:{}", owner, key.id);
+        let request = match CertificateRequest::for_user(
+            key,
+            key_id,
+            &grants,
+            valid_after,
+            valid_before,
+        ) {
+            Ok(request) => request,
+            Err(err) => return Ok(Err(err)),
+        };
+        let authority =
+            match CertificateAuthorityView::fetch(req, CertificateType::User).await?
+            {
+                Ok(authority) => authority.0,
+                Err(err) => return Ok(Err(err)),
+            };
+        let certificate = match authority.sign(&base.app_secret, &request) {
+            Ok(certificate) => certificate,
+            Err(err) => return Ok(Err(err)),
+        };
+
+        let details = EventDetails::CertificateIssue {
+            key_id: Cow::Borrowed(&key.id),
+            fingerprint: key
+                .fingerprint_md5
+                .as_ref()
+                .map(|v| Cow::Owned(v.to_str().into_owned())),
+            serial: certificate.serial,
+            principals: request.principals.iter().map(Cow::from).collect(),
+            valid_after: request.valid_after,
+            valid_before: request.valid_before,
+        };
+        let event = Event::new(
+            Cow::Owned(db.generate_id()?),
+            Some(Cow::Borrowed(auth.get_id())),
+            details,
+            Some(Cow::Borrowed(&key.entity_id)),
+        );
+        db.create(&event, auth)?;
+        Ok(Ok(certificate))
+    }
+}
@@ -0,0 +1,248 @@
+use core_common::{
+    database::{Database, DbList, FetchAll, FetchById},
+    objects::{Entity, Event, EventDetails, EventFilter},
+    sec::Auth,
+    serde::Serialize,
+    serde_json::{self, Value},
+    types::Id,
+    web::{AppError, Request, TemplateEngine},
+};
+use std::{borrow::Cow, collections::HashMap, fmt::Write};
+
+/// A single key value pair of the event details
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct EventDetail {
+    /// Name of the detail
+    pub key: String,
+    /// Human readable value of the detail
+    pub value: String,
+}
+
+/// An event ready to be presented
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct EventView<'a> {
+    /// The event to show to the user
+    pub event: Event<'a>,
+    /// The entity which triggered the event
+    pub actor: Option<Entity<'a>>,
+    /// The details of the event in a readable form
+    pub details: Vec<EventDetail>,
+}
+
+impl<'a> EventView<'a> {
+    /// Creates a view for the given event by splitting the details
+    /// into key value pairs. Legacy details which are not a json object
+    /// are kept as a single entry.
+    #[must_use]
+    #[inline]
+    pub fn new(event: Event<'a>, actor: Option<Entity<'a>>) -> Self {
+        let value = match event.details {
+            EventDetails::Legacy { ref raw, .. } => serde_json::from_str(raw)
+                .unwrap_or_else(|_| Value::String(raw.clone())),
+            ref details => serde_json::to_value(details).unwrap_or(Value::Null),
+        };
+        let details = match value {
+            Value::Object(map) => map
+                .into_iter()
+                .map(|(key, value)| EventDetail {
+                    key,
+                    value: Self::value_to_string(value),
+                })
+                .collect(),
+            other => vec![EventDetail {
+                key: "details".into(),
+                value: Self::value_to_string(other),
+            }],
+        };
+        Self {
+            event,
+            actor,
+            details,
+        }
+    }
+
+    /// Returns the action of the event if there is one
+    #[must_use]
+    #[inline]
+    pub fn action(&self) -> Option<&str> {
+        self.details
+            .iter()
+            .find(|detail| detail.key == "action")
+            .map(|detail| detail.value.as_ref())
+    }
+
+    fn value_to_string(value: Value) -> String {
+        match value {
+            Value::String(text) => text,
+            Value::Null => String::new(),
+            other => other.to_string(),
+        }
+    }
+}
+
+/// A List of events ready to be presented
+#[derive(Debug)]
+pub struct EventListView<'a>(pub DbList<EventView<'a>>);
+
+impl<'a> EventListView<'a> {
+    /// Fetches one page of events visible to the given user
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes)]
+    pub async fn fetch<A, D, T, R>(
+        req: &R,
+        filter: &EventFilter<'_>,
+        page: usize,
+    ) -> Result<EventListView<'a>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b, 'c> D: Database
+            + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>
+            + FetchById<'b, A, Entity<'a>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+
+        let list = db.fetch_all(filter, auth, page)?;
+        let mut actors = HashMap::new();
+        let data = Self::to_views(req, list.data, &mut actors)?;
+        Ok(Self(DbList {
+            data,
+            count: list.count,
+            page: list.page,
+            page_max: list.page_max,
+        }))
+    }
+
+    /// Fetches all events visible to the given user. Used for exporting
+    /// the audit log.
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes)]
+    pub async fn fetch_all<A, D, T, R>(
+        req: &R,
+        filter: &EventFilter<'_>,
+    ) -> Result<Vec<EventView<'a>>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b, 'c> D: Database
+            + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>
+            + FetchById<'b, A, Entity<'a>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+
+        let mut actors = HashMap::new();
+        let mut views = Vec::new();
+        let mut page = 1;
+        loop {
+            let list = db.fetch_all(filter, auth, page)?;
+            let page_max = list.page_max;
+            views.append(&mut Self::to_views(req, list.data, &mut actors)?);
+            if page >= page_max {
+                break;
+            }
+            page = page.saturating_add(1);
+        }
+        Ok(views)
+    }
+
+    fn to_views<A, D, T, R>(
+        req: &R,
+        events: Vec<Event<'a>>,
+        actors: &mut HashMap<Id, Option<Entity<'a>>>,
+    ) -> Result<Vec<EventView<'a>>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + FetchById<'b, A, Entity<'a>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+
+        let mut views = Vec::with_capacity(events.len());
+        for event in events {
+            let actor = if let Some(ref actor_id) = event.actor_id {
+                if let Some(actor) = actors.get(actor_id.as_ref()) {
+                    actor.clone()
+                } else {
+                    let actor: Option<Entity<'a>> = db.fetch(actor_id, auth)?;
+                    let _ = actors.insert(actor_id.as_ref().clone(), actor.clone());
+                    actor
+                }
+            } else {
+                None
+            };
+            views.push(EventView::new(event, actor));
+        }
+        Ok(views)
+    }
+
+    /// Converts the given events to csv. Values which may be interpreted
+    /// as formula by spreadsheet applications are escaped.
+    #[must_use]
+    #[inline]
+    pub fn to_csv(events: &[EventView<'_>]) -> String {
+        let mut csv =
+            String::from("id,date,actor_id,actor,type,object_id,action,details\r\n");
+        for view in events {
+            let event = &view.event;
+            let id = event.id.to_string();
+            let date = event.date.map(|d| d.to_string()).unwrap_or_default();
+            let actor_id = event
+                .actor_id
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            let actor = view
+                .actor
+                .as_ref()
+                .and_then(|actor| actor.name.as_ref())
+                .map_or(Cow::Borrowed(""), Clone::clone);
+            let type_ = format!("{:?}", event.type_);
+            let object_id = event
+                .object_id
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            let details = event.details.to_json();
+            let fields: [&str; 8] = [
+                &id,
+                &date,
+                &actor_id,
+                &actor,
+                &type_,
+                &object_id,
+                view.action().unwrap_or_default(),
+                &details,
+            ];
+            let line: Vec<String> =
+                fields.iter().map(|f| Self::escape_csv(f)).collect();
+            let _ = write!(csv, "{}\r\n", line.join(","));
+        }
+        csv
+    }
+
+    /// Quotes a single csv field and escapes possible formulas
+    pub(crate) fn escape_csv(field: &str) -> String {
+        let field = if field.starts_with(|c| matches!(c, '=' | '+' | '-' | '@')) {
+            Cow::Owned(format!("'{}", field))
+        } else {
+            Cow::Borrowed(field)
+        };
+        if field.contains(|c| matches!(c, ',' | '"' | '\r' | '\n')) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.into_owned()
+        }
+    }
+}
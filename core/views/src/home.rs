@@ -0,0 +1,211 @@
+use core_common::{
+    chrono::{Duration, NaiveDateTime, Utc},
+    database::{
+        AccessRequestStore, ApiTokenStore, Create, Database, DbList, FetchAll,
+        NotificationStore,
+    },
+    objects::{
+        ApiToken, Event, EventDetails, NotificationPreferences, PublicKey,
+        PublicKeyFilter,
+    },
+    sec::Auth,
+    serde::Serialize,
+    types::Id,
+    web::{AppError, Request, TemplateEngine},
+};
+use std::{borrow::Cow, convert::TryFrom};
+
+/// The start page showing the keys of the current user
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct HomeView<'a> {
+    /// Public keys owned by the current user
+    pub keys: DbList<PublicKey<'a>>,
+    /// Visible public keys which expire within the warning period of the
+    /// key policy. Admins see the keys of every user
+    pub expiring: DbList<PublicKey<'a>>,
+    /// Keys expiring before this date are considered to be expiring soon
+    pub warning_date: NaiveDateTime,
+    /// Number of access requests waiting for a decision of the current
+    /// user. Admins are shown every pending request
+    pub pending_requests: usize,
+    /// The mail notifications the current user receives
+    pub notifications: NotificationPreferences<'a>,
+    /// Whether mails can be sent, i.e. notifications are delivered at all
+    pub mail: bool,
+    /// The valid api tokens of the current user, newest first
+    pub api_tokens: Vec<ApiToken<'a>>,
+}
+
+impl<'a> HomeView<'a> {
+    /// Fetches the keys of the current user, all keys expiring soon, the
+    /// number of access requests waiting for a decision, the notification
+    /// preferences and the api tokens of the user
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes)]
+    pub async fn fetch<A, D, T, R>(
+        req: &R,
+        page: usize,
+        expiring_page: usize,
+    ) -> Result<HomeView<'a>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b, 'c> D: Database
+            + AccessRequestStore
+            + ApiTokenStore
+            + NotificationStore
+            + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+        let warning_date = req
+            .get_base_data()
+            .key_policy
+            .warning_date(Utc::now().naive_utc());
+
+        let filter = PublicKeyFilter {
+            entity_id: Some(Cow::Borrowed(auth.get_id())),
+            ..PublicKeyFilter::default()
+        };
+        let keys = db.fetch_all(&filter, auth, page)?;
+        let filter = PublicKeyFilter {
+            expires_before: Some(warning_date),
+            expired: true,
+            ..PublicKeyFilter::default()
+        };
+        let expiring = db.fetch_all(&filter, auth, expiring_page)?;
+        let approver_id = if auth.is_admin() {
+            None
+        } else {
+            Some(auth.get_id())
+        };
+        let pending_requests = db.fetch_pending(approver_id)?.len();
+        let notifications = db.fetch_notification_preferences(auth.get_id())?;
+        let api_tokens = db.fetch_api_tokens(auth.get_id())?;
+        Ok(Self {
+            keys,
+            expiring,
+            warning_date,
+            pending_requests,
+            notifications,
+            mail: req.get_base_data().mailer.is_some(),
+            api_tokens,
+        })
+    }
+
+    /// Stores which mail notifications the current user receives. The
+    /// preferences always belong to the current user
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    pub async fn save_notifications<A, D, T, R>(
+        req: &R,
+        preferences: NotificationPreferences<'_>,
+    ) -> Result<(), AppError<A, D, T, R>>
+    where
+        A: Auth,
+        D: Database + NotificationStore,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let preferences = NotificationPreferences {
+            entity_id: Cow::Borrowed(req.get_auth().get_id()),
+            ..preferences
+        };
+        req.get_database()
+            .save_notification_preferences(&preferences)?;
+        Ok(())
+    }
+
+    /// Creates a token for the current user which authenticates api
+    /// requests. The token is stored so it can be revoked, is valid for the
+    /// number of days configured and its creation is recorded as event.
+    /// Returns `Ok(None)` if the authentication does not support api tokens
+    ///
+    /// # Errors
+    /// Fails when database connection fails or the token cannot be signed
+    #[inline]
+    #[allow(single_use_lifetimes)]
+    pub async fn create_api_token<A, D, T, R>(
+        req: &R,
+    ) -> Result<Option<String>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + ApiTokenStore + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+        let days = i64::from(req.get_base_data().api_token_validity);
+        let now = Utc::now();
+        let valid_until = now + Duration::days(days);
+        let exp = u64::try_from(valid_until.timestamp()).unwrap_or_default();
+        let token_id = db.generate_id()?;
+        let token = match auth.get_api_token(req, &token_id, exp)? {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+        let api_token = ApiToken {
+            id: Cow::Borrowed(&token_id),
+            entity_id: Cow::Borrowed(auth.get_id()),
+            valid_until: valid_until.naive_utc(),
+            created: now.naive_utc(),
+        };
+        db.create_api_token(&api_token)?;
+
+        let details = EventDetails::ApiTokenCreate {
+            token_id: Cow::Borrowed(&token_id),
+            valid_until: valid_until.naive_utc(),
+        };
+        let event = Event::new(
+            Cow::Owned(db.generate_id()?),
+            Some(Cow::Borrowed(auth.get_id())),
+            details,
+            Some(Cow::Borrowed(auth.get_id())),
+        );
+        db.create(&event, auth)?;
+        Ok(Some(token))
+    }
+
+    /// Revokes an api token of the current user. Requests using the token
+    /// are rejected from then on. Returns `Ok(false)` if the user has no
+    /// such token
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(single_use_lifetimes)]
+    pub async fn revoke_api_token<A, D, T, R>(
+        req: &R,
+        token_id: &Id,
+    ) -> Result<bool, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + ApiTokenStore + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+        if !db.revoke_api_token(token_id, auth.get_id())? {
+            return Ok(false);
+        }
+        let details = EventDetails::ApiTokenRevoke {
+            token_id: Cow::Borrowed(token_id),
+        };
+        let event = Event::new(
+            Cow::Owned(db.generate_id()?),
+            Some(Cow::Borrowed(auth.get_id())),
+            details,
+            Some(Cow::Borrowed(auth.get_id())),
+        );
+        db.create(&event, auth)?;
+        Ok(true)
+    }
+}
@@ -52,6 +52,33 @@
     clippy::module_name_repetitions
 )]
 
+mod access_request;
+mod break_glass;
+mod certificate;
+mod event;
+mod home;
 mod public_key;
+mod recertification;
+mod server;
+mod webhook;
 
-pub use crate::public_key::{PublicKeyListView, PublicKeyView};
+pub use crate::{
+    access_request::{AccessRequestForm, AccessRequestListView, AccessRequestView},
+    break_glass::{BreakGlassForm, BreakGlassReportView, BreakGlassView},
+    certificate::{
+        CertificateAuthorityView, HostCertificateView, ServerCertificatesView,
+    },
+    event::{EventDetail, EventListView, EventView},
+    home::HomeView,
+    public_key::{
+        CompromisedKeyReportView, DeactivatedKey, DeactivatedKeyListView,
+        KeyPolicyReportView, KeyPolicyViolation, PublicKeyListView, PublicKeyView,
+        SharedKey,
+    },
+    recertification::{RecertificationListView, RecertificationView},
+    server::{
+        ContactForm, KnownHostsView, ServerForm, ServerListView, ServerView,
+        SshConfigView,
+    },
+    webhook::{WebhookForm, WebhookListView, WebhookView},
+};
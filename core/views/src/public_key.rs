@@ -1,15 +1,24 @@
 use core_common::{
+    chrono::Utc,
     database::{
         Create, Database, DatabaseError, DbList, FetchAll, FetchById, FetchByUid,
+        KeyCount, Restore,
     },
     log,
-    objects::{Entity, PublicKey, PublicKeyConversionError, PublicKeyFilter, User},
+    objects::{
+        AuthorizedKeysLine, Entity, Event, EventFilter, ImportStatus, KeyImportLine,
+        KeyImportSummary, KeyPolicy, PolicyViolation, PublicKey,
+        PublicKeyConversionError, PublicKeyFilter, User,
+    },
     sec::{Auth, CsrfToken},
     serde::Serialize,
     types::Id,
     web::{AppError, Notification, Request, TemplateEngine},
 };
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+};
 
 /// A List of public keys ready to be presented
 #[derive(Debug)]
@@ -41,7 +50,9 @@ impl<'a> PublicKeyListView<'a> {
             .map_err(AppError::DatabaseError)
     }
 
-    /// Creates a `PublicKey` using the information in the request body
+    /// Creates a `PublicKey` using the information in the request body.
+    /// The requested expiry date is shortened to the maximum key age of the
+    /// key policy.
     ///
     /// # Errors
     /// Fails when database connection fails
@@ -50,13 +61,14 @@ impl<'a> PublicKeyListView<'a> {
     pub async fn create<'e, A, D, T, R>(
         req: &mut R,
         data: Option<Cow<'_, str>>,
+        expires: Option<Cow<'_, str>>,
         uid: Option<&'e User<'_>>,
         csrf: &CsrfToken,
     ) -> Result<[Notification<'e>; 1], AppError<A, D, T, R>>
     where
         A: Auth,
-        for<'b, 'c, 'd> D: Database
-            + FetchAll<'b, A, PublicKey<'d>, PublicKeyFilter<'c>, D>
+        for<'d> D: Database
+            + KeyCount
             + FetchByUid<A, User<'d>, D>
             + Create<A, PublicKey<'d>, D>,
         T: TemplateEngine,
@@ -69,10 +81,27 @@ impl<'a> PublicKeyListView<'a> {
                 help: "../help/#pubkey_err",
             }]);
         }
+        let expires = match expires {
+            Some(expires) => match PublicKey::parse_expiry(&expires) {
+                Some(expires) => Some(expires),
+                None => {
+                    return Ok([Notification::Error {
+                        name: "Public Key",
+                        para: "Expiry date",
+                        help: "../help/#pubkey_err",
+                    }]);
+                }
+            },
+            None => None,
+        };
+        let expires = req
+            .get_base_data()
+            .key_policy
+            .expiry(expires, Utc::now().naive_utc());
         let db = req.get_database();
-        let auth = req.get_auth();
+        let blocklist = &req.get_base_data().key_blocklist;
         let body = if let (Some(data), Some(uid)) = (data.as_ref(), uid) {
-            match PublicKey::parse(data, &uid.entity_id, db) {
+            match PublicKey::parse(data, &uid.entity_id, db, blocklist) {
                 Err(PublicKeyConversionError::DatabaseError(err)) => {
                     return Err(AppError::DatabaseError(err));
                 }
@@ -80,11 +109,34 @@ impl<'a> PublicKeyListView<'a> {
                     log::warn!("Error while tring to convert publickey: {}", err);
                     (None, Some(()))
                 }
-                Ok(key) => (Some(key), Some(())),
+                Err(PublicKeyConversionError::InvalidSecurityKey) => {
+                    log::warn!("Error while tring to convert security key");
+                    (None, Some(()))
+                }
+                Err(PublicKeyConversionError::Compromised) => {
+                    log::warn!("Rejected compromised public key");
+                    return Ok([Notification::Error {
+                        name: "Public Key",
+                        para: "Key fingerprint",
+                        help: "../help/#pubkey_err",
+                    }]);
+                }
+                Ok(key) => (Some(PublicKey { expires, ..key }), Some(())),
             }
         } else {
             (None, None)
         };
+        if let (Some(ref key), Some(_)) = body {
+            if let Err(violation) = Self::check_policy(req, key).await? {
+                log::info!("Rejected public key: {}", violation);
+                return Ok([Notification::Error {
+                    name: "Public Key",
+                    para: violation.para(),
+                    help: "../help/#pubkey_err",
+                }]);
+            }
+        }
+        let auth = req.get_auth();
         match body {
             (Some(key), Some(_)) => match db.create(&key, auth) {
                 Err(err @ DatabaseError::Custom(_)) => {
@@ -113,6 +165,460 @@ impl<'a> PublicKeyListView<'a> {
             }]),
         }
     }
+
+    /// Imports every key of an `authorized_keys` file for the given owner.
+    /// Keys are deduplicated by their sha256 fingerprint. Every non empty
+    /// line is reported in the returned summary
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes)]
+    pub async fn import<A, D, T, R>(
+        req: &R,
+        data: &str,
+        owner: &Id,
+    ) -> Result<KeyImportSummary, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'d> D: Database + KeyCount + Create<A, PublicKey<'d>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let db = req.get_database();
+        let auth = req.get_auth();
+        let blocklist = &req.get_base_data().key_blocklist;
+        let expires = req
+            .get_base_data()
+            .key_policy
+            .expiry(None, Utc::now().naive_utc());
+        let mut summary = KeyImportSummary::default();
+        let mut seen = HashSet::new();
+        for (number, line) in data.lines().enumerate() {
+            let parsed = match AuthorizedKeysLine::parse(line) {
+                Some(parsed) => parsed,
+                None => continue,
+            };
+            let mut result = KeyImportLine {
+                line: number.saturating_add(1),
+                status: ImportStatus::Invalid,
+                fingerprint: None,
+                comment: None,
+                options: parsed.options,
+                unknown_options: parsed.unknown_options,
+                message: None,
+            };
+            let key = match PublicKey::parse(parsed.key, owner, db, blocklist) {
+                Ok(key) => PublicKey { expires, ..key },
+                Err(PublicKeyConversionError::DatabaseError(err)) => {
+                    return Err(AppError::DatabaseError(err));
+                }
+                Err(PublicKeyConversionError::OpenSshError(err)) => {
+                    result.message = Some(err.to_string());
+                    summary.push(result);
+                    continue;
+                }
+                Err(PublicKeyConversionError::InvalidSecurityKey) => {
+                    result.message = Some("Security key data is malformed".into());
+                    summary.push(result);
+                    continue;
+                }
+                Err(PublicKeyConversionError::Compromised) => {
+                    result.message = Some("Key is known to be compromised".into());
+                    summary.push(result);
+                    continue;
+                }
+            };
+            result.fingerprint = key
+                .fingerprint_sha256
+                .as_ref()
+                .map(|v| v.to_str().into_owned());
+            result.comment = key.comment.as_deref().map(String::from);
+            if let Some(ref fingerprint) = result.fingerprint {
+                if !seen.insert(fingerprint.clone()) {
+                    result.status = ImportStatus::Duplicate;
+                    result.message = Some("Key appears more than once".into());
+                    summary.push(result);
+                    continue;
+                }
+            }
+            if let Err(violation) = Self::check_policy(req, &key).await? {
+                result.message = Some(violation.to_string());
+                summary.push(result);
+                continue;
+            }
+            match db.create(&key, auth) {
+                Err(err @ DatabaseError::Custom(_)) => {
+                    return Err(AppError::DatabaseError(err));
+                }
+                Err(DatabaseError::NonUnique) => {
+                    result.status = ImportStatus::Duplicate;
+                    result.message = Some(
+                        "Public Key with this fingerprint already exists".into(),
+                    );
+                }
+                Ok(()) => result.status = ImportStatus::Imported,
+            }
+            summary.push(result);
+        }
+        Ok(summary)
+    }
+
+    /// Checks the key against the key policy. Also verifies that its owner
+    /// does not exceed the maximum number of keys, counted on the primary
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    pub async fn check_policy<A, D, T, R>(
+        req: &R,
+        key: &PublicKey<'_>,
+    ) -> Result<Result<(), PolicyViolation>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        D: Database + KeyCount,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let policy = &req.get_base_data().key_policy;
+        if let Err(violation) = policy.check(key) {
+            return Ok(Err(violation));
+        }
+        if policy.max_keys.is_some() {
+            let count = req.get_database().count_keys(&key.entity_id)?;
+            return Ok(policy.check_count(count));
+        }
+        Ok(Ok(()))
+    }
+}
+
+/// A public key which violates the key policy
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct KeyPolicyViolation<'a> {
+    /// The offending public key
+    pub public_key: PublicKey<'a>,
+    /// Why the key violates the policy
+    pub violation: PolicyViolation,
+    /// Human readable description of the violation
+    pub reason: String,
+}
+
+/// Report of all existing keys which violate the key policy
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct KeyPolicyReportView<'a> {
+    /// The policy the keys were checked against
+    pub policy: KeyPolicy,
+    /// Keys violating the policy
+    pub violations: Vec<KeyPolicyViolation<'a>>,
+}
+
+impl<'a> KeyPolicyReportView<'a> {
+    /// Checks all public keys visible to the given user against the
+    /// key policy. Owners exceeding the maximum number of keys are
+    /// reported with all of their keys.
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes)]
+    pub async fn fetch<A, D, T, R>(
+        req: &R,
+    ) -> Result<KeyPolicyReportView<'a>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b, 'c> D:
+            Database + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+        let policy = req.get_base_data().key_policy.clone();
+
+        let filter = PublicKeyFilter::default();
+        let mut keys = Vec::new();
+        let mut page = 1;
+        loop {
+            let list = db.fetch_all(&filter, auth, page)?;
+            keys.extend(list.data);
+            if page >= list.page_max {
+                break;
+            }
+            page = page.saturating_add(1);
+        }
+
+        let mut counts: HashMap<Id, usize> = HashMap::new();
+        for key in &keys {
+            let count = counts.entry(key.entity_id.as_ref().clone()).or_insert(0);
+            *count = count.saturating_add(1);
+        }
+        let violations = keys
+            .into_iter()
+            .filter_map(|public_key| {
+                let count = counts
+                    .get(public_key.entity_id.as_ref())
+                    .copied()
+                    .unwrap_or(0);
+                let violation = policy
+                    .check(&public_key)
+                    .and_then(|_| policy.check_count(count.saturating_sub(1)))
+                    .err()?;
+                Some(KeyPolicyViolation {
+                    reason: violation.to_string(),
+                    public_key,
+                    violation,
+                })
+            })
+            .collect();
+        Ok(Self { policy, violations })
+    }
+}
+
+/// Key material which is used by more than one entity
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct SharedKey<'a> {
+    /// The public keys sharing the key material, including deleted ones
+    pub keys: Vec<PublicKey<'a>>,
+}
+
+/// Report of public keys which are compromised or shared between entities
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct CompromisedKeyReportView<'a> {
+    /// Number of fingerprints in the blocklist
+    pub blocklist_size: usize,
+    /// Existing keys whose fingerprint is part of the blocklist
+    pub blocked: Vec<PublicKey<'a>>,
+    /// Key material used by more than one entity
+    pub shared: Vec<SharedKey<'a>>,
+    /// Attempts to upload a key which is owned by another entity
+    pub collisions: DbList<Event<'a>>,
+}
+
+impl<'a> CompromisedKeyReportView<'a> {
+    /// Checks all public keys, including deleted ones, against the
+    /// blocklist and searches for key material owned by multiple entities.
+    /// Only admins are able to see the keys of other entities
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes)]
+    pub async fn fetch<A, D, T, R>(
+        req: &R,
+        page: usize,
+    ) -> Result<CompromisedKeyReportView<'a>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b, 'c> D: Database
+            + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+            + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+        let blocklist = &req.get_base_data().key_blocklist;
+
+        let mut blocked = Vec::new();
+        let mut groups: BTreeMap<String, Vec<PublicKey<'a>>> = BTreeMap::new();
+        for deactivated in &[false, true] {
+            let filter = PublicKeyFilter {
+                expired: true,
+                deactivated: *deactivated,
+                ..PublicKeyFilter::default()
+            };
+            let mut current = 1;
+            loop {
+                let list = db.fetch_all(&filter, auth, current)?;
+                for key in list.data {
+                    if blocklist.contains(&key) {
+                        blocked.push(key.clone());
+                    }
+                    groups
+                        .entry(format!("{} {}", key.type_, key.keydata))
+                        .or_default()
+                        .push(key);
+                }
+                if current >= list.page_max {
+                    break;
+                }
+                current = current.saturating_add(1);
+            }
+        }
+        let shared = groups
+            .into_iter()
+            .filter_map(|(_, keys)| {
+                let owners = keys
+                    .iter()
+                    .map(|key| key.entity_id.as_ref())
+                    .collect::<HashSet<_>>()
+                    .len();
+                if owners > 1 {
+                    Some(SharedKey { keys })
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let filter = EventFilter {
+            action: Some(Cow::Borrowed("key_collision")),
+            ..EventFilter::default()
+        };
+        let collisions = db.fetch_all(&filter, auth, page)?;
+        Ok(Self {
+            blocklist_size: blocklist.len(),
+            blocked,
+            shared,
+            collisions,
+        })
+    }
+}
+
+/// A deleted public key together with the entity who deleted it
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct DeactivatedKey<'a> {
+    /// The deleted public key
+    pub public_key: PublicKey<'a>,
+    /// The entity which deleted the key. `None` if the removal was not
+    /// recorded
+    pub removed_by: Option<Entity<'a>>,
+}
+
+/// A list of deleted public keys which may be restored or purged
+#[derive(Debug)]
+pub struct DeactivatedKeyListView<'a>(pub DbList<DeactivatedKey<'a>>);
+
+impl<'a> DeactivatedKeyListView<'a> {
+    /// Fetches the deleted public keys and looks up who removed them
+    /// in the event log. Only admins are able to see deleted keys
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes)]
+    pub async fn fetch<A, D, T, R>(
+        req: &R,
+        page: usize,
+    ) -> Result<DeactivatedKeyListView<'a>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b, 'c> D: Database
+            + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+            + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>
+            + FetchById<'b, A, Entity<'a>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+
+        let filter = PublicKeyFilter {
+            expired: true,
+            deactivated: true,
+            ..PublicKeyFilter::default()
+        };
+        let keys = db.fetch_all(&filter, auth, page)?;
+        let mut data = Vec::with_capacity(keys.data.len());
+        for public_key in keys.data {
+            let filter = EventFilter {
+                action: Some(Cow::Borrowed("key_remove")),
+                details: Some(Cow::Owned(format!("%{}%", public_key.id))),
+                ..EventFilter::default()
+            };
+            let events = db.fetch_all(&filter, auth, 1)?;
+            let actor_id = events
+                .data
+                .into_iter()
+                .next()
+                .and_then(|event| event.actor_id);
+            let removed_by = match actor_id {
+                Some(actor_id) => db.fetch(&actor_id, auth)?,
+                None => None,
+            };
+            data.push(DeactivatedKey {
+                public_key,
+                removed_by,
+            });
+        }
+        Ok(Self(DbList {
+            data,
+            count: keys.count,
+            page: keys.page,
+            page_max: keys.page_max,
+        }))
+    }
+}
+
+impl DeactivatedKeyListView<'_> {
+    /// Restores the given deleted public keys. Every key has to pass the
+    /// checks a newly created key has to pass: it must not be compromised,
+    /// must conform to the key policy and must not be expired. Nothing is
+    /// restored if one of the keys fails a check
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes)]
+    pub async fn restore<A, D, T, R>(
+        req: &R,
+        ids: &[Id],
+    ) -> Result<Option<[Notification<'static>; 1]>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b, 'c, 'd> D: Database
+            + FetchAll<'b, A, PublicKey<'d>, PublicKeyFilter<'c>, D>
+            + KeyCount
+            + Restore<A, PublicKey<'d>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+        let blocklist = &req.get_base_data().key_blocklist;
+        let now = Utc::now().naive_utc();
+
+        let filter = PublicKeyFilter {
+            expired: true,
+            deactivated: true,
+            ..PublicKeyFilter::default()
+        };
+        let mut keys = Vec::with_capacity(ids.len());
+        let mut current = 1;
+        loop {
+            let list = db.fetch_all(&filter, auth, current)?;
+            keys.extend(
+                list.data
+                    .into_iter()
+                    .filter(|key| ids.contains(key.id.as_ref())),
+            );
+            if current >= list.page_max {
+                break;
+            }
+            current = current.saturating_add(1);
+        }
+        for key in &keys {
+            let para = if blocklist.contains(key) {
+                "Key fingerprint"
+            } else if key.is_expired(now) {
+                "Expiry date"
+            } else if let Err(violation) =
+                PublicKeyListView::check_policy(req, key).await?
+            {
+                violation.para()
+            } else {
+                continue;
+            };
+            log::info!("Rejected restoring public key {}", key.id);
+            return Ok(Some([Notification::Error {
+                name: "Public Key",
+                para,
+                help: "../../help/#pubkey_err",
+            }]));
+        }
+        db.restore(ids, auth)?;
+        Ok(None)
+    }
 }
 
 /// A public key ready to be presented
@@ -0,0 +1,367 @@
+use crate::EventListView;
+use core_common::{
+    chrono::Utc,
+    database::{
+        AccessRequestStore, Create, Database, DatabaseError, RecertificationStore,
+    },
+    objects::{
+        Event, EventDetails, Recertification, RecertificationEntry,
+        RecertificationItem, RecertificationProgress,
+    },
+    sec::{Auth, CsrfToken},
+    serde::Serialize,
+    types::{Id, RecertificationDecision, RecertificationKind},
+    web::{AppError, Notification, Request, TemplateEngine},
+};
+use std::{borrow::Cow, convert::TryFrom, fmt::Write};
+
+/// Every access recertification campaign
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct RecertificationListView<'a> {
+    /// The campaigns with their progress, newest first
+    pub campaigns: Vec<RecertificationProgress<'a>>,
+}
+
+impl RecertificationListView<'_> {
+    /// Fetches every campaign
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    pub async fn fetch<A, D, T, R>(req: &R) -> Result<Self, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        D: Database + RecertificationStore,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let campaigns = req.get_database().fetch_campaigns()?;
+        Ok(Self { campaigns })
+    }
+
+    /// Starts a new campaign in the name of the current user. Every access
+    /// grant and every membership in a group with access is copied into the
+    /// campaign. Only one campaign may be open at a time
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes, clippy::needless_lifetimes)]
+    pub async fn create<'e, A, D, T, R>(
+        req: &R,
+        name: Option<Cow<'_, str>>,
+        csrf: &CsrfToken,
+    ) -> Result<[Notification<'e>; 1], AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + RecertificationStore + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        if !csrf.valid {
+            return Ok([Notification::Error {
+                name: "Recertification",
+                para: "csrf",
+                help: "../help/#recertification",
+            }]);
+        }
+        let name = match name {
+            Some(name) if !name.trim().is_empty() => name,
+            _ => {
+                return Ok([Notification::Error {
+                    name: "Recertification",
+                    para: "Name",
+                    help: "../help/#recertification",
+                }])
+            }
+        };
+        let db = req.get_database();
+        let auth = req.get_auth();
+        let campaign = Recertification::new(
+            Cow::Owned(db.generate_id()?),
+            name,
+            Some(Cow::Borrowed(auth.get_id())),
+        );
+        let items = match db.create_campaign(&campaign) {
+            Err(DatabaseError::NonUnique) => {
+                return Ok([Notification::Unique {
+                    name: "Recertification",
+                    para: "status open",
+                    help: "../help/#recertification",
+                }])
+            }
+            Err(err) => return Err(AppError::DatabaseError(err)),
+            Ok(items) => items,
+        };
+
+        let details = EventDetails::RecertificationStart {
+            campaign_id: Cow::Borrowed(&campaign.id),
+            name: Cow::Borrowed(&*campaign.name),
+            items,
+        };
+        let event = Event::new(
+            Cow::Owned(db.generate_id()?),
+            Some(Cow::Borrowed(auth.get_id())),
+            details,
+            Some(Cow::Borrowed(auth.get_id())),
+        );
+        db.create(&event, auth)?;
+        Ok([Notification::Info {
+            name: "Recertification",
+            url: ".",
+            id: Cow::Owned(campaign.id.into_owned()),
+        }])
+    }
+}
+
+/// A single campaign and the items the current user may decide on
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct RecertificationView<'a> {
+    /// The campaign and its overall progress
+    pub progress: RecertificationProgress<'a>,
+    /// The items of the servers and groups the current user administers.
+    /// Admins see every item
+    pub entries: Vec<RecertificationEntry<'a>>,
+    /// Whether the current user may still decide on the items
+    pub can_decide: bool,
+    /// Whether the current user may close the campaign
+    pub can_close: bool,
+}
+
+impl RecertificationView<'_> {
+    /// Fetches the campaign with the given id
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    pub async fn fetch<A, D, T, R>(
+        req: &R,
+        key: &str,
+    ) -> Result<Option<Self>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        D: Database + RecertificationStore,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+        let id = match Id::from_string(key) {
+            Ok(id) => id,
+            Err(_) => return Ok(None),
+        };
+        let progress = match db.fetch_campaign(&id)? {
+            Some(progress) => progress,
+            None => return Ok(None),
+        };
+        let reviewer_id = if auth.is_admin() {
+            None
+        } else {
+            Some(auth.get_id())
+        };
+        let entries = db.fetch_items(&id, reviewer_id)?;
+        let open = progress.campaign.is_open();
+        Ok(Some(Self {
+            progress,
+            entries,
+            can_decide: open,
+            can_close: open && auth.is_admin(),
+        }))
+    }
+
+    /// Stores the decision of the current user on the item with the given
+    /// id. Only admins of the server of the item may decide on access,
+    /// only admins of the group on a membership. Returns
+    /// `false` if the item does not belong to the campaign, the user may
+    /// not decide on it or the campaign was closed
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    pub async fn decide<A, D, T, R>(
+        &mut self,
+        req: &R,
+        item_id: &str,
+        decision: RecertificationDecision,
+    ) -> Result<bool, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        D: Database + AccessRequestStore + RecertificationStore,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        if !self.can_decide {
+            return Ok(false);
+        }
+        let auth = req.get_auth();
+        let db = req.get_database();
+        let id = match Id::from_string(item_id) {
+            Ok(id) => id,
+            Err(_) => return Ok(false),
+        };
+        let entry = match db.fetch_item(&id)? {
+            Some(entry) => entry,
+            None => return Ok(false),
+        };
+        let mut item = entry.item;
+        if item.campaign_id != self.progress.campaign.id {
+            return Ok(false);
+        }
+        let allowed = auth.is_admin()
+            || match item.kind {
+                RecertificationKind::Access => {
+                    db.is_approver(&item.server_id, auth.get_id())?
+                }
+                RecertificationKind::Membership => {
+                    db.is_group_admin(&item.dest_id, auth.get_id())?
+                }
+            };
+        if !allowed {
+            return Ok(false);
+        }
+        let decided_by = Cow::Borrowed(auth.get_id());
+        if !item.decide(decision, decided_by, Utc::now().naive_utc()) {
+            return Ok(true);
+        }
+        Ok(db.decide_item(&item)?)
+    }
+
+    /// Closes the campaign in the name of the current user and removes
+    /// every access marked for revocation. Every removal is recorded as
+    /// event. Returns `false` if the campaign was already closed
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(single_use_lifetimes)]
+    pub async fn close<A, D, T, R>(
+        &mut self,
+        req: &R,
+    ) -> Result<bool, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + RecertificationStore + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        if !self.can_close {
+            return Ok(false);
+        }
+        let auth = req.get_auth();
+        let db = req.get_database();
+        let campaign = &mut self.progress.campaign;
+        let closed_by = Cow::Owned(auth.get_id().clone());
+        if !campaign.close(closed_by, Utc::now().naive_utc()) {
+            return Ok(false);
+        }
+        let revoked = match db.close_campaign(campaign)? {
+            Some(revoked) => revoked,
+            None => return Ok(false),
+        };
+        self.can_decide = false;
+        self.can_close = false;
+
+        for item in &revoked {
+            record_revocation(req, item)?;
+        }
+        let details = EventDetails::RecertificationClose {
+            campaign_id: Cow::Borrowed(&campaign.id),
+            revoked: u64::try_from(revoked.len()).unwrap_or(u64::MAX),
+        };
+        let event = Event::new(
+            Cow::Owned(db.generate_id()?),
+            Some(Cow::Borrowed(auth.get_id())),
+            details,
+            Some(Cow::Borrowed(auth.get_id())),
+        );
+        db.create(&event, auth)?;
+        Ok(true)
+    }
+
+    /// Converts the items of the campaign and their decisions to csv
+    #[must_use]
+    #[inline]
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "campaign,hostname,kind,source_id,source,dest_id,dest,decision,decided_by,decision_date\r\n",
+        );
+        let campaign = &self.progress.campaign.name;
+        for entry in &self.entries {
+            let item = &entry.item;
+            let kind = format!("{:?}", item.kind);
+            let source_id = item.source_id.to_string();
+            let dest_id = item.dest_id.to_string();
+            let decision = item
+                .decision
+                .map(|v| format!("{:?}", v))
+                .unwrap_or_default();
+            let decided_by = item
+                .decided_by
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            let decision_date = item
+                .decision_date
+                .map(|d| d.to_string())
+                .unwrap_or_default();
+            let fields: [&str; 10] = [
+                campaign,
+                &entry.hostname,
+                &kind,
+                &source_id,
+                entry.source_name.as_deref().unwrap_or_default(),
+                &dest_id,
+                entry.dest_name.as_deref().unwrap_or_default(),
+                &decision,
+                &decided_by,
+                &decision_date,
+            ];
+            let line: Vec<String> = fields
+                .iter()
+                .map(|f| EventListView::escape_csv(f))
+                .collect();
+            let _ = write!(csv, "{}\r\n", line.join(","));
+        }
+        csv
+    }
+}
+
+/// Records the removal of an access grant or a group membership at the
+/// end of a campaign
+#[allow(single_use_lifetimes)]
+fn record_revocation<A, D, T, R>(
+    req: &R,
+    item: &RecertificationItem<'_>,
+) -> Result<(), AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'b> D: Database + RecertificationStore + Create<A, Event<'b>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let db = req.get_database();
+    let auth = req.get_auth();
+    let (details, object_id) = match item.kind {
+        RecertificationKind::Access => (
+            EventDetails::AccessRevoke {
+                source_id: Cow::Borrowed(&item.source_id),
+                dest_id: Cow::Borrowed(&item.dest_id),
+            },
+            &item.source_id,
+        ),
+        RecertificationKind::Membership => (
+            EventDetails::MemberRemove {
+                member_id: Cow::Borrowed(&item.source_id),
+            },
+            &item.dest_id,
+        ),
+    };
+    let event = Event::new(
+        Cow::Owned(db.generate_id()?),
+        Some(Cow::Borrowed(auth.get_id())),
+        details,
+        Some(Cow::Borrowed(object_id)),
+    );
+    db.create(&event, auth)?;
+    Ok(())
+}
@@ -0,0 +1,947 @@
+use crate::CertificateAuthorityView;
+use core_common::{
+    chrono::Utc,
+    database::{
+        AccessRequestStore, CertificateStore, ContactStore, Create, Database,
+        DatabaseError, DbList, Delete, FetchAll, FetchAllFor, FetchById, LoginStore,
+        Save, ServerAdminStore, ServerSettingsStore, ServerTokenStore,
+    },
+    log,
+    mail::Mail,
+    objects::{
+        Entity, Event, EventDetails, Server, ServerAccount, ServerFilter,
+        ServerLogin, ServerNote, ServerNoteFilter,
+    },
+    sec::{generate_token, Auth, CsrfToken, SecretError},
+    serde::Serialize,
+    types::{
+        AuthorizationType, CertificateType, ContactRecipients, Id, KeyManagement,
+        SyncStatusType,
+    },
+    web::{AppError, Notification, Request, TemplateEngine},
+};
+use std::{borrow::Cow, convert::TryFrom};
+
+/// The host keys of all servers the user can access
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct KnownHostsView<'a> {
+    /// The host certificate authority. `None` if no host certificate was
+    /// issued yet
+    pub authority: Option<CertificateAuthorityView<'a>>,
+    /// The servers the user can access
+    pub servers: Vec<Server<'a>>,
+}
+
+impl<'a> KnownHostsView<'a> {
+    /// Fetches every server the current user has access to. Admins only
+    /// get the servers they can access as well
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes)]
+    pub async fn fetch<A, D, T, R>(req: &R) -> Result<Self, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database
+            + CertificateStore
+            + FetchAllFor<A, Server<'a>, ServerFilter<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+        let authority = db
+            .fetch_authority(CertificateType::Host)?
+            .map(CertificateAuthorityView);
+
+        let filter = ServerFilter::default();
+        let mut servers = Vec::new();
+        let mut page = 1;
+        loop {
+            let mut list = db.fetch_all_for(&filter, auth, page)?;
+            servers.append(&mut list.data);
+            if page >= list.page_max {
+                break;
+            }
+            page = page.saturating_add(1);
+        }
+        Ok(Self { authority, servers })
+    }
+
+    /// Renders the `known_hosts` file. The host certificate authority is
+    /// trusted for the listed servers. Host keys of servers without a
+    /// stored host key are skipped
+    ///
+    /// # Errors
+    /// Fails when no random data is available to hash the hostnames
+    #[inline]
+    pub fn to_file(&self, hashed: bool) -> Result<String, SecretError> {
+        let mut file = match self.authority {
+            Some(ref authority) => {
+                authority.to_known_hosts(&self.servers, hashed)?
+            }
+            None => String::new(),
+        };
+        for server in &self.servers {
+            if let Some(entry) = server.to_known_hosts(hashed)? {
+                file.push_str(&entry);
+            }
+        }
+        Ok(file)
+    }
+}
+
+/// The server accounts the user can log into
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct SshConfigView<'a>(pub Vec<ServerLogin<'a>>);
+
+impl SshConfigView<'_> {
+    /// Fetches the server accounts the current user or one of its groups
+    /// has access to
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    pub async fn fetch<A, D, T, R>(req: &R) -> Result<Self, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        D: Database + LoginStore,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+        Ok(Self(db.fetch_logins(auth.get_id())?))
+    }
+
+    /// Renders the `ssh_config` file. Accounts which cannot be written
+    /// safely are skipped
+    #[must_use]
+    #[inline]
+    pub fn to_file(&self) -> String {
+        let mut file = String::new();
+        for login in &self.0 {
+            match login.to_ssh_config() {
+                Some(block) => {
+                    if !file.is_empty() {
+                        file.push('\n');
+                    }
+                    file.push_str(&block);
+                }
+                None => log::warn!(
+                    "Skipping account {} on server {} in ssh_config",
+                    login.account,
+                    login.server.id
+                ),
+            }
+        }
+        file
+    }
+}
+
+/// Information required to create a server
+#[derive(Debug, Clone, Default)]
+pub struct ServerForm<'a> {
+    /// The hostname of the server
+    pub hostname: Option<Cow<'a, str>>,
+    /// The ssh port
+    pub port: Option<Cow<'a, str>>,
+    /// The Key Management configuration
+    pub key_management: Option<Cow<'a, str>>,
+    /// Uid of a user or name of a group which administers the server
+    pub admin: Option<Cow<'a, str>>,
+    /// Whether only keys backed by a hardware security key are accepted
+    pub require_security_key: bool,
+}
+
+/// A mail to the users of a server
+#[derive(Debug, Clone, Default)]
+pub struct ContactForm<'a> {
+    /// Which users receive the mail
+    pub recipients: Option<Cow<'a, str>>,
+    /// Whether the recipients are hidden from each other
+    pub hide_recipients: bool,
+    /// The subject of the mail
+    pub subject: Option<Cow<'a, str>>,
+    /// The text of the mail
+    pub body: Option<Cow<'a, str>>,
+}
+
+/// A List of servers ready to be presented
+#[derive(Debug)]
+pub struct ServerListView<'a>(pub DbList<Server<'a>>);
+
+impl<'a> ServerListView<'a> {
+    /// Fetches all servers. Users only get the servers they administer or
+    /// can access
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes)]
+    pub async fn fetch<A, D, T, R>(
+        req: &R,
+        filter: &ServerFilter<'_>,
+        page: usize,
+    ) -> Result<ServerListView<'a>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b, 'c> D: Database + FetchAll<'b, A, Server<'a>, ServerFilter<'c>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+
+        db.fetch_all(filter, auth, page)
+            .map(Self)
+            .map_err(AppError::DatabaseError)
+    }
+
+    /// Creates a `Server` using the information in the request body. The
+    /// optional administrator is assigned right away
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes, clippy::needless_lifetimes)]
+    pub async fn create<'e, A, D, T, R>(
+        req: &mut R,
+        form: ServerForm<'_>,
+        csrf: &CsrfToken,
+    ) -> Result<[Notification<'e>; 1], AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b, 'd> D: Database
+            + ServerAdminStore
+            + Create<A, Server<'d>, D>
+            + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        if !csrf.valid {
+            return Ok([Notification::Error {
+                name: "Server",
+                para: "csrf",
+                help: "../help/#server_admin",
+            }]);
+        }
+        let db = req.get_database();
+        let auth = req.get_auth();
+        let hostname = match form.hostname {
+            Some(hostname) if !hostname.trim().is_empty() => hostname,
+            _ => {
+                return Ok([Notification::Error {
+                    name: "Server",
+                    para: "Hostname",
+                    help: "../help/#server_admin",
+                }])
+            }
+        };
+        let port = match form.port.as_deref().map(str::parse::<u16>) {
+            None => 22,
+            Some(Ok(port)) if port > 0 => i32::from(port),
+            Some(_) => {
+                return Ok([Notification::Error {
+                    name: "Server",
+                    para: "Port",
+                    help: "../help/#server_admin",
+                }])
+            }
+        };
+        let key_management = match form.key_management.as_deref() {
+            None => KeyManagement::Keys,
+            Some(v) => match KeyManagement::try_from(v) {
+                Ok(v) => v,
+                Err(_) => {
+                    return Ok([Notification::Error {
+                        name: "Server",
+                        para: "Key Management",
+                        help: "../help/#server_admin",
+                    }])
+                }
+            },
+        };
+        let admin = match form.admin {
+            Some(name) => match db.fetch_entity_by_name(&name)? {
+                Some(admin) => Some(admin),
+                None => {
+                    return Ok([Notification::Error {
+                        name: "Server",
+                        para: "Administrator",
+                        help: "../help/#server_admin",
+                    }])
+                }
+            },
+            None => None,
+        };
+
+        let server = Server {
+            id: Cow::Owned(db.generate_id()?),
+            hostname,
+            ip_address: None,
+            name: None,
+            key_management,
+            authorization: AuthorizationType::Manual,
+            sync_status: SyncStatusType::NotSyncedYet,
+            rsa_key_fingerprint: None,
+            port,
+            require_security_key: form.require_security_key,
+            host_key: None,
+            proxy_jump: None,
+        };
+        match db.create(&server, auth) {
+            Err(DatabaseError::NonUnique) => {
+                return Ok([Notification::Unique {
+                    name: "Server",
+                    para: "hostname",
+                    help: "../help/#server_admin",
+                }])
+            }
+            Err(err) => return Err(AppError::DatabaseError(err)),
+            Ok(()) => {}
+        }
+        if let Some(admin) = admin {
+            db.add_server_admin(&server.id, &admin.entity_id)?;
+            let details = EventDetails::ServerAdminAdd {
+                entity_id: admin.entity_id,
+            };
+            record_event(req, &server.id, details)?;
+        }
+        Ok([Notification::Info {
+            name: "Server",
+            url: ".",
+            id: Cow::Owned(server.id.into_owned()),
+        }])
+    }
+}
+
+/// A server with its administrators and accounts ready to be presented
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct ServerView<'a> {
+    /// The server to show to the user
+    pub server: Server<'a>,
+    /// The users and groups which administer the server
+    pub admins: Vec<Entity<'a>>,
+    /// The accounts of the server and who can access them
+    pub accounts: Vec<ServerAccount<'a>>,
+    /// Whether the current user may manage administrators, accounts and
+    /// access. True for admins and administrators of the server
+    pub can_manage: bool,
+    /// The notes admins left on the server, newest first. Empty for non
+    /// admins
+    pub notes: Vec<ServerNote<'a>>,
+}
+
+impl ServerView<'_> {
+    /// Fetches the server with the given id
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes)]
+    pub async fn fetch<'a, A, D, T, R>(
+        req: &R,
+        key: &str,
+    ) -> Result<Option<ServerView<'a>>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b, 'c> D: Database
+            + AccessRequestStore
+            + ServerAdminStore
+            + FetchById<'b, A, Server<'a>, D>
+            + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+        let id = match Id::from_string(key) {
+            Ok(id) => id,
+            Err(_) => return Ok(None),
+        };
+        let server = match db.fetch(&id, auth)? {
+            Some(server) => server,
+            None => return Ok(None),
+        };
+        let can_manage = auth.is_admin() || db.is_approver(&id, auth.get_id())?;
+
+        let filter = ServerNoteFilter {
+            server_id: Some(Cow::Borrowed(&id)),
+        };
+        let mut notes = Vec::new();
+        let mut page = 1;
+        loop {
+            let mut list = db.fetch_all(&filter, auth, page)?;
+            notes.append(&mut list.data);
+            if page >= list.page_max {
+                break;
+            }
+            page = page.saturating_add(1);
+        }
+        Ok(Some(ServerView {
+            server,
+            admins: db.fetch_server_admins(&id)?,
+            accounts: db.fetch_server_accounts(&id)?,
+            can_manage,
+            notes,
+        }))
+    }
+
+    /// Adds a note to the server in the name of the current user. Only
+    /// admins may add notes. Returns `false` if the note is empty
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(single_use_lifetimes)]
+    pub async fn add_note<A, D, T, R>(
+        &mut self,
+        req: &R,
+        note: &str,
+    ) -> Result<bool, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + Create<A, ServerNote<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        if note.trim().is_empty() {
+            return Ok(false);
+        }
+        let db = req.get_database();
+        let auth = req.get_auth();
+        let note = ServerNote {
+            id: Cow::Owned(db.generate_id()?),
+            server_id: Cow::Borrowed(&self.server.id),
+            entity_id: Some(Cow::Borrowed(auth.get_id())),
+            author: None,
+            date: Utc::now().naive_utc(),
+            note: Cow::Borrowed(note),
+        };
+        db.create(&note, auth)?;
+        Ok(true)
+    }
+
+    /// Replaces the text of the note with the given id. Returns `false` if
+    /// the note does not belong to the server
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(single_use_lifetimes)]
+    pub async fn edit_note<A, D, T, R>(
+        &mut self,
+        req: &R,
+        note_id: &str,
+        note: &str,
+    ) -> Result<bool, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + Save<A, ServerNote<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        if note.trim().is_empty() {
+            return Ok(false);
+        }
+        let id = match Id::from_string(note_id) {
+            Ok(id) => id,
+            Err(_) => return Ok(false),
+        };
+        let existing = match self.notes.iter_mut().find(|v| *v.id == id) {
+            Some(existing) => existing,
+            None => return Ok(false),
+        };
+        existing.note = Cow::Owned(note.to_string());
+        let db = req.get_database();
+        db.save(existing, req.get_auth())?;
+        Ok(true)
+    }
+
+    /// Deletes the note with the given id. Returns `false` if the note does
+    /// not belong to the server
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(single_use_lifetimes)]
+    pub async fn delete_note<A, D, T, R>(
+        &mut self,
+        req: &R,
+        note_id: &str,
+    ) -> Result<bool, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + Delete<A, ServerNote<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let id = match Id::from_string(note_id) {
+            Ok(id) => id,
+            Err(_) => return Ok(false),
+        };
+        if !self.notes.iter().any(|v| *v.id == id) {
+            return Ok(false);
+        }
+        let db = req.get_database();
+        db.delete(&[id.clone()], req.get_auth())?;
+        self.notes.retain(|v| *v.id != id);
+        Ok(true)
+    }
+
+    /// Sends a mail to the selected users of the server. Replies go to the
+    /// current user. Returns an error notification if no SMTP relay is
+    /// configured or the form is incomplete
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(single_use_lifetimes)]
+    pub async fn contact<A, D, T, R>(
+        &self,
+        req: &R,
+        form: ContactForm<'_>,
+    ) -> Result<Option<[Notification<'static>; 1]>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + ContactStore + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let error = |para| {
+            Ok(Some([Notification::Error {
+                name: "Mail",
+                para,
+                help: "../../../help/#server_contact",
+            }]))
+        };
+        let mailer = match &req.get_base_data().mailer {
+            Some(mailer) => mailer,
+            None => return error("smtp"),
+        };
+        let recipients =
+            match form.recipients.as_deref().map(ContactRecipients::try_from) {
+                Some(Ok(recipients)) => recipients,
+                _ => return error("recipients"),
+            };
+        let subject = match form.subject.as_deref().map(str::trim) {
+            Some(subject) if !subject.is_empty() => subject,
+            _ => return error("subject"),
+        };
+        let body = match form.body.as_deref() {
+            Some(body) if !body.trim().is_empty() => body,
+            _ => return error("body"),
+        };
+
+        let db = req.get_database();
+        let contacts = db.fetch_server_contacts(&self.server.id, recipients)?;
+        if contacts.is_empty() {
+            return error("recipients");
+        }
+        let sender = db.fetch_contact(req.get_auth().get_id())?;
+        let mail = Mail {
+            recipients: &contacts,
+            hide_recipients: form.hide_recipients,
+            reply_to: sender.as_ref(),
+            subject,
+            body,
+        };
+        if let Err(err) = mailer.send(&mail).await {
+            log::error!("Unable to contact users of {}: {}", self.server.id, err);
+            return error("smtp");
+        }
+        let details = EventDetails::ServerContact {
+            recipients,
+            subject: Cow::Borrowed(subject),
+            sent: u64::try_from(contacts.len()).unwrap_or(u64::MAX),
+        };
+        record_event(req, &self.server.id, details)?;
+        Ok(None)
+    }
+
+    /// Makes the user with the given uid or the group with the given name
+    /// administrator of the server. Only application admins may add
+    /// administrators
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes, clippy::needless_lifetimes)]
+    pub async fn add_admin<'e, A, D, T, R>(
+        &mut self,
+        req: &R,
+        name: &str,
+    ) -> Result<[Notification<'e>; 1], AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + ServerAdminStore + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        if !req.get_auth().is_admin() {
+            return Ok([Notification::Error {
+                name: "Administrator",
+                para: "permission",
+                help: "../../../help/#server_admin",
+            }]);
+        }
+        let db = req.get_database();
+        let admin = match db.fetch_entity_by_name(name)? {
+            Some(admin) => admin,
+            None => {
+                return Ok([Notification::Error {
+                    name: "Administrator",
+                    para: "name",
+                    help: "../../../help/#server_admin",
+                }])
+            }
+        };
+        match db.add_server_admin(&self.server.id, &admin.entity_id) {
+            Err(DatabaseError::NonUnique) => {
+                return Ok([Notification::Unique {
+                    name: "Administrator",
+                    para: "name",
+                    help: "../../../help/#server_admin",
+                }])
+            }
+            Err(err) => return Err(AppError::DatabaseError(err)),
+            Ok(()) => {}
+        }
+        let details = EventDetails::ServerAdminAdd {
+            entity_id: Cow::Borrowed(&admin.entity_id),
+        };
+        record_event(req, &self.server.id, details)?;
+        self.admins.push(admin);
+        Ok([Notification::Info {
+            name: "Administrator",
+            url: "../..",
+            id: Cow::Owned(self.server.id.clone().into_owned()),
+        }])
+    }
+
+    /// Changes whether the server only accepts keys backed by a hardware
+    /// security key. Returns `false` if the setting did not change
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(single_use_lifetimes)]
+    pub async fn set_require_security_key<A, D, T, R>(
+        &mut self,
+        req: &R,
+        required: bool,
+    ) -> Result<bool, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + ServerSettingsStore + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        if self.server.require_security_key == required {
+            return Ok(false);
+        }
+        let db = req.get_database();
+        if !db.set_require_security_key(&self.server.id, required)? {
+            return Ok(false);
+        }
+        let details = EventDetails::ServerSecurityKey { required };
+        record_event(req, &self.server.id, details)?;
+        self.server.require_security_key = required;
+        Ok(true)
+    }
+
+    /// Creates the token the server authenticates with at the api and
+    /// returns it. A previous token of the server stops working. Only the
+    /// digest of the token is stored
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(single_use_lifetimes)]
+    pub async fn create_token<A, D, T, R>(
+        &self,
+        req: &R,
+    ) -> Result<Result<String, SecretError>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + ServerTokenStore + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let (token, digest) = match generate_token() {
+            Ok(token) => token,
+            Err(err) => return Ok(Err(err)),
+        };
+        let db = req.get_database();
+        let _ = db.save_server_token(&self.server.id, Some(&digest))?;
+        let details = EventDetails::ServerToken { revoked: false };
+        record_event(req, &self.server.id, details)?;
+        Ok(Ok(token))
+    }
+
+    /// Revokes the token the server authenticates with at the api. Returns
+    /// `false` if the server had no token
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(single_use_lifetimes)]
+    pub async fn revoke_token<A, D, T, R>(
+        &self,
+        req: &R,
+    ) -> Result<bool, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + ServerTokenStore + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let db = req.get_database();
+        if !db.save_server_token(&self.server.id, None)? {
+            return Ok(false);
+        }
+        let details = EventDetails::ServerToken { revoked: true };
+        record_event(req, &self.server.id, details)?;
+        Ok(true)
+    }
+
+    /// Removes the user or group with the given id from the administrators
+    /// of the server. Returns `false` if it was no administrator or the
+    /// current user is no application admin
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(single_use_lifetimes)]
+    pub async fn remove_admin<A, D, T, R>(
+        &mut self,
+        req: &R,
+        entity_id: &str,
+    ) -> Result<bool, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + ServerAdminStore + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        if !req.get_auth().is_admin() {
+            return Ok(false);
+        }
+        let db = req.get_database();
+        let entity_id = match Id::from_string(entity_id) {
+            Ok(id) => id,
+            Err(_) => return Ok(false),
+        };
+        if !db.remove_server_admin(&self.server.id, &entity_id)? {
+            return Ok(false);
+        }
+        let details = EventDetails::ServerAdminRemove {
+            entity_id: Cow::Borrowed(&entity_id),
+        };
+        record_event(req, &self.server.id, details)?;
+        self.admins.retain(|v| *v.entity_id != entity_id);
+        Ok(true)
+    }
+
+    /// Creates an account with the given name on the server
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes, clippy::needless_lifetimes)]
+    pub async fn add_account<'e, A, D, T, R>(
+        &mut self,
+        req: &R,
+        name: &str,
+    ) -> Result<[Notification<'e>; 1], AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + ServerAdminStore + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(is_account_char) {
+            return Ok([Notification::Error {
+                name: "Account",
+                para: "name",
+                help: "../../../help/#server_admin",
+            }]);
+        }
+        let db = req.get_database();
+        let account_id = db.generate_id()?;
+        match db.create_server_account(&self.server.id, &account_id, name) {
+            Err(DatabaseError::NonUnique) => {
+                return Ok([Notification::Unique {
+                    name: "Account",
+                    para: "name",
+                    help: "../../../help/#server_admin",
+                }])
+            }
+            Err(err) => return Err(AppError::DatabaseError(err)),
+            Ok(()) => {}
+        }
+        let details = EventDetails::AccountCreate {
+            account_id: Cow::Borrowed(&account_id),
+            name: Cow::Borrowed(name),
+        };
+        record_event(req, &self.server.id, details)?;
+        self.accounts = db.fetch_server_accounts(&self.server.id)?;
+        Ok([Notification::Info {
+            name: "Account",
+            url: "../..",
+            id: Cow::Owned(self.server.id.clone().into_owned()),
+        }])
+    }
+
+    /// Grants the user with the given uid or the group with the given name
+    /// access to the account of the server
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes, clippy::needless_lifetimes)]
+    pub async fn grant<'e, A, D, T, R>(
+        &mut self,
+        req: &R,
+        account_id: &str,
+        name: &str,
+    ) -> Result<[Notification<'e>; 1], AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + ServerAdminStore + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let account_id = match self.account_id(account_id) {
+            Some(id) => id,
+            None => {
+                return Ok([Notification::Error {
+                    name: "Access",
+                    para: "account",
+                    help: "../../../help/#server_admin",
+                }])
+            }
+        };
+        let db = req.get_database();
+        let auth = req.get_auth();
+        let source = match db.fetch_entity_by_name(name)? {
+            Some(source) => source,
+            None => {
+                return Ok([Notification::Error {
+                    name: "Access",
+                    para: "name",
+                    help: "../../../help/#server_admin",
+                }])
+            }
+        };
+        let granted = db.grant_server_access(
+            &self.server.id,
+            &account_id,
+            &source.entity_id,
+            auth.get_id(),
+        );
+        match granted {
+            Err(DatabaseError::NonUnique) => {
+                return Ok([Notification::Unique {
+                    name: "Access",
+                    para: "name",
+                    help: "../../../help/#server_admin",
+                }])
+            }
+            Err(err) => return Err(AppError::DatabaseError(err)),
+            Ok(()) => {}
+        }
+        let details = EventDetails::AccessGrant {
+            source_id: Cow::Borrowed(&source.entity_id),
+            dest_id: Cow::Borrowed(&account_id),
+        };
+        record_event(req, &source.entity_id, details)?;
+        self.accounts = db.fetch_server_accounts(&self.server.id)?;
+        Ok([Notification::Info {
+            name: "Access",
+            url: "../..",
+            id: Cow::Owned(self.server.id.clone().into_owned()),
+        }])
+    }
+
+    /// Revokes access of the entity with the given id to the account of the
+    /// server. Returns `false` if the entity had no access
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(single_use_lifetimes)]
+    pub async fn revoke<A, D, T, R>(
+        &mut self,
+        req: &R,
+        account_id: &str,
+        source_id: &str,
+    ) -> Result<bool, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b> D: Database + ServerAdminStore + Create<A, Event<'b>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let (account_id, source_id) =
+            match (self.account_id(account_id), Id::from_string(source_id)) {
+                (Some(account_id), Ok(source_id)) => (account_id, source_id),
+                _ => return Ok(false),
+            };
+        let db = req.get_database();
+        if !db.revoke_server_access(&self.server.id, &account_id, &source_id)? {
+            return Ok(false);
+        }
+        let details = EventDetails::AccessRevoke {
+            source_id: Cow::Borrowed(&source_id),
+            dest_id: Cow::Borrowed(&account_id),
+        };
+        record_event(req, &source_id, details)?;
+        self.accounts = db.fetch_server_accounts(&self.server.id)?;
+        Ok(true)
+    }
+
+    /// Parses the account id and checks that the account belongs to the
+    /// server
+    fn account_id(&self, account_id: &str) -> Option<Id> {
+        let id = Id::from_string(account_id).ok()?;
+        if self.accounts.iter().any(|v| *v.account_id == id) {
+            Some(id)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether the character may be part of an account name
+fn is_account_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+/// Records a change of a server in the name of the current user
+#[allow(single_use_lifetimes)]
+fn record_event<A, D, T, R>(
+    req: &R,
+    object_id: &Id,
+    details: EventDetails<'_>,
+) -> Result<(), AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'b> D: Database + Create<A, Event<'b>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let db = req.get_database();
+    let auth = req.get_auth();
+    let event = Event::new(
+        Cow::Owned(db.generate_id()?),
+        Some(Cow::Borrowed(auth.get_id())),
+        details,
+        Some(Cow::Borrowed(object_id)),
+    );
+    db.create(&event, auth)?;
+    Ok(())
+}
@@ -0,0 +1,208 @@
+use core_common::{
+    database::{Create, Database, DatabaseError, DbList, FetchAll, FetchById},
+    log,
+    objects::{Webhook, WebhookDelivery, WebhookDeliveryFilter, WebhookFilter},
+    sec::{Auth, CsrfToken},
+    serde::Serialize,
+    types::Id,
+    url::Url,
+    web::{AppError, Notification, Request, TemplateEngine},
+};
+use std::borrow::Cow;
+
+/// Information required to create a webhook
+#[derive(Debug, Clone, Default)]
+pub struct WebhookForm<'a> {
+    /// The display name
+    pub name: Option<Cow<'a, str>>,
+    /// The url the events are posted to
+    pub url: Option<Cow<'a, str>>,
+    /// The secret used to sign the payload
+    pub secret: Option<Cow<'a, str>>,
+    /// Comma separated list of event actions to send
+    pub actions: Option<Cow<'a, str>>,
+}
+
+/// A List of webhooks ready to be presented
+#[derive(Debug)]
+pub struct WebhookListView<'a>(pub DbList<Webhook<'a>>);
+
+impl<'a> WebhookListView<'a> {
+    /// Fetches all webhooks. Returns an empty list for non admins
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes)]
+    pub async fn fetch<A, D, T, R>(
+        req: &R,
+        filter: &WebhookFilter<'_>,
+    ) -> Result<WebhookListView<'a>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b, 'c> D: Database + FetchAll<'b, A, Webhook<'a>, WebhookFilter<'c>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+
+        db.fetch_all(filter, auth, 0)
+            .map(Self)
+            .map_err(AppError::DatabaseError)
+    }
+
+    /// Creates a `Webhook` using the information in the request body
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes, clippy::needless_lifetimes)]
+    pub async fn create<'e, A, D, T, R>(
+        req: &mut R,
+        form: WebhookForm<'_>,
+        csrf: &CsrfToken,
+    ) -> Result<[Notification<'e>; 1], AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b, 'c, 'd> D: Database
+            + FetchAll<'b, A, Webhook<'d>, WebhookFilter<'c>, D>
+            + Create<A, Webhook<'d>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        if !csrf.valid {
+            return Ok([Notification::Error {
+                name: "Webhook",
+                para: "csrf",
+                help: "../help/#webhook_err",
+            }]);
+        }
+        let db = req.get_database();
+        let auth = req.get_auth();
+        let (name, url, secret) = match (form.name, form.url, form.secret) {
+            (Some(name), Some(url), Some(secret)) => (name, url, secret),
+            (None, _, _) => {
+                return Ok([Notification::Error {
+                    name: "Webhook",
+                    para: "Name",
+                    help: "../help/#webhook_err",
+                }])
+            }
+            (_, None, _) => {
+                return Ok([Notification::Error {
+                    name: "Webhook",
+                    para: "Url",
+                    help: "../help/#webhook_err",
+                }])
+            }
+            (_, _, None) => {
+                return Ok([Notification::Error {
+                    name: "Webhook",
+                    para: "Secret",
+                    help: "../help/#webhook_err",
+                }])
+            }
+        };
+        let valid_url = Url::parse(&url)
+            .map(|u| u.scheme() == "http" || u.scheme() == "https")
+            .unwrap_or_default();
+        if !valid_url {
+            return Ok([Notification::Error {
+                name: "Webhook",
+                para: "Url",
+                help: "../help/#webhook_err",
+            }]);
+        }
+        let app_secret = &req.get_base_data().app_secret;
+        let secret = match Webhook::encrypt_secret(app_secret, &secret) {
+            Ok(secret) => secret,
+            Err(err) => {
+                log::error!("Unable to encrypt webhook secret: {}", err);
+                return Ok([Notification::Error {
+                    name: "Webhook",
+                    para: "Secret",
+                    help: "../help/#webhook_err",
+                }]);
+            }
+        };
+        let webhook = Webhook {
+            id: Cow::Owned(db.generate_id()?),
+            name,
+            url,
+            secret: Cow::Owned(secret),
+            actions: form.actions,
+            active: true,
+        };
+        match db.create(&webhook, auth) {
+            Err(err @ DatabaseError::Custom(_)) => Err(AppError::DatabaseError(err)),
+            Err(DatabaseError::NonUnique) => Ok([Notification::Unique {
+                name: "Webhook",
+                para: "name",
+                help: "../help/#webhook_err",
+            }]),
+            Ok(()) => Ok([Notification::Info {
+                name: "Webhook",
+                url: ".",
+                id: Cow::Owned(webhook.id.into_owned()),
+            }]),
+        }
+    }
+}
+
+/// A webhook and its delivery log ready to be presented
+#[derive(Debug, Clone, Hash, Serialize)]
+pub struct WebhookView<'a> {
+    /// The webhook to show to the user
+    pub webhook: Webhook<'a>,
+    /// The latest deliveries to the webhook
+    pub deliveries: DbList<WebhookDelivery<'a>>,
+}
+
+impl WebhookView<'_> {
+    /// Fetches the webhook with the given id and its deliveries
+    ///
+    /// # Errors
+    /// Fails when database connection fails
+    #[inline]
+    #[allow(unused_lifetimes, single_use_lifetimes)]
+    pub async fn fetch<'a, A, D, T, R>(
+        req: &R,
+        key: &str,
+        filter: &WebhookDeliveryFilter<'_>,
+        page: usize,
+    ) -> Result<Option<WebhookView<'a>>, AppError<A, D, T, R>>
+    where
+        A: Auth,
+        for<'b, 'c> D: Database
+            + FetchById<'b, A, Webhook<'a>, D>
+            + FetchAll<'b, A, WebhookDelivery<'a>, WebhookDeliveryFilter<'c>, D>,
+        T: TemplateEngine,
+        R: Request<A, D, T>,
+    {
+        let auth = req.get_auth();
+        let db = req.get_database();
+
+        let id = match Id::from_string(key) {
+            Err(_) => {
+                return Ok(None);
+            }
+            Ok(id) => id,
+        };
+
+        let webhook: Option<Webhook<'_>> = db.fetch(&id, auth)?;
+        if let Some(webhook) = webhook {
+            let filter = WebhookDeliveryFilter {
+                webhook_id: Some(Cow::Borrowed(&id)),
+                status: filter.status,
+            };
+            let deliveries = db.fetch_all(&filter, auth, page)?;
+            Ok(Some(WebhookView {
+                webhook,
+                deliveries,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+}
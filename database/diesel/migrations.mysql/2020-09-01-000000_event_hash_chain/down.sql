@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE `event_checkpoint`;
+ALTER TABLE `event`
+    DROP KEY `UNIQUE_event_seq`,
+    DROP COLUMN `seq`,
+    DROP COLUMN `hash`,
+    DROP COLUMN `prev_hash`;
@@ -0,0 +1,25 @@
+-- chain events by storing a hash over each row and its predecessor.
+-- existing events are chained by the application on startup
+ALTER TABLE `event`
+    ADD COLUMN `seq` bigint,
+    ADD COLUMN `hash` varbinary(32),
+    ADD COLUMN `prev_hash` varbinary(32),
+    ADD UNIQUE KEY `UNIQUE_event_seq` (`seq`);
+
+CREATE TABLE `event_checkpoint` (
+    `id` Binary(16) NOT NULL,
+    `seq` bigint NOT NULL,
+    `hash` varbinary(32) NOT NULL,
+    `signature` varbinary(32) NOT NULL,
+    `date` datetime NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (`id`),
+    KEY `KEY_event_checkpoint_seq` (`seq`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TRIGGER `event_checkpoint_before_insert` 
+BEFORE INSERT ON `event_checkpoint` FOR EACH ROW 
+BEGIN
+    IF new.id IS NULL THEN
+        SET new.id = GEN_UUID();
+    END IF;
+END;
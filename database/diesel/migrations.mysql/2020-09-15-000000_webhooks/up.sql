@@ -0,0 +1,43 @@
+-- outbound webhooks and their persistent delivery queue
+CREATE TABLE `webhook` (
+    `id` Binary(16) NOT NULL,
+    `name` varchar(150) NOT NULL,
+    `url` text NOT NULL,
+    `secret` text NOT NULL,
+    `actions` text DEFAULT NULL,
+    `active` tinyint(1) NOT NULL DEFAULT 1,
+    PRIMARY KEY (`id`),
+    UNIQUE KEY `UNIQUE_webhook_name` (`name`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TRIGGER `webhook_before_insert` 
+BEFORE INSERT ON `webhook` FOR EACH ROW 
+BEGIN
+    IF new.id IS NULL THEN
+        SET new.id = GEN_UUID();
+    END IF;
+END;
+
+CREATE TABLE `webhook_delivery` (
+    `id` Binary(16) NOT NULL,
+    `webhook_id` Binary(16) NOT NULL,
+    `event_id` Binary(16) NOT NULL,
+    `payload` mediumtext NOT NULL,
+    `status` enum('pending', 'delivered', 'failed') NOT NULL DEFAULT 'pending',
+    `attempts` int NOT NULL DEFAULT 0,
+    `next_attempt` datetime DEFAULT CURRENT_TIMESTAMP,
+    `response_code` int DEFAULT NULL,
+    `error` text DEFAULT NULL,
+    `date` datetime NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (`id`),
+    KEY `KEY_webhook_delivery_webhook_id` (`webhook_id`),
+    KEY `KEY_webhook_delivery_due` (`status`, `next_attempt`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TRIGGER `webhook_delivery_before_insert` 
+BEFORE INSERT ON `webhook_delivery` FOR EACH ROW 
+BEGIN
+    IF new.id IS NULL THEN
+        SET new.id = GEN_UUID();
+    END IF;
+END;
@@ -0,0 +1,7 @@
+-- security key types like sk-ecdsa-sha2-nistp256@openssh.com exceed 30 chars
+ALTER TABLE `public_key`
+    MODIFY COLUMN `type` varchar(50) NOT NULL,
+    ADD COLUMN `application` varchar(255) DEFAULT NULL;
+
+ALTER TABLE `server`
+    ADD COLUMN `require_security_key` BOOLEAN NOT NULL DEFAULT FALSE;
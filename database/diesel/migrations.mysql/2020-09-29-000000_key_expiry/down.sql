@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE `public_key`
+    DROP KEY `KEY_public_key_expires`,
+    DROP COLUMN `expiry_reminder`,
+    DROP COLUMN `expires`;
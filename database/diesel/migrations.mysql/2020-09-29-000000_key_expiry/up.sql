@@ -0,0 +1,4 @@
+ALTER TABLE `public_key`
+    ADD COLUMN `expires` datetime DEFAULT NULL,
+    ADD COLUMN `expiry_reminder` datetime DEFAULT NULL,
+    ADD KEY `KEY_public_key_expires` (`expires`);
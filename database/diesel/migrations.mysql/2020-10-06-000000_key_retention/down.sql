@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE `public_key`
+    DROP KEY `KEY_public_key_deactivated`,
+    DROP COLUMN `deactivated`;
@@ -0,0 +1,5 @@
+ALTER TABLE `public_key`
+    ADD COLUMN `deactivated` datetime DEFAULT NULL,
+    ADD KEY `KEY_public_key_deactivated` (`deactivated`);
+
+UPDATE `public_key` SET `deactivated` = CURRENT_TIMESTAMP WHERE `active` = false;
@@ -0,0 +1,19 @@
+-- openssh certificate authorities. The private key is encrypted with the
+-- app secret
+CREATE TABLE `certificate_authority` (
+    `id` Binary(16) NOT NULL,
+    `type` enum('user', 'host') NOT NULL,
+    `public_key` text NOT NULL,
+    `private_key` blob NOT NULL,
+    `created` datetime NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (`id`),
+    UNIQUE KEY `UNIQUE_certificate_authority_type` (`type`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TRIGGER `certificate_authority_before_insert` 
+BEFORE INSERT ON `certificate_authority` FOR EACH ROW 
+BEGIN
+    IF new.id IS NULL THEN
+        SET new.id = GEN_UUID();
+    END IF;
+END;
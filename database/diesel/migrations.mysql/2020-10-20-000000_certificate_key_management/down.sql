@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+UPDATE `server` SET `key_management` = 'other'
+    WHERE `key_management` = 'certificates';
+ALTER TABLE `server`
+    MODIFY COLUMN `key_management` enum('none', 'keys', 'other') NOT NULL DEFAULT 'keys';
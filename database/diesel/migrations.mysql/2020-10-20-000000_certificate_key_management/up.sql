@@ -0,0 +1,4 @@
+-- servers which trust the user certificate authority instead of receiving
+-- keys
+ALTER TABLE `server`
+    MODIFY COLUMN `key_management` enum('none', 'keys', 'other', 'certificates') NOT NULL DEFAULT 'keys';
@@ -0,0 +1,10 @@
+-- the host certificate most recently issued for every server
+CREATE TABLE `host_certificate` (
+    `server_id` Binary(16) NOT NULL,
+    `serial` bigint NOT NULL,
+    `certificate` text NOT NULL,
+    `valid_after` datetime NOT NULL,
+    `valid_before` datetime NOT NULL,
+    PRIMARY KEY (`server_id`),
+    CONSTRAINT `FK_host_certificate_server` FOREIGN KEY (`server_id`) REFERENCES `server` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
@@ -0,0 +1,3 @@
+-- the host key matching rsa_key_fingerprint, used for known_hosts exports
+ALTER TABLE `server`
+    ADD COLUMN `host_key` text DEFAULT NULL;
@@ -0,0 +1,25 @@
+-- requests for access to server accounts waiting for a decision
+CREATE TABLE `access_request` (
+    `id` Binary(16) NOT NULL,
+    `source_id` Binary(16) NOT NULL,
+    `dest_id` Binary(16) NOT NULL,
+    `reason` text DEFAULT NULL,
+    `status` enum('pending', 'approved', 'rejected') NOT NULL DEFAULT 'pending',
+    `request_date` datetime NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    `decided_by` Binary(16) DEFAULT NULL,
+    `decision_date` datetime DEFAULT NULL,
+    PRIMARY KEY (`id`),
+    KEY `KEY_access_request_source_id` (`source_id`),
+    KEY `KEY_access_request_dest_id_status` (`dest_id`, `status`),
+    CONSTRAINT `FK_access_request_source` FOREIGN KEY (`source_id`) REFERENCES `entity` (`id`) ON DELETE CASCADE,
+    CONSTRAINT `FK_access_request_dest` FOREIGN KEY (`dest_id`) REFERENCES `entity` (`id`) ON DELETE CASCADE,
+    CONSTRAINT `FK_access_request_decided_by` FOREIGN KEY (`decided_by`) REFERENCES `entity` (`id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TRIGGER `access_request_before_insert` 
+BEFORE INSERT ON `access_request` FOR EACH ROW 
+BEGIN
+    IF new.id IS NULL THEN
+        SET new.id = GEN_UUID();
+    END IF;
+END;
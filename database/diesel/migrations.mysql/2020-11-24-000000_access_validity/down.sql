@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE `access_request`
+    DROP COLUMN `valid_from`,
+    DROP COLUMN `valid_until`;
+
+ALTER TABLE `access`
+    DROP INDEX `IDX_access_valid_from`,
+    DROP INDEX `IDX_access_valid_until`,
+    DROP COLUMN `valid_from`,
+    DROP COLUMN `valid_until`,
+    DROP COLUMN `validity_synced`;
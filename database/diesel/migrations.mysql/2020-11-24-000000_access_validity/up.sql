@@ -0,0 +1,13 @@
+-- optional period in which a grant is effective, validity_synced records
+-- when a resync was last queued because the grant started or ended
+ALTER TABLE `access`
+    ADD COLUMN `valid_from` datetime DEFAULT NULL,
+    ADD COLUMN `valid_until` datetime DEFAULT NULL,
+    ADD COLUMN `validity_synced` datetime DEFAULT NULL,
+    ADD INDEX `IDX_access_valid_from` (`valid_from`),
+    ADD INDEX `IDX_access_valid_until` (`valid_until`);
+
+-- period of access requested by the entity
+ALTER TABLE `access_request`
+    ADD COLUMN `valid_from` datetime DEFAULT NULL,
+    ADD COLUMN `valid_until` datetime DEFAULT NULL;
@@ -0,0 +1,25 @@
+-- emergency access taken without approval, reviewed after the fact
+CREATE TABLE `break_glass` (
+    `id` Binary(16) NOT NULL,
+    `source_id` Binary(16) NOT NULL,
+    `dest_id` Binary(16) NOT NULL,
+    `reason` text NOT NULL,
+    `start_date` datetime NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    `valid_until` datetime NOT NULL,
+    `reviewed_by` Binary(16) DEFAULT NULL,
+    `review_date` datetime DEFAULT NULL,
+    PRIMARY KEY (`id`),
+    KEY `KEY_break_glass_start_date` (`start_date`),
+    KEY `KEY_break_glass_source_id` (`source_id`),
+    CONSTRAINT `FK_break_glass_source` FOREIGN KEY (`source_id`) REFERENCES `entity` (`id`) ON DELETE CASCADE,
+    CONSTRAINT `FK_break_glass_dest` FOREIGN KEY (`dest_id`) REFERENCES `entity` (`id`) ON DELETE CASCADE,
+    CONSTRAINT `FK_break_glass_reviewed_by` FOREIGN KEY (`reviewed_by`) REFERENCES `entity` (`id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TRIGGER `break_glass_before_insert` 
+BEFORE INSERT ON `break_glass` FOR EACH ROW 
+BEGIN
+    IF new.id IS NULL THEN
+        SET new.id = GEN_UUID();
+    END IF;
+END;
@@ -0,0 +1,48 @@
+-- campaigns in which server admins review who can log in where
+CREATE TABLE `recertification` (
+    `id` Binary(16) NOT NULL,
+    `name` text NOT NULL,
+    `started_by` Binary(16) DEFAULT NULL,
+    `start_date` datetime NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    `closed_by` Binary(16) DEFAULT NULL,
+    `close_date` datetime DEFAULT NULL,
+    PRIMARY KEY (`id`),
+    KEY `KEY_recertification_start_date` (`start_date`),
+    CONSTRAINT `FK_recertification_started_by` FOREIGN KEY (`started_by`) REFERENCES `entity` (`id`) ON DELETE SET NULL,
+    CONSTRAINT `FK_recertification_closed_by` FOREIGN KEY (`closed_by`) REFERENCES `entity` (`id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TRIGGER `recertification_before_insert` 
+BEFORE INSERT ON `recertification` FOR EACH ROW 
+BEGIN
+    IF new.id IS NULL THEN
+        SET new.id = GEN_UUID();
+    END IF;
+END;
+
+-- the access reviewed during a campaign. Copied when the campaign starts and
+-- kept after the access or the entities are gone so the campaign can still
+-- be exported
+CREATE TABLE `recertification_item` (
+    `id` Binary(16) NOT NULL,
+    `campaign_id` Binary(16) NOT NULL,
+    `server_id` Binary(16) NOT NULL,
+    `source_id` Binary(16) NOT NULL,
+    `dest_id` Binary(16) NOT NULL,
+    `kind` enum('access', 'membership') NOT NULL,
+    `decision` enum('keep', 'revoke') DEFAULT NULL,
+    `decided_by` Binary(16) DEFAULT NULL,
+    `decision_date` datetime DEFAULT NULL,
+    PRIMARY KEY (`id`),
+    UNIQUE KEY `KEY_recertification_item_combination` (`campaign_id`, `server_id`, `source_id`, `dest_id`),
+    CONSTRAINT `FK_recertification_item_campaign` FOREIGN KEY (`campaign_id`) REFERENCES `recertification` (`id`) ON DELETE CASCADE,
+    CONSTRAINT `FK_recertification_item_decided_by` FOREIGN KEY (`decided_by`) REFERENCES `entity` (`id`) ON DELETE SET NULL
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TRIGGER `recertification_item_before_insert` 
+BEFORE INSERT ON `recertification_item` FOR EACH ROW 
+BEGIN
+    IF new.id IS NULL THEN
+        SET new.id = GEN_UUID();
+    END IF;
+END;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE `notification_delivery`;
+DROP TABLE `notification_preference`;
@@ -0,0 +1,37 @@
+-- which mail notifications a user receives. Users without a row receive
+-- every notification
+CREATE TABLE `notification_preference` (
+    `entity_id` Binary(16) NOT NULL,
+    `key_add` tinyint(1) NOT NULL DEFAULT 1,
+    `access` tinyint(1) NOT NULL DEFAULT 1,
+    `sync_failure` tinyint(1) NOT NULL DEFAULT 1,
+    `key_expiring` tinyint(1) NOT NULL DEFAULT 1,
+    PRIMARY KEY (`entity_id`),
+    CONSTRAINT `FK_notification_preference_entity` FOREIGN KEY (`entity_id`) REFERENCES `entity` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+-- persistent queue of mail notifications about events
+CREATE TABLE `notification_delivery` (
+    `id` Binary(16) NOT NULL,
+    `entity_id` Binary(16) NOT NULL,
+    `event_id` Binary(16) NOT NULL,
+    `details` mediumtext NOT NULL,
+    `target` text DEFAULT NULL,
+    `status` enum('pending', 'delivered', 'failed') NOT NULL DEFAULT 'pending',
+    `attempts` int NOT NULL DEFAULT 0,
+    `next_attempt` datetime DEFAULT CURRENT_TIMESTAMP,
+    `error` text DEFAULT NULL,
+    `date` datetime NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (`id`),
+    KEY `KEY_notification_delivery_entity_id` (`entity_id`),
+    KEY `KEY_notification_delivery_due` (`status`, `next_attempt`),
+    CONSTRAINT `FK_notification_delivery_entity` FOREIGN KEY (`entity_id`) REFERENCES `entity` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
+CREATE TRIGGER `notification_delivery_before_insert` 
+BEFORE INSERT ON `notification_delivery` FOR EACH ROW 
+BEGIN
+    IF new.id IS NULL THEN
+        SET new.id = GEN_UUID();
+    END IF;
+END;
@@ -0,0 +1,8 @@
+-- the digest of the token a server authenticates with at the api
+CREATE TABLE `server_token` (
+    `server_id` Binary(16) NOT NULL,
+    `token` varbinary(32) NOT NULL,
+    `created` datetime NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (`server_id`),
+    CONSTRAINT `FK_server_token_server` FOREIGN KEY (`server_id`) REFERENCES `server` (`id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
@@ -0,0 +1,10 @@
+-- api tokens of users, a token is rejected once its row is gone
+CREATE TABLE `api_token` (
+    `id` Binary(16) NOT NULL,
+    `entity_id` Binary(16) NOT NULL,
+    `valid_until` datetime NOT NULL,
+    `created` datetime NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (`id`),
+    KEY `KEY_api_token_entity_id` (`entity_id`),
+    CONSTRAINT `FK_api_token_users` FOREIGN KEY (`entity_id`) REFERENCES `users` (`entity_id`) ON DELETE CASCADE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM `access` WHERE `break_glass_id` IS NOT NULL;
+ALTER TABLE `access` ADD UNIQUE KEY `source_entity_id_dest_entity_id` (`source_id`, `dest_id`);
+ALTER TABLE `access`
+    DROP FOREIGN KEY `FK_access_break_glass`,
+    DROP INDEX `access_break_glass_id`,
+    DROP INDEX `access_source_dest_regular`,
+    DROP COLUMN `regular_grant`,
+    DROP COLUMN `break_glass_id`;
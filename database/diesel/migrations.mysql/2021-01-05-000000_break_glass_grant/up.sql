@@ -0,0 +1,9 @@
+-- emergency access is granted by a row of its own, which is removed with its
+-- break_glass row. A regular grant per source and destination stays unique.
+ALTER TABLE `access`
+    ADD COLUMN `break_glass_id` Binary(16) DEFAULT NULL,
+    ADD COLUMN `regular_grant` tinyint(1) AS (IF(`break_glass_id` IS NULL, 1, NULL)) STORED,
+    ADD UNIQUE KEY `access_source_dest_regular` (`source_id`, `dest_id`, `regular_grant`),
+    ADD UNIQUE KEY `access_break_glass_id` (`break_glass_id`),
+    ADD CONSTRAINT `FK_access_break_glass` FOREIGN KEY (`break_glass_id`) REFERENCES `break_glass` (`id`) ON DELETE CASCADE;
+ALTER TABLE `access` DROP INDEX `source_entity_id_dest_entity_id`;
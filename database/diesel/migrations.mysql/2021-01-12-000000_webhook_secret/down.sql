@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+UPDATE `webhook` SET `secret` = '', `active` = 0;
+ALTER TABLE `webhook` MODIFY `secret` text NOT NULL;
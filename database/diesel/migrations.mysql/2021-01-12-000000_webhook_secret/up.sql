@@ -0,0 +1,5 @@
+-- signing secrets of webhooks are stored encrypted with the app secret.
+-- Plain text secrets cannot be encrypted here, so existing webhooks are
+-- deactivated and have to be created again.
+UPDATE `webhook` SET `secret` = '', `active` = 0;
+ALTER TABLE `webhook` MODIFY `secret` blob NOT NULL;
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS "event_checkpoint";
+DROP INDEX IF EXISTS "UNIQUE_event_seq";
+ALTER TABLE "event"
+    DROP COLUMN IF EXISTS "seq",
+    DROP COLUMN IF EXISTS "hash",
+    DROP COLUMN IF EXISTS "prev_hash";
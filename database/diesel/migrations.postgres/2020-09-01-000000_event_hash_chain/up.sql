@@ -0,0 +1,17 @@
+-- chain events by storing a hash over each row and its predecessor.
+-- existing events are chained by the application on startup
+ALTER TABLE "event"
+    ADD COLUMN "seq" bigint,
+    ADD COLUMN "hash" bytea,
+    ADD COLUMN "prev_hash" bytea;
+CREATE UNIQUE INDEX "UNIQUE_event_seq" ON "event" ("seq");
+
+CREATE TABLE "event_checkpoint" (
+    "id" bytea NOT NULL DEFAULT GEN_UUID(),
+    "seq" bigint NOT NULL,
+    "hash" bytea NOT NULL,
+    "signature" bytea NOT NULL,
+    "date" timestamp with time zone NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY ("id")
+);
+CREATE INDEX "KEY_event_checkpoint_seq" ON "event_checkpoint" ("seq");
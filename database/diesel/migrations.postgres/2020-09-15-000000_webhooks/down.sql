@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS "webhook_delivery";
+DROP TYPE IF EXISTS webhook_delivery_status;
+DROP TABLE IF EXISTS "webhook";
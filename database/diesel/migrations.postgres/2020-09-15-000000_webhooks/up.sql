@@ -0,0 +1,27 @@
+-- outbound webhooks and their persistent delivery queue
+CREATE TABLE "webhook" (
+    "id" bytea NOT NULL DEFAULT GEN_UUID(),
+    "name" varchar(150) NOT NULL UNIQUE,
+    "url" text NOT NULL,
+    "secret" text NOT NULL,
+    "actions" text DEFAULT NULL,
+    "active" boolean NOT NULL DEFAULT true,
+    PRIMARY KEY ("id")
+);
+
+CREATE TYPE webhook_delivery_status AS ENUM ('pending', 'delivered', 'failed');
+CREATE TABLE "webhook_delivery" (
+    "id" bytea NOT NULL DEFAULT GEN_UUID(),
+    "webhook_id" bytea NOT NULL REFERENCES webhook(id) ON DELETE CASCADE,
+    "event_id" bytea NOT NULL,
+    "payload" text NOT NULL,
+    "status" webhook_delivery_status NOT NULL DEFAULT 'pending',
+    "attempts" integer NOT NULL DEFAULT 0,
+    "next_attempt" timestamp with time zone DEFAULT CURRENT_TIMESTAMP,
+    "response_code" integer DEFAULT NULL,
+    "error" text DEFAULT NULL,
+    "date" timestamp with time zone NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY ("id")
+);
+CREATE INDEX "KEY_webhook_delivery_webhook_id" ON "webhook_delivery" ("webhook_id");
+CREATE INDEX "KEY_webhook_delivery_due" ON "webhook_delivery" ("status", "next_attempt");
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE "server"
+    DROP COLUMN "require_security_key";
+
+ALTER TABLE "public_key"
+    DROP COLUMN "application",
+    ALTER COLUMN "type" TYPE varchar(30);
@@ -0,0 +1,7 @@
+-- security key types like sk-ecdsa-sha2-nistp256@openssh.com exceed 30 chars
+ALTER TABLE "public_key"
+    ALTER COLUMN "type" TYPE varchar(50),
+    ADD COLUMN "application" text DEFAULT NULL;
+
+ALTER TABLE "server"
+    ADD COLUMN "require_security_key" boolean NOT NULL DEFAULT false;
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX "KEY_public_key_expires";
+
+ALTER TABLE "public_key"
+    DROP COLUMN "expiry_reminder",
+    DROP COLUMN "expires";
@@ -0,0 +1,5 @@
+ALTER TABLE "public_key"
+    ADD COLUMN "expires" timestamp DEFAULT NULL,
+    ADD COLUMN "expiry_reminder" timestamp DEFAULT NULL;
+
+CREATE INDEX "KEY_public_key_expires" ON "public_key" ("expires");
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX "KEY_public_key_deactivated";
+
+ALTER TABLE "public_key"
+    DROP COLUMN "deactivated";
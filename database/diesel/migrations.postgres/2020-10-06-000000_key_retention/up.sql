@@ -0,0 +1,6 @@
+ALTER TABLE "public_key"
+    ADD COLUMN "deactivated" timestamp DEFAULT NULL;
+
+UPDATE "public_key" SET "deactivated" = CURRENT_TIMESTAMP WHERE "active" = false;
+
+CREATE INDEX "KEY_public_key_deactivated" ON "public_key" ("deactivated");
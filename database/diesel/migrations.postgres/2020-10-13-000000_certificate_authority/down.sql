@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS "certificate_authority";
+DROP TYPE IF EXISTS certificate_type;
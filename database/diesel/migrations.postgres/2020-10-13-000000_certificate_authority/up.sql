@@ -0,0 +1,11 @@
+-- openssh certificate authorities. The private key is encrypted with the
+-- app secret
+CREATE TYPE certificate_type AS ENUM ('user', 'host');
+CREATE TABLE "certificate_authority" (
+    "id" bytea NOT NULL DEFAULT GEN_UUID(),
+    "type" certificate_type NOT NULL UNIQUE,
+    "public_key" text NOT NULL,
+    "private_key" bytea NOT NULL,
+    "created" timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY ("id")
+);
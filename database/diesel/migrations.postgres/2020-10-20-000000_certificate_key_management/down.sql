@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+UPDATE "server" SET "key_management" = 'other'
+    WHERE "key_management" = 'certificates';
+CREATE TYPE server_key_management_v3 AS ENUM ('none', 'keys', 'other');
+ALTER TABLE "server"
+    ALTER COLUMN "key_management" DROP DEFAULT,
+    ALTER COLUMN "key_management" TYPE server_key_management_v3
+        USING "key_management"::text::server_key_management_v3,
+    ALTER COLUMN "key_management" SET DEFAULT 'keys';
+DROP TYPE server_key_management;
+ALTER TYPE server_key_management_v3
+    RENAME TO server_key_management;
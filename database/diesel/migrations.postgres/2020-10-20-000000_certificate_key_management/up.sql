@@ -0,0 +1,12 @@
+-- servers which trust the user certificate authority instead of receiving
+-- keys. Enum values cannot be added inside a transaction, so the type is
+-- recreated
+CREATE TYPE server_key_management_v4 AS ENUM ('none', 'keys', 'other', 'certificates');
+ALTER TABLE "server"
+    ALTER COLUMN "key_management" DROP DEFAULT,
+    ALTER COLUMN "key_management" TYPE server_key_management_v4
+        USING "key_management"::text::server_key_management_v4,
+    ALTER COLUMN "key_management" SET DEFAULT 'keys';
+DROP TYPE server_key_management;
+ALTER TYPE server_key_management_v4
+    RENAME TO server_key_management;
@@ -0,0 +1,9 @@
+-- the host certificate most recently issued for every server
+CREATE TABLE "host_certificate" (
+    "server_id" bytea NOT NULL REFERENCES server(id) ON DELETE CASCADE,
+    "serial" bigint NOT NULL,
+    "certificate" text NOT NULL,
+    "valid_after" timestamp NOT NULL,
+    "valid_before" timestamp NOT NULL,
+    PRIMARY KEY ("server_id")
+);
@@ -0,0 +1,3 @@
+-- jump host used to reach the server, used for ssh_config exports
+ALTER TABLE "server"
+    ADD COLUMN "proxy_jump" text DEFAULT NULL;
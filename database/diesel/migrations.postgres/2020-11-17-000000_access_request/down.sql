@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS "access_request";
+DROP TYPE IF EXISTS access_request_status;
@@ -0,0 +1,15 @@
+-- requests for access to server accounts waiting for a decision
+CREATE TYPE access_request_status AS ENUM ('pending', 'approved', 'rejected');
+CREATE TABLE "access_request" (
+    "id" bytea NOT NULL DEFAULT GEN_UUID(),
+    "source_id" bytea NOT NULL REFERENCES entity(id) ON DELETE CASCADE,
+    "dest_id" bytea NOT NULL REFERENCES entity(id) ON DELETE CASCADE,
+    "reason" text DEFAULT NULL,
+    "status" access_request_status NOT NULL DEFAULT 'pending',
+    "request_date" timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "decided_by" bytea DEFAULT NULL REFERENCES entity(id) ON DELETE SET NULL,
+    "decision_date" timestamp DEFAULT NULL,
+    PRIMARY KEY ("id")
+);
+CREATE INDEX "KEY_access_request_source_id" ON "access_request" ("source_id");
+CREATE INDEX "KEY_access_request_dest_id_status" ON "access_request" ("dest_id", "status");
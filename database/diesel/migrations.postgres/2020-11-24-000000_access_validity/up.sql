@@ -0,0 +1,13 @@
+-- optional period in which a grant is effective, validity_synced records
+-- when a resync was last queued because the grant started or ended
+ALTER TABLE "access"
+    ADD COLUMN "valid_from" timestamp DEFAULT NULL,
+    ADD COLUMN "valid_until" timestamp DEFAULT NULL,
+    ADD COLUMN "validity_synced" timestamp DEFAULT NULL;
+CREATE INDEX "IDX_access_valid_from" ON "access" ("valid_from");
+CREATE INDEX "IDX_access_valid_until" ON "access" ("valid_until");
+
+-- period of access requested by the entity
+ALTER TABLE "access_request"
+    ADD COLUMN "valid_from" timestamp DEFAULT NULL,
+    ADD COLUMN "valid_until" timestamp DEFAULT NULL;
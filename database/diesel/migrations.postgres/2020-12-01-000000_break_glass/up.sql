@@ -0,0 +1,14 @@
+-- emergency access taken without approval, reviewed after the fact
+CREATE TABLE "break_glass" (
+    "id" bytea NOT NULL DEFAULT GEN_UUID(),
+    "source_id" bytea NOT NULL REFERENCES entity(id) ON DELETE CASCADE,
+    "dest_id" bytea NOT NULL REFERENCES entity(id) ON DELETE CASCADE,
+    "reason" text NOT NULL,
+    "start_date" timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "valid_until" timestamp NOT NULL,
+    "reviewed_by" bytea DEFAULT NULL REFERENCES entity(id) ON DELETE SET NULL,
+    "review_date" timestamp DEFAULT NULL,
+    PRIMARY KEY ("id")
+);
+CREATE INDEX "KEY_break_glass_start_date" ON "break_glass" ("start_date");
+CREATE INDEX "KEY_break_glass_source_id" ON "break_glass" ("source_id");
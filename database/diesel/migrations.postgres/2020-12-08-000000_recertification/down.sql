@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS "recertification_item";
+DROP TYPE IF EXISTS recertification_decision;
+DROP TYPE IF EXISTS recertification_kind;
+DROP TABLE IF EXISTS "recertification";
@@ -0,0 +1,30 @@
+-- campaigns in which server admins review who can log in where
+CREATE TABLE "recertification" (
+    "id" bytea NOT NULL DEFAULT GEN_UUID(),
+    "name" text NOT NULL,
+    "started_by" bytea DEFAULT NULL REFERENCES entity(id) ON DELETE SET NULL,
+    "start_date" timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "closed_by" bytea DEFAULT NULL REFERENCES entity(id) ON DELETE SET NULL,
+    "close_date" timestamp DEFAULT NULL,
+    PRIMARY KEY ("id")
+);
+CREATE INDEX "KEY_recertification_start_date" ON "recertification" ("start_date");
+
+-- the access reviewed during a campaign. Copied when the campaign starts and
+-- kept after the access or the entities are gone so the campaign can still
+-- be exported
+CREATE TYPE recertification_kind AS ENUM ('access', 'membership');
+CREATE TYPE recertification_decision AS ENUM ('keep', 'revoke');
+CREATE TABLE "recertification_item" (
+    "id" bytea NOT NULL DEFAULT GEN_UUID(),
+    "campaign_id" bytea NOT NULL REFERENCES recertification(id) ON DELETE CASCADE,
+    "server_id" bytea NOT NULL,
+    "source_id" bytea NOT NULL,
+    "dest_id" bytea NOT NULL,
+    "kind" recertification_kind NOT NULL,
+    "decision" recertification_decision DEFAULT NULL,
+    "decided_by" bytea DEFAULT NULL REFERENCES entity(id) ON DELETE SET NULL,
+    "decision_date" timestamp DEFAULT NULL,
+    PRIMARY KEY ("id"),
+    CONSTRAINT "KEY_recertification_item_combination" UNIQUE("campaign_id", "server_id", "source_id", "dest_id")
+);
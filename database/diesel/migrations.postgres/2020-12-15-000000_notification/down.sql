@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS "notification_delivery";
+DROP TABLE IF EXISTS "notification_preference";
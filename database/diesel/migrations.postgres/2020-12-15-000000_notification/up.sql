@@ -0,0 +1,27 @@
+-- which mail notifications a user receives. Users without a row receive
+-- every notification
+CREATE TABLE "notification_preference" (
+    "entity_id" bytea NOT NULL REFERENCES entity(id) ON DELETE CASCADE,
+    "key_add" boolean NOT NULL DEFAULT true,
+    "access" boolean NOT NULL DEFAULT true,
+    "sync_failure" boolean NOT NULL DEFAULT true,
+    "key_expiring" boolean NOT NULL DEFAULT true,
+    PRIMARY KEY ("entity_id")
+);
+
+-- persistent queue of mail notifications about events
+CREATE TABLE "notification_delivery" (
+    "id" bytea NOT NULL DEFAULT GEN_UUID(),
+    "entity_id" bytea NOT NULL REFERENCES entity(id) ON DELETE CASCADE,
+    "event_id" bytea NOT NULL,
+    "details" text NOT NULL,
+    "target" text DEFAULT NULL,
+    "status" webhook_delivery_status NOT NULL DEFAULT 'pending',
+    "attempts" integer NOT NULL DEFAULT 0,
+    "next_attempt" timestamp DEFAULT CURRENT_TIMESTAMP,
+    "error" text DEFAULT NULL,
+    "date" timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY ("id")
+);
+CREATE INDEX "KEY_notification_delivery_entity_id" ON "notification_delivery" ("entity_id");
+CREATE INDEX "KEY_notification_delivery_due" ON "notification_delivery" ("status", "next_attempt");
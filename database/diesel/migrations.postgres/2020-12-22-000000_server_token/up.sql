@@ -0,0 +1,7 @@
+-- the digest of the token a server authenticates with at the api
+CREATE TABLE "server_token" (
+    "server_id" bytea NOT NULL REFERENCES server(id) ON DELETE CASCADE,
+    "token" bytea NOT NULL,
+    "created" timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY ("server_id")
+);
@@ -0,0 +1,8 @@
+-- api tokens of users, a token is rejected once its row is gone
+CREATE TABLE "api_token" (
+    "id" bytea NOT NULL,
+    "entity_id" bytea NOT NULL REFERENCES users(entity_id) ON DELETE CASCADE,
+    "valid_until" timestamp NOT NULL,
+    "created" timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY ("id")
+);
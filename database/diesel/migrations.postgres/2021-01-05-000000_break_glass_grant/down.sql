@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM "access" WHERE "break_glass_id" IS NOT NULL;
+DROP INDEX IF EXISTS "access_source_dest_regular";
+ALTER TABLE "access"
+    DROP COLUMN "break_glass_id",
+    ADD CONSTRAINT "source_entity_id_dest_entity_id" UNIQUE("source_id", "dest_id");
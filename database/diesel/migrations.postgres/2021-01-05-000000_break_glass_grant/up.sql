@@ -0,0 +1,7 @@
+-- emergency access is granted by a row of its own, which is removed with its
+-- break_glass row. A regular grant per source and destination stays unique.
+ALTER TABLE "access"
+    ADD COLUMN "break_glass_id" bytea DEFAULT NULL UNIQUE REFERENCES break_glass(id) ON DELETE CASCADE,
+    DROP CONSTRAINT "source_entity_id_dest_entity_id";
+CREATE UNIQUE INDEX "access_source_dest_regular" ON "access" ("source_id", "dest_id")
+    WHERE "break_glass_id" IS NULL;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE "webhook" ALTER COLUMN "secret" TYPE text USING '';
+UPDATE "webhook" SET "active" = false;
@@ -0,0 +1,5 @@
+-- signing secrets of webhooks are stored encrypted with the app secret.
+-- Plain text secrets cannot be encrypted here, so existing webhooks are
+-- deactivated and have to be created again.
+ALTER TABLE "webhook" ALTER COLUMN "secret" TYPE bytea USING ''::bytea;
+UPDATE "webhook" SET "active" = false;
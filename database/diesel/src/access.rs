@@ -0,0 +1,178 @@
+use crate::{
+    error::DieselError,
+    exec, exec_unique,
+    migrate::Migrate,
+    schema::{access, server_account, sync_request},
+    BinaryWrapper, DbWrapper, DieselDB, DieselPooledConnection, UniqueExtension,
+};
+use core_common::{
+    chrono::NaiveDateTime,
+    database::{AccessSchedule, Database, DatabaseError, DbResult},
+    objects::{AccessTransition, AccessValidity, Event, EventDetails},
+    types::{EventTypes, Id},
+};
+use diesel::{
+    backend::{Backend, SupportsDefaultKeyword, UsesAnsiSavepointSyntax},
+    connection::AnsiTransactionManager,
+    deserialize::FromSql,
+    insert_into,
+    serialize::ToSql,
+    sql_types::{BigInt, Bool, HasSqlType, Timestamp},
+    BoolExpressionMethods, Connection, ExpressionMethods, JoinOnDsl, QueryDsl,
+    Queryable, RunQueryDsl,
+};
+use std::{borrow::Cow, convert::TryFrom};
+
+/// A grant together with the server of its account
+type TransitionRow = (
+    BinaryWrapper<Id>,
+    BinaryWrapper<Id>,
+    BinaryWrapper<Id>,
+    BinaryWrapper<Id>,
+    Option<NaiveDateTime>,
+    Option<NaiveDateTime>,
+);
+
+impl<B, C> DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+{
+    /// Queues a resync of the server account. Does nothing if a resync of
+    /// the account is already queued. The insert runs in a savepoint, so a
+    /// queued resync does not abort a surrounding transaction
+    pub(crate) fn queue_account_sync(
+        &self,
+        conn: &DieselPooledConnection<C>,
+        server_id: &Id,
+        account_id: &Id,
+    ) -> DbResult<(), Self> {
+        let query = insert_into(sync_request::dsl::sync_request).values((
+            sync_request::id.eq(BinaryWrapper(self.generate_id()?)),
+            sync_request::server_id.eq(BinaryWrapper(server_id)),
+            sync_request::account_id.eq(BinaryWrapper(account_id)),
+        ));
+        match Self::transaction(conn, || exec_unique!(query, *conn, execute)) {
+            Ok(_) | Err(DatabaseError::NonUnique) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl<B, C> AccessSchedule for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<BigInt>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<EventTypes>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    i64: FromSql<BigInt, B> + ToSql<BigInt, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
+{
+    #[inline]
+    fn fetch_transitions(
+        &self,
+        before: NaiveDateTime,
+        limit: usize,
+    ) -> DbResult<Vec<AccessTransition<'static>>, Self> {
+        let conn = self.get()?;
+        let started = access::valid_from.le(before).and(
+            access::validity_synced
+                .is_null()
+                .or(access::validity_synced.lt(access::valid_from)),
+        );
+        let ended = access::valid_until.le(before).and(
+            access::validity_synced
+                .is_null()
+                .or(access::validity_synced.lt(access::valid_until)),
+        );
+        let query = access::dsl::access
+            .inner_join(
+                server_account::dsl::server_account
+                    .on(server_account::entity_id.eq(access::dest_id)),
+            )
+            .select((
+                access::id,
+                access::source_id,
+                server_account::server_id,
+                access::dest_id,
+                access::valid_from,
+                access::valid_until,
+            ))
+            .filter(started.or(ended))
+            .limit(i64::try_from(limit).unwrap_or(i64::MAX));
+        let rows: Vec<TransitionRow> = exec!(query, conn, load)?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(
+                    access_id,
+                    source_id,
+                    server_id,
+                    account_id,
+                    valid_from,
+                    valid_until,
+                )| {
+                    AccessTransition {
+                        access_id: Cow::Owned(access_id.0),
+                        source_id: Cow::Owned(source_id.0),
+                        server_id: Cow::Owned(server_id.0),
+                        account_id: Cow::Owned(account_id.0),
+                        validity: AccessValidity {
+                            valid_from,
+                            valid_until,
+                        },
+                    }
+                },
+            )
+            .collect())
+    }
+
+    #[inline]
+    fn process_transition(
+        &self,
+        transition: &AccessTransition<'_>,
+        now: NaiveDateTime,
+    ) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        Self::transaction(&conn, || {
+            if transition.validity.valid_until.map_or(false, |v| v <= now) {
+                let details = EventDetails::AccessRevoke {
+                    source_id: Cow::Borrowed(&transition.source_id),
+                    dest_id: Cow::Borrowed(&transition.account_id),
+                };
+                let event = Event::new(
+                    Cow::Owned(self.generate_id()?),
+                    None,
+                    details,
+                    Some(Cow::Borrowed(&transition.source_id)),
+                );
+                self.append_event_with(&conn, &event)?;
+            }
+            self.queue_account_sync(
+                &conn,
+                &transition.server_id,
+                &transition.account_id,
+            )?;
+            let query = diesel::update(
+                access::dsl::access.find(BinaryWrapper(&transition.access_id)),
+            )
+            .set(access::validity_synced.eq(now));
+            let _ = exec!(query, conn, execute)?;
+            Ok(())
+        })
+    }
+}
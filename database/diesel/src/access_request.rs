@@ -0,0 +1,445 @@
+use crate::{
+    error::DieselError,
+    exec, exec_opt, exec_unique,
+    migrate::Migrate,
+    schema::{
+        access, access_request, group_admin, server, server_account, server_admin,
+        users,
+    },
+    server::{login_from_row, LoginRow},
+    BinaryWrapper, DbWrapper, DieselDB, DieselPooledConnection, UniqueExtension,
+};
+use core_common::{
+    chrono::{NaiveDateTime, Utc},
+    database::{AccessRequestStore, Database, DatabaseError, DbResult},
+    objects::{AccessRequest, AccessRequestEntry, AccessValidity, ServerLogin},
+    types::{
+        AccessRequestStatus, AuthorizationType, Id, KeyManagement, SyncStatusType,
+    },
+};
+use diesel::{
+    backend::{Backend, SupportsDefaultKeyword, UsesAnsiSavepointSyntax},
+    connection::AnsiTransactionManager,
+    deserialize::FromSql,
+    insert_into,
+    serialize::ToSql,
+    sql_types::{Bool, HasSqlType, Timestamp},
+    BoolExpressionMethods, Connection, ExpressionMethods, JoinOnDsl,
+    OptionalExtension, QueryDsl, Queryable, RunQueryDsl,
+};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
+#[derive(Debug, Clone, Queryable)]
+struct InnerAccessRequest<'a> {
+    id: BinaryWrapper<Cow<'a, Id>>,
+    source_id: BinaryWrapper<Cow<'a, Id>>,
+    dest_id: BinaryWrapper<Cow<'a, Id>>,
+    reason: Option<Cow<'a, str>>,
+    status: DbWrapper<AccessRequestStatus>,
+    request_date: NaiveDateTime,
+    decided_by: Option<BinaryWrapper<Cow<'a, Id>>>,
+    decision_date: Option<NaiveDateTime>,
+    valid_from: Option<NaiveDateTime>,
+    valid_until: Option<NaiveDateTime>,
+}
+
+impl<'a> Into<AccessRequest<'a>> for InnerAccessRequest<'a> {
+    fn into(self) -> AccessRequest<'a> {
+        AccessRequest {
+            id: self.id.0,
+            source_id: self.source_id.0,
+            dest_id: self.dest_id.0,
+            reason: self.reason,
+            validity: AccessValidity {
+                valid_from: self.valid_from,
+                valid_until: self.valid_until,
+            },
+            status: self.status.0,
+            request_date: Some(self.request_date),
+            decided_by: self.decided_by.map(|v| v.0),
+            decision_date: self.decision_date,
+        }
+    }
+}
+
+impl<B, C> DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<DbWrapper<KeyManagement>>
+        + HasSqlType<DbWrapper<AuthorizationType>>
+        + HasSqlType<DbWrapper<SyncStatusType>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    DbWrapper<KeyManagement>: Queryable<DbWrapper<KeyManagement>, B>,
+    DbWrapper<AuthorizationType>: Queryable<DbWrapper<AuthorizationType>, B>,
+    DbWrapper<SyncStatusType>: Queryable<DbWrapper<SyncStatusType>, B>,
+{
+    /// Fetches the named server accounts with the given ids together with
+    /// their server
+    pub(crate) fn account_logins(
+        conn: &DieselPooledConnection<C>,
+        account_ids: &[BinaryWrapper<&Id>],
+    ) -> DbResult<HashMap<Id, ServerLogin<'static>>, Self> {
+        let query = server_account::dsl::server_account
+            .inner_join(
+                server::dsl::server.on(server::id.eq(server_account::server_id)),
+            )
+            .select((
+                server::all_columns,
+                server_account::entity_id,
+                server_account::name,
+            ))
+            .filter(server_account::entity_id.eq_any(account_ids));
+        let rows: Vec<LoginRow> = exec!(query, *conn, load)?;
+        Ok(rows
+            .into_iter()
+            .filter_map(login_from_row)
+            .map(|login| (login.account_id.clone().into_owned(), login))
+            .collect())
+    }
+
+    /// Fetches the uids of the users with the given ids. Entities which are
+    /// not users are skipped
+    pub(crate) fn user_uids(
+        conn: &DieselPooledConnection<C>,
+        entity_ids: &[BinaryWrapper<&Id>],
+    ) -> DbResult<HashMap<Id, String>, Self> {
+        let query = users::dsl::users
+            .select((users::entity_id, users::uid))
+            .filter(users::entity_id.eq_any(entity_ids));
+        let uids: Vec<(BinaryWrapper<Id>, String)> = exec!(query, *conn, load)?;
+        Ok(uids.into_iter().map(|(id, uid)| (id.0, uid)).collect())
+    }
+
+    /// Fetches the ids which make the entity a server admin when listed as
+    /// one: the entity itself, its groups and the groups it administers
+    pub(crate) fn admin_ids<'a>(
+        &self,
+        entity_id: &'a Id,
+    ) -> DbResult<Vec<BinaryWrapper<Cow<'a, Id>>>, Self> {
+        let mut ids: Vec<BinaryWrapper<Cow<'_, Id>>> = self
+            .fetch_permission_ids(Cow::Borrowed(entity_id))?
+            .into_iter()
+            .map(BinaryWrapper)
+            .collect();
+        let conn = self.get_read()?;
+        let query = group_admin::dsl::group_admin
+            .select(group_admin::group_id)
+            .filter(group_admin::admin_id.eq_any(&ids));
+        let groups: Vec<BinaryWrapper<Id>> = exec!(query, conn, load)?;
+        ids.extend(groups.into_iter().map(|v| BinaryWrapper(Cow::Owned(v.0))));
+        Ok(ids)
+    }
+
+    /// Queues a resync of the requested account, so the granted access is
+    /// deployed right away
+    fn queue_request_sync(
+        &self,
+        conn: &DieselPooledConnection<C>,
+        request: &AccessRequest<'_>,
+    ) -> DbResult<(), Self> {
+        let query = server_account::dsl::server_account
+            .select(server_account::server_id)
+            .filter(server_account::entity_id.eq(BinaryWrapper(&request.dest_id)));
+        let server_id: Option<BinaryWrapper<Id>> = exec_opt!(query, *conn, first)?;
+        match server_id {
+            Some(server_id) => {
+                self.queue_account_sync(conn, &server_id.0, &request.dest_id)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Adds the requested account and the uid of the requesting user to
+    /// every request. Requests for accounts without a name are skipped
+    fn request_entries(
+        conn: &DieselPooledConnection<C>,
+        requests: Vec<InnerAccessRequest<'static>>,
+    ) -> DbResult<Vec<AccessRequestEntry<'static>>, Self> {
+        if requests.is_empty() {
+            return Ok(Vec::new());
+        }
+        let dest_ids: Vec<BinaryWrapper<&Id>> = requests
+            .iter()
+            .map(|v| BinaryWrapper(&*v.dest_id.0))
+            .collect();
+        let logins = Self::account_logins(conn, &dest_ids)?;
+        let source_ids: Vec<BinaryWrapper<&Id>> = requests
+            .iter()
+            .map(|v| BinaryWrapper(&*v.source_id.0))
+            .collect();
+        let uids = Self::user_uids(conn, &source_ids)?;
+
+        Ok(requests
+            .into_iter()
+            .filter_map(|request| {
+                let login = logins.get(&*request.dest_id.0)?.clone();
+                let requester = uids.get(&*request.source_id.0).cloned();
+                Some(AccessRequestEntry {
+                    request: request.into(),
+                    requester: requester.map(Cow::Owned),
+                    login,
+                })
+            })
+            .collect())
+    }
+}
+
+impl<B, C> AccessRequestStore for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<KeyManagement>>
+        + HasSqlType<DbWrapper<AuthorizationType>>
+        + HasSqlType<DbWrapper<SyncStatusType>>
+        + HasSqlType<DbWrapper<AccessRequestStatus>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<KeyManagement>: Queryable<DbWrapper<KeyManagement>, B>,
+    DbWrapper<AuthorizationType>: Queryable<DbWrapper<AuthorizationType>, B>,
+    DbWrapper<SyncStatusType>: Queryable<DbWrapper<SyncStatusType>, B>,
+    DbWrapper<AccessRequestStatus>: Queryable<DbWrapper<AccessRequestStatus>, B>,
+{
+    #[inline]
+    fn fetch_account(
+        &self,
+        hostname: &str,
+        account: &str,
+    ) -> DbResult<Option<ServerLogin<'static>>, Self> {
+        let conn = self.get_read()?;
+        let query = server_account::dsl::server_account
+            .inner_join(
+                server::dsl::server.on(server::id.eq(server_account::server_id)),
+            )
+            .select((
+                server::all_columns,
+                server_account::entity_id,
+                server_account::name,
+            ))
+            .filter(server::hostname.eq(hostname))
+            .filter(server_account::name.eq(account));
+        let row: Option<LoginRow> = exec_opt!(query, conn, first)?;
+        Ok(row.and_then(login_from_row))
+    }
+
+    #[inline]
+    fn create_request(&self, request: &AccessRequest<'_>) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        let source_id = BinaryWrapper(&request.source_id);
+        let dest_id = BinaryWrapper(&request.dest_id);
+        let query = access::dsl::access
+            .select(access::id)
+            .filter(access::source_id.eq(&source_id))
+            .filter(access::dest_id.eq(&dest_id))
+            .filter(access::break_glass_id.is_null())
+            .filter(
+                access::valid_until
+                    .is_null()
+                    .or(access::valid_until.gt(Utc::now().naive_utc())),
+            );
+        let granted: Option<BinaryWrapper<Id>> = exec_opt!(query, conn, first)?;
+        let query = access_request::dsl::access_request
+            .select(access_request::id)
+            .filter(access_request::source_id.eq(&source_id))
+            .filter(access_request::dest_id.eq(&dest_id))
+            .filter(
+                access_request::status.eq(DbWrapper(AccessRequestStatus::Pending)),
+            );
+        let pending: Option<BinaryWrapper<Id>> = exec_opt!(query, conn, first)?;
+        if granted.is_some() || pending.is_some() {
+            return Err(DatabaseError::NonUnique);
+        }
+
+        let query = insert_into(access_request::dsl::access_request).values((
+            access_request::id.eq(BinaryWrapper(&request.id)),
+            access_request::source_id.eq(&source_id),
+            access_request::dest_id.eq(&dest_id),
+            access_request::reason.eq(&request.reason),
+            access_request::status.eq(DbWrapper(request.status)),
+            access_request::valid_from.eq(request.validity.valid_from),
+            access_request::valid_until.eq(request.validity.valid_until),
+        ));
+        let _ = exec_unique!(query, conn, execute)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn fetch_request(
+        &self,
+        id: &Id,
+    ) -> DbResult<Option<AccessRequestEntry<'static>>, Self> {
+        let conn = self.get_read()?;
+        let query = access_request::dsl::access_request.find(BinaryWrapper(id));
+        let request: Option<InnerAccessRequest<'static>> =
+            exec_opt!(query, conn, first)?;
+        let requests = request.into_iter().collect();
+        Ok(Self::request_entries(&conn, requests)?.pop())
+    }
+
+    #[inline]
+    fn fetch_requests(
+        &self,
+        source_id: &Id,
+    ) -> DbResult<Vec<AccessRequestEntry<'static>>, Self> {
+        let conn = self.get_read()?;
+        let query = access_request::dsl::access_request
+            .filter(access_request::source_id.eq(BinaryWrapper(source_id)))
+            .order(access_request::request_date.desc());
+        let requests: Vec<InnerAccessRequest<'static>> = exec!(query, conn, load)?;
+        Self::request_entries(&conn, requests)
+    }
+
+    #[inline]
+    fn fetch_pending(
+        &self,
+        approver_id: Option<&Id>,
+    ) -> DbResult<Vec<AccessRequestEntry<'static>>, Self> {
+        let admin_ids = match approver_id {
+            Some(approver_id) => Some(self.admin_ids(approver_id)?),
+            None => None,
+        };
+
+        let conn = self.get_read()?;
+        let mut query = access_request::dsl::access_request
+            .filter(
+                access_request::status.eq(DbWrapper(AccessRequestStatus::Pending)),
+            )
+            .order(access_request::request_date)
+            .into_boxed::<B>();
+        if let Some(ref ids) = admin_ids {
+            let server_query = server_admin::dsl::server_admin
+                .select(server_admin::server_id)
+                .filter(server_admin::entity_id.eq_any(ids));
+            let account_query = server_account::dsl::server_account
+                .select(server_account::entity_id)
+                .filter(server_account::server_id.eq_any(server_query));
+            query = query.filter(access_request::dest_id.eq_any(account_query));
+        }
+        let requests: Vec<InnerAccessRequest<'static>> = exec!(query, conn, load)?;
+        Self::request_entries(&conn, requests)
+    }
+
+    #[inline]
+    fn is_approver(&self, server_id: &Id, entity_id: &Id) -> DbResult<bool, Self> {
+        let ids: HashSet<Cow<'_, Id>> = self
+            .fetch_permission_ids(Cow::Borrowed(entity_id))?
+            .into_iter()
+            .collect();
+        let conn = self.get_read()?;
+        let query = server_admin::dsl::server_admin
+            .select(server_admin::entity_id)
+            .filter(server_admin::server_id.eq(BinaryWrapper(server_id)));
+        let admins: Vec<BinaryWrapper<Id>> = exec!(query, conn, load)?;
+        if admins.iter().any(|v| ids.contains(&v.0)) {
+            return Ok(true);
+        }
+        if admins.is_empty() {
+            return Ok(false);
+        }
+
+        let ids: Vec<BinaryWrapper<&Id>> =
+            ids.iter().map(|v| BinaryWrapper(&**v)).collect();
+        let query = group_admin::dsl::group_admin
+            .select(group_admin::group_id)
+            .filter(group_admin::group_id.eq_any(&admins))
+            .filter(group_admin::admin_id.eq_any(&ids));
+        let group: Option<BinaryWrapper<Id>> = exec_opt!(query, conn, first)?;
+        Ok(group.is_some())
+    }
+
+    #[inline]
+    fn decide_request(&self, request: &AccessRequest<'_>) -> DbResult<bool, Self> {
+        let conn = self.get()?;
+        let decision_date = request
+            .decision_date
+            .unwrap_or_else(|| Utc::now().naive_utc());
+        // the decision and the grant are stored together, an approved
+        // request never stays without access
+        Self::transaction(&conn, || {
+            let query = diesel::update(
+                access_request::dsl::access_request
+                    .find(BinaryWrapper(&request.id))
+                    .filter(
+                        access_request::status
+                            .eq(DbWrapper(AccessRequestStatus::Pending)),
+                    ),
+            )
+            .set((
+                access_request::status.eq(DbWrapper(request.status)),
+                access_request::decided_by
+                    .eq(request.decided_by.as_ref().map(BinaryWrapper)),
+                access_request::decision_date.eq(decision_date),
+            ));
+            if exec!(query, conn, execute)? == 0 {
+                return Ok(false);
+            }
+            if request.status != AccessRequestStatus::Approved {
+                return Ok(true);
+            }
+
+            let query = access::dsl::access
+                .select((access::id, access::valid_until))
+                .filter(access::source_id.eq(BinaryWrapper(&request.source_id)))
+                .filter(access::dest_id.eq(BinaryWrapper(&request.dest_id)))
+                .filter(access::break_glass_id.is_null());
+            let existing: Option<(BinaryWrapper<Id>, Option<NaiveDateTime>)> =
+                exec_opt!(query, conn, first)?;
+            let (existing_id, valid_until) = match existing {
+                Some(existing) => existing,
+                None => {
+                    let query = insert_into(access::dsl::access).values((
+                        access::id.eq(BinaryWrapper(self.generate_id()?)),
+                        access::source_id.eq(BinaryWrapper(&request.source_id)),
+                        access::dest_id.eq(BinaryWrapper(&request.dest_id)),
+                        access::grant_date.eq(decision_date),
+                        access::granted_by
+                            .eq(request.decided_by.as_ref().map(BinaryWrapper)),
+                        access::valid_from.eq(request.validity.valid_from),
+                        access::valid_until.eq(request.validity.valid_until),
+                    ));
+                    let _ = exec!(query, conn, execute)?;
+                    self.queue_request_sync(&conn, request)?;
+                    return Ok(true);
+                }
+            };
+
+            // an expired grant is renewed, access granted in another way
+            // meanwhile is kept as it is
+            let current = AccessValidity {
+                valid_from: None,
+                valid_until,
+            };
+            if !current.has_ended(decision_date) {
+                return Ok(true);
+            }
+            let query =
+                diesel::update(access::dsl::access.find(&existing_id)).set((
+                    access::grant_date.eq(decision_date),
+                    access::granted_by
+                        .eq(request.decided_by.as_ref().map(BinaryWrapper)),
+                    access::valid_from.eq(request.validity.valid_from),
+                    access::valid_until.eq(request.validity.valid_until),
+                    access::validity_synced.eq(None::<NaiveDateTime>),
+                ));
+            let _ = exec!(query, conn, execute)?;
+            self.queue_request_sync(&conn, request)?;
+            Ok(true)
+        })
+    }
+}
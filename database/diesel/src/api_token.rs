@@ -0,0 +1,101 @@
+use crate::{
+    error::DieselError, exec, exec_opt, migrate::Migrate, schema::api_token,
+    BinaryWrapper, DieselDB,
+};
+use core_common::{
+    chrono::{NaiveDateTime, Utc},
+    database::{ApiTokenStore, DatabaseError, DbResult},
+    objects::ApiToken,
+    types::Id,
+};
+use diesel::{
+    backend::{Backend, SupportsDefaultKeyword, UsesAnsiSavepointSyntax},
+    connection::AnsiTransactionManager,
+    deserialize::FromSql,
+    insert_into,
+    serialize::ToSql,
+    sql_types::{HasSqlType, Timestamp},
+    Connection, ExpressionMethods, OptionalExtension, QueryDsl, Queryable,
+    RunQueryDsl,
+};
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Queryable)]
+struct InnerApiToken {
+    id: BinaryWrapper<Id>,
+    entity_id: BinaryWrapper<Id>,
+    valid_until: NaiveDateTime,
+    created: NaiveDateTime,
+}
+
+impl Into<ApiToken<'static>> for InnerApiToken {
+    fn into(self) -> ApiToken<'static> {
+        ApiToken {
+            id: Cow::Owned(self.id.0),
+            entity_id: Cow::Owned(self.entity_id.0),
+            valid_until: self.valid_until,
+            created: self.created,
+        }
+    }
+}
+
+impl<B, C> ApiTokenStore for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Timestamp>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+{
+    #[inline]
+    fn create_api_token(&self, token: &ApiToken<'_>) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        let query = insert_into(api_token::dsl::api_token).values((
+            api_token::id.eq(BinaryWrapper(token.id.as_ref())),
+            api_token::entity_id.eq(BinaryWrapper(token.entity_id.as_ref())),
+            api_token::valid_until.eq(token.valid_until),
+            api_token::created.eq(token.created),
+        ));
+        let _ = exec!(query, conn, execute)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn fetch_api_token(&self, id: &Id) -> DbResult<Option<ApiToken<'static>>, Self> {
+        let conn = self.get_read()?;
+        let query = api_token::dsl::api_token
+            .filter(api_token::id.eq(BinaryWrapper(id)))
+            .filter(api_token::valid_until.gt(Utc::now().naive_utc()));
+        let token: Option<InnerApiToken> = exec_opt!(query, conn, first)?;
+        Ok(token.map(Into::into))
+    }
+
+    #[inline]
+    fn fetch_api_tokens(
+        &self,
+        entity_id: &Id,
+    ) -> DbResult<Vec<ApiToken<'static>>, Self> {
+        let conn = self.get_read()?;
+        let query = api_token::dsl::api_token
+            .filter(api_token::entity_id.eq(BinaryWrapper(entity_id)))
+            .filter(api_token::valid_until.gt(Utc::now().naive_utc()))
+            .order_by(api_token::created.desc());
+        let tokens: Vec<InnerApiToken> = exec!(query, conn, load)?;
+        Ok(tokens.into_iter().map(Into::into).collect())
+    }
+
+    #[inline]
+    fn revoke_api_token(&self, id: &Id, entity_id: &Id) -> DbResult<bool, Self> {
+        let conn = self.get()?;
+        let query = diesel::delete(
+            api_token::dsl::api_token
+                .filter(api_token::id.eq(BinaryWrapper(id)))
+                .filter(api_token::entity_id.eq(BinaryWrapper(entity_id))),
+        );
+        Ok(exec!(query, conn, execute)? > 0)
+    }
+}
@@ -0,0 +1,541 @@
+use crate::{
+    error::DieselError,
+    exec, exec_opt, exec_unique,
+    migrate::Migrate,
+    schema::{event, event_checkpoint},
+    BinaryWrapper, DbWrapper, DieselDB, DieselPooledConnection, UniqueExtension,
+};
+use core_common::{
+    chrono::{NaiveDateTime, Utc},
+    database::{Database, DatabaseError, DbResult},
+    log,
+    objects::Event,
+    ring::{digest, hmac},
+    types::{EventTypes, Id},
+};
+use diesel::{
+    backend::{Backend, SupportsDefaultKeyword, UsesAnsiSavepointSyntax},
+    connection::AnsiTransactionManager,
+    deserialize::{FromSql, Queryable},
+    insert_into,
+    serialize::ToSql,
+    sql_types::{BigInt, Bool, HasSqlType, Timestamp},
+    update, Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl,
+};
+use std::{
+    collections::HashMap,
+    convert::{TryFrom, TryInto},
+    fmt,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// Number of events loaded at once when walking the chain
+const CHAIN_BATCH: i64 = 500;
+/// Number of attempts to append an event when other events are appended
+/// concurrently
+const CHAIN_ATTEMPTS: u8 = 5;
+
+/// Describes the first inconsistency found in the audit chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditBreak {
+    /// The event was never added to the chain
+    Unsealed(Id),
+    /// An event is missing or was inserted into the chain
+    Sequence {
+        /// Sequence number which was expected next
+        expected: i64,
+        /// Sequence number which was found instead
+        found: i64,
+    },
+    /// The previous hash stored in the event does not match its predecessor
+    PrevHash(i64),
+    /// The content of the event does not match its hash
+    Hash(i64),
+    /// The signature of the checkpoint is invalid
+    Signature(i64),
+    /// The checkpoint does not match the event chain
+    Checkpoint(i64),
+}
+
+impl fmt::Display for AuditBreak {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unsealed(id) => write!(f, "Event {} is not part of the chain", id),
+            Self::Sequence { expected, found } => write!(
+                f,
+                "Expected event #{} but found #{}. Events are missing or were added",
+                expected, found
+            ),
+            Self::PrevHash(seq) => {
+                write!(f, "Event #{} does not reference its predecessor", seq)
+            }
+            Self::Hash(seq) => write!(f, "Event #{} was modified", seq),
+            Self::Signature(seq) => {
+                write!(f, "Signature of checkpoint at event #{} is invalid", seq)
+            }
+            Self::Checkpoint(seq) => {
+                write!(f, "Checkpoint at event #{} does not match the chain", seq)
+            }
+        }
+    }
+}
+
+/// Result of walking the audit chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditReport {
+    /// Number of events verified
+    pub events: u64,
+    /// Number of checkpoints verified
+    pub checkpoints: u64,
+    /// The first inconsistency found. `None` if the chain is intact
+    pub broken: Option<AuditBreak>,
+}
+
+impl fmt::Display for AuditReport {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.broken {
+            Some(ref broken) => write!(
+                f,
+                "Audit log is broken after {} events: {}",
+                self.events, broken
+            ),
+            None => write!(
+                f,
+                "Audit log is intact. Verified {} events and {} checkpoints",
+                self.events, self.checkpoints
+            ),
+        }
+    }
+}
+
+/// Content of an event which is protected by the chain
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ChainLink<'a> {
+    pub(crate) seq: i64,
+    pub(crate) id: &'a Id,
+    pub(crate) actor_id: Option<&'a Id>,
+    pub(crate) date: &'a NaiveDateTime,
+    pub(crate) details: &'a str,
+    pub(crate) type_: EventTypes,
+    pub(crate) object_id: Option<&'a Id>,
+    pub(crate) prev_hash: Option<&'a [u8]>,
+}
+
+impl ChainLink<'_> {
+    /// Computes the sha256 over all fields. Every field is prefixed with
+    /// its length so that content cannot be moved between fields.
+    pub(crate) fn hash(&self) -> Vec<u8> {
+        fn field(ctx: &mut digest::Context, value: Option<&[u8]>) {
+            match value {
+                Some(value) => {
+                    let len = u64::try_from(value.len()).unwrap_or_default();
+                    ctx.update(&[1]);
+                    ctx.update(&len.to_be_bytes());
+                    ctx.update(value);
+                }
+                None => ctx.update(&[0]),
+            }
+        }
+
+        let type_: &[u8] = match self.type_ {
+            EventTypes::Server => b"server",
+            EventTypes::Entity => b"entity",
+        };
+        let date = self.date.format("%Y-%m-%dT%H:%M:%S%.f").to_string();
+        let mut ctx = digest::Context::new(&digest::SHA256);
+        field(&mut ctx, Some(self.seq.to_be_bytes().as_ref()));
+        field(&mut ctx, Some(self.id.bytes.as_bytes().as_ref()));
+        field(
+            &mut ctx,
+            self.actor_id.map(|id| id.bytes.as_bytes().as_ref()),
+        );
+        field(&mut ctx, Some(date.as_bytes()));
+        field(&mut ctx, Some(self.details.as_bytes()));
+        field(&mut ctx, Some(type_));
+        field(
+            &mut ctx,
+            self.object_id.map(|id| id.bytes.as_bytes().as_ref()),
+        );
+        field(&mut ctx, self.prev_hash);
+        ctx.finish().as_ref().to_vec()
+    }
+}
+
+/// Event as stored in the database including its chain information
+#[derive(Debug, Clone, Queryable)]
+struct ChainEvent {
+    id: BinaryWrapper<Id>,
+    actor_id: Option<BinaryWrapper<Id>>,
+    date: NaiveDateTime,
+    details: String,
+    type_: DbWrapper<EventTypes>,
+    object_id: Option<BinaryWrapper<Id>>,
+    seq: Option<i64>,
+    hash: Option<Vec<u8>>,
+    prev_hash: Option<Vec<u8>>,
+}
+
+type ChainSelectType = (
+    event::id,
+    event::actor_id,
+    event::date,
+    event::details,
+    event::type_,
+    event::object_id,
+    event::seq,
+    event::hash,
+    event::prev_hash,
+);
+
+impl ChainEvent {
+    fn keys() -> ChainSelectType {
+        (
+            event::id,
+            event::actor_id,
+            event::date,
+            event::details,
+            event::type_,
+            event::object_id,
+            event::seq,
+            event::hash,
+            event::prev_hash,
+        )
+    }
+
+    fn link<'a>(&'a self, seq: i64, prev_hash: Option<&'a [u8]>) -> ChainLink<'a> {
+        ChainLink {
+            seq,
+            id: &self.id,
+            actor_id: self.actor_id.as_ref().map(|v| &v.0),
+            date: &self.date,
+            details: &self.details,
+            type_: self.type_.0,
+            object_id: self.object_id.as_ref().map(|v| &v.0),
+            prev_hash,
+        }
+    }
+}
+
+fn sign(secret: &[u8], seq: i64, hash: &[u8]) -> Vec<u8> {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+    let mut ctx = hmac::Context::with_key(&key);
+    ctx.update(&seq.to_be_bytes());
+    ctx.update(hash);
+    ctx.sign().as_ref().to_vec()
+}
+
+fn verify_signature(secret: &[u8], seq: i64, hash: &[u8], signature: &[u8]) -> bool {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+    let mut msg = seq.to_be_bytes().to_vec();
+    msg.extend_from_slice(hash);
+    hmac::verify(&key, &msg, signature).is_ok()
+}
+
+impl<B, C> DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<BigInt>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<EventTypes>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    i64: FromSql<BigInt, B> + ToSql<BigInt, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
+{
+    /// Returns sequence number and hash of the last event in the chain
+    fn last_link(
+        conn: &DieselPooledConnection<C>,
+    ) -> DbResult<Option<(i64, Vec<u8>)>, Self> {
+        let query = event::dsl::event
+            .select((event::seq, event::hash))
+            .filter(event::seq.is_not_null())
+            .order(event::seq.desc());
+        let res: Option<(Option<i64>, Option<Vec<u8>>)> =
+            exec_opt!(query, *conn, first)?;
+        Ok(res.and_then(|(seq, hash)| Some((seq?, hash.unwrap_or_default()))))
+    }
+
+    /// Appends the event to the end of the chain. Concurrent appends are
+    /// detected by the unique sequence number and retried.
+    pub(crate) fn append_event(&self, object: &Event<'_>) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        self.append_event_with(&conn, object)
+    }
+
+    /// Appends the event using the given connection, so that it is stored
+    /// as part of a surrounding transaction. Every attempt runs in a
+    /// savepoint as a conflicting append would abort the transaction
+    pub(crate) fn append_event_with(
+        &self,
+        conn: &DieselPooledConnection<C>,
+        object: &Event<'_>,
+    ) -> DbResult<(), Self> {
+        let details = object.details.to_json();
+        let date = object.date.unwrap_or_else(|| {
+            NaiveDateTime::from_timestamp(Utc::now().timestamp(), 0)
+        });
+        let mut attempt = 1;
+        loop {
+            let last = Self::last_link(conn)?;
+            let (seq, prev_hash) = last.map_or((1, None), |(seq, hash)| {
+                (seq.saturating_add(1), Some(hash))
+            });
+            let hash = ChainLink {
+                seq,
+                id: &object.id,
+                actor_id: object.actor_id.as_deref(),
+                date: &date,
+                details: &details,
+                type_: object.type_,
+                object_id: object.object_id.as_deref(),
+                prev_hash: prev_hash.as_deref(),
+            }
+            .hash();
+            let query = insert_into(event::dsl::event).values((
+                event::id.eq(BinaryWrapper(&object.id)),
+                event::actor_id.eq(object.actor_id.as_ref().map(BinaryWrapper)),
+                event::date.eq(date),
+                event::details.eq(&details),
+                event::type_.eq(DbWrapper(object.type_)),
+                event::object_id.eq(object.object_id.as_ref().map(BinaryWrapper)),
+                event::seq.eq(seq),
+                event::hash.eq(&hash),
+                event::prev_hash.eq(prev_hash.as_ref()),
+            ));
+            match Self::transaction(conn, || exec_unique!(query, *conn, execute)) {
+                Err(DatabaseError::NonUnique) if attempt < CHAIN_ATTEMPTS => {
+                    attempt = attempt.saturating_add(1);
+                }
+                Err(err) => return Err(err),
+                Ok(_) => break,
+            }
+        }
+
+        // The event is stored at this point. Failing to queue its webhook
+        // deliveries or notifications must not be reported as a failure to
+        // store the event
+        let mut event = object.clone();
+        event.date = Some(date);
+        if let Err(err) =
+            Self::transaction(conn, || self.enqueue_webhooks(conn, &event))
+        {
+            log::error!(
+                "Unable to queue webhook deliveries for {}: {}",
+                event.id,
+                err
+            );
+        }
+        if let Err(err) =
+            Self::transaction(conn, || self.enqueue_notifications(conn, &event))
+        {
+            log::error!("Unable to queue notifications for {}: {}", event.id, err);
+        }
+        Ok(())
+    }
+
+    /// Adds all events which are not yet part of the chain to its end.
+    /// Used to chain events created before the chain was introduced.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    #[inline]
+    pub fn seal_events(&self) -> DbResult<u64, Self> {
+        let conn = self.get()?;
+        let mut sealed: u64 = 0;
+        loop {
+            let query = event::dsl::event
+                .select(ChainEvent::keys())
+                .filter(event::seq.is_null())
+                .order((event::date.asc(), event::id.asc()))
+                .limit(CHAIN_BATCH);
+            let events: Vec<ChainEvent> = exec!(query, conn, load)?;
+            if events.is_empty() {
+                return Ok(sealed);
+            }
+            for event in events {
+                let mut attempt = 1;
+                loop {
+                    let last = Self::last_link(&conn)?;
+                    let (seq, prev_hash) = last.map_or((1, None), |(seq, hash)| {
+                        (seq.saturating_add(1), Some(hash))
+                    });
+                    let hash = event.link(seq, prev_hash.as_deref()).hash();
+                    let query = update(event::dsl::event)
+                        .filter(event::id.eq(BinaryWrapper(&event.id.0)))
+                        .filter(event::seq.is_null())
+                        .set((
+                            event::seq.eq(seq),
+                            event::hash.eq(&hash),
+                            event::prev_hash.eq(prev_hash.as_ref()),
+                        ));
+                    match exec_unique!(query, conn, execute) {
+                        Err(DatabaseError::NonUnique)
+                            if attempt < CHAIN_ATTEMPTS =>
+                        {
+                            attempt = attempt.saturating_add(1);
+                        }
+                        res => {
+                            sealed = sealed
+                                .saturating_add(res?.try_into().unwrap_or_default());
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Walks the whole chain and reports the first broken link. Checkpoints
+    /// are verified using the given secret. Reads from the primary, as a
+    /// lagging replica would report a chain which is not broken as broken.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    #[inline]
+    pub fn verify_audit(&self, secret: &[u8]) -> DbResult<AuditReport, Self> {
+        let conn = self.get()?;
+        let mut report = AuditReport {
+            events: 0,
+            checkpoints: 0,
+            broken: None,
+        };
+
+        let query = event_checkpoint::dsl::event_checkpoint
+            .select((
+                event_checkpoint::seq,
+                event_checkpoint::hash,
+                event_checkpoint::signature,
+            ))
+            .order(event_checkpoint::seq.asc());
+        let checkpoints: Vec<(i64, Vec<u8>, Vec<u8>)> = exec!(query, conn, load)?;
+        let mut checkpoint_map = HashMap::with_capacity(checkpoints.len());
+        for (seq, hash, signature) in checkpoints {
+            if !verify_signature(secret, seq, &hash, &signature) {
+                report.broken = Some(AuditBreak::Signature(seq));
+                return Ok(report);
+            }
+            let _ = checkpoint_map.insert(seq, hash);
+        }
+
+        let mut expected: i64 = 1;
+        let mut prev_hash: Option<Vec<u8>> = None;
+        loop {
+            let query = event::dsl::event
+                .select(ChainEvent::keys())
+                .filter(event::seq.ge(expected))
+                .order(event::seq.asc())
+                .limit(CHAIN_BATCH);
+            let events: Vec<ChainEvent> = exec!(query, conn, load)?;
+            if events.is_empty() {
+                break;
+            }
+            for event in events {
+                let seq = event.seq.unwrap_or_default();
+                let checkpoint = checkpoint_map.remove(&seq);
+                let broken = if seq != expected {
+                    Some(AuditBreak::Sequence {
+                        expected,
+                        found: seq,
+                    })
+                } else if event.prev_hash != prev_hash {
+                    Some(AuditBreak::PrevHash(seq))
+                } else if event.hash.as_deref()
+                    != Some(event.link(seq, prev_hash.as_deref()).hash().as_slice())
+                {
+                    Some(AuditBreak::Hash(seq))
+                } else if checkpoint
+                    .as_ref()
+                    .map_or(false, |hash| Some(hash) != event.hash.as_ref())
+                {
+                    Some(AuditBreak::Checkpoint(seq))
+                } else {
+                    None
+                };
+                if broken.is_some() {
+                    report.broken = broken;
+                    return Ok(report);
+                }
+                if checkpoint.is_some() {
+                    report.checkpoints = report.checkpoints.saturating_add(1);
+                }
+                report.events = report.events.saturating_add(1);
+                expected = expected.saturating_add(1);
+                prev_hash = event.hash;
+            }
+        }
+
+        // Checkpoints which refer to events which no longer exist show
+        // that the end of the chain was removed
+        if let Some(seq) = checkpoint_map.keys().min() {
+            report.broken = Some(AuditBreak::Checkpoint(*seq));
+            return Ok(report);
+        }
+
+        let query = event::dsl::event
+            .select(event::id)
+            .filter(event::seq.is_null());
+        let unsealed: Option<BinaryWrapper<Id>> = exec_opt!(query, conn, first)?;
+        report.broken = unsealed.map(|id| AuditBreak::Unsealed(id.0));
+        Ok(report)
+    }
+
+    /// Signs the current end of the chain using the given secret. Returns
+    /// the sequence number of the signed event or `None` if there was
+    /// nothing new to sign.
+    ///
+    /// # Errors
+    /// Fails on connection or deserialisation errors.
+    #[inline]
+    pub fn create_checkpoint(&self, secret: &[u8]) -> DbResult<Option<i64>, Self> {
+        let conn = self.get()?;
+        let (seq, hash) = match Self::last_link(&conn)? {
+            Some(last) => last,
+            None => return Ok(None),
+        };
+        let query = event_checkpoint::dsl::event_checkpoint
+            .select(event_checkpoint::seq)
+            .order(event_checkpoint::seq.desc());
+        let last_checkpoint: Option<i64> = exec_opt!(query, conn, first)?;
+        if last_checkpoint == Some(seq) {
+            return Ok(None);
+        }
+        let signature = sign(secret, seq, &hash);
+        let query = insert_into(event_checkpoint::dsl::event_checkpoint).values((
+            event_checkpoint::id.eq(BinaryWrapper(self.generate_id()?)),
+            event_checkpoint::seq.eq(seq),
+            event_checkpoint::hash.eq(&hash),
+            event_checkpoint::signature.eq(&signature),
+        ));
+        let _ = exec!(query, conn, execute)?;
+        Ok(Some(seq))
+    }
+
+    /// Starts a background thread which signs the end of the chain
+    /// in the given interval.
+    #[inline]
+    pub fn spawn_checkpoint(
+        &self,
+        secret: [u8; 32],
+        interval: Duration,
+    ) -> JoinHandle<()> {
+        let db = self.clone();
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            match db.create_checkpoint(&secret) {
+                Ok(Some(seq)) => log::debug!("Signed audit checkpoint at #{}", seq),
+                Ok(None) => {}
+                Err(err) => log::error!("Unable to sign audit checkpoint: {}", err),
+            }
+        })
+    }
+}
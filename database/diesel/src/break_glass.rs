@@ -0,0 +1,255 @@
+use crate::{
+    error::DieselError,
+    exec, exec_opt,
+    migrate::Migrate,
+    schema::{access, break_glass, server_account},
+    BinaryWrapper, DbWrapper, DieselDB, DieselPooledConnection,
+};
+use core_common::{
+    chrono::{NaiveDateTime, Utc},
+    database::{BreakGlassStore, Database, DatabaseError, DbResult},
+    objects::{AccessValidity, BreakGlass, BreakGlassEntry},
+    types::{AuthorizationType, Id, KeyManagement, SyncStatusType},
+};
+use diesel::{
+    backend::{Backend, SupportsDefaultKeyword, UsesAnsiSavepointSyntax},
+    connection::AnsiTransactionManager,
+    deserialize::FromSql,
+    insert_into,
+    serialize::ToSql,
+    sql_types::{Bool, HasSqlType, Timestamp},
+    BoolExpressionMethods, Connection, ExpressionMethods, OptionalExtension,
+    QueryDsl, Queryable, RunQueryDsl,
+};
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Queryable)]
+struct InnerBreakGlass<'a> {
+    id: BinaryWrapper<Cow<'a, Id>>,
+    source_id: BinaryWrapper<Cow<'a, Id>>,
+    dest_id: BinaryWrapper<Cow<'a, Id>>,
+    reason: Cow<'a, str>,
+    start_date: NaiveDateTime,
+    valid_until: NaiveDateTime,
+    reviewed_by: Option<BinaryWrapper<Cow<'a, Id>>>,
+    review_date: Option<NaiveDateTime>,
+}
+
+impl<'a> Into<BreakGlass<'a>> for InnerBreakGlass<'a> {
+    fn into(self) -> BreakGlass<'a> {
+        BreakGlass {
+            id: self.id.0,
+            source_id: self.source_id.0,
+            dest_id: self.dest_id.0,
+            reason: self.reason,
+            start_date: self.start_date,
+            valid_until: self.valid_until,
+            reviewed_by: self.reviewed_by.map(|v| v.0),
+            review_date: self.review_date,
+        }
+    }
+}
+
+/// The validity of an existing grant
+type ValidityRow = (Option<NaiveDateTime>, Option<NaiveDateTime>);
+
+impl<B, C> DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<DbWrapper<KeyManagement>>
+        + HasSqlType<DbWrapper<AuthorizationType>>
+        + HasSqlType<DbWrapper<SyncStatusType>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    DbWrapper<KeyManagement>: Queryable<DbWrapper<KeyManagement>, B>,
+    DbWrapper<AuthorizationType>: Queryable<DbWrapper<AuthorizationType>, B>,
+    DbWrapper<SyncStatusType>: Queryable<DbWrapper<SyncStatusType>, B>,
+{
+    /// Adds the account and the uid of the user to every emergency access.
+    /// Accounts without a name are skipped
+    fn break_glass_entries(
+        conn: &DieselPooledConnection<C>,
+        rows: Vec<InnerBreakGlass<'static>>,
+    ) -> DbResult<Vec<BreakGlassEntry<'static>>, Self> {
+        if rows.is_empty() {
+            return Ok(Vec::new());
+        }
+        let dest_ids: Vec<BinaryWrapper<&Id>> =
+            rows.iter().map(|v| BinaryWrapper(&*v.dest_id.0)).collect();
+        let logins = Self::account_logins(conn, &dest_ids)?;
+        let source_ids: Vec<BinaryWrapper<&Id>> = rows
+            .iter()
+            .map(|v| BinaryWrapper(&*v.source_id.0))
+            .collect();
+        let uids = Self::user_uids(conn, &source_ids)?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let login = logins.get(&*row.dest_id.0)?.clone();
+                let requester = uids.get(&*row.source_id.0).cloned();
+                Some(BreakGlassEntry {
+                    break_glass: row.into(),
+                    requester: requester.map(Cow::Owned),
+                    login,
+                })
+            })
+            .collect())
+    }
+}
+
+impl<B, C> BreakGlassStore for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<KeyManagement>>
+        + HasSqlType<DbWrapper<AuthorizationType>>
+        + HasSqlType<DbWrapper<SyncStatusType>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<KeyManagement>: Queryable<DbWrapper<KeyManagement>, B>,
+    DbWrapper<AuthorizationType>: Queryable<DbWrapper<AuthorizationType>, B>,
+    DbWrapper<SyncStatusType>: Queryable<DbWrapper<SyncStatusType>, B>,
+{
+    #[inline]
+    fn create_break_glass(&self, emergency: &BreakGlass<'_>) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        let source_id = BinaryWrapper(&emergency.source_id);
+        let dest_id = BinaryWrapper(&emergency.dest_id);
+        let now = emergency.start_date;
+        // the emergency access, its grant and the resync are stored
+        // together, existing grants are left as they are
+        Self::transaction(&conn, || {
+            let query = access::dsl::access
+                .select((access::valid_from, access::valid_until))
+                .filter(access::source_id.eq(&source_id))
+                .filter(access::dest_id.eq(&dest_id));
+            let grants: Vec<ValidityRow> = exec!(query, conn, load)?;
+            let active = grants.into_iter().any(|(valid_from, valid_until)| {
+                AccessValidity {
+                    valid_from,
+                    valid_until,
+                }
+                .is_active(now)
+            });
+            if active {
+                return Err(DatabaseError::NonUnique);
+            }
+
+            let query = insert_into(break_glass::dsl::break_glass).values((
+                break_glass::id.eq(BinaryWrapper(&emergency.id)),
+                break_glass::source_id.eq(&source_id),
+                break_glass::dest_id.eq(&dest_id),
+                break_glass::reason.eq(&*emergency.reason),
+                break_glass::start_date.eq(emergency.start_date),
+                break_glass::valid_until.eq(emergency.valid_until),
+            ));
+            let _ = exec!(query, conn, execute)?;
+
+            let query = insert_into(access::dsl::access).values((
+                access::id.eq(BinaryWrapper(self.generate_id()?)),
+                access::source_id.eq(&source_id),
+                access::dest_id.eq(&dest_id),
+                access::grant_date.eq(now),
+                access::granted_by.eq(&source_id),
+                access::valid_from.eq(emergency.start_date),
+                access::valid_until.eq(emergency.valid_until),
+                access::validity_synced.eq(now),
+                access::break_glass_id.eq(BinaryWrapper(&emergency.id)),
+            ));
+            let _ = exec!(query, conn, execute)?;
+
+            let query = server_account::dsl::server_account
+                .find(&dest_id)
+                .select(server_account::server_id);
+            let server_id: Option<BinaryWrapper<Id>> =
+                exec_opt!(query, conn, first)?;
+            if let Some(server_id) = server_id {
+                self.queue_account_sync(&conn, &server_id, &emergency.dest_id)?;
+            }
+            Ok(())
+        })
+    }
+
+    #[inline]
+    fn had_access(&self, entity_id: &Id, account_id: &Id) -> DbResult<bool, Self> {
+        let ids: Vec<BinaryWrapper<Cow<'_, Id>>> = self
+            .fetch_permission_ids(Cow::Borrowed(entity_id))?
+            .into_iter()
+            .map(BinaryWrapper)
+            .collect();
+        let conn = self.get_read()?;
+        let query = access::dsl::access
+            .select(access::id)
+            .filter(access::source_id.eq_any(&ids))
+            .filter(access::dest_id.eq(BinaryWrapper(account_id)))
+            .filter(access::break_glass_id.is_null())
+            .filter(
+                access::valid_from
+                    .is_null()
+                    .or(access::valid_from.le(Utc::now().naive_utc())),
+            );
+        let grant: Option<BinaryWrapper<Id>> = exec_opt!(query, conn, first)?;
+        Ok(grant.is_some())
+    }
+
+    #[inline]
+    fn fetch_break_glass(
+        &self,
+        id: &Id,
+    ) -> DbResult<Option<BreakGlassEntry<'static>>, Self> {
+        let conn = self.get_read()?;
+        let query = break_glass::dsl::break_glass.find(BinaryWrapper(id));
+        let row: Option<InnerBreakGlass<'static>> = exec_opt!(query, conn, first)?;
+        let rows = row.into_iter().collect();
+        Ok(Self::break_glass_entries(&conn, rows)?.pop())
+    }
+
+    #[inline]
+    fn fetch_break_glass_report(
+        &self,
+        unreviewed: bool,
+    ) -> DbResult<Vec<BreakGlassEntry<'static>>, Self> {
+        let conn = self.get_read()?;
+        let mut query = break_glass::dsl::break_glass
+            .order(break_glass::start_date.desc())
+            .into_boxed::<B>();
+        if unreviewed {
+            query = query.filter(break_glass::review_date.is_null());
+        }
+        let rows: Vec<InnerBreakGlass<'static>> = exec!(query, conn, load)?;
+        Self::break_glass_entries(&conn, rows)
+    }
+
+    #[inline]
+    fn review_break_glass(
+        &self,
+        emergency: &BreakGlass<'_>,
+    ) -> DbResult<bool, Self> {
+        let conn = self.get()?;
+        let query = diesel::update(
+            break_glass::dsl::break_glass
+                .find(BinaryWrapper(&emergency.id))
+                .filter(break_glass::review_date.is_null()),
+        )
+        .set((
+            break_glass::reviewed_by
+                .eq(emergency.reviewed_by.as_ref().map(BinaryWrapper)),
+            break_glass::review_date.eq(emergency.review_date),
+        ));
+        Ok(exec!(query, conn, execute)? > 0)
+    }
+}
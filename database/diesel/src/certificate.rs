@@ -0,0 +1,322 @@
+use crate::{
+    error::DieselError,
+    exec, exec_opt, exec_unique,
+    migrate::Migrate,
+    schema::{
+        access, access_option, certificate_authority, host_certificate, server,
+        server_account,
+    },
+    BinaryWrapper, DbWrapper, DieselDB, UniqueExtension,
+};
+use core_common::{
+    chrono::{NaiveDateTime, Utc},
+    database::{CertificateStore, Database, DatabaseError, DbResult},
+    objects::{
+        principal_name, AuthorizedPrincipals, CertificateAuthority,
+        CertificatePrincipal, HostCertificate,
+    },
+    types::{AccessOption, CertificateType, Id},
+};
+use diesel::{
+    backend::{Backend, SupportsDefaultKeyword, UsesAnsiSavepointSyntax},
+    connection::AnsiTransactionManager,
+    deserialize::{FromSql, Queryable},
+    insert_into,
+    serialize::ToSql,
+    sql_types::{BigInt, Bool, HasSqlType, Timestamp},
+    BoolExpressionMethods, Connection, ExpressionMethods, JoinOnDsl,
+    OptionalExtension, QueryDsl, RunQueryDsl,
+};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
+
+#[derive(Debug, Clone, Queryable)]
+struct InnerCertificateAuthority<'a> {
+    id: BinaryWrapper<Cow<'a, Id>>,
+    type_: DbWrapper<CertificateType>,
+    public_key: Cow<'a, str>,
+    private_key: Vec<u8>,
+    created: NaiveDateTime,
+}
+
+impl<'a> Into<CertificateAuthority<'a>> for InnerCertificateAuthority<'a> {
+    fn into(self) -> CertificateAuthority<'a> {
+        CertificateAuthority {
+            id: self.id.0,
+            type_: self.type_.0,
+            public_key: self.public_key,
+            private_key: Cow::Owned(self.private_key),
+            created: Some(self.created),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Queryable)]
+struct InnerHostCertificate<'a> {
+    server_id: BinaryWrapper<Cow<'a, Id>>,
+    serial: i64,
+    certificate: Cow<'a, str>,
+    valid_after: NaiveDateTime,
+    valid_before: NaiveDateTime,
+}
+
+impl<'a> Into<HostCertificate<'a>> for InnerHostCertificate<'a> {
+    fn into(self) -> HostCertificate<'a> {
+        HostCertificate {
+            server_id: self.server_id.0,
+            serial: u64::from_be_bytes(self.serial.to_be_bytes()),
+            certificate: self.certificate,
+            valid_after: self.valid_after,
+            valid_before: self.valid_before,
+        }
+    }
+}
+
+type AccountRow = (BinaryWrapper<Id>, Option<String>, String);
+type PrincipalRow = (BinaryWrapper<Id>, Option<String>, String, bool);
+type GrantRow = (BinaryWrapper<Id>, BinaryWrapper<Id>, Option<NaiveDateTime>);
+type OptionRow = (BinaryWrapper<Id>, DbWrapper<AccessOption>, Option<String>);
+type GrantOptions = Vec<(AccessOption, Option<Cow<'static, str>>)>;
+
+impl<B, C> CertificateStore for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<Timestamp>
+        + HasSqlType<BigInt>
+        + HasSqlType<DbWrapper<AccessOption>>
+        + HasSqlType<DbWrapper<CertificateType>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    i64: FromSql<BigInt, B> + ToSql<BigInt, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<AccessOption>: Queryable<DbWrapper<AccessOption>, B>,
+    DbWrapper<CertificateType>: Queryable<DbWrapper<CertificateType>, B>,
+{
+    #[inline]
+    fn fetch_authority(
+        &self,
+        type_: CertificateType,
+    ) -> DbResult<Option<CertificateAuthority<'static>>, Self> {
+        // uses the primary as the authority is fetched again right after
+        // it was created
+        let conn = self.get()?;
+        let query = certificate_authority::dsl::certificate_authority
+            .filter(certificate_authority::type_.eq(DbWrapper(type_)));
+        let res: Option<InnerCertificateAuthority<'_>> =
+            exec_opt!(query, conn, first)?;
+        Ok(res.map(|v| v.into()))
+    }
+
+    #[inline]
+    fn create_authority(
+        &self,
+        authority: &CertificateAuthority<'_>,
+    ) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        let query = insert_into(certificate_authority::dsl::certificate_authority)
+            .values((
+                certificate_authority::id.eq(BinaryWrapper(&authority.id)),
+                certificate_authority::type_.eq(DbWrapper(authority.type_)),
+                certificate_authority::public_key.eq(&authority.public_key),
+                certificate_authority::private_key
+                    .eq(authority.private_key.as_ref()),
+            ));
+        let _ = exec_unique!(query, conn, execute)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn fetch_principals(
+        &self,
+        entity_id: &Id,
+    ) -> DbResult<Vec<CertificatePrincipal<'static>>, Self> {
+        let ids: Vec<BinaryWrapper<Cow<'_, Id>>> = self
+            .fetch_permission_ids(Cow::Borrowed(entity_id))?
+            .into_iter()
+            .map(BinaryWrapper)
+            .collect();
+        let conn = self.get_read()?;
+        let now = Utc::now().naive_utc();
+        let query = access::dsl::access
+            .select((access::id, access::dest_id, access::valid_until))
+            .filter(access::source_id.eq_any(&ids))
+            .filter(access::valid_from.is_null().or(access::valid_from.le(now)))
+            .filter(
+                access::valid_until
+                    .is_null()
+                    .or(access::valid_until.gt(now)),
+            );
+        let grants: Vec<GrantRow> = exec!(query, conn, load)?;
+        if grants.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let dest_ids: Vec<BinaryWrapper<&Id>> = grants
+            .iter()
+            .map(|(_, dest, _)| BinaryWrapper(&dest.0))
+            .collect();
+        let query = server_account::dsl::server_account
+            .inner_join(
+                server::dsl::server.on(server::id.eq(server_account::server_id)),
+            )
+            .select((
+                server_account::entity_id,
+                server_account::name,
+                server::hostname,
+                server::require_security_key,
+            ))
+            .filter(server_account::entity_id.eq_any(&dest_ids));
+        let accounts: Vec<PrincipalRow> = exec!(query, conn, load)?;
+        let names: HashMap<Id, (String, bool)> = accounts
+            .into_iter()
+            .filter_map(|(id, name, hostname, security_key)| {
+                name.map(|name| {
+                    (id.0, (principal_name(&name, &hostname), security_key))
+                })
+            })
+            .collect();
+
+        let access_ids: Vec<BinaryWrapper<&Id>> =
+            grants.iter().map(|(id, ..)| BinaryWrapper(&id.0)).collect();
+        let query = access_option::dsl::access_option
+            .select((
+                access_option::access_id,
+                access_option::option,
+                access_option::value,
+            ))
+            .filter(access_option::access_id.eq_any(&access_ids));
+        let rows: Vec<OptionRow> = exec!(query, conn, load)?;
+        let mut options: HashMap<Id, GrantOptions> = HashMap::new();
+        for (access_id, option, value) in rows {
+            options
+                .entry(access_id.0)
+                .or_default()
+                .push((option.0, value.map(Cow::Owned)));
+        }
+
+        Ok(grants
+            .into_iter()
+            .filter_map(|(id, dest, valid_until)| {
+                let (name, security_key) = names.get(&dest.0)?;
+                Some(CertificatePrincipal {
+                    name: Cow::Owned(name.clone()),
+                    options: options.remove(&id.0).unwrap_or_default(),
+                    require_security_key: *security_key,
+                    valid_until,
+                })
+            })
+            .collect())
+    }
+
+    #[inline]
+    fn fetch_authorized_principals(
+        &self,
+        server_id: &Id,
+    ) -> DbResult<Vec<AuthorizedPrincipals<'static>>, Self> {
+        let conn = self.get_read()?;
+        let query = server_account::dsl::server_account
+            .inner_join(
+                server::dsl::server.on(server::id.eq(server_account::server_id)),
+            )
+            .select((
+                server_account::entity_id,
+                server_account::name,
+                server::hostname,
+            ))
+            .filter(server_account::server_id.eq(BinaryWrapper(server_id)))
+            .order(server_account::name);
+        let accounts: Vec<AccountRow> = exec!(query, conn, load)?;
+
+        let account_ids: Vec<BinaryWrapper<&Id>> = accounts
+            .iter()
+            .map(|(id, _, _)| BinaryWrapper(&id.0))
+            .collect();
+        let now = Utc::now().naive_utc();
+        let query = access::dsl::access
+            .select(access::dest_id)
+            .filter(access::dest_id.eq_any(&account_ids))
+            .filter(access::valid_from.is_null().or(access::valid_from.le(now)))
+            .filter(
+                access::valid_until
+                    .is_null()
+                    .or(access::valid_until.gt(now)),
+            );
+        let granted: Vec<BinaryWrapper<Id>> = exec!(query, conn, load)?;
+        let granted: HashSet<Id> = granted.into_iter().map(|id| id.0).collect();
+
+        Ok(accounts
+            .into_iter()
+            .filter_map(|(id, name, hostname)| {
+                let name = name?;
+                let principals = if granted.contains(&id.0) {
+                    vec![Cow::Owned(principal_name(&name, &hostname))]
+                } else {
+                    Vec::new()
+                };
+                Some(AuthorizedPrincipals {
+                    account: Cow::Owned(name),
+                    principals,
+                })
+            })
+            .collect())
+    }
+
+    #[inline]
+    fn fetch_host_certificate(
+        &self,
+        server_id: &Id,
+    ) -> DbResult<Option<HostCertificate<'static>>, Self> {
+        let conn = self.get_read()?;
+        let query =
+            host_certificate::dsl::host_certificate.find(BinaryWrapper(server_id));
+        let res: Option<InnerHostCertificate<'_>> = exec_opt!(query, conn, first)?;
+        Ok(res.map(|v| v.into()))
+    }
+
+    #[inline]
+    fn save_host_certificate(
+        &self,
+        certificate: &HostCertificate<'_>,
+    ) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        let serial = i64::from_be_bytes(certificate.serial.to_be_bytes());
+        let values = (
+            host_certificate::serial.eq(serial),
+            host_certificate::certificate.eq(certificate.certificate.as_ref()),
+            host_certificate::valid_after.eq(certificate.valid_after),
+            host_certificate::valid_before.eq(certificate.valid_before),
+        );
+        let query = diesel::update(
+            host_certificate::dsl::host_certificate
+                .find(BinaryWrapper(&certificate.server_id)),
+        )
+        .set(values);
+        if exec!(query, conn, execute)? == 0 {
+            let query =
+                insert_into(host_certificate::dsl::host_certificate).values((
+                    host_certificate::server_id
+                        .eq(BinaryWrapper(&certificate.server_id)),
+                    values,
+                ));
+            let _ = exec!(query, conn, execute)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn save_host_key(&self, server_id: &Id, host_key: &str) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        let query =
+            diesel::update(server::dsl::server.find(BinaryWrapper(server_id)))
+                .set(server::host_key.eq(host_key));
+        let _ = exec!(query, conn, execute)?;
+        Ok(())
+    }
+}
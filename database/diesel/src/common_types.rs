@@ -1,7 +1,9 @@
 use crate::{DbFrom, DbName, DbTo, DieselError};
 use core_common::types::{
-    AccessOption, AuthorizationType, EntityTypes, EventTypes, FingerprintMd5,
-    FingerprintSha256, Id, KeyManagement, SyncStatusType, UserTypes,
+    AccessOption, AccessRequestStatus, AuthorizationType, CertificateType,
+    DeliveryStatus, EntityTypes, EventTypes, FingerprintMd5, FingerprintSha256, Id,
+    KeyManagement, RecertificationDecision, RecertificationKind, SyncStatusType,
+    UserTypes,
 };
 use std::borrow::Cow;
 
@@ -273,6 +275,7 @@ impl DbFrom for KeyManagement {
             b"none" => Ok(Self::None),
             b"keys" => Ok(Self::Keys),
             b"other" => Ok(Self::Other),
+            b"certificates" => Ok(Self::Certificates),
             _ => {
                 let data = String::from_utf8_lossy(v).into();
                 Err(DieselError::TransformationError(data))
@@ -288,6 +291,7 @@ impl DbTo for KeyManagement {
             Self::None => b"none",
             Self::Keys => b"keys",
             Self::Other => b"other",
+            Self::Certificates => b"certificates",
         }
     }
 }
@@ -299,6 +303,7 @@ impl DbTo for &KeyManagement {
             KeyManagement::None => b"none",
             KeyManagement::Keys => b"keys",
             KeyManagement::Other => b"other",
+            KeyManagement::Certificates => b"certificates",
         }
     }
 }
@@ -390,3 +395,184 @@ impl DbTo for &SyncStatusType {
         }
     }
 }
+
+impl DbName for DeliveryStatus {
+    #[inline]
+    fn db_type_name() -> &'static str {
+        "webhook_delivery_status"
+    }
+}
+
+impl DbFrom for DeliveryStatus {
+    #[inline]
+    fn convert(v: &'_ [u8]) -> Result<Self, DieselError> {
+        match v {
+            b"pending" => Ok(Self::Pending),
+            b"delivered" => Ok(Self::Delivered),
+            b"failed" => Ok(Self::Failed),
+            _ => {
+                let data = String::from_utf8_lossy(v).into();
+                Err(DieselError::TransformationError(data))
+            }
+        }
+    }
+}
+
+impl DbTo for DeliveryStatus {
+    #[inline]
+    fn convert_back(&self) -> &'_ [u8] {
+        match self {
+            Self::Pending => b"pending",
+            Self::Delivered => b"delivered",
+            Self::Failed => b"failed",
+        }
+    }
+}
+
+impl DbTo for &DeliveryStatus {
+    #[inline]
+    fn convert_back(&self) -> &'_ [u8] {
+        match *self {
+            DeliveryStatus::Pending => b"pending",
+            DeliveryStatus::Delivered => b"delivered",
+            DeliveryStatus::Failed => b"failed",
+        }
+    }
+}
+
+impl DbName for AccessRequestStatus {
+    #[inline]
+    fn db_type_name() -> &'static str {
+        "access_request_status"
+    }
+}
+
+impl DbFrom for AccessRequestStatus {
+    #[inline]
+    fn convert(v: &'_ [u8]) -> Result<Self, DieselError> {
+        match v {
+            b"pending" => Ok(Self::Pending),
+            b"approved" => Ok(Self::Approved),
+            b"rejected" => Ok(Self::Rejected),
+            _ => {
+                let data = String::from_utf8_lossy(v).into();
+                Err(DieselError::TransformationError(data))
+            }
+        }
+    }
+}
+
+impl DbTo for AccessRequestStatus {
+    #[inline]
+    fn convert_back(&self) -> &'_ [u8] {
+        match self {
+            Self::Pending => b"pending",
+            Self::Approved => b"approved",
+            Self::Rejected => b"rejected",
+        }
+    }
+}
+
+impl DbTo for &AccessRequestStatus {
+    #[inline]
+    fn convert_back(&self) -> &'_ [u8] {
+        match *self {
+            AccessRequestStatus::Pending => b"pending",
+            AccessRequestStatus::Approved => b"approved",
+            AccessRequestStatus::Rejected => b"rejected",
+        }
+    }
+}
+
+impl DbName for CertificateType {
+    #[inline]
+    fn db_type_name() -> &'static str {
+        "certificate_type"
+    }
+}
+
+impl DbFrom for CertificateType {
+    #[inline]
+    fn convert(v: &'_ [u8]) -> Result<Self, DieselError> {
+        match v {
+            b"user" => Ok(Self::User),
+            b"host" => Ok(Self::Host),
+            _ => {
+                let data = String::from_utf8_lossy(v).into();
+                Err(DieselError::TransformationError(data))
+            }
+        }
+    }
+}
+
+impl DbTo for CertificateType {
+    #[inline]
+    fn convert_back(&self) -> &'_ [u8] {
+        match self {
+            Self::User => b"user",
+            Self::Host => b"host",
+        }
+    }
+}
+
+impl DbName for RecertificationKind {
+    #[inline]
+    fn db_type_name() -> &'static str {
+        "recertification_kind"
+    }
+}
+
+impl DbFrom for RecertificationKind {
+    #[inline]
+    fn convert(v: &'_ [u8]) -> Result<Self, DieselError> {
+        match v {
+            b"access" => Ok(Self::Access),
+            b"membership" => Ok(Self::Membership),
+            _ => {
+                let data = String::from_utf8_lossy(v).into();
+                Err(DieselError::TransformationError(data))
+            }
+        }
+    }
+}
+
+impl DbTo for RecertificationKind {
+    #[inline]
+    fn convert_back(&self) -> &'_ [u8] {
+        match self {
+            Self::Access => b"access",
+            Self::Membership => b"membership",
+        }
+    }
+}
+
+impl DbName for RecertificationDecision {
+    #[inline]
+    fn db_type_name() -> &'static str {
+        "recertification_decision"
+    }
+}
+
+impl DbFrom for RecertificationDecision {
+    #[inline]
+    fn convert(v: &'_ [u8]) -> Result<Self, DieselError> {
+        match v {
+            b"keep" => Ok(Self::Keep),
+            b"revoke" => Ok(Self::Revoke),
+            _ => {
+                let data = String::from_utf8_lossy(v).into();
+                Err(DieselError::TransformationError(data))
+            }
+        }
+    }
+}
+
+impl DbTo for RecertificationDecision {
+    #[inline]
+    fn convert_back(&self) -> &'_ [u8] {
+        match self {
+            Self::Keep => b"keep",
+            Self::Revoke => b"revoke",
+        }
+    }
+}
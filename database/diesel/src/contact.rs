@@ -0,0 +1,161 @@
+use crate::{
+    error::DieselError,
+    exec, exec_opt,
+    migrate::Migrate,
+    schema::{access, group_member, server_account, server_admin, users},
+    BinaryWrapper, DieselDB, DieselPooledConnection,
+};
+use core_common::{
+    chrono::{NaiveDateTime, Utc},
+    database::{ContactStore, DatabaseError, DbResult},
+    objects::{AccessValidity, Contact},
+    types::{ContactRecipients, Id},
+};
+use diesel::{
+    backend::{Backend, UsesAnsiSavepointSyntax},
+    connection::AnsiTransactionManager,
+    deserialize::FromSql,
+    sql_types::{HasSqlType, Timestamp},
+    Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl,
+};
+use std::{borrow::Cow, collections::HashSet};
+
+/// Name of the account whose users are reached by `ContactRecipients::RootUsers`
+const ROOT_ACCOUNT: &str = "root";
+
+/// A user as selected from the database
+type ContactRow = (BinaryWrapper<Id>, String, Option<String>, Option<String>);
+
+/// A grant with its validity
+type GrantRow = (
+    BinaryWrapper<Id>,
+    Option<NaiveDateTime>,
+    Option<NaiveDateTime>,
+);
+
+fn into_contact((id, uid, name, email): ContactRow) -> Option<Contact<'static>> {
+    Some(Contact {
+        entity_id: Cow::Owned(id.0),
+        uid: Cow::Owned(uid),
+        name: name.map(Cow::Owned),
+        email: Cow::Owned(email.filter(|v| !v.is_empty())?),
+    })
+}
+
+impl<B, C> DieselDB<C>
+where
+    B: 'static + Backend<RawValue = [u8]> + UsesAnsiSavepointSyntax,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+{
+    /// Returns the given entities together with the members of the groups
+    /// among them, including members of nested groups
+    pub(crate) fn resolve_members(
+        conn: &DieselPooledConnection<C>,
+        mut sources: Vec<BinaryWrapper<Id>>,
+    ) -> DbResult<Vec<BinaryWrapper<Id>>, Self> {
+        let mut seen: HashSet<Id> = HashSet::new();
+        let mut ids = Vec::new();
+        while !sources.is_empty() {
+            sources.retain(|v| seen.insert(v.0.clone()));
+            ids.extend(sources.iter().cloned());
+            let query = group_member::dsl::group_member
+                .select(group_member::member_id)
+                .filter(group_member::group_id.eq_any(&sources));
+            sources = exec!(query, *conn, load)?;
+        }
+        Ok(ids)
+    }
+}
+
+impl<B, C> ContactStore for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Timestamp>,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    NaiveDateTime: FromSql<Timestamp, B>,
+{
+    #[inline]
+    fn fetch_contact(
+        &self,
+        entity_id: &Id,
+    ) -> DbResult<Option<Contact<'static>>, Self> {
+        let conn = self.get_read()?;
+        let query = users::dsl::users
+            .select((users::entity_id, users::uid, users::name, users::email))
+            .filter(users::entity_id.eq(BinaryWrapper(entity_id)));
+        let row: Option<ContactRow> = exec_opt!(query, conn, first)?;
+        Ok(row.and_then(into_contact))
+    }
+
+    #[inline]
+    fn fetch_server_contacts(
+        &self,
+        server_id: &Id,
+        recipients: ContactRecipients,
+    ) -> DbResult<Vec<Contact<'static>>, Self> {
+        let conn = self.get_read()?;
+        let server_id = BinaryWrapper(server_id);
+        let sources: Vec<BinaryWrapper<Id>> = match recipients {
+            ContactRecipients::Admins => {
+                let query = server_admin::dsl::server_admin
+                    .select(server_admin::entity_id)
+                    .filter(server_admin::server_id.eq(&server_id));
+                exec!(query, conn, load)?
+            }
+            ContactRecipients::RootUsers | ContactRecipients::Users => {
+                let query = server_account::dsl::server_account
+                    .select((server_account::entity_id, server_account::name))
+                    .filter(server_account::server_id.eq(&server_id));
+                let accounts: Vec<(BinaryWrapper<Id>, Option<String>)> =
+                    exec!(query, conn, load)?;
+                let accounts: Vec<BinaryWrapper<Id>> = accounts
+                    .into_iter()
+                    .filter(|(_, name)| {
+                        recipients == ContactRecipients::Users
+                            || name.as_deref() == Some(ROOT_ACCOUNT)
+                    })
+                    .map(|(id, _)| id)
+                    .collect();
+                let query = access::dsl::access
+                    .select((
+                        access::source_id,
+                        access::valid_from,
+                        access::valid_until,
+                    ))
+                    .filter(access::dest_id.eq_any(&accounts));
+                let grants: Vec<GrantRow> = exec!(query, conn, load)?;
+                let now = Utc::now().naive_utc();
+                grants
+                    .into_iter()
+                    .filter(|(_, valid_from, valid_until)| {
+                        AccessValidity {
+                            valid_from: *valid_from,
+                            valid_until: *valid_until,
+                        }
+                        .is_active(now)
+                    })
+                    .map(|(source_id, _, _)| source_id)
+                    .collect()
+            }
+        };
+
+        let ids = Self::resolve_members(&conn, sources)?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let query = users::dsl::users
+            .select((users::entity_id, users::uid, users::name, users::email))
+            .filter(users::entity_id.eq_any(&ids))
+            .filter(users::email.is_not_null())
+            .order(users::uid);
+        let rows: Vec<ContactRow> = exec!(query, conn, load)?;
+        Ok(rows.into_iter().filter_map(into_contact).collect())
+    }
+}
@@ -1,10 +1,10 @@
 use crate::{
     database::coalesce4,
     error::DieselError,
-    exec_opt,
+    exec, exec_opt,
     migrate::Migrate,
     schema::{entity, groups, server, server_account, users},
-    BinaryWrapper, DbWrapper, DieselDB,
+    BinaryWrapper, DbWrapper, DieselDB, DieselPooledConnection,
 };
 use core_common::{
     database::{DatabaseError, DbResult, FetchById},
@@ -73,6 +73,44 @@ impl<'a> Into<Entity<'a>> for InnerEntity<'a> {
     }
 }
 
+impl<B, C> DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + SupportsDefaultKeyword
+        + HasSqlType<DbWrapper<EntityTypes>>,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    DbWrapper<EntityTypes>: Queryable<DbWrapper<EntityTypes>, B>,
+{
+    /// Fetches the entities with the given ids together with their names.
+    /// Unknown ids are skipped
+    pub(crate) fn fetch_entities(
+        conn: &DieselPooledConnection<C>,
+        ids: &[BinaryWrapper<&Id>],
+    ) -> DbResult<Vec<Entity<'static>>, Self> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let query = entity::dsl::entity
+            .left_join(users::dsl::users.on(users::entity_id.eq(entity::id)))
+            .left_join(groups::dsl::groups.on(groups::entity_id.eq(entity::id)))
+            .left_join(
+                server_account::dsl::server_account
+                    .on(server_account::entity_id.eq(entity::id)),
+            )
+            .left_join(
+                server::dsl::server.on(server::id.eq(server_account::server_id)),
+            )
+            .select(InnerEntity::keys())
+            .filter(entity::id.eq_any(ids));
+        let res: Vec<InnerEntity<'static>> = exec!(query, *conn, load)?;
+        Ok(res.into_iter().map(Into::into).collect())
+    }
+}
+
 #[allow(clippy::type_repetition_in_bounds)]
 impl<'a, B, C, A> FetchById<'_, A, Entity<'a>, Self> for DieselDB<C>
 where
@@ -90,7 +128,7 @@ where
     #[inline]
     fn fetch(&self, id: &Id, _auth: &A) -> DbResult<Option<Entity<'a>>, Self> {
         let res: Option<InnerEntity<'a>>;
-        let conn = self.get()?;
+        let conn = self.get_read()?;
 
         let query = entity::dsl::entity
             .left_join(users::dsl::users.on(users::entity_id.eq(entity::id)))
@@ -1,13 +1,13 @@
 use crate::{
-    error::DieselError, exec, exec_opt, exec_unique, migrate::Migrate,
-    schema::event, BinaryWrapper, DbWrapper, DieselDB, UniqueExtension,
+    error::DieselError, exec, exec_opt, migrate::Migrate, schema::event,
+    BinaryWrapper, DbWrapper, DieselDB,
 };
 use core_common::{
     chrono::NaiveDateTime,
     database::{
         Create, DatabaseError, DbList, DbResult, FetchAll, FetchById, FetchFirst,
     },
-    objects::{Event, EventFilter},
+    objects::{Event, EventDetails, EventFilter},
     sec::Auth,
     types::{EventTypes, Id},
 };
@@ -17,12 +17,12 @@ use diesel::{
     deserialize::FromSql,
     dsl::count_star,
     expression::nullable::Nullable,
-    insert_into,
     query_builder::BoxedSelectStatement,
     serialize::ToSql,
-    sql_types::{Bool, HasSqlType, Timestamp},
-    Connection, ExpressionMethods, NullableExpressionMethods, OptionalExtension,
-    QueryDsl, Queryable, RunQueryDsl, TextExpressionMethods,
+    sql_types::{BigInt, Bool, HasSqlType, Timestamp},
+    BoolExpressionMethods, Connection, EscapeExpressionMethods, ExpressionMethods,
+    NullableExpressionMethods, OptionalExtension, QueryDsl, Queryable, RunQueryDsl,
+    TextExpressionMethods,
 };
 use std::borrow::Cow;
 
@@ -60,36 +60,101 @@ impl InnerEvent<'_> {
     fn filter<'a, B, T>(
         mut query: BoxedSelectStatement<'a, T, event::table, B>,
         filter: &'a EventFilter<'_>,
+        action: Option<&'a str>,
     ) -> BoxedSelectStatement<'a, T, event::table, B>
     where
         B: 'a
             + Backend<RawValue = [u8]>
             + UsesAnsiSavepointSyntax
-            + HasSqlType<Bool>,
+            + HasSqlType<Bool>
+            + HasSqlType<Timestamp>
+            + HasSqlType<DbWrapper<EventTypes>>,
         bool: ToSql<Bool, B>,
+        NaiveDateTime: ToSql<Timestamp, B>,
     {
         if let Some(ref v) = filter.actor_id {
             query = query.filter(event::actor_id.eq(BinaryWrapper(v)));
         }
         if let Some(ref details) = filter.details {
-            query = query.filter(event::details.like(details));
+            let pattern = Self::details_pattern(details);
+            query = query.filter(event::details.like(pattern).escape('\\'));
+        }
+        if let Some(action) = action {
+            query = query.filter(event::details.like(action));
         }
         if let Some(ref v) = filter.object_id {
             query = query.filter(event::object_id.eq(BinaryWrapper(v)));
         }
+        if let Some(v) = filter.type_ {
+            query = query.filter(event::type_.eq(DbWrapper(v)));
+        }
+        if let Some(v) = filter.date_ge {
+            query = query.filter(event::date.ge(v));
+        }
+        if let Some(v) = filter.date_le {
+            query = query.filter(event::date.le(v));
+        }
         query
     }
+
+    /// Restricts the query to events the given entity did or which were
+    /// made on the given entity
+    fn permission_filter<'a, B, T>(
+        query: BoxedSelectStatement<'a, T, event::table, B>,
+        id: &'a Id,
+    ) -> BoxedSelectStatement<'a, T, event::table, B>
+    where
+        B: 'a
+            + Backend<RawValue = [u8]>
+            + UsesAnsiSavepointSyntax
+            + HasSqlType<Bool>,
+        bool: ToSql<Bool, B>,
+    {
+        query.filter(
+            event::actor_id
+                .eq(BinaryWrapper(id))
+                .or(event::object_id.eq(BinaryWrapper(id))),
+        )
+    }
+
+    /// Converts the details filter into a like expression matching the
+    /// value anywhere inside the json details. Like wildcards and the
+    /// escape character in the value are matched literally
+    fn details_pattern(details: &str) -> String {
+        let mut escaped = String::with_capacity(details.len());
+        for c in details.chars() {
+            if matches!(c, '%' | '_' | '\\') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        format!("%{}%", escaped)
+    }
+
+    /// Converts the action filter into a like expression matching
+    /// the action key inside the json details. Only known action names
+    /// are used so the filter cannot contain like wildcards
+    fn action_pattern(filter: &EventFilter<'_>) -> Option<String> {
+        filter
+            .action
+            .as_deref()
+            .filter(|&action| EventDetails::ACTIONS.iter().any(|&v| v == action))
+            .map(|action| format!("%\"action\":\"{}\"%", action))
+    }
 }
 
 impl<'a> Into<Event<'a>> for InnerEvent<'a> {
     fn into(self) -> Event<'a> {
+        let details = EventDetails::parse(&self.details);
+        let severity = details.severity();
         Event {
             id: self.id.0,
             actor_id: self.actor_id.map(|v| v.0),
             date: self.date,
-            details: self.details,
+            details,
             type_: self.type_.0,
             object_id: self.object_id.map(|v| v.0),
+            severity,
         }
     }
 }
@@ -101,27 +166,28 @@ where
         + Backend<RawValue = [u8]>
         + UsesAnsiSavepointSyntax
         + HasSqlType<Bool>
+        + HasSqlType<Timestamp>
         + HasSqlType<DbWrapper<EventTypes>>
         + SupportsDefaultKeyword,
     C: 'static
         + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
         + Migrate,
     bool: ToSql<Bool, B>,
-    NaiveDateTime: FromSql<Timestamp, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
     DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
 {
     #[inline]
     fn fetch(&self, id: &Id, auth: &A) -> DbResult<Option<Event<'a>>, Self> {
-        if auth.is_admin() {
-            let conn = self.get()?;
-            let query = event::dsl::event
-                .select(InnerEvent::keys())
-                .find(BinaryWrapper(id));
-            let res: Option<InnerEvent<'_>> = exec_opt!(query, conn, first)?;
-            Ok(res.map(|v| v.into()))
-        } else {
-            Ok(None)
+        let conn = self.get_read()?;
+        let mut query = event::dsl::event
+            .select(InnerEvent::keys())
+            .filter(event::id.eq(BinaryWrapper(id)))
+            .into_boxed::<B>();
+        if !auth.is_admin() {
+            query = InnerEvent::permission_filter(query, auth.get_id());
         }
+        let res: Option<InnerEvent<'_>> = exec_opt!(query, conn, first)?;
+        Ok(res.map(|v| v.into()))
     }
 }
 
@@ -132,13 +198,14 @@ where
         + Backend<RawValue = [u8]>
         + UsesAnsiSavepointSyntax
         + HasSqlType<Bool>
+        + HasSqlType<Timestamp>
         + HasSqlType<DbWrapper<EventTypes>>
         + SupportsDefaultKeyword,
     C: 'static
         + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
         + Migrate,
     bool: ToSql<Bool, B>,
-    NaiveDateTime: FromSql<Timestamp, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
     DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
 {
     #[inline]
@@ -147,18 +214,18 @@ where
         filter: &EventFilter<'_>,
         auth: &A,
     ) -> DbResult<Option<Event<'a>>, Self> {
-        if auth.is_admin() {
-            let conn = self.get()?;
-            let query = event::dsl::event
-                .select(InnerEvent::keys())
-                .order_by(event::date.desc())
-                .into_boxed::<B>();
-            let query = InnerEvent::filter(query, filter);
-            let res: Option<InnerEvent<'_>> = exec_opt!(query, conn, first)?;
-            Ok(res.map(|v| v.into()))
-        } else {
-            Ok(None)
+        let conn = self.get_read()?;
+        let action = InnerEvent::action_pattern(filter);
+        let mut query = event::dsl::event
+            .select(InnerEvent::keys())
+            .order_by(event::date.desc())
+            .into_boxed::<B>();
+        if !auth.is_admin() {
+            query = InnerEvent::permission_filter(query, auth.get_id());
         }
+        let query = InnerEvent::filter(query, filter, action.as_deref());
+        let res: Option<InnerEvent<'_>> = exec_opt!(query, conn, first)?;
+        Ok(res.map(|v| v.into()))
     }
 }
 
@@ -170,13 +237,14 @@ where
         + Backend<RawValue = [u8]>
         + UsesAnsiSavepointSyntax
         + HasSqlType<Bool>
+        + HasSqlType<Timestamp>
         + HasSqlType<DbWrapper<EventTypes>>
         + SupportsDefaultKeyword,
     C: 'static
         + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
         + Migrate,
     bool: ToSql<Bool, B>,
-    NaiveDateTime: FromSql<Timestamp, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
     DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
 {
     #[inline]
@@ -186,40 +254,38 @@ where
         auth: &'b A,
         page: usize,
     ) -> DbResult<DbList<Event<'a>>, Self> {
-        if auth.is_admin() {
-            let res: Vec<InnerEvent<'_>>;
-            let conn = self.get()?;
+        let res: Vec<InnerEvent<'_>>;
+        let conn = self.get_read()?;
+        let action = InnerEvent::action_pattern(filter);
 
-            let offset = Self::compute_offset(page);
-            let count_query =
-                event::dsl::event.select(count_star()).into_boxed::<B>();
-            let count_query = InnerEvent::filter(count_query, filter);
-            let count = Self::compute_count(exec!(count_query, conn, first)?);
-            let page_max = Self::compute_page_max(count);
-
-            let query = event::dsl::event
-                .select(InnerEvent::keys())
-                .limit(25)
-                .offset(offset)
-                .order_by(event::date.desc())
-                .into_boxed::<B>();
-            let query = InnerEvent::filter(query, filter);
-            res = exec!(query, conn, load)?;
+        let offset = Self::compute_offset(page);
+        let mut count_query =
+            event::dsl::event.select(count_star()).into_boxed::<B>();
+        if !auth.is_admin() {
+            count_query = InnerEvent::permission_filter(count_query, auth.get_id());
+        }
+        let count_query = InnerEvent::filter(count_query, filter, action.as_deref());
+        let count = Self::compute_count(exec!(count_query, conn, first)?);
+        let page_max = Self::compute_page_max(count);
 
-            Ok(DbList {
-                data: res.into_iter().map(|v| v.into()).collect(),
-                count,
-                page,
-                page_max,
-            })
-        } else {
-            Ok(DbList {
-                data: vec![],
-                count: 0,
-                page: 0,
-                page_max: 0,
-            })
+        let mut query = event::dsl::event
+            .select(InnerEvent::keys())
+            .limit(25)
+            .offset(offset)
+            .order_by(event::date.desc())
+            .into_boxed::<B>();
+        if !auth.is_admin() {
+            query = InnerEvent::permission_filter(query, auth.get_id());
         }
+        let query = InnerEvent::filter(query, filter, action.as_deref());
+        res = exec!(query, conn, load)?;
+
+        Ok(DbList {
+            data: res.into_iter().map(|v| v.into()).collect(),
+            count,
+            page,
+            page_max,
+        })
     }
 }
 
@@ -230,21 +296,21 @@ where
         + Backend<RawValue = [u8]>
         + SupportsDefaultKeyword
         + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<BigInt>
+        + HasSqlType<Timestamp>
         + HasSqlType<DbWrapper<EventTypes>>,
     C: 'static
         + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
         + Migrate,
+    bool: ToSql<Bool, B>,
+    i64: FromSql<BigInt, B> + ToSql<BigInt, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
 {
+    /// Events are appended to the hash chain of the audit log
     #[inline]
     fn create(&self, object: &Event<'_>, _auth: &A) -> DbResult<(), Self> {
-        let conn = self.get()?;
-        let query = insert_into(event::dsl::event).values((
-            event::id.eq(BinaryWrapper(&object.id)),
-            event::actor_id.eq(object.actor_id.as_ref().map(BinaryWrapper)),
-            event::details.eq(&object.details),
-            event::type_.eq(DbWrapper(object.type_)),
-            event::object_id.eq(object.object_id.as_ref().map(BinaryWrapper)),
-        ));
-        exec_unique!(query, conn, execute).map(|_| ())
+        self.append_event(object)
     }
 }
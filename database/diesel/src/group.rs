@@ -85,7 +85,7 @@ where
     #[inline]
     fn fetch(&self, id: &Id, _auth: &A) -> DbResult<Option<Group<'a>>, Self> {
         let res: Option<InnerGroup<'a>>;
-        let conn = self.get()?;
+        let conn = self.get_read()?;
 
         let query = groups::dsl::groups.find(BinaryWrapper(id));
         res = exec_opt!(query, conn, first)?;
@@ -115,7 +115,7 @@ where
         page: usize,
     ) -> DbResult<DbList<Group<'a>>, Self> {
         let res: Vec<InnerGroup<'a>>;
-        let conn = self.get()?;
+        let conn = self.get_read()?;
 
         let offset = Self::compute_offset(page);
 
@@ -57,8 +57,15 @@ extern crate diesel;
 #[macro_use]
 extern crate diesel_migrations;
 
+mod access;
+mod access_request;
+mod api_token;
+mod audit;
 mod binary_wrapper;
+mod break_glass;
+mod certificate;
 mod common_types;
+mod contact;
 mod database;
 mod db_traits;
 mod db_wrapper;
@@ -68,14 +75,22 @@ mod event;
 mod group;
 mod macros;
 mod migrate;
+mod notification;
 mod public_key;
+mod recertification;
 mod schema;
 mod server;
+mod server_admin;
+mod server_note;
+mod server_settings;
+mod server_token;
 mod user;
+mod webhook;
 
 pub use crate::{
+    audit::{AuditBreak, AuditReport},
     binary_wrapper::BinaryWrapper,
-    database::{DieselDB, DieselPooledConnection, UniqueExtension},
+    database::{DieselDB, DieselPooledConnection, PoolConfig, UniqueExtension},
     db_traits::{DbFrom, DbName, DbTo},
     db_wrapper::DbWrapper,
     error::DieselError,
@@ -0,0 +1,387 @@
+use crate::{
+    error::DieselError,
+    exec, exec_opt, exec_unique,
+    migrate::Migrate,
+    schema::{
+        group_admin, notification_delivery, notification_preference, server,
+        server_account, server_admin, users,
+    },
+    BinaryWrapper, DbWrapper, DieselDB, DieselPooledConnection,
+};
+use core_common::{
+    chrono::{NaiveDateTime, Utc},
+    database::{DatabaseError, DbResult, NotificationQueue, NotificationStore},
+    notification::notification_type,
+    objects::{Event, EventDetails, NotificationDelivery, NotificationPreferences},
+    types::{DeliveryStatus, Id, NotificationType},
+};
+use diesel::{
+    backend::{Backend, SupportsDefaultKeyword, UsesAnsiSavepointSyntax},
+    connection::AnsiTransactionManager,
+    deserialize::FromSql,
+    insert_into,
+    serialize::ToSql,
+    sql_types::{Bool, HasSqlType, Timestamp},
+    update, Connection, ExpressionMethods, OptionalExtension, QueryDsl, Queryable,
+    RunQueryDsl,
+};
+use std::{borrow::Cow, collections::HashSet, convert::TryFrom};
+
+#[derive(Debug, Clone, Queryable)]
+struct InnerPreferences {
+    entity_id: BinaryWrapper<Id>,
+    key_add: bool,
+    access: bool,
+    sync_failure: bool,
+    key_expiring: bool,
+}
+
+impl Into<NotificationPreferences<'static>> for InnerPreferences {
+    fn into(self) -> NotificationPreferences<'static> {
+        NotificationPreferences {
+            entity_id: Cow::Owned(self.entity_id.0),
+            key_add: self.key_add,
+            access: self.access,
+            sync_failure: self.sync_failure,
+            key_expiring: self.key_expiring,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Queryable)]
+struct InnerNotificationDelivery {
+    id: BinaryWrapper<Id>,
+    entity_id: BinaryWrapper<Id>,
+    event_id: BinaryWrapper<Id>,
+    details: String,
+    target: Option<String>,
+    status: DbWrapper<DeliveryStatus>,
+    attempts: i32,
+    next_attempt: Option<NaiveDateTime>,
+    error: Option<String>,
+    date: NaiveDateTime,
+}
+
+impl Into<NotificationDelivery<'static>> for InnerNotificationDelivery {
+    fn into(self) -> NotificationDelivery<'static> {
+        NotificationDelivery {
+            id: Cow::Owned(self.id.0),
+            entity_id: Cow::Owned(self.entity_id.0),
+            event_id: Cow::Owned(self.event_id.0),
+            details: EventDetails::parse(&self.details),
+            target: self.target.map(Cow::Owned),
+            status: self.status.0,
+            attempts: self.attempts,
+            next_attempt: self.next_attempt,
+            error: self.error.map(Cow::Owned),
+            date: Some(self.date),
+        }
+    }
+}
+
+impl<B, C> DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+{
+    /// Queues a notification of the event for every user it concerns who
+    /// has an email and did not opt out. Does nothing unless notifications
+    /// are enabled
+    pub(crate) fn enqueue_notifications(
+        &self,
+        conn: &DieselPooledConnection<C>,
+        event: &Event<'_>,
+    ) -> DbResult<(), Self> {
+        if !self.notifications_enabled() {
+            return Ok(());
+        }
+        let type_ = match notification_type(&event.details) {
+            Some(type_) => type_,
+            None => return Ok(()),
+        };
+        #[allow(clippy::wildcard_enum_match_arm)]
+        let (sources, target) = match event.details {
+            EventDetails::AccessGrant {
+                ref source_id,
+                ref dest_id,
+            }
+            | EventDetails::AccessRevoke {
+                ref source_id,
+                ref dest_id,
+            } => {
+                let target = Self::fetch_target(conn, dest_id)?.map(|(_, v)| v);
+                (vec![BinaryWrapper(source_id.as_ref().clone())], target)
+            }
+            EventDetails::AccessRequestCreate { ref dest_id, .. } => {
+                Self::fetch_approvers(conn, dest_id)?
+            }
+            EventDetails::SyncResult { .. } => match event.object_id {
+                Some(ref object_id) => Self::fetch_admins(conn, object_id)?,
+                None => return Ok(()),
+            },
+            _ => match event.object_id {
+                Some(ref object_id) => {
+                    (vec![BinaryWrapper(object_id.as_ref().clone())], None)
+                }
+                None => return Ok(()),
+            },
+        };
+
+        let ids = Self::resolve_members(conn, sources)?;
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let query = users::dsl::users
+            .select(users::entity_id)
+            .filter(users::entity_id.eq_any(&ids))
+            .filter(users::email.is_not_null());
+        let recipients: Vec<BinaryWrapper<Id>> = exec!(query, *conn, load)?;
+        if recipients.is_empty() {
+            return Ok(());
+        }
+        let query = notification_preference::dsl::notification_preference
+            .select(notification_preference::entity_id)
+            .filter(notification_preference::entity_id.eq_any(&recipients))
+            .into_boxed::<B>();
+        let query = match type_ {
+            NotificationType::KeyAdd => {
+                query.filter(notification_preference::key_add.eq(false))
+            }
+            NotificationType::Access => {
+                query.filter(notification_preference::access.eq(false))
+            }
+            NotificationType::SyncFailure => {
+                query.filter(notification_preference::sync_failure.eq(false))
+            }
+            NotificationType::KeyExpiring => {
+                query.filter(notification_preference::key_expiring.eq(false))
+            }
+        };
+        let opted_out: Vec<BinaryWrapper<Id>> = exec!(query, *conn, load)?;
+        let opted_out: HashSet<Id> = opted_out.into_iter().map(|v| v.0).collect();
+
+        let details = event.details.to_json();
+        let mut values = Vec::with_capacity(recipients.len());
+        for entity_id in recipients {
+            if opted_out.contains(&entity_id.0) {
+                continue;
+            }
+            values.push((
+                notification_delivery::id.eq(BinaryWrapper(self.generate_id()?)),
+                notification_delivery::entity_id.eq(entity_id),
+                notification_delivery::event_id.eq(BinaryWrapper(&event.id)),
+                notification_delivery::details.eq(&details),
+                notification_delivery::target.eq(target.as_ref()),
+            ));
+        }
+        if values.is_empty() {
+            return Ok(());
+        }
+        let query = insert_into(notification_delivery::dsl::notification_delivery)
+            .values(values);
+        let _ = exec!(query, *conn, execute)?;
+        Ok(())
+    }
+
+    /// Fetches the administrators of the server the sync result refers to.
+    /// The event refers to either the server itself or one of its accounts
+    fn fetch_admins(
+        conn: &DieselPooledConnection<C>,
+        object_id: &Id,
+    ) -> DbResult<(Vec<BinaryWrapper<Id>>, Option<String>), Self> {
+        let (server_id, target) = match Self::fetch_target(conn, object_id)? {
+            Some(account) => account,
+            None => {
+                let query = server::dsl::server
+                    .select(server::hostname)
+                    .filter(server::id.eq(BinaryWrapper(object_id)));
+                let hostname: Option<String> = exec_opt!(query, *conn, first)?;
+                match hostname {
+                    Some(hostname) => (BinaryWrapper(object_id.clone()), hostname),
+                    None => return Ok((Vec::new(), None)),
+                }
+            }
+        };
+        let query = server_admin::dsl::server_admin
+            .select(server_admin::entity_id)
+            .filter(server_admin::server_id.eq(&server_id));
+        let admins = exec!(query, *conn, load)?;
+        Ok((admins, Some(target)))
+    }
+
+    /// Fetches the entities which decide requests for access to the
+    /// account: the administrators of its server and the administrators of
+    /// groups administering the server
+    fn fetch_approvers(
+        conn: &DieselPooledConnection<C>,
+        account_id: &Id,
+    ) -> DbResult<(Vec<BinaryWrapper<Id>>, Option<String>), Self> {
+        let (server_id, target) = match Self::fetch_target(conn, account_id)? {
+            Some(account) => account,
+            None => return Ok((Vec::new(), None)),
+        };
+        let query = server_admin::dsl::server_admin
+            .select(server_admin::entity_id)
+            .filter(server_admin::server_id.eq(&server_id));
+        let mut admins: Vec<BinaryWrapper<Id>> = exec!(query, *conn, load)?;
+        if !admins.is_empty() {
+            let query = group_admin::dsl::group_admin
+                .select(group_admin::admin_id)
+                .filter(group_admin::group_id.eq_any(&admins));
+            let group_admins: Vec<BinaryWrapper<Id>> = exec!(query, *conn, load)?;
+            admins.extend(group_admins);
+        }
+        Ok((admins, Some(target)))
+    }
+
+    /// Fetches the server of the account together with the name of the
+    /// account in the form `<account>@<hostname>`
+    fn fetch_target(
+        conn: &DieselPooledConnection<C>,
+        account_id: &Id,
+    ) -> DbResult<Option<(BinaryWrapper<Id>, String)>, Self> {
+        let query = server_account::dsl::server_account
+            .select((server_account::server_id, server_account::name))
+            .filter(server_account::entity_id.eq(BinaryWrapper(account_id)));
+        let account: Option<(BinaryWrapper<Id>, Option<String>)> =
+            exec_opt!(query, *conn, first)?;
+        let (server_id, name) = match account {
+            Some(account) => account,
+            None => return Ok(None),
+        };
+        let query = server::dsl::server
+            .select(server::hostname)
+            .filter(server::id.eq(&server_id));
+        let hostname: Option<String> = exec_opt!(query, *conn, first)?;
+        Ok(hostname.map(|hostname| {
+            let target = match name {
+                Some(name) => format!("{}@{}", name, hostname),
+                None => hostname,
+            };
+            (server_id, target)
+        }))
+    }
+}
+
+impl<B, C> NotificationStore for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B> + FromSql<Bool, B>,
+{
+    #[inline]
+    fn fetch_notification_preferences(
+        &self,
+        entity_id: &Id,
+    ) -> DbResult<NotificationPreferences<'static>, Self> {
+        let conn = self.get_read()?;
+        let query = notification_preference::dsl::notification_preference
+            .find(BinaryWrapper(entity_id));
+        let preferences: Option<InnerPreferences> = exec_opt!(query, conn, first)?;
+        Ok(preferences.map_or_else(
+            || NotificationPreferences::new(Cow::Owned(entity_id.clone())),
+            Into::into,
+        ))
+    }
+
+    #[inline]
+    fn save_notification_preferences(
+        &self,
+        preferences: &NotificationPreferences<'_>,
+    ) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        let entity_id = BinaryWrapper(preferences.entity_id.as_ref());
+        let query = update(
+            notification_preference::dsl::notification_preference.find(&entity_id),
+        )
+        .set((
+            notification_preference::key_add.eq(preferences.key_add),
+            notification_preference::access.eq(preferences.access),
+            notification_preference::sync_failure.eq(preferences.sync_failure),
+            notification_preference::key_expiring.eq(preferences.key_expiring),
+        ));
+        if exec!(query, conn, execute)? > 0 {
+            return Ok(());
+        }
+        let query =
+            insert_into(notification_preference::dsl::notification_preference)
+                .values((
+                    notification_preference::entity_id.eq(&entity_id),
+                    notification_preference::key_add.eq(preferences.key_add),
+                    notification_preference::access.eq(preferences.access),
+                    notification_preference::sync_failure
+                        .eq(preferences.sync_failure),
+                    notification_preference::key_expiring
+                        .eq(preferences.key_expiring),
+                ));
+        // a concurrent save inserted the preferences in the meantime
+        match exec_unique!(query, conn, execute) {
+            Err(DatabaseError::NonUnique) | Ok(_) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl<B, C> NotificationQueue for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<DeliveryStatus>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<DeliveryStatus>: Queryable<DbWrapper<DeliveryStatus>, B>,
+{
+    #[inline]
+    fn fetch_due_notifications(
+        &self,
+        limit: usize,
+    ) -> DbResult<Vec<NotificationDelivery<'static>>, Self> {
+        let conn = self.get()?;
+        let now = Utc::now().naive_utc();
+        let query = notification_delivery::dsl::notification_delivery
+            .filter(
+                notification_delivery::status.eq(DbWrapper(DeliveryStatus::Pending)),
+            )
+            .filter(notification_delivery::next_attempt.le(now))
+            .order_by(notification_delivery::next_attempt.asc())
+            .limit(i64::try_from(limit).unwrap_or(i64::MAX));
+        let deliveries: Vec<InnerNotificationDelivery> = exec!(query, conn, load)?;
+        Ok(deliveries.into_iter().map(Into::into).collect())
+    }
+
+    #[inline]
+    fn finish_notification(
+        &self,
+        delivery: &NotificationDelivery<'_>,
+    ) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        let query = update(notification_delivery::dsl::notification_delivery)
+            .filter(notification_delivery::id.eq(BinaryWrapper(&delivery.id)))
+            .set((
+                notification_delivery::status.eq(DbWrapper(delivery.status)),
+                notification_delivery::attempts.eq(delivery.attempts),
+                notification_delivery::next_attempt.eq(delivery.next_attempt),
+                notification_delivery::error.eq(&delivery.error),
+            ));
+        let _ = exec!(query, conn, execute)?;
+        Ok(())
+    }
+}
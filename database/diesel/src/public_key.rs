@@ -3,14 +3,13 @@ use crate::{
     schema::public_key, BinaryWrapper, DbWrapper, DieselDB, UniqueExtension,
 };
 use core_common::{
-    chrono::NaiveDateTime,
+    chrono::{NaiveDateTime, Utc},
     database::{
         Create, Database, DatabaseError, DbList, DbResult, Delete, FetchAll,
-        FetchAllFor, FetchById,
+        FetchAllFor, FetchById, KeyCount, KeyExpiry, KeyRetention, Purge, Restore,
     },
-    objects::{Event, PublicKey, PublicKeyFilter},
+    objects::{Event, EventDetails, PublicKey, PublicKeyFilter},
     sec::Auth,
-    serde_json::json,
     types::{EventTypes, FingerprintMd5, FingerprintSha256, Id},
 };
 use diesel::{
@@ -22,11 +21,23 @@ use diesel::{
     insert_into,
     query_builder::BoxedSelectStatement,
     serialize::ToSql,
-    sql_types::{Bool, HasSqlType, Timestamp},
-    Connection, ExpressionMethods, NullableExpressionMethods, OptionalExtension,
-    QueryDsl, RunQueryDsl, TextExpressionMethods,
+    sql_types::{BigInt, Bool, HasSqlType, Timestamp},
+    BoolExpressionMethods, Connection, ExpressionMethods, NullableExpressionMethods,
+    OptionalExtension, QueryDsl, Queryable, RunQueryDsl, TextExpressionMethods,
 };
-use std::{borrow::Cow, convert::AsRef};
+use std::{
+    borrow::Cow,
+    convert::{AsRef, TryFrom},
+};
+
+const SECURITY_KEY_PATTERN: &str = "sk-%";
+
+/// Id, owner and md5 fingerprint of a public key
+type KeyRow<'a> = (
+    BinaryWrapper<Id>,
+    BinaryWrapper<Id>,
+    Option<BinaryWrapper<FingerprintMd5<'a>>>,
+);
 
 #[derive(Debug, Clone, Queryable)]
 struct InnerPublicKey<'a> {
@@ -41,6 +52,9 @@ struct InnerPublicKey<'a> {
     randomart_md5: Option<Cow<'a, str>>,
     randomart_sha256: Option<Cow<'a, str>>,
     upload_date: Option<NaiveDateTime>,
+    application: Option<Cow<'a, str>>,
+    expires: Option<NaiveDateTime>,
+    deactivated: Option<NaiveDateTime>,
 }
 
 type SelectType = (
@@ -55,6 +69,9 @@ type SelectType = (
     public_key::randomart_md5,
     public_key::randomart_sha256,
     Nullable<public_key::upload_date>,
+    public_key::application,
+    public_key::expires,
+    public_key::deactivated,
 );
 
 impl InnerPublicKey<'_> {
@@ -71,6 +88,9 @@ impl InnerPublicKey<'_> {
             public_key::randomart_md5,
             public_key::randomart_sha256,
             public_key::upload_date.nullable(),
+            public_key::application,
+            public_key::expires,
+            public_key::deactivated,
         )
     }
 
@@ -78,13 +98,16 @@ impl InnerPublicKey<'_> {
         mut query: BoxedSelectStatement<'a, T, public_key::table, B>,
         filter: &'a PublicKeyFilter<'_>,
         entity_id: Option<&'a Id>,
+        deactivated: bool,
     ) -> BoxedSelectStatement<'a, T, public_key::table, B>
     where
         B: 'a
             + Backend<RawValue = [u8]>
             + UsesAnsiSavepointSyntax
-            + HasSqlType<Bool>,
+            + HasSqlType<Bool>
+            + HasSqlType<Timestamp>,
         bool: ToSql<Bool, B>,
+        NaiveDateTime: ToSql<Timestamp, B>,
     {
         if let Some(ref v) = filter.fingerprint_md5 {
             query = query.filter(public_key::fingerprint_md5.eq(BinaryWrapper(v)));
@@ -109,8 +132,32 @@ impl InnerPublicKey<'_> {
         if let Some(ref keysize_le) = filter.keysize_le {
             query = query.filter(public_key::keysize.le(keysize_le));
         }
+        match filter.security_key {
+            Some(true) => {
+                query = query.filter(public_key::type_.like(SECURITY_KEY_PATTERN));
+            }
+            Some(false) => {
+                query =
+                    query.filter(public_key::type_.not_like(SECURITY_KEY_PATTERN));
+            }
+            None => {}
+        }
+        if let Some(ref expires_before) = filter.expires_before {
+            query = query.filter(public_key::expires.lt(expires_before));
+        }
+        if let Some(ref expires_after) = filter.expires_after {
+            query = query.filter(public_key::expires.gt(expires_after));
+        }
+        if !filter.expired {
+            let now = Utc::now().naive_utc();
+            query = query.filter(
+                public_key::expires
+                    .is_null()
+                    .or(public_key::expires.gt(now)),
+            );
+        }
 
-        query = query.filter(public_key::active.eq(true));
+        query = query.filter(public_key::active.eq(!deactivated));
         query
     }
 }
@@ -129,6 +176,9 @@ impl<'a> Into<PublicKey<'a>> for InnerPublicKey<'a> {
             randomart_md5: self.randomart_md5,
             randomart_sha256: self.randomart_sha256,
             upload_date: self.upload_date,
+            application: self.application,
+            expires: self.expires,
+            deactivated: self.deactivated,
         }
     }
 }
@@ -150,7 +200,7 @@ where
     #[inline]
     fn fetch(&self, id: &Id, auth: &A) -> DbResult<Option<PublicKey<'a>>, Self> {
         let res: Option<InnerPublicKey<'_>>;
-        let conn = self.get()?;
+        let conn = self.get_read()?;
 
         let query = public_key::dsl::public_key
             .select(InnerPublicKey::keys())
@@ -175,12 +225,13 @@ where
         + Backend<RawValue = [u8]>
         + UsesAnsiSavepointSyntax
         + HasSqlType<Bool>
+        + HasSqlType<Timestamp>
         + SupportsDefaultKeyword,
     C: 'static
         + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
         + Migrate,
     bool: ToSql<Bool, B>,
-    NaiveDateTime: FromSql<Timestamp, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
 {
     #[inline]
     fn fetch_all(
@@ -190,7 +241,7 @@ where
         page: usize,
     ) -> DbResult<DbList<PublicKey<'b>>, Self> {
         let res: Vec<InnerPublicKey<'b>>;
-        let conn = self.get()?;
+        let conn = self.get_read()?;
 
         let offset = Self::compute_offset(page);
 
@@ -199,11 +250,13 @@ where
         } else {
             Some(auth.get_id())
         };
+        let deactivated = filter.deactivated && auth.is_admin();
 
         let count_query = public_key::dsl::public_key
             .select(count_star())
             .into_boxed::<B>();
-        let count_query = InnerPublicKey::filter(count_query, filter, entity_id);
+        let count_query =
+            InnerPublicKey::filter(count_query, filter, entity_id, deactivated);
         let count = Self::compute_count(exec!(count_query, conn, first)?);
         let page_max = Self::compute_page_max(count);
 
@@ -212,7 +265,7 @@ where
             .limit(25)
             .offset(offset)
             .into_boxed::<B>();
-        let query = InnerPublicKey::filter(query, filter, entity_id);
+        let query = InnerPublicKey::filter(query, filter, entity_id, deactivated);
         res = exec!(query, conn, load)?;
 
         Ok(DbList {
@@ -232,12 +285,13 @@ where
         + Backend<RawValue = [u8]>
         + UsesAnsiSavepointSyntax
         + HasSqlType<Bool>
+        + HasSqlType<Timestamp>
         + SupportsDefaultKeyword,
     C: 'static
         + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
         + Migrate,
     bool: ToSql<Bool, B>,
-    NaiveDateTime: FromSql<Timestamp, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
 {
     #[inline]
     fn fetch_all_for(
@@ -247,7 +301,7 @@ where
         page: usize,
     ) -> DbResult<DbList<PublicKey<'a>>, Self> {
         let res: Vec<InnerPublicKey<'a>>;
-        let conn = self.get()?;
+        let conn = self.get_read()?;
         let entity_id = Some(auth.get_id());
 
         let offset = Self::compute_offset(page);
@@ -255,7 +309,8 @@ where
         let count_query = public_key::dsl::public_key
             .select(count_star())
             .into_boxed::<B>();
-        let count_query = InnerPublicKey::filter(count_query, filter, entity_id);
+        let count_query =
+            InnerPublicKey::filter(count_query, filter, entity_id, false);
         let count = Self::compute_count(exec!(count_query, conn, first)?);
         let page_max = Self::compute_page_max(count);
 
@@ -264,7 +319,7 @@ where
             .limit(25)
             .offset(offset)
             .into_boxed::<B>();
-        let query = InnerPublicKey::filter(query, filter, entity_id);
+        let query = InnerPublicKey::filter(query, filter, entity_id, false);
         res = exec!(query, conn, load)?;
 
         Ok(DbList {
@@ -284,10 +339,17 @@ where
         + Backend<RawValue = [u8]>
         + SupportsDefaultKeyword
         + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<BigInt>
+        + HasSqlType<Timestamp>
         + HasSqlType<DbWrapper<EventTypes>>,
     C: 'static
         + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
         + Migrate,
+    bool: ToSql<Bool, B>,
+    i64: FromSql<BigInt, B> + ToSql<BigInt, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
 {
     #[inline]
     fn create(&self, object: &PublicKey<'a>, auth: &A) -> DbResult<(), Self> {
@@ -305,25 +367,27 @@ where
                 .eq(object.fingerprint_sha256.as_ref().map(BinaryWrapper)),
             public_key::randomart_md5.eq(&object.randomart_md5),
             public_key::randomart_sha256.eq(&object.randomart_sha256),
+            public_key::application.eq(&object.application),
+            public_key::expires.eq(&object.expires),
         ));
         let res = exec_unique!(query, conn, execute).map(|_| ());
+        if let DbResult::Err(DatabaseError::NonUnique) = res {
+            self.record_collision(object, auth)?;
+        }
         if let DbResult::Ok(_) = res {
-            let details = Cow::Owned(
-                json!({
-                    "action": "Pubkey add",
-                    "value": object.fingerprint_md5,
-                    "id": &object.id
-                })
-                .to_string(),
-            );
-            let event: Event<'_> = Event {
-                id: Cow::Owned(self.generate_id()?),
-                actor_id: Some(Cow::Borrowed(auth.get_id())),
-                date: None,
-                details,
-                type_: EventTypes::Entity,
-                object_id: Some(Cow::Borrowed(&object.entity_id)),
+            let details = EventDetails::KeyAdd {
+                key_id: Cow::Borrowed(&object.id),
+                fingerprint: object
+                    .fingerprint_md5
+                    .as_ref()
+                    .map(|v| Cow::Owned(v.to_str().into_owned())),
             };
+            let event = Event::new(
+                Cow::Owned(self.generate_id()?),
+                Some(Cow::Borrowed(auth.get_id())),
+                details,
+                Some(Cow::Borrowed(&object.entity_id)),
+            );
             self.create(&event, auth)?;
         }
         res
@@ -338,27 +402,392 @@ where
         + Backend
         + UsesAnsiSavepointSyntax
         + HasSqlType<Bool>
+        + HasSqlType<BigInt>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<EventTypes>>
         + SupportsDefaultKeyword,
     C: 'static
         + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
         + Migrate,
     bool: ToSql<Bool, B>,
+    i64: FromSql<BigInt, B> + ToSql<BigInt, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
 {
     #[inline]
     fn delete(&self, ids: &[Id], auth: &A) -> DbResult<(), Self> {
         let conn = self.get()?;
         let ids: Vec<BinaryWrapper<&Id>> = ids.iter().map(BinaryWrapper).collect();
 
+        let mut select = public_key::dsl::public_key
+            .select((
+                public_key::id,
+                public_key::entity_id,
+                public_key::fingerprint_md5,
+            ))
+            .filter(public_key::id.eq_any(&ids))
+            .filter(public_key::active.eq(true))
+            .into_boxed::<B>();
         let mut query = diesel::update(public_key::dsl::public_key)
-            .set(public_key::active.eq(false))
+            .set((
+                public_key::active.eq(false),
+                public_key::deactivated.eq(Utc::now().naive_utc()),
+            ))
             .filter(public_key::id.eq_any(&ids))
             .into_boxed::<B>();
 
         if !auth.is_admin() {
+            select = select
+                .filter(public_key::entity_id.eq(BinaryWrapper(auth.get_id())));
             query =
                 query.filter(public_key::entity_id.eq(BinaryWrapper(auth.get_id())));
         }
+        let keys: Vec<KeyRow<'_>> = exec!(select, conn, load)?;
         let _ = exec!(query, conn, execute)?;
+
+        for (key_id, entity_id, fingerprint) in keys {
+            let details = EventDetails::KeyRemove {
+                key_id: Cow::Owned(key_id.0),
+                fingerprint: fingerprint
+                    .map(|v| Cow::Owned(v.0.to_str().into_owned())),
+            };
+            let event = Event::new(
+                Cow::Owned(self.generate_id()?),
+                Some(Cow::Borrowed(auth.get_id())),
+                details,
+                Some(Cow::Owned(entity_id.0)),
+            );
+            self.create(&event, auth)?;
+        }
+        Ok(())
+    }
+}
+
+impl<A, B, C> Restore<A, PublicKey<'_>, Self> for DieselDB<C>
+where
+    A: Auth,
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<BigInt>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<EventTypes>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    i64: FromSql<BigInt, B> + ToSql<BigInt, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
+{
+    #[inline]
+    fn restore(&self, ids: &[Id], auth: &A) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        let ids: Vec<BinaryWrapper<&Id>> = ids.iter().map(BinaryWrapper).collect();
+
+        let mut select = public_key::dsl::public_key
+            .select((
+                public_key::id,
+                public_key::entity_id,
+                public_key::fingerprint_md5,
+            ))
+            .filter(public_key::id.eq_any(&ids))
+            .filter(public_key::active.eq(false))
+            .into_boxed::<B>();
+        let mut query = diesel::update(public_key::dsl::public_key)
+            .set((
+                public_key::active.eq(true),
+                public_key::deactivated.eq(None::<NaiveDateTime>),
+            ))
+            .filter(public_key::id.eq_any(&ids))
+            .into_boxed::<B>();
+
+        if !auth.is_admin() {
+            select = select
+                .filter(public_key::entity_id.eq(BinaryWrapper(auth.get_id())));
+            query =
+                query.filter(public_key::entity_id.eq(BinaryWrapper(auth.get_id())));
+        }
+        let keys: Vec<KeyRow<'_>> = exec!(select, conn, load)?;
+        let _ = exec!(query, conn, execute)?;
+
+        for (key_id, entity_id, fingerprint) in keys {
+            let details = EventDetails::KeyRestore {
+                key_id: Cow::Owned(key_id.0),
+                fingerprint: fingerprint
+                    .map(|v| Cow::Owned(v.0.to_str().into_owned())),
+            };
+            let event = Event::new(
+                Cow::Owned(self.generate_id()?),
+                Some(Cow::Borrowed(auth.get_id())),
+                details,
+                Some(Cow::Owned(entity_id.0)),
+            );
+            self.create(&event, auth)?;
+        }
+        Ok(())
+    }
+}
+
+impl<A, B, C> Purge<A, PublicKey<'_>, Self> for DieselDB<C>
+where
+    A: Auth,
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<BigInt>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<EventTypes>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    i64: FromSql<BigInt, B> + ToSql<BigInt, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
+{
+    #[inline]
+    fn purge(&self, ids: &[Id], auth: &A) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        let ids: Vec<BinaryWrapper<&Id>> = ids.iter().map(BinaryWrapper).collect();
+
+        let mut select = public_key::dsl::public_key
+            .select((
+                public_key::id,
+                public_key::entity_id,
+                public_key::fingerprint_md5,
+            ))
+            .filter(public_key::id.eq_any(&ids))
+            .filter(public_key::active.eq(false))
+            .into_boxed::<B>();
+        if !auth.is_admin() {
+            select = select
+                .filter(public_key::entity_id.eq(BinaryWrapper(auth.get_id())));
+        }
+        let keys: Vec<KeyRow<'_>> = exec!(select, conn, load)?;
+        let _ = self.purge_keys(keys, Some(auth.get_id()))?;
+        Ok(())
+    }
+}
+
+impl<B, C> KeyCount for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<BigInt>,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    i64: FromSql<BigInt, B>,
+{
+    #[inline]
+    fn count_keys(&self, entity_id: &Id) -> DbResult<usize, Self> {
+        let conn = self.get()?;
+        let query = public_key::dsl::public_key
+            .select(count_star())
+            .filter(public_key::entity_id.eq(BinaryWrapper(entity_id)))
+            .filter(public_key::active.eq(true));
+        Ok(Self::compute_count(exec!(query, conn, first)?))
+    }
+}
+
+impl<B, C> KeyRetention for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<BigInt>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<EventTypes>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    i64: FromSql<BigInt, B> + ToSql<BigInt, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
+{
+    #[inline]
+    fn purge_deactivated(
+        &self,
+        before: NaiveDateTime,
+        limit: usize,
+    ) -> DbResult<usize, Self> {
+        let conn = self.get()?;
+        let select = public_key::dsl::public_key
+            .select((
+                public_key::id,
+                public_key::entity_id,
+                public_key::fingerprint_md5,
+            ))
+            .filter(public_key::active.eq(false))
+            .filter(public_key::deactivated.lt(before))
+            .limit(i64::try_from(limit).unwrap_or(i64::MAX));
+        let keys: Vec<KeyRow<'_>> = exec!(select, conn, load)?;
+        self.purge_keys(keys, None)
+    }
+}
+
+impl<B, C> DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<BigInt>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<EventTypes>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    i64: FromSql<BigInt, B> + ToSql<BigInt, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
+{
+    /// Records a `KeyCollision` event if the fingerprint of the key is
+    /// already used by a key of another entity, including deleted keys
+    fn record_collision<A: Auth>(
+        &self,
+        object: &PublicKey<'_>,
+        auth: &A,
+    ) -> DbResult<(), Self> {
+        let fingerprint = match object.fingerprint_sha256 {
+            Some(ref fingerprint) => fingerprint,
+            None => return Ok(()),
+        };
+        let conn = self.get()?;
+        let query = public_key::dsl::public_key
+            .select(public_key::entity_id)
+            .filter(public_key::fingerprint_sha256.eq(BinaryWrapper(fingerprint)))
+            .filter(public_key::entity_id.ne(BinaryWrapper(&object.entity_id)));
+        let owner: Option<BinaryWrapper<Id>> = exec_opt!(query, conn, first)?;
+        if let Some(owner) = owner {
+            let details = EventDetails::KeyCollision {
+                fingerprint: Cow::Owned(fingerprint.to_str().into_owned()),
+                owner_id: Cow::Owned(owner.0),
+            };
+            let event = Event::new(
+                Cow::Owned(self.generate_id()?),
+                Some(Cow::Borrowed(auth.get_id())),
+                details,
+                Some(Cow::Borrowed(&object.entity_id)),
+            );
+            self.append_event(&event)?;
+        }
         Ok(())
     }
+
+    /// Permanently removes the given deleted keys and records a
+    /// `KeyPurge` event for each of them
+    fn purge_keys(
+        &self,
+        keys: Vec<KeyRow<'_>>,
+        actor_id: Option<&Id>,
+    ) -> DbResult<usize, Self> {
+        if keys.is_empty() {
+            return Ok(0);
+        }
+        let conn = self.get()?;
+        let ids: Vec<BinaryWrapper<&Id>> =
+            keys.iter().map(|(id, _, _)| BinaryWrapper(&id.0)).collect();
+        let query = diesel::delete(public_key::dsl::public_key)
+            .filter(public_key::id.eq_any(&ids))
+            .filter(public_key::active.eq(false));
+        let count = exec!(query, conn, execute)?;
+
+        for (key_id, entity_id, fingerprint) in keys {
+            let details = EventDetails::KeyPurge {
+                key_id: Cow::Owned(key_id.0),
+                fingerprint: fingerprint
+                    .map(|v| Cow::Owned(v.0.to_str().into_owned())),
+            };
+            let event = Event::new(
+                Cow::Owned(self.generate_id()?),
+                actor_id.map(Cow::Borrowed),
+                details,
+                Some(Cow::Owned(entity_id.0)),
+            );
+            self.append_event(&event)?;
+        }
+        Ok(count)
+    }
+}
+
+impl<B, C> KeyExpiry for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<BigInt>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<EventTypes>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    i64: FromSql<BigInt, B> + ToSql<BigInt, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
+{
+    #[inline]
+    fn fetch_expiring(
+        &self,
+        before: NaiveDateTime,
+        limit: usize,
+    ) -> DbResult<Vec<PublicKey<'static>>, Self> {
+        let conn = self.get()?;
+        let query = public_key::dsl::public_key
+            .select(InnerPublicKey::keys())
+            .filter(public_key::active.eq(true))
+            .filter(public_key::expires.lt(before))
+            .filter(public_key::expiry_reminder.is_null())
+            .order_by(public_key::expires.asc())
+            .limit(i64::try_from(limit).unwrap_or(i64::MAX));
+        let res: Vec<InnerPublicKey<'static>> = exec!(query, conn, load)?;
+        Ok(res.into_iter().map(|v| v.into()).collect())
+    }
+
+    #[inline]
+    fn remind_expiring(&self, key: &PublicKey<'_>) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        let expires = match key.expires {
+            Some(expires) => expires,
+            None => return Ok(()),
+        };
+        let query = diesel::update(public_key::dsl::public_key)
+            .filter(public_key::id.eq(BinaryWrapper(&key.id)))
+            .filter(public_key::expiry_reminder.is_null())
+            .set(public_key::expiry_reminder.eq(Utc::now().naive_utc()));
+        if exec!(query, conn, execute)? == 0 {
+            return Ok(());
+        }
+        let details = EventDetails::KeyExpiring {
+            key_id: Cow::Borrowed(&key.id),
+            fingerprint: key
+                .fingerprint_md5
+                .as_ref()
+                .map(|v| Cow::Owned(v.to_str().into_owned())),
+            expires,
+        };
+        let event = Event::new(
+            Cow::Owned(self.generate_id()?),
+            None,
+            details,
+            Some(Cow::Borrowed(&key.entity_id)),
+        );
+        self.append_event(&event)
+    }
 }
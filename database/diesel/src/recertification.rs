@@ -0,0 +1,568 @@
+use crate::{
+    error::DieselError,
+    exec, exec_opt,
+    migrate::Migrate,
+    schema::{
+        access, group_admin, group_member, groups, recertification,
+        recertification_item, server, server_account, server_admin,
+    },
+    BinaryWrapper, DbWrapper, DieselDB, DieselPooledConnection,
+};
+use core_common::{
+    chrono::{NaiveDateTime, Utc},
+    database::{Database, DatabaseError, DbResult, RecertificationStore},
+    objects::{
+        Recertification, RecertificationEntry, RecertificationItem,
+        RecertificationProgress,
+    },
+    types::{
+        AuthorizationType, Id, KeyManagement, RecertificationDecision,
+        RecertificationKind, SyncStatusType,
+    },
+};
+use diesel::{
+    backend::{Backend, SupportsDefaultKeyword, UsesAnsiSavepointSyntax},
+    connection::AnsiTransactionManager,
+    deserialize::FromSql,
+    insert_into,
+    serialize::ToSql,
+    sql_types::{Bool, HasSqlType, Timestamp},
+    BoolExpressionMethods, Connection, ExpressionMethods, JoinOnDsl,
+    OptionalExtension, QueryDsl, Queryable, RunQueryDsl,
+};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+};
+
+#[derive(Debug, Clone, Queryable)]
+struct InnerRecertification<'a> {
+    id: BinaryWrapper<Cow<'a, Id>>,
+    name: Cow<'a, str>,
+    started_by: Option<BinaryWrapper<Cow<'a, Id>>>,
+    start_date: NaiveDateTime,
+    closed_by: Option<BinaryWrapper<Cow<'a, Id>>>,
+    close_date: Option<NaiveDateTime>,
+}
+
+impl<'a> Into<Recertification<'a>> for InnerRecertification<'a> {
+    fn into(self) -> Recertification<'a> {
+        Recertification {
+            id: self.id.0,
+            name: self.name,
+            started_by: self.started_by.map(|v| v.0),
+            start_date: Some(self.start_date),
+            closed_by: self.closed_by.map(|v| v.0),
+            close_date: self.close_date,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Queryable)]
+struct InnerRecertificationItem<'a> {
+    id: BinaryWrapper<Cow<'a, Id>>,
+    campaign_id: BinaryWrapper<Cow<'a, Id>>,
+    server_id: BinaryWrapper<Cow<'a, Id>>,
+    source_id: BinaryWrapper<Cow<'a, Id>>,
+    dest_id: BinaryWrapper<Cow<'a, Id>>,
+    kind: DbWrapper<RecertificationKind>,
+    decision: Option<DbWrapper<RecertificationDecision>>,
+    decided_by: Option<BinaryWrapper<Cow<'a, Id>>>,
+    decision_date: Option<NaiveDateTime>,
+}
+
+impl<'a> Into<RecertificationItem<'a>> for InnerRecertificationItem<'a> {
+    fn into(self) -> RecertificationItem<'a> {
+        RecertificationItem {
+            id: self.id.0,
+            campaign_id: self.campaign_id.0,
+            server_id: self.server_id.0,
+            source_id: self.source_id.0,
+            dest_id: self.dest_id.0,
+            kind: self.kind.0,
+            decision: self.decision.map(|v| v.0),
+            decided_by: self.decided_by.map(|v| v.0),
+            decision_date: self.decision_date,
+        }
+    }
+}
+
+/// A grant which has not ended together with the server of its account
+type GrantRow = (BinaryWrapper<Id>, BinaryWrapper<Id>, BinaryWrapper<Id>);
+
+impl<B, C> DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<DbWrapper<KeyManagement>>
+        + HasSqlType<DbWrapper<AuthorizationType>>
+        + HasSqlType<DbWrapper<SyncStatusType>>
+        + HasSqlType<DbWrapper<RecertificationKind>>
+        + HasSqlType<DbWrapper<RecertificationDecision>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    DbWrapper<KeyManagement>: Queryable<DbWrapper<KeyManagement>, B>,
+    DbWrapper<AuthorizationType>: Queryable<DbWrapper<AuthorizationType>, B>,
+    DbWrapper<SyncStatusType>: Queryable<DbWrapper<SyncStatusType>, B>,
+    DbWrapper<RecertificationKind>: Queryable<DbWrapper<RecertificationKind>, B>,
+    DbWrapper<RecertificationDecision>:
+        Queryable<DbWrapper<RecertificationDecision>, B>,
+{
+    /// Counts the decided and revoked items of every campaign
+    fn campaign_progress(
+        conn: &DieselPooledConnection<C>,
+        campaigns: Vec<InnerRecertification<'static>>,
+    ) -> DbResult<Vec<RecertificationProgress<'static>>, Self> {
+        if campaigns.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids: Vec<BinaryWrapper<&Id>> =
+            campaigns.iter().map(|v| BinaryWrapper(&*v.id.0)).collect();
+        let query = recertification_item::dsl::recertification_item
+            .select((
+                recertification_item::campaign_id,
+                recertification_item::decision,
+            ))
+            .filter(recertification_item::campaign_id.eq_any(&ids));
+        let decisions: Vec<(
+            BinaryWrapper<Id>,
+            Option<DbWrapper<RecertificationDecision>>,
+        )> = exec!(query, *conn, load)?;
+        let mut counts: HashMap<Id, (u64, u64, u64)> = HashMap::new();
+        for (campaign_id, decision) in decisions {
+            let count = counts.entry(campaign_id.0).or_default();
+            count.0 = count.0.saturating_add(1);
+            if let Some(decision) = decision {
+                count.1 = count.1.saturating_add(1);
+                if decision.0 == RecertificationDecision::Revoke {
+                    count.2 = count.2.saturating_add(1);
+                }
+            }
+        }
+
+        Ok(campaigns
+            .into_iter()
+            .map(|campaign| {
+                let (total, decided, revoked) =
+                    counts.get(&*campaign.id.0).copied().unwrap_or_default();
+                RecertificationProgress {
+                    campaign: campaign.into(),
+                    total,
+                    decided,
+                    revoked,
+                }
+            })
+            .collect())
+    }
+
+    /// Adds the hostname and the names of the entities to every item.
+    /// Entries are ordered by hostname, account or group and entity
+    fn item_entries(
+        conn: &DieselPooledConnection<C>,
+        items: Vec<InnerRecertificationItem<'static>>,
+    ) -> DbResult<Vec<RecertificationEntry<'static>>, Self> {
+        if items.is_empty() {
+            return Ok(Vec::new());
+        }
+        let server_ids: Vec<BinaryWrapper<&Id>> = items
+            .iter()
+            .map(|v| BinaryWrapper(&*v.server_id.0))
+            .collect();
+        let query = server::dsl::server
+            .select((server::id, server::hostname))
+            .filter(server::id.eq_any(&server_ids));
+        let hostnames: Vec<(BinaryWrapper<Id>, String)> = exec!(query, *conn, load)?;
+        let hostnames: HashMap<Id, String> = hostnames
+            .into_iter()
+            .map(|(id, hostname)| (id.0, hostname))
+            .collect();
+
+        let entity_ids: Vec<BinaryWrapper<&Id>> = items
+            .iter()
+            .flat_map(|v| {
+                vec![BinaryWrapper(&*v.source_id.0), BinaryWrapper(&*v.dest_id.0)]
+            })
+            .collect();
+        let mut names = Self::user_uids(conn, &entity_ids)?;
+        let query = groups::dsl::groups
+            .select((groups::entity_id, groups::name))
+            .filter(groups::entity_id.eq_any(&entity_ids));
+        let group_names: Vec<(BinaryWrapper<Id>, String)> =
+            exec!(query, *conn, load)?;
+        names.extend(group_names.into_iter().map(|(id, name)| (id.0, name)));
+        let query = server_account::dsl::server_account
+            .select((server_account::entity_id, server_account::name))
+            .filter(server_account::entity_id.eq_any(&entity_ids));
+        let accounts: Vec<(BinaryWrapper<Id>, Option<String>)> =
+            exec!(query, *conn, load)?;
+        names.extend(
+            accounts
+                .into_iter()
+                .filter_map(|(id, name)| Some((id.0, name?))),
+        );
+
+        let mut entries: Vec<RecertificationEntry<'static>> = items
+            .into_iter()
+            .map(|item| {
+                let hostname = hostnames
+                    .get(&*item.server_id.0)
+                    .cloned()
+                    .unwrap_or_else(|| item.server_id.0.to_string());
+                let source_name = names.get(&*item.source_id.0).cloned();
+                let dest_name = names.get(&*item.dest_id.0).cloned();
+                RecertificationEntry {
+                    item: item.into(),
+                    hostname: Cow::Owned(hostname),
+                    source_name: source_name.map(Cow::Owned),
+                    dest_name: dest_name.map(Cow::Owned),
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            a.hostname
+                .cmp(&b.hostname)
+                .then_with(|| a.dest_name.cmp(&b.dest_name))
+                .then_with(|| a.source_name.cmp(&b.source_name))
+        });
+        Ok(entries)
+    }
+}
+
+impl<B, C> RecertificationStore for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<KeyManagement>>
+        + HasSqlType<DbWrapper<AuthorizationType>>
+        + HasSqlType<DbWrapper<SyncStatusType>>
+        + HasSqlType<DbWrapper<RecertificationKind>>
+        + HasSqlType<DbWrapper<RecertificationDecision>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<KeyManagement>: Queryable<DbWrapper<KeyManagement>, B>,
+    DbWrapper<AuthorizationType>: Queryable<DbWrapper<AuthorizationType>, B>,
+    DbWrapper<SyncStatusType>: Queryable<DbWrapper<SyncStatusType>, B>,
+    DbWrapper<RecertificationKind>: Queryable<DbWrapper<RecertificationKind>, B>,
+    DbWrapper<RecertificationDecision>:
+        Queryable<DbWrapper<RecertificationDecision>, B>,
+{
+    #[inline]
+    fn create_campaign(
+        &self,
+        campaign: &Recertification<'_>,
+    ) -> DbResult<u64, Self> {
+        let conn = self.get()?;
+        let query = recertification::dsl::recertification
+            .select(recertification::id)
+            .filter(recertification::close_date.is_null());
+        let open: Option<BinaryWrapper<Id>> = exec_opt!(query, conn, first)?;
+        if open.is_some() {
+            return Err(DatabaseError::NonUnique);
+        }
+        let query = insert_into(recertification::dsl::recertification).values((
+            recertification::id.eq(BinaryWrapper(&campaign.id)),
+            recertification::name.eq(&*campaign.name),
+            recertification::started_by
+                .eq(campaign.started_by.as_ref().map(BinaryWrapper)),
+        ));
+        let _ = exec!(query, conn, execute)?;
+
+        let query = access::dsl::access
+            .inner_join(
+                server_account::dsl::server_account
+                    .on(server_account::entity_id.eq(access::dest_id)),
+            )
+            .select((
+                access::source_id,
+                access::dest_id,
+                server_account::server_id,
+            ))
+            .filter(access::break_glass_id.is_null())
+            .filter(
+                access::valid_until
+                    .is_null()
+                    .or(access::valid_until.gt(Utc::now().naive_utc())),
+            );
+        let grants: Vec<GrantRow> = exec!(query, conn, load)?;
+        let source_ids: Vec<BinaryWrapper<&Id>> =
+            grants.iter().map(|v| BinaryWrapper(&v.0 .0)).collect();
+        let query = group_member::dsl::group_member
+            .select((group_member::group_id, group_member::member_id))
+            .filter(group_member::group_id.eq_any(&source_ids));
+        let memberships: Vec<(BinaryWrapper<Id>, BinaryWrapper<Id>)> =
+            exec!(query, conn, load)?;
+        let mut members: HashMap<&Id, Vec<&Id>> = HashMap::new();
+        for (group_id, member_id) in &memberships {
+            members.entry(&group_id.0).or_default().push(&member_id.0);
+        }
+
+        let mut items = Vec::new();
+        let mut seen = HashSet::new();
+        for (source_id, dest_id, server_id) in &grants {
+            items.push((
+                &server_id.0,
+                &source_id.0,
+                &dest_id.0,
+                RecertificationKind::Access,
+            ));
+            // a membership affects every server of the group, so it is
+            // reviewed once by the admins of the group
+            for member_id in members.get(&source_id.0).into_iter().flatten() {
+                if seen.insert((*member_id, &source_id.0)) {
+                    items.push((
+                        &server_id.0,
+                        *member_id,
+                        &source_id.0,
+                        RecertificationKind::Membership,
+                    ));
+                }
+            }
+        }
+        for (server_id, source_id, dest_id, kind) in &items {
+            let query = insert_into(recertification_item::dsl::recertification_item)
+                .values((
+                    recertification_item::id.eq(BinaryWrapper(self.generate_id()?)),
+                    recertification_item::campaign_id
+                        .eq(BinaryWrapper(&campaign.id)),
+                    recertification_item::server_id.eq(BinaryWrapper(*server_id)),
+                    recertification_item::source_id.eq(BinaryWrapper(*source_id)),
+                    recertification_item::dest_id.eq(BinaryWrapper(*dest_id)),
+                    recertification_item::kind.eq(DbWrapper(*kind)),
+                ));
+            let _ = exec!(query, conn, execute)?;
+        }
+        Ok(u64::try_from(items.len()).unwrap_or(u64::MAX))
+    }
+
+    #[inline]
+    fn fetch_campaigns(
+        &self,
+    ) -> DbResult<Vec<RecertificationProgress<'static>>, Self> {
+        let conn = self.get_read()?;
+        let query = recertification::dsl::recertification
+            .order(recertification::start_date.desc());
+        let campaigns: Vec<InnerRecertification<'static>> =
+            exec!(query, conn, load)?;
+        Self::campaign_progress(&conn, campaigns)
+    }
+
+    #[inline]
+    fn fetch_campaign(
+        &self,
+        id: &Id,
+    ) -> DbResult<Option<RecertificationProgress<'static>>, Self> {
+        let conn = self.get_read()?;
+        let query = recertification::dsl::recertification.find(BinaryWrapper(id));
+        let campaign: Option<InnerRecertification<'static>> =
+            exec_opt!(query, conn, first)?;
+        let campaigns = campaign.into_iter().collect();
+        Ok(Self::campaign_progress(&conn, campaigns)?.pop())
+    }
+
+    #[inline]
+    fn fetch_items(
+        &self,
+        campaign_id: &Id,
+        reviewer_id: Option<&Id>,
+    ) -> DbResult<Vec<RecertificationEntry<'static>>, Self> {
+        let admin_ids = match reviewer_id {
+            Some(reviewer_id) => Some(self.admin_ids(reviewer_id)?),
+            None => None,
+        };
+
+        let conn = self.get_read()?;
+        let mut query = recertification_item::dsl::recertification_item
+            .filter(recertification_item::campaign_id.eq(BinaryWrapper(campaign_id)))
+            .into_boxed::<B>();
+        if let Some(ref ids) = admin_ids {
+            let server_query = server_admin::dsl::server_admin
+                .select(server_admin::server_id)
+                .filter(server_admin::entity_id.eq_any(ids));
+            let group_query = group_admin::dsl::group_admin
+                .select(group_admin::group_id)
+                .filter(group_admin::admin_id.eq_any(ids));
+            query = query.filter(
+                recertification_item::kind
+                    .eq(DbWrapper(RecertificationKind::Access))
+                    .and(recertification_item::server_id.eq_any(server_query))
+                    .or(recertification_item::kind
+                        .eq(DbWrapper(RecertificationKind::Membership))
+                        .and(recertification_item::dest_id.eq_any(group_query))),
+            );
+        }
+        let items: Vec<InnerRecertificationItem<'static>> =
+            exec!(query, conn, load)?;
+        Self::item_entries(&conn, items)
+    }
+
+    #[inline]
+    fn fetch_item(
+        &self,
+        id: &Id,
+    ) -> DbResult<Option<RecertificationEntry<'static>>, Self> {
+        let conn = self.get_read()?;
+        let query =
+            recertification_item::dsl::recertification_item.find(BinaryWrapper(id));
+        let item: Option<InnerRecertificationItem<'static>> =
+            exec_opt!(query, conn, first)?;
+        let items = item.into_iter().collect();
+        Ok(Self::item_entries(&conn, items)?.pop())
+    }
+
+    #[inline]
+    fn decide_item(&self, item: &RecertificationItem<'_>) -> DbResult<bool, Self> {
+        let conn = self.get()?;
+        // the campaign is checked by the update itself, a decision never
+        // lands after the campaign was closed
+        let open = recertification::dsl::recertification
+            .select(recertification::id)
+            .filter(recertification::id.eq(BinaryWrapper(&item.campaign_id)))
+            .filter(recertification::close_date.is_null());
+        let query = diesel::update(
+            recertification_item::dsl::recertification_item
+                .find(BinaryWrapper(&item.id))
+                .filter(recertification_item::campaign_id.eq_any(open)),
+        )
+        .set((
+            recertification_item::decision.eq(item.decision.map(DbWrapper)),
+            recertification_item::decided_by
+                .eq(item.decided_by.as_ref().map(BinaryWrapper)),
+            recertification_item::decision_date.eq(item.decision_date),
+        ));
+        Ok(exec!(query, conn, execute)? > 0)
+    }
+
+    #[inline]
+    fn is_group_admin(&self, group_id: &Id, entity_id: &Id) -> DbResult<bool, Self> {
+        let ids: Vec<BinaryWrapper<Cow<'_, Id>>> = self
+            .fetch_permission_ids(Cow::Borrowed(entity_id))?
+            .into_iter()
+            .map(BinaryWrapper)
+            .collect();
+        let conn = self.get_read()?;
+        let query = group_admin::dsl::group_admin
+            .select(group_admin::group_id)
+            .filter(group_admin::group_id.eq(BinaryWrapper(group_id)))
+            .filter(group_admin::admin_id.eq_any(&ids));
+        let group: Option<BinaryWrapper<Id>> = exec_opt!(query, conn, first)?;
+        Ok(group.is_some())
+    }
+
+    #[inline]
+    fn close_campaign(
+        &self,
+        campaign: &Recertification<'_>,
+    ) -> DbResult<Option<Vec<RecertificationItem<'static>>>, Self> {
+        let conn = self.get()?;
+        let close_date = campaign
+            .close_date
+            .unwrap_or_else(|| Utc::now().naive_utc());
+        // the removals are stored along with the closing, a closed campaign
+        // never leaves access it revoked
+        Self::transaction(&conn, || {
+            let query = diesel::update(
+                recertification::dsl::recertification
+                    .find(BinaryWrapper(&campaign.id))
+                    .filter(recertification::close_date.is_null()),
+            )
+            .set((
+                recertification::closed_by
+                    .eq(campaign.closed_by.as_ref().map(BinaryWrapper)),
+                recertification::close_date.eq(close_date),
+            ));
+            if exec!(query, conn, execute)? == 0 {
+                return Ok(None);
+            }
+
+            let query = recertification_item::dsl::recertification_item
+                .filter(
+                    recertification_item::campaign_id
+                        .eq(BinaryWrapper(&campaign.id)),
+                )
+                .filter(recertification_item::decision.is_not_null());
+            let items: Vec<InnerRecertificationItem<'static>> =
+                exec!(query, conn, load)?;
+            let items: Vec<RecertificationItem<'static>> =
+                items.into_iter().map(Into::into).collect();
+            let items = RecertificationItem::revocations(&items);
+            let mut revoked = Vec::new();
+            for item in items {
+                let removed = {
+                    let source_id = BinaryWrapper(&*item.source_id);
+                    let dest_id = BinaryWrapper(&*item.dest_id);
+                    match item.kind {
+                        RecertificationKind::Access => {
+                            let query = diesel::delete(
+                                access::dsl::access
+                                    .filter(access::source_id.eq(&source_id))
+                                    .filter(access::dest_id.eq(&dest_id))
+                                    .filter(access::break_glass_id.is_null()),
+                            );
+                            let removed = exec!(query, conn, execute)? > 0;
+                            if removed {
+                                self.queue_account_sync(
+                                    &conn,
+                                    &item.server_id,
+                                    &item.dest_id,
+                                )?;
+                            }
+                            removed
+                        }
+                        RecertificationKind::Membership => {
+                            let query = diesel::delete(
+                                group_member::dsl::group_member
+                                    .filter(group_member::group_id.eq(&dest_id))
+                                    .filter(group_member::member_id.eq(&source_id)),
+                            );
+                            let removed = exec!(query, conn, execute)? > 0;
+                            if removed {
+                                // the former member loses access to every
+                                // account of the group
+                                let query = access::dsl::access
+                                    .inner_join(
+                                        server_account::dsl::server_account
+                                            .on(server_account::entity_id
+                                                .eq(access::dest_id)),
+                                    )
+                                    .select((
+                                        server_account::server_id,
+                                        access::dest_id,
+                                    ))
+                                    .filter(access::source_id.eq(&dest_id));
+                                let accounts: Vec<(
+                                    BinaryWrapper<Id>,
+                                    BinaryWrapper<Id>,
+                                )> = exec!(query, conn, load)?;
+                                for (server_id, account_id) in &accounts {
+                                    self.queue_account_sync(
+                                        &conn,
+                                        &server_id.0,
+                                        &account_id.0,
+                                    )?;
+                                }
+                            }
+                            removed
+                        }
+                    }
+                };
+                if removed {
+                    revoked.push(item);
+                }
+            }
+            Ok(Some(revoked))
+        })
+    }
+}
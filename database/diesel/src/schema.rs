@@ -8,6 +8,25 @@ table! {
         dest_id -> Binary,
         grant_date -> Timestamp,
         granted_by -> Nullable<Binary>,
+        valid_from -> Nullable<Timestamp>,
+        valid_until -> Nullable<Timestamp>,
+        validity_synced -> Nullable<Timestamp>,
+        break_glass_id -> Nullable<Binary>,
+    }
+}
+
+table! {
+    access_request (id) {
+        id -> Binary,
+        source_id -> Binary,
+        dest_id -> Binary,
+        reason -> Nullable<Text>,
+        status -> crate::DbWrapper<core_common::types::AccessRequestStatus>,
+        request_date -> Timestamp,
+        decided_by -> Nullable<Binary>,
+        decision_date -> Nullable<Timestamp>,
+        valid_from -> Nullable<Timestamp>,
+        valid_until -> Nullable<Timestamp>,
     }
 }
 
@@ -20,6 +39,39 @@ table! {
     }
 }
 
+table! {
+    api_token (id) {
+        id -> Binary,
+        entity_id -> Binary,
+        valid_until -> Timestamp,
+        created -> Timestamp,
+    }
+}
+
+table! {
+    break_glass (id) {
+        id -> Binary,
+        source_id -> Binary,
+        dest_id -> Binary,
+        reason -> Text,
+        start_date -> Timestamp,
+        valid_until -> Timestamp,
+        reviewed_by -> Nullable<Binary>,
+        review_date -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    certificate_authority (id) {
+        id -> Binary,
+        #[sql_name = "type"]
+        type_ -> crate::DbWrapper<core_common::types::CertificateType>,
+        public_key -> Text,
+        private_key -> Binary,
+        created -> Timestamp,
+    }
+}
+
 table! {
     entity (id) {
         id -> Binary,
@@ -37,6 +89,19 @@ table! {
         #[sql_name = "type"]
         type_ -> crate::DbWrapper<core_common::types::EventTypes>,
         object_id -> Nullable<Binary>,
+        seq -> Nullable<BigInt>,
+        hash -> Nullable<Binary>,
+        prev_hash -> Nullable<Binary>,
+    }
+}
+
+table! {
+    event_checkpoint (id) {
+        id -> Binary,
+        seq -> BigInt,
+        hash -> Binary,
+        signature -> Binary,
+        date -> Timestamp,
     }
 }
 
@@ -66,6 +131,41 @@ table! {
     }
 }
 
+table! {
+    host_certificate (server_id) {
+        server_id -> Binary,
+        serial -> BigInt,
+        certificate -> Text,
+        valid_after -> Timestamp,
+        valid_before -> Timestamp,
+    }
+}
+
+table! {
+    notification_delivery (id) {
+        id -> Binary,
+        entity_id -> Binary,
+        event_id -> Binary,
+        details -> Text,
+        target -> Nullable<Text>,
+        status -> crate::DbWrapper<core_common::types::DeliveryStatus>,
+        attempts -> Integer,
+        next_attempt -> Nullable<Timestamp>,
+        error -> Nullable<Text>,
+        date -> Timestamp,
+    }
+}
+
+table! {
+    notification_preference (entity_id) {
+        entity_id -> Binary,
+        key_add -> Bool,
+        access -> Bool,
+        sync_failure -> Bool,
+        key_expiring -> Bool,
+    }
+}
+
 table! {
     public_key (id) {
         id -> Binary,
@@ -81,6 +181,35 @@ table! {
         randomart_sha256 -> Nullable<Text>,
         upload_date -> Timestamp,
         active -> Bool,
+        application -> Nullable<Text>,
+        expires -> Nullable<Timestamp>,
+        expiry_reminder -> Nullable<Timestamp>,
+        deactivated -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    recertification (id) {
+        id -> Binary,
+        name -> Text,
+        started_by -> Nullable<Binary>,
+        start_date -> Timestamp,
+        closed_by -> Nullable<Binary>,
+        close_date -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    recertification_item (id) {
+        id -> Binary,
+        campaign_id -> Binary,
+        server_id -> Binary,
+        source_id -> Binary,
+        dest_id -> Binary,
+        kind -> crate::DbWrapper<core_common::types::RecertificationKind>,
+        decision -> Nullable<crate::DbWrapper<core_common::types::RecertificationDecision>>,
+        decided_by -> Nullable<Binary>,
+        decision_date -> Nullable<Timestamp>,
     }
 }
 
@@ -95,6 +224,9 @@ table! {
         sync_status -> crate::DbWrapper<core_common::types::SyncStatusType>,
         rsa_key_fingerprint -> Nullable<Text>,
         port -> Integer,
+        require_security_key -> Bool,
+        host_key -> Nullable<Text>,
+        proxy_jump -> Nullable<Text>,
     }
 }
 
@@ -124,6 +256,14 @@ table! {
     }
 }
 
+table! {
+    server_token (server_id) {
+        server_id -> Binary,
+        token -> Binary,
+        created -> Timestamp,
+    }
+}
+
 table! {
     sync_request (id) {
         id -> Binary,
@@ -145,14 +285,48 @@ table! {
     }
 }
 
+table! {
+    webhook (id) {
+        id -> Binary,
+        name -> Text,
+        url -> Text,
+        secret -> Binary,
+        actions -> Nullable<Text>,
+        active -> Bool,
+    }
+}
+
+table! {
+    webhook_delivery (id) {
+        id -> Binary,
+        webhook_id -> Binary,
+        event_id -> Binary,
+        payload -> Text,
+        status -> crate::DbWrapper<core_common::types::DeliveryStatus>,
+        attempts -> Integer,
+        next_attempt -> Nullable<Timestamp>,
+        response_code -> Nullable<Integer>,
+        error -> Nullable<Text>,
+        date -> Timestamp,
+    }
+}
+
 allow_tables_to_appear_in_same_query!(
     server,
     server_admin,
     server_account,
+    server_token,
     access,
+    access_request,
+    api_token,
+    break_glass,
+    recertification,
+    recertification_item,
     users,
     groups,
     group_admin,
     entity,
     group_member,
+    webhook,
+    webhook_delivery,
 );
@@ -6,14 +6,16 @@ use crate::{
     BinaryWrapper, DbWrapper, DieselDB, UniqueExtension,
 };
 use core_common::{
-    chrono::NaiveDateTime,
+    chrono::{NaiveDateTime, Utc},
     database::{
         Create, Database, DatabaseError, DbList, DbResult, Delete, FetchAll,
-        FetchAllFor, FetchById,
+        FetchAllFor, FetchById, LoginStore,
+    },
+    objects::{
+        AccessValidity, Event, EventDetails, Server, ServerFilter, ServerLogin,
     },
-    objects::{Server, ServerFilter},
     sec::Auth,
-    types::{AuthorizationType, Id, KeyManagement, SyncStatusType},
+    types::{AuthorizationType, EventTypes, Id, KeyManagement, SyncStatusType},
 };
 use diesel::{
     backend::{Backend, SupportsDefaultKeyword, UsesAnsiSavepointSyntax},
@@ -23,14 +25,17 @@ use diesel::{
     insert_into,
     query_builder::BoxedSelectStatement,
     serialize::ToSql,
-    sql_types::{Bool, HasSqlType, Timestamp},
-    BoolExpressionMethods, Connection, ExpressionMethods, OptionalExtension,
-    QueryDsl, Queryable, RunQueryDsl, TextExpressionMethods,
+    sql_types::{BigInt, Bool, HasSqlType, Timestamp},
+    BoolExpressionMethods, Connection, ExpressionMethods, JoinOnDsl,
+    OptionalExtension, QueryDsl, Queryable, RunQueryDsl, TextExpressionMethods,
+};
+use std::{
+    borrow::{Borrow, Cow},
+    collections::HashMap,
 };
-use std::borrow::{Borrow, Cow};
 
 #[derive(Debug, Clone, Queryable)]
-struct InnerServer<'a> {
+pub(crate) struct InnerServer<'a> {
     id: BinaryWrapper<Cow<'a, Id>>,
     hostname: Cow<'a, str>,
     ip_address: Option<Cow<'a, str>>,
@@ -40,6 +45,9 @@ struct InnerServer<'a> {
     sync_status: DbWrapper<SyncStatusType>,
     rsa_key_fingerprint: Option<Cow<'a, str>>,
     port: i32,
+    require_security_key: bool,
+    host_key: Option<Cow<'a, str>>,
+    proxy_jump: Option<Cow<'a, str>>,
 }
 
 impl InnerServer<'_> {
@@ -126,10 +134,35 @@ impl<'a> Into<Server<'a>> for InnerServer<'a> {
             sync_status: self.sync_status.0,
             rsa_key_fingerprint: self.rsa_key_fingerprint,
             port: self.port,
+            require_security_key: self.require_security_key,
+            host_key: self.host_key,
+            proxy_jump: self.proxy_jump,
         }
     }
 }
 
+/// An access grant with the account it refers to and its validity
+type GrantRow = (
+    BinaryWrapper<Id>,
+    Option<NaiveDateTime>,
+    Option<NaiveDateTime>,
+);
+
+pub(crate) type LoginRow = (InnerServer<'static>, BinaryWrapper<Id>, Option<String>);
+
+/// Converts a server joined with one of its accounts. Returns `None` for
+/// accounts without a name
+pub(crate) fn login_from_row(
+    (server, account_id, account): LoginRow,
+) -> Option<ServerLogin<'static>> {
+    Some(ServerLogin {
+        server: server.into(),
+        account_id: Cow::Owned(account_id.0),
+        account: Cow::Owned(account?),
+        validity: AccessValidity::default(),
+    })
+}
+
 #[allow(clippy::type_repetition_in_bounds)]
 impl<'a, 'b, B, C, A> FetchById<'a, A, Server<'b>, Self> for DieselDB<C>
 where
@@ -155,7 +188,7 @@ where
     fn fetch(&self, id: &'a Id, auth: &A) -> DbResult<Option<Server<'b>>, Self> {
         let ids;
         let res: Option<InnerServer<'_>>;
-        let conn = self.get()?;
+        let conn = self.get_read()?;
 
         let query = server::dsl::server.find(BinaryWrapper(id));
         res = if auth.is_admin() {
@@ -208,7 +241,7 @@ where
         let ids: Vec<Cow<'_, Id>>;
         let id_ref: &'_ [Cow<'_, Id>];
         let res: Vec<InnerServer<'a>>;
-        let conn = self.get()?;
+        let conn = self.get_read()?;
 
         let offset = Self::compute_offset(page);
 
@@ -283,7 +316,7 @@ where
         page: usize,
     ) -> DbResult<DbList<Server<'a>>, Self> {
         let res: Vec<InnerServer<'a>>;
-        let conn = self.get()?;
+        let conn = self.get_read()?;
 
         let offset = Self::compute_offset(page);
         let id = auth.get_id();
@@ -319,6 +352,90 @@ where
     }
 }
 
+impl<B, C> LoginStore for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<KeyManagement>>
+        + HasSqlType<DbWrapper<AuthorizationType>>
+        + HasSqlType<DbWrapper<SyncStatusType>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<KeyManagement>: Queryable<DbWrapper<KeyManagement>, B>,
+    DbWrapper<AuthorizationType>: Queryable<DbWrapper<AuthorizationType>, B>,
+    DbWrapper<SyncStatusType>: Queryable<DbWrapper<SyncStatusType>, B>,
+{
+    #[inline]
+    fn fetch_logins(
+        &self,
+        entity_id: &Id,
+    ) -> DbResult<Vec<ServerLogin<'static>>, Self> {
+        let ids: Vec<BinaryWrapper<Cow<'_, Id>>> = self
+            .fetch_permission_ids(Cow::Borrowed(entity_id))?
+            .into_iter()
+            .map(BinaryWrapper)
+            .collect();
+        let conn = self.get_read()?;
+        let now = Utc::now().naive_utc();
+        let query = access::dsl::access
+            .select((access::dest_id, access::valid_from, access::valid_until))
+            .filter(access::source_id.eq_any(&ids))
+            .filter(access::valid_from.is_null().or(access::valid_from.le(now)))
+            .filter(
+                access::valid_until
+                    .is_null()
+                    .or(access::valid_until.gt(now)),
+            );
+        let grants: Vec<GrantRow> = exec!(query, conn, load)?;
+        let mut validity: HashMap<Id, AccessValidity> = HashMap::new();
+        for (dest_id, valid_from, valid_until) in grants {
+            let grant = AccessValidity {
+                valid_from,
+                valid_until,
+            };
+            let _ = validity
+                .entry(dest_id.0)
+                .and_modify(|v| *v = v.union(grant))
+                .or_insert(grant);
+        }
+        if validity.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let account_ids: Vec<BinaryWrapper<&Id>> =
+            validity.keys().map(BinaryWrapper).collect();
+        let query = server_account::dsl::server_account
+            .inner_join(
+                server::dsl::server.on(server::id.eq(server_account::server_id)),
+            )
+            .select((
+                server::all_columns,
+                server_account::entity_id,
+                server_account::name,
+            ))
+            .filter(server_account::entity_id.eq_any(&account_ids))
+            .order((server::hostname, server_account::name));
+        let rows: Vec<LoginRow> = exec!(query, conn, load)?;
+        Ok(rows
+            .into_iter()
+            .filter_map(login_from_row)
+            .map(|mut login| {
+                if let Some(v) = validity.get(&*login.account_id) {
+                    login.validity = *v;
+                }
+                login
+            })
+            .collect())
+    }
+}
+
 impl<'a, A, B, C: 'static + Connection> Create<A, Server<'a>, Self> for DieselDB<C>
 where
     A: Auth,
@@ -328,13 +445,21 @@ where
         + UsesAnsiSavepointSyntax
         + HasSqlType<DbWrapper<KeyManagement>>
         + HasSqlType<DbWrapper<AuthorizationType>>
-        + HasSqlType<DbWrapper<SyncStatusType>>,
+        + HasSqlType<DbWrapper<SyncStatusType>>
+        + HasSqlType<Bool>
+        + HasSqlType<BigInt>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<EventTypes>>,
     C: 'static
         + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
         + Migrate,
+    bool: ToSql<Bool, B>,
+    i64: FromSql<BigInt, B> + ToSql<BigInt, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
 {
     #[inline]
-    fn create(&self, object: &Server<'a>, _auth: &A) -> DbResult<(), Self> {
+    fn create(&self, object: &Server<'a>, auth: &A) -> DbResult<(), Self> {
         let conn = self.get()?;
         let query = insert_into(server::dsl::server).values((
             server::id.eq(BinaryWrapper(&object.id)),
@@ -344,29 +469,24 @@ where
             server::key_management.eq(DbWrapper(object.key_management)),
             server::authorization.eq(DbWrapper(object.authorization)),
             server::sync_status.eq(DbWrapper(object.sync_status)),
-            server::name.eq(&object.rsa_key_fingerprint),
+            server::rsa_key_fingerprint.eq(&object.rsa_key_fingerprint),
             server::port.eq(object.port),
+            server::require_security_key.eq(object.require_security_key),
+            server::host_key.eq(&object.host_key),
+            server::proxy_jump.eq(&object.proxy_jump),
         ));
-        exec_unique!(query, conn, execute).map(|_| ())
-        // if let DbResult::Ok(_) = res {
-        //     let details = Cow::Owned(
-        //         json!({
-        //             "action": "Pubkey add",
-        //             "value": object.fingerprint_md5,
-        //             "id": &object.id
-        //         })
-        //         .to_string(),
-        //     );
-        //     let event: Event<'_> = Event {
-        //         id: Cow::Owned(self.generate_id()?),
-        //         actor_id: Some(Cow::Borrowed(auth.get_id())),
-        //         date: None,
-        //         details,
-        //         type_: EventTypes::Entity,
-        //         object_id: Some(Cow::Borrowed(&object.entity_id)),
-        //     };
-        //     self.create(&event, auth)?;
-        // }
+        let _ = exec_unique!(query, conn, execute)?;
+
+        let details = EventDetails::ServerCreate {
+            hostname: Cow::Borrowed(&object.hostname),
+        };
+        let event = Event::new(
+            Cow::Owned(self.generate_id()?),
+            Some(Cow::Borrowed(auth.get_id())),
+            details,
+            Some(Cow::Borrowed(&object.id)),
+        );
+        self.create(&event, auth)
     }
 }
 
@@ -380,11 +500,17 @@ where
         + HasSqlType<DbWrapper<KeyManagement>>
         + HasSqlType<DbWrapper<AuthorizationType>>
         + HasSqlType<DbWrapper<SyncStatusType>>
+        + HasSqlType<BigInt>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<EventTypes>>
         + SupportsDefaultKeyword,
     C: 'static
         + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
         + Migrate,
     bool: ToSql<Bool, B>,
+    i64: FromSql<BigInt, B> + ToSql<BigInt, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
 {
     #[inline]
     fn delete(&self, ids: &[Id], auth: &A) -> DbResult<(), Self> {
@@ -392,10 +518,28 @@ where
             let conn = self.get()?;
             let ids: Vec<BinaryWrapper<&Id>> =
                 ids.iter().map(BinaryWrapper).collect();
+            let select = server::dsl::server
+                .select((server::id, server::hostname))
+                .filter(server::id.eq_any(&ids));
+            let servers: Vec<(BinaryWrapper<Id>, String)> =
+                exec!(select, conn, load)?;
             let query = diesel::delete(server::dsl::server)
                 .filter(server::id.eq_any(&ids))
                 .into_boxed::<B>();
             let _ = exec!(query, conn, execute)?;
+
+            for (id, hostname) in servers {
+                let details = EventDetails::ServerDelete {
+                    hostname: Some(Cow::Owned(hostname)),
+                };
+                let event = Event::new(
+                    Cow::Owned(self.generate_id()?),
+                    Some(Cow::Borrowed(auth.get_id())),
+                    details,
+                    Some(Cow::Owned(id.0)),
+                );
+                self.create(&event, auth)?;
+            }
         }
         Ok(())
     }
@@ -0,0 +1,309 @@
+use crate::{
+    error::DieselError,
+    exec, exec_opt, exec_unique,
+    migrate::Migrate,
+    schema::{access, entity, groups, server_account, server_admin, users},
+    BinaryWrapper, DbWrapper, DieselDB, UniqueExtension,
+};
+use core_common::{
+    chrono::{NaiveDateTime, Utc},
+    database::{Database, DatabaseError, DbResult, ServerAdminStore},
+    objects::{AccessValidity, Entity, ServerAccess, ServerAccount},
+    types::{AuthorizationType, EntityTypes, Id, KeyManagement, SyncStatusType},
+};
+use diesel::{
+    backend::{Backend, SupportsDefaultKeyword, UsesAnsiSavepointSyntax},
+    connection::AnsiTransactionManager,
+    deserialize::FromSql,
+    insert_into,
+    serialize::ToSql,
+    sql_types::{Bool, HasSqlType, Timestamp},
+    BoolExpressionMethods, Connection, ExpressionMethods, OptionalExtension,
+    QueryDsl, Queryable, RunQueryDsl,
+};
+use std::{borrow::Cow, collections::HashMap};
+
+/// An existing grant of an entity to an account
+type GrantRow = (
+    BinaryWrapper<Id>,
+    Option<NaiveDateTime>,
+    Option<NaiveDateTime>,
+);
+
+/// A grant which has not ended
+type AccessRow = (
+    BinaryWrapper<Id>,
+    BinaryWrapper<Id>,
+    Option<NaiveDateTime>,
+    Option<NaiveDateTime>,
+);
+
+impl<B, C> ServerAdminStore for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<EntityTypes>>
+        + HasSqlType<DbWrapper<KeyManagement>>
+        + HasSqlType<DbWrapper<AuthorizationType>>
+        + HasSqlType<DbWrapper<SyncStatusType>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<EntityTypes>: Queryable<DbWrapper<EntityTypes>, B>,
+    DbWrapper<KeyManagement>: Queryable<DbWrapper<KeyManagement>, B>,
+    DbWrapper<AuthorizationType>: Queryable<DbWrapper<AuthorizationType>, B>,
+    DbWrapper<SyncStatusType>: Queryable<DbWrapper<SyncStatusType>, B>,
+{
+    #[inline]
+    fn fetch_entity_by_name(
+        &self,
+        name: &str,
+    ) -> DbResult<Option<Entity<'static>>, Self> {
+        let conn = self.get_read()?;
+        let query = users::dsl::users
+            .select(users::entity_id)
+            .filter(users::uid.eq(name));
+        let mut id: Option<BinaryWrapper<Id>> = exec_opt!(query, conn, first)?;
+        if id.is_none() {
+            let query = groups::dsl::groups
+                .select(groups::entity_id)
+                .filter(groups::name.eq(name));
+            id = exec_opt!(query, conn, first)?;
+        }
+        let id = match id {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        Ok(Self::fetch_entities(&conn, &[BinaryWrapper(&id.0)])?.pop())
+    }
+
+    #[inline]
+    fn fetch_server_admins(
+        &self,
+        server_id: &Id,
+    ) -> DbResult<Vec<Entity<'static>>, Self> {
+        let conn = self.get_read()?;
+        let query = server_admin::dsl::server_admin
+            .select(server_admin::entity_id)
+            .filter(server_admin::server_id.eq(BinaryWrapper(server_id)));
+        let ids: Vec<BinaryWrapper<Id>> = exec!(query, conn, load)?;
+        let ids: Vec<BinaryWrapper<&Id>> =
+            ids.iter().map(|v| BinaryWrapper(&v.0)).collect();
+        let mut admins = Self::fetch_entities(&conn, &ids)?;
+        admins.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(admins)
+    }
+
+    #[inline]
+    fn add_server_admin(
+        &self,
+        server_id: &Id,
+        entity_id: &Id,
+    ) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        let query = insert_into(server_admin::dsl::server_admin).values((
+            server_admin::server_id.eq(BinaryWrapper(server_id)),
+            server_admin::entity_id.eq(BinaryWrapper(entity_id)),
+        ));
+        let _ = exec_unique!(query, conn, execute)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn remove_server_admin(
+        &self,
+        server_id: &Id,
+        entity_id: &Id,
+    ) -> DbResult<bool, Self> {
+        let conn = self.get()?;
+        let query = diesel::delete(
+            server_admin::dsl::server_admin
+                .filter(server_admin::server_id.eq(BinaryWrapper(server_id)))
+                .filter(server_admin::entity_id.eq(BinaryWrapper(entity_id))),
+        );
+        Ok(exec!(query, conn, execute)? > 0)
+    }
+
+    #[inline]
+    fn fetch_server_accounts(
+        &self,
+        server_id: &Id,
+    ) -> DbResult<Vec<ServerAccount<'static>>, Self> {
+        let conn = self.get_read()?;
+        let query = server_account::dsl::server_account
+            .select((
+                server_account::entity_id,
+                server_account::name,
+                server_account::sync_status,
+            ))
+            .filter(server_account::server_id.eq(BinaryWrapper(server_id)))
+            .order(server_account::name);
+        let accounts: Vec<(
+            BinaryWrapper<Id>,
+            Option<String>,
+            DbWrapper<SyncStatusType>,
+        )> = exec!(query, conn, load)?;
+        if accounts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let account_ids: Vec<BinaryWrapper<&Id>> =
+            accounts.iter().map(|v| BinaryWrapper(&v.0 .0)).collect();
+        let query = access::dsl::access
+            .select((
+                access::source_id,
+                access::dest_id,
+                access::valid_from,
+                access::valid_until,
+            ))
+            .filter(access::dest_id.eq_any(&account_ids))
+            .filter(
+                access::valid_until
+                    .is_null()
+                    .or(access::valid_until.gt(Utc::now().naive_utc())),
+            );
+        let grants: Vec<AccessRow> = exec!(query, conn, load)?;
+        let source_ids: Vec<BinaryWrapper<&Id>> =
+            grants.iter().map(|v| BinaryWrapper(&v.0 .0)).collect();
+        let sources: HashMap<Id, Entity<'static>> =
+            Self::fetch_entities(&conn, &source_ids)?
+                .into_iter()
+                .map(|v| (v.entity_id.clone().into_owned(), v))
+                .collect();
+        let mut by_account: HashMap<Id, Vec<ServerAccess<'static>>> = HashMap::new();
+        for (source_id, dest_id, valid_from, valid_until) in grants {
+            if let Some(source) = sources.get(&source_id.0) {
+                by_account.entry(dest_id.0).or_default().push(ServerAccess {
+                    source: source.clone(),
+                    validity: AccessValidity {
+                        valid_from,
+                        valid_until,
+                    },
+                });
+            }
+        }
+
+        Ok(accounts
+            .into_iter()
+            .filter_map(|(id, name, sync_status)| {
+                let name = name?;
+                let mut access = by_account.remove(&id.0).unwrap_or_default();
+                access.sort_by(|a, b| a.source.name.cmp(&b.source.name));
+                Some(ServerAccount {
+                    account_id: Cow::Owned(id.0),
+                    name: Cow::Owned(name),
+                    sync_status: sync_status.0,
+                    access,
+                })
+            })
+            .collect())
+    }
+
+    #[inline]
+    fn create_server_account(
+        &self,
+        server_id: &Id,
+        account_id: &Id,
+        name: &str,
+    ) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        let query = server_account::dsl::server_account
+            .select(server_account::entity_id)
+            .filter(server_account::server_id.eq(BinaryWrapper(server_id)))
+            .filter(server_account::name.eq(name));
+        let existing: Option<BinaryWrapper<Id>> = exec_opt!(query, conn, first)?;
+        if existing.is_some() {
+            return Err(DatabaseError::NonUnique);
+        }
+
+        let query = insert_into(entity::dsl::entity).values((
+            entity::id.eq(BinaryWrapper(account_id)),
+            entity::type_.eq(DbWrapper(EntityTypes::ServerAccount)),
+        ));
+        let _ = exec_unique!(query, conn, execute)?;
+        let query = insert_into(server_account::dsl::server_account).values((
+            server_account::entity_id.eq(BinaryWrapper(account_id)),
+            server_account::server_id.eq(BinaryWrapper(server_id)),
+            server_account::name.eq(name),
+        ));
+        let _ = exec_unique!(query, conn, execute)?;
+        self.queue_account_sync(&conn, server_id, account_id)
+    }
+
+    #[inline]
+    fn grant_server_access(
+        &self,
+        server_id: &Id,
+        account_id: &Id,
+        source_id: &Id,
+        granted_by: &Id,
+    ) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        let source_id = BinaryWrapper(source_id);
+        let dest_id = BinaryWrapper(account_id);
+        let now = Utc::now().naive_utc();
+        let query = access::dsl::access
+            .select((access::id, access::valid_from, access::valid_until))
+            .filter(access::source_id.eq(&source_id))
+            .filter(access::dest_id.eq(&dest_id))
+            .filter(access::break_glass_id.is_null());
+        let grant: Option<GrantRow> = exec_opt!(query, conn, first)?;
+        match grant {
+            Some((id, valid_from, valid_until)) => {
+                let current = AccessValidity {
+                    valid_from,
+                    valid_until,
+                };
+                if current.is_active(now) {
+                    return Err(DatabaseError::NonUnique);
+                }
+                // an expired or upcoming grant becomes unlimited
+                let query = diesel::update(access::dsl::access.find(id)).set((
+                    access::grant_date.eq(now),
+                    access::granted_by.eq(BinaryWrapper(granted_by)),
+                    access::valid_from.eq(None::<NaiveDateTime>),
+                    access::valid_until.eq(None::<NaiveDateTime>),
+                    access::validity_synced.eq(now),
+                ));
+                let _ = exec!(query, conn, execute)?;
+            }
+            None => {
+                let query = insert_into(access::dsl::access).values((
+                    access::id.eq(BinaryWrapper(self.generate_id()?)),
+                    access::source_id.eq(&source_id),
+                    access::dest_id.eq(&dest_id),
+                    access::grant_date.eq(now),
+                    access::granted_by.eq(BinaryWrapper(granted_by)),
+                ));
+                let _ = exec!(query, conn, execute)?;
+            }
+        }
+        self.queue_account_sync(&conn, server_id, account_id)
+    }
+
+    #[inline]
+    fn revoke_server_access(
+        &self,
+        server_id: &Id,
+        account_id: &Id,
+        source_id: &Id,
+    ) -> DbResult<bool, Self> {
+        let conn = self.get()?;
+        let query = diesel::delete(
+            access::dsl::access
+                .filter(access::source_id.eq(BinaryWrapper(source_id)))
+                .filter(access::dest_id.eq(BinaryWrapper(account_id))),
+        );
+        if exec!(query, conn, execute)? == 0 {
+            return Ok(false);
+        }
+        self.queue_account_sync(&conn, server_id, account_id)?;
+        Ok(true)
+    }
+}
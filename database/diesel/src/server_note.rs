@@ -0,0 +1,347 @@
+use crate::{
+    error::DieselError, exec, exec_opt, migrate::Migrate, schema::server_note,
+    BinaryWrapper, DbWrapper, DieselDB,
+};
+use core_common::{
+    chrono::NaiveDateTime,
+    database::{
+        Create, DatabaseError, DbList, DbResult, Delete, FetchAll, FetchById, Save,
+    },
+    objects::{Event, EventDetails, ServerNote, ServerNoteFilter},
+    sec::Auth,
+    types::{EntityTypes, EventTypes, Id},
+};
+use diesel::{
+    backend::{Backend, SupportsDefaultKeyword, UsesAnsiSavepointSyntax},
+    connection::AnsiTransactionManager,
+    deserialize::FromSql,
+    dsl::count_star,
+    insert_into,
+    query_builder::BoxedSelectStatement,
+    serialize::ToSql,
+    sql_types::{BigInt, Bool, HasSqlType, Timestamp},
+    update, Connection, ExpressionMethods, OptionalExtension, QueryDsl, Queryable,
+    RunQueryDsl,
+};
+use std::{borrow::Cow, collections::HashMap};
+
+#[derive(Debug, Clone, Queryable)]
+struct InnerServerNote<'a> {
+    id: BinaryWrapper<Cow<'a, Id>>,
+    server_id: BinaryWrapper<Cow<'a, Id>>,
+    entity_id: Option<BinaryWrapper<Cow<'a, Id>>>,
+    date: NaiveDateTime,
+    note: Cow<'a, str>,
+}
+
+impl InnerServerNote<'_> {
+    fn filter<'a, B, T>(
+        mut query: BoxedSelectStatement<'a, T, server_note::table, B>,
+        filter: &'a ServerNoteFilter<'_>,
+    ) -> BoxedSelectStatement<'a, T, server_note::table, B>
+    where
+        B: 'a
+            + Backend<RawValue = [u8]>
+            + UsesAnsiSavepointSyntax
+            + HasSqlType<Bool>,
+        bool: ToSql<Bool, B>,
+    {
+        if let Some(ref v) = filter.server_id {
+            query = query.filter(server_note::server_id.eq(BinaryWrapper(v)));
+        }
+        query
+    }
+}
+
+impl<'a> Into<ServerNote<'a>> for InnerServerNote<'a> {
+    fn into(self) -> ServerNote<'a> {
+        ServerNote {
+            id: self.id.0,
+            server_id: self.server_id.0,
+            entity_id: self.entity_id.map(|v| v.0),
+            author: None,
+            date: self.date,
+            note: self.note,
+        }
+    }
+}
+
+impl<'a, B, C, A> FetchById<'_, A, ServerNote<'a>, Self> for DieselDB<C>
+where
+    A: Auth,
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<EntityTypes>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    NaiveDateTime: FromSql<Timestamp, B>,
+    DbWrapper<EntityTypes>: Queryable<DbWrapper<EntityTypes>, B>,
+{
+    #[inline]
+    fn fetch(&self, id: &Id, auth: &A) -> DbResult<Option<ServerNote<'a>>, Self> {
+        if !auth.is_admin() {
+            return Ok(None);
+        }
+        let conn = self.get_read()?;
+        let query = server_note::dsl::server_note.find(BinaryWrapper(id));
+        let res: Option<InnerServerNote<'a>> = exec_opt!(query, conn, first)?;
+        let mut note: ServerNote<'a> = match res {
+            Some(note) => note.into(),
+            None => return Ok(None),
+        };
+        if let Some(ref entity_id) = note.entity_id {
+            note.author =
+                Self::fetch_entities(&conn, &[BinaryWrapper(&**entity_id)])?
+                    .pop()
+                    .and_then(|v| v.name);
+        }
+        Ok(Some(note))
+    }
+}
+
+impl<'a, B, C, A> FetchAll<'_, A, ServerNote<'a>, ServerNoteFilter<'_>, Self>
+    for DieselDB<C>
+where
+    A: Auth,
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<EntityTypes>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    NaiveDateTime: FromSql<Timestamp, B>,
+    DbWrapper<EntityTypes>: Queryable<DbWrapper<EntityTypes>, B>,
+{
+    #[inline]
+    fn fetch_all(
+        &self,
+        filter: &ServerNoteFilter<'_>,
+        auth: &A,
+        page: usize,
+    ) -> DbResult<DbList<ServerNote<'a>>, Self> {
+        if !auth.is_admin() {
+            return Ok(DbList {
+                data: Vec::new(),
+                count: 0,
+                page,
+                page_max: 0,
+            });
+        }
+        let res: Vec<InnerServerNote<'a>>;
+        let conn = self.get_read()?;
+
+        let offset = Self::compute_offset(page);
+        let count_query = server_note::dsl::server_note
+            .select(count_star())
+            .into_boxed::<B>();
+        let count_query = InnerServerNote::filter(count_query, filter);
+        let count = Self::compute_count(exec!(count_query, conn, first)?);
+        let page_max = Self::compute_page_max(count);
+
+        let query = server_note::dsl::server_note
+            .limit(25)
+            .offset(offset)
+            .order_by(server_note::date.desc())
+            .into_boxed::<B>();
+        let query = InnerServerNote::filter(query, filter);
+        res = exec!(query, conn, load)?;
+
+        let author_ids: Vec<BinaryWrapper<&Id>> = res
+            .iter()
+            .filter_map(|v| v.entity_id.as_ref())
+            .map(|v| BinaryWrapper(&*v.0))
+            .collect();
+        let authors: HashMap<Id, Cow<'static, str>> =
+            Self::fetch_entities(&conn, &author_ids)?
+                .into_iter()
+                .filter_map(|v| Some((v.entity_id.into_owned(), v.name?)))
+                .collect();
+        let data = res
+            .into_iter()
+            .map(|v| {
+                let mut note: ServerNote<'a> = v.into();
+                note.author = note
+                    .entity_id
+                    .as_ref()
+                    .and_then(|id| authors.get(&**id))
+                    .cloned();
+                note
+            })
+            .collect();
+
+        Ok(DbList {
+            data,
+            count,
+            page,
+            page_max,
+        })
+    }
+}
+
+impl<'a, A, B, C> Create<A, ServerNote<'a>, Self> for DieselDB<C>
+where
+    A: Auth,
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + SupportsDefaultKeyword
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<BigInt>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<EventTypes>>,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    i64: FromSql<BigInt, B> + ToSql<BigInt, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
+{
+    #[inline]
+    fn create(&self, object: &ServerNote<'a>, auth: &A) -> DbResult<(), Self> {
+        if !auth.is_admin() {
+            return Ok(());
+        }
+        let conn = self.get()?;
+        let query = insert_into(server_note::dsl::server_note).values((
+            server_note::id.eq(BinaryWrapper(&object.id)),
+            server_note::server_id.eq(BinaryWrapper(&object.server_id)),
+            server_note::entity_id
+                .eq(object.entity_id.as_deref().map(BinaryWrapper)),
+            server_note::date.eq(object.date),
+            server_note::note.eq(&object.note),
+        ));
+        let _ = exec!(query, conn, execute)?;
+
+        let details = EventDetails::ServerNoteAdd {
+            note_id: Cow::Borrowed(&object.id),
+            note: Cow::Borrowed(&object.note),
+        };
+        let event = Event::new(
+            Cow::Owned(self.generate_id()?),
+            Some(Cow::Borrowed(auth.get_id())),
+            details,
+            Some(Cow::Borrowed(&object.server_id)),
+        );
+        self.create(&event, auth)
+    }
+}
+
+impl<'a, A, B, C> Save<A, ServerNote<'a>, Self> for DieselDB<C>
+where
+    A: Auth,
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + SupportsDefaultKeyword
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<BigInt>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<EventTypes>>,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    i64: FromSql<BigInt, B> + ToSql<BigInt, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
+{
+    #[inline]
+    fn save(&self, object: &ServerNote<'a>, auth: &A) -> DbResult<(), Self> {
+        if !auth.is_admin() {
+            return Ok(());
+        }
+        let conn = self.get()?;
+        let query = server_note::dsl::server_note
+            .find(BinaryWrapper(&object.id))
+            .select(server_note::note);
+        let previous: String = match exec_opt!(query, conn, first)? {
+            Some(previous) => previous,
+            None => return Ok(()),
+        };
+        if previous == object.note {
+            return Ok(());
+        }
+        let query =
+            update(server_note::dsl::server_note.find(BinaryWrapper(&object.id)))
+                .set(server_note::note.eq(&object.note));
+        let _ = exec!(query, conn, execute)?;
+
+        let details = EventDetails::ServerNoteEdit {
+            note_id: Cow::Borrowed(&object.id),
+            previous: Cow::Owned(previous),
+            note: Cow::Borrowed(&object.note),
+        };
+        let event = Event::new(
+            Cow::Owned(self.generate_id()?),
+            Some(Cow::Borrowed(auth.get_id())),
+            details,
+            Some(Cow::Borrowed(&object.server_id)),
+        );
+        self.create(&event, auth)
+    }
+}
+
+impl<A, B, C> Delete<A, ServerNote<'_>, Self> for DieselDB<C>
+where
+    A: Auth,
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + SupportsDefaultKeyword
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<BigInt>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<EventTypes>>,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    i64: FromSql<BigInt, B> + ToSql<BigInt, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<EventTypes>: Queryable<DbWrapper<EventTypes>, B>,
+{
+    #[inline]
+    fn delete(&self, ids: &[Id], auth: &A) -> DbResult<(), Self> {
+        if !auth.is_admin() {
+            return Ok(());
+        }
+        let conn = self.get()?;
+        let ids: Vec<BinaryWrapper<&Id>> = ids.iter().map(BinaryWrapper).collect();
+        let select = server_note::dsl::server_note
+            .select((server_note::id, server_note::server_id, server_note::note))
+            .filter(server_note::id.eq_any(&ids));
+        let notes: Vec<(BinaryWrapper<Id>, BinaryWrapper<Id>, String)> =
+            exec!(select, conn, load)?;
+        let query = diesel::delete(server_note::dsl::server_note)
+            .filter(server_note::id.eq_any(&ids))
+            .into_boxed::<B>();
+        let _ = exec!(query, conn, execute)?;
+
+        for (id, server_id, note) in notes {
+            let details = EventDetails::ServerNoteDelete {
+                note_id: Cow::Owned(id.0),
+                note: Cow::Owned(note),
+            };
+            let event = Event::new(
+                Cow::Owned(self.generate_id()?),
+                Some(Cow::Borrowed(auth.get_id())),
+                details,
+                Some(Cow::Owned(server_id.0)),
+            );
+            self.create(&event, auth)?;
+        }
+        Ok(())
+    }
+}
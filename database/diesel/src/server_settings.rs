@@ -0,0 +1,55 @@
+use crate::{
+    error::DieselError,
+    exec,
+    migrate::Migrate,
+    schema::{server, server_account},
+    BinaryWrapper, DieselDB,
+};
+use core_common::{
+    database::{DbResult, ServerSettingsStore},
+    types::Id,
+};
+use diesel::{
+    backend::{Backend, SupportsDefaultKeyword, UsesAnsiSavepointSyntax},
+    connection::AnsiTransactionManager,
+    serialize::ToSql,
+    sql_types::{Bool, HasSqlType},
+    Connection, ExpressionMethods, QueryDsl, RunQueryDsl,
+};
+
+impl<B, C> ServerSettingsStore for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+{
+    #[inline]
+    fn set_require_security_key(
+        &self,
+        server_id: &Id,
+        required: bool,
+    ) -> DbResult<bool, Self> {
+        let conn = self.get()?;
+        let query = diesel::update(
+            server::dsl::server.filter(server::id.eq(BinaryWrapper(server_id))),
+        )
+        .set(server::require_security_key.eq(required));
+        if exec!(query, conn, execute)? == 0 {
+            return Ok(false);
+        }
+        let query = server_account::dsl::server_account
+            .select(server_account::entity_id)
+            .filter(server_account::server_id.eq(BinaryWrapper(server_id)));
+        let accounts: Vec<BinaryWrapper<Id>> = exec!(query, conn, load)?;
+        for account in accounts {
+            self.queue_account_sync(&conn, server_id, &account.0)?;
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{
+    error::DieselError,
+    exec, exec_opt,
+    migrate::Migrate,
+    schema::{server, server_token},
+    server::InnerServer,
+    BinaryWrapper, DbWrapper, DieselDB,
+};
+use core_common::{
+    chrono::{NaiveDateTime, Utc},
+    database::{DbResult, ServerTokenStore},
+    objects::Server,
+    types::{AuthorizationType, Id, KeyManagement, SyncStatusType},
+};
+use diesel::{
+    backend::{Backend, SupportsDefaultKeyword, UsesAnsiSavepointSyntax},
+    connection::AnsiTransactionManager,
+    deserialize::FromSql,
+    insert_into,
+    serialize::ToSql,
+    sql_types::{Bool, HasSqlType, Timestamp},
+    Connection, ExpressionMethods, JoinOnDsl, OptionalExtension, QueryDsl,
+    Queryable, RunQueryDsl,
+};
+
+impl<B, C> ServerTokenStore for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<KeyManagement>>
+        + HasSqlType<DbWrapper<AuthorizationType>>
+        + HasSqlType<DbWrapper<SyncStatusType>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<KeyManagement>: Queryable<DbWrapper<KeyManagement>, B>,
+    DbWrapper<AuthorizationType>: Queryable<DbWrapper<AuthorizationType>, B>,
+    DbWrapper<SyncStatusType>: Queryable<DbWrapper<SyncStatusType>, B>,
+{
+    #[inline]
+    fn save_server_token(
+        &self,
+        server_id: &Id,
+        digest: Option<&[u8]>,
+    ) -> DbResult<bool, Self> {
+        let conn = self.get()?;
+        Self::transaction(&conn, || {
+            let query = diesel::delete(
+                server_token::dsl::server_token
+                    .filter(server_token::server_id.eq(BinaryWrapper(server_id))),
+            );
+            let revoked = exec!(query, conn, execute)? > 0;
+            if let Some(digest) = digest {
+                let query = insert_into(server_token::dsl::server_token).values((
+                    server_token::server_id.eq(BinaryWrapper(server_id)),
+                    server_token::token.eq(digest),
+                    server_token::created.eq(Utc::now().naive_utc()),
+                ));
+                let _ = exec!(query, conn, execute)?;
+            }
+            Ok(revoked)
+        })
+    }
+
+    #[inline]
+    fn fetch_server_by_token(
+        &self,
+        server_id: &Id,
+        digest: &[u8],
+    ) -> DbResult<Option<Server<'static>>, Self> {
+        let conn = self.get_read()?;
+        let query = server::dsl::server
+            .inner_join(
+                server_token::dsl::server_token
+                    .on(server_token::server_id.eq(server::id)),
+            )
+            .select(server::all_columns)
+            .filter(server::id.eq(BinaryWrapper(server_id)))
+            .filter(server_token::token.eq(digest));
+        let server: Option<InnerServer<'static>> = exec_opt!(query, conn, first)?;
+        Ok(server.map(Into::into))
+    }
+}
@@ -121,7 +121,7 @@ where
     #[inline]
     fn fetch(&self, id: &Id, _auth: &A) -> DbResult<Option<User<'a>>, Self> {
         let res: Option<InnerUser<'a>>;
-        let conn = self.get()?;
+        let conn = self.get_read()?;
 
         let query = users::dsl::users
             .select(InnerUser::keys())
@@ -154,6 +154,7 @@ where
         _auth: &A,
     ) -> DbResult<Option<User<'a>>, Self> {
         let res: Option<InnerUser<'a>>;
+        // Uses the primary as the user may have just been created during login
         let conn = self.get()?;
 
         let query = users::dsl::users
@@ -188,7 +189,7 @@ where
         page: usize,
     ) -> DbResult<DbList<User<'a>>, Self> {
         let res: Vec<InnerUser<'a>>;
-        let conn = self.get()?;
+        let conn = self.get_read()?;
 
         let offset = Self::compute_offset(page);
         let types: Option<Vec<DbWrapper<UserTypes>>> = if auth.is_admin() {
@@ -0,0 +1,484 @@
+use crate::{
+    error::DieselError,
+    exec, exec_opt, exec_unique,
+    migrate::Migrate,
+    schema::{webhook, webhook_delivery},
+    BinaryWrapper, DbWrapper, DieselDB, DieselPooledConnection, UniqueExtension,
+};
+use core_common::{
+    chrono::{Duration, NaiveDateTime, Utc},
+    database::{
+        Create, DatabaseError, DbList, DbResult, Delete, FetchAll, FetchById,
+        WebhookQueue,
+    },
+    objects::{
+        Event, Webhook, WebhookDelivery, WebhookDeliveryFilter, WebhookFilter,
+    },
+    sec::Auth,
+    types::{DeliveryStatus, Id},
+    webhook::payload,
+};
+use diesel::{
+    backend::{Backend, SupportsDefaultKeyword, UsesAnsiSavepointSyntax},
+    connection::AnsiTransactionManager,
+    deserialize::FromSql,
+    dsl::count_star,
+    insert_into,
+    query_builder::BoxedSelectStatement,
+    serialize::ToSql,
+    sql_types::{Bool, HasSqlType, Timestamp},
+    update, Connection, ExpressionMethods, OptionalExtension, QueryDsl, Queryable,
+    RunQueryDsl, TextExpressionMethods,
+};
+use std::{borrow::Cow, collections::HashMap, convert::TryFrom};
+
+/// Seconds a claimed delivery is hidden from other workers. Deliveries of
+/// a crashed worker are attempted again afterwards
+const CLAIM_SECS: i64 = 900;
+
+#[derive(Debug, Clone, Queryable)]
+struct InnerWebhook<'a> {
+    id: BinaryWrapper<Cow<'a, Id>>,
+    name: Cow<'a, str>,
+    url: Cow<'a, str>,
+    secret: Vec<u8>,
+    actions: Option<Cow<'a, str>>,
+    active: bool,
+}
+
+impl InnerWebhook<'_> {
+    fn filter<'a, B, T>(
+        mut query: BoxedSelectStatement<'a, T, webhook::table, B>,
+        filter: &'a WebhookFilter<'_>,
+    ) -> BoxedSelectStatement<'a, T, webhook::table, B>
+    where
+        B: 'a
+            + Backend<RawValue = [u8]>
+            + UsesAnsiSavepointSyntax
+            + HasSqlType<Bool>,
+        bool: ToSql<Bool, B>,
+    {
+        if let Some(ref v) = filter.name {
+            query = query.filter(webhook::name.like(v));
+        }
+        if let Some(ref v) = filter.url {
+            query = query.filter(webhook::url.like(v));
+        }
+        query
+    }
+}
+
+impl<'a> Into<Webhook<'a>> for InnerWebhook<'a> {
+    fn into(self) -> Webhook<'a> {
+        Webhook {
+            id: self.id.0,
+            name: self.name,
+            url: self.url,
+            secret: Cow::Owned(self.secret),
+            actions: self.actions,
+            active: self.active,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Queryable)]
+struct InnerWebhookDelivery<'a> {
+    id: BinaryWrapper<Cow<'a, Id>>,
+    webhook_id: BinaryWrapper<Cow<'a, Id>>,
+    event_id: BinaryWrapper<Cow<'a, Id>>,
+    payload: Cow<'a, str>,
+    status: DbWrapper<DeliveryStatus>,
+    attempts: i32,
+    next_attempt: Option<NaiveDateTime>,
+    response_code: Option<i32>,
+    error: Option<Cow<'a, str>>,
+    date: NaiveDateTime,
+}
+
+impl InnerWebhookDelivery<'_> {
+    fn filter<'a, B, T>(
+        mut query: BoxedSelectStatement<'a, T, webhook_delivery::table, B>,
+        filter: &'a WebhookDeliveryFilter<'_>,
+    ) -> BoxedSelectStatement<'a, T, webhook_delivery::table, B>
+    where
+        B: 'a
+            + Backend<RawValue = [u8]>
+            + UsesAnsiSavepointSyntax
+            + HasSqlType<Bool>
+            + HasSqlType<DbWrapper<DeliveryStatus>>,
+        bool: ToSql<Bool, B>,
+    {
+        if let Some(ref v) = filter.webhook_id {
+            query = query.filter(webhook_delivery::webhook_id.eq(BinaryWrapper(v)));
+        }
+        if let Some(v) = filter.status {
+            query = query.filter(webhook_delivery::status.eq(DbWrapper(v)));
+        }
+        query
+    }
+}
+
+impl<'a> Into<WebhookDelivery<'a>> for InnerWebhookDelivery<'a> {
+    fn into(self) -> WebhookDelivery<'a> {
+        WebhookDelivery {
+            id: self.id.0,
+            webhook_id: self.webhook_id.0,
+            event_id: self.event_id.0,
+            payload: self.payload,
+            status: self.status.0,
+            attempts: self.attempts,
+            next_attempt: self.next_attempt,
+            response_code: self.response_code,
+            error: self.error,
+            date: Some(self.date),
+        }
+    }
+}
+
+impl<B, C> DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+{
+    /// Queues a delivery of the event for every active webhook
+    /// subscribed to its action
+    pub(crate) fn enqueue_webhooks(
+        &self,
+        conn: &DieselPooledConnection<C>,
+        event: &Event<'_>,
+    ) -> DbResult<(), Self> {
+        let query = webhook::dsl::webhook
+            .select((webhook::id, webhook::actions))
+            .filter(webhook::active.eq(true));
+        let webhooks: Vec<(BinaryWrapper<Id>, Option<String>)> =
+            exec!(query, *conn, load)?;
+        let action = event.details.action();
+        let webhooks: Vec<BinaryWrapper<Id>> = webhooks
+            .into_iter()
+            .filter(|(_, actions)| {
+                Webhook::action_matches(actions.as_deref(), action)
+            })
+            .map(|(id, _)| id)
+            .collect();
+        if webhooks.is_empty() {
+            return Ok(());
+        }
+
+        let body = payload(event);
+        let mut values = Vec::with_capacity(webhooks.len());
+        for webhook_id in webhooks {
+            values.push((
+                webhook_delivery::id.eq(BinaryWrapper(self.generate_id()?)),
+                webhook_delivery::webhook_id.eq(webhook_id),
+                webhook_delivery::event_id.eq(BinaryWrapper(&event.id)),
+                webhook_delivery::payload.eq(&body),
+            ));
+        }
+        let query =
+            insert_into(webhook_delivery::dsl::webhook_delivery).values(values);
+        let _ = exec!(query, *conn, execute)?;
+        Ok(())
+    }
+}
+
+impl<'a, B, C, A> FetchById<'_, A, Webhook<'a>, Self> for DieselDB<C>
+where
+    A: Auth,
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B> + FromSql<Bool, B>,
+{
+    #[inline]
+    fn fetch(&self, id: &Id, auth: &A) -> DbResult<Option<Webhook<'a>>, Self> {
+        if !auth.is_admin() {
+            return Ok(None);
+        }
+        let conn = self.get_read()?;
+        let query = webhook::dsl::webhook.find(BinaryWrapper(id));
+        let res: Option<InnerWebhook<'_>> = exec_opt!(query, conn, first)?;
+        Ok(res.map(|v| v.into()))
+    }
+}
+
+impl<'a, B, C, A> FetchAll<'_, A, Webhook<'a>, WebhookFilter<'_>, Self>
+    for DieselDB<C>
+where
+    A: Auth,
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B> + FromSql<Bool, B>,
+{
+    #[inline]
+    fn fetch_all(
+        &self,
+        filter: &WebhookFilter<'_>,
+        auth: &A,
+        page: usize,
+    ) -> DbResult<DbList<Webhook<'a>>, Self> {
+        if !auth.is_admin() {
+            return Ok(DbList {
+                data: Vec::new(),
+                count: 0,
+                page,
+                page_max: 0,
+            });
+        }
+        let res: Vec<InnerWebhook<'a>>;
+        let conn = self.get_read()?;
+
+        let offset = Self::compute_offset(page);
+        let count_query =
+            webhook::dsl::webhook.select(count_star()).into_boxed::<B>();
+        let count_query = InnerWebhook::filter(count_query, filter);
+        let count = Self::compute_count(exec!(count_query, conn, first)?);
+        let page_max = Self::compute_page_max(count);
+
+        let query = webhook::dsl::webhook
+            .limit(25)
+            .offset(offset)
+            .order_by(webhook::name.asc())
+            .into_boxed::<B>();
+        let query = InnerWebhook::filter(query, filter);
+        res = exec!(query, conn, load)?;
+
+        Ok(DbList {
+            data: res.into_iter().map(|v| v.into()).collect(),
+            count,
+            page,
+            page_max,
+        })
+    }
+}
+
+impl<'a, A, B, C: 'static + Connection> Create<A, Webhook<'a>, Self> for DieselDB<C>
+where
+    A: Auth,
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + SupportsDefaultKeyword
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+{
+    #[inline]
+    fn create(&self, object: &Webhook<'a>, auth: &A) -> DbResult<(), Self> {
+        if auth.is_admin() {
+            let conn = self.get()?;
+            let query = insert_into(webhook::dsl::webhook).values((
+                webhook::id.eq(BinaryWrapper(&object.id)),
+                webhook::name.eq(&object.name),
+                webhook::url.eq(&object.url),
+                webhook::secret.eq(object.secret.as_ref()),
+                webhook::actions.eq(&object.actions),
+                webhook::active.eq(object.active),
+            ));
+            let _ = exec_unique!(query, conn, execute)?;
+        }
+        Ok(())
+    }
+}
+
+impl<A, B, C> Delete<A, Webhook<'_>, Self> for DieselDB<C>
+where
+    A: Auth,
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+{
+    #[inline]
+    fn delete(&self, ids: &[Id], auth: &A) -> DbResult<(), Self> {
+        if auth.is_admin() {
+            let conn = self.get()?;
+            let ids: Vec<BinaryWrapper<&Id>> =
+                ids.iter().map(BinaryWrapper).collect();
+            let query = diesel::delete(webhook_delivery::dsl::webhook_delivery)
+                .filter(webhook_delivery::webhook_id.eq_any(&ids))
+                .into_boxed::<B>();
+            let _ = exec!(query, conn, execute)?;
+            let query = diesel::delete(webhook::dsl::webhook)
+                .filter(webhook::id.eq_any(&ids))
+                .into_boxed::<B>();
+            let _ = exec!(query, conn, execute)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, B, C, A>
+    FetchAll<'_, A, WebhookDelivery<'a>, WebhookDeliveryFilter<'_>, Self>
+    for DieselDB<C>
+where
+    A: Auth,
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<DeliveryStatus>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<DeliveryStatus>: Queryable<DbWrapper<DeliveryStatus>, B>,
+{
+    #[inline]
+    fn fetch_all(
+        &self,
+        filter: &WebhookDeliveryFilter<'_>,
+        auth: &A,
+        page: usize,
+    ) -> DbResult<DbList<WebhookDelivery<'a>>, Self> {
+        if !auth.is_admin() {
+            return Ok(DbList {
+                data: Vec::new(),
+                count: 0,
+                page,
+                page_max: 0,
+            });
+        }
+        let res: Vec<InnerWebhookDelivery<'a>>;
+        let conn = self.get_read()?;
+
+        let offset = Self::compute_offset(page);
+        let count_query = webhook_delivery::dsl::webhook_delivery
+            .select(count_star())
+            .into_boxed::<B>();
+        let count_query = InnerWebhookDelivery::filter(count_query, filter);
+        let count = Self::compute_count(exec!(count_query, conn, first)?);
+        let page_max = Self::compute_page_max(count);
+
+        let query = webhook_delivery::dsl::webhook_delivery
+            .limit(25)
+            .offset(offset)
+            .order_by(webhook_delivery::date.desc())
+            .into_boxed::<B>();
+        let query = InnerWebhookDelivery::filter(query, filter);
+        res = exec!(query, conn, load)?;
+
+        Ok(DbList {
+            data: res.into_iter().map(|v| v.into()).collect(),
+            count,
+            page,
+            page_max,
+        })
+    }
+}
+
+impl<B, C> WebhookQueue for DieselDB<C>
+where
+    B: 'static
+        + Backend<RawValue = [u8]>
+        + UsesAnsiSavepointSyntax
+        + HasSqlType<Bool>
+        + HasSqlType<Timestamp>
+        + HasSqlType<DbWrapper<DeliveryStatus>>
+        + SupportsDefaultKeyword,
+    C: 'static
+        + Connection<Backend = B, TransactionManager = AnsiTransactionManager>
+        + Migrate,
+    bool: ToSql<Bool, B> + FromSql<Bool, B>,
+    NaiveDateTime: FromSql<Timestamp, B> + ToSql<Timestamp, B>,
+    DbWrapper<DeliveryStatus>: Queryable<DbWrapper<DeliveryStatus>, B>,
+{
+    #[inline]
+    fn fetch_due(
+        &self,
+        limit: usize,
+    ) -> DbResult<Vec<(Webhook<'static>, WebhookDelivery<'static>)>, Self> {
+        let conn = self.get()?;
+        let query = webhook::dsl::webhook.filter(webhook::active.eq(true));
+        let webhooks: Vec<InnerWebhook<'static>> = exec!(query, conn, load)?;
+        if webhooks.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids: Vec<BinaryWrapper<Id>> = webhooks
+            .iter()
+            .map(|v| BinaryWrapper(v.id.0.as_ref().clone()))
+            .collect();
+        let webhooks: HashMap<Id, Webhook<'static>> = webhooks
+            .into_iter()
+            .map(|v| {
+                let webhook: Webhook<'static> = v.into();
+                (webhook.id.as_ref().clone(), webhook)
+            })
+            .collect();
+
+        // Deliveries of inactive webhooks are skipped in the query so they
+        // cannot take up the whole batch
+        let now = Utc::now().naive_utc();
+        let query = webhook_delivery::dsl::webhook_delivery
+            .filter(webhook_delivery::status.eq(DbWrapper(DeliveryStatus::Pending)))
+            .filter(webhook_delivery::next_attempt.le(now))
+            .filter(webhook_delivery::webhook_id.eq_any(&ids))
+            .order_by(webhook_delivery::next_attempt.asc())
+            .limit(i64::try_from(limit).unwrap_or(i64::MAX));
+        let deliveries: Vec<InnerWebhookDelivery<'static>> =
+            exec!(query, conn, load)?;
+
+        let lease = now.checked_add_signed(Duration::seconds(CLAIM_SECS));
+        let mut due = Vec::with_capacity(deliveries.len());
+        for delivery in deliveries {
+            // Claims the delivery by moving its next attempt. Only one
+            // instance is able to update the row while it is still due
+            let query = update(webhook_delivery::dsl::webhook_delivery)
+                .filter(
+                    webhook_delivery::id.eq(BinaryWrapper(delivery.id.0.as_ref())),
+                )
+                .filter(webhook_delivery::next_attempt.eq(delivery.next_attempt))
+                .set(webhook_delivery::next_attempt.eq(lease));
+            if exec!(query, conn, execute)? == 0 {
+                continue;
+            }
+            if let Some(webhook) = webhooks.get(delivery.webhook_id.0.as_ref()) {
+                due.push((webhook.clone(), delivery.into()));
+            }
+        }
+        Ok(due)
+    }
+
+    #[inline]
+    fn finish_delivery(&self, delivery: &WebhookDelivery<'_>) -> DbResult<(), Self> {
+        let conn = self.get()?;
+        let query = update(webhook_delivery::dsl::webhook_delivery)
+            .filter(webhook_delivery::id.eq(BinaryWrapper(&delivery.id)))
+            .set((
+                webhook_delivery::status.eq(DbWrapper(delivery.status)),
+                webhook_delivery::attempts.eq(delivery.attempts),
+                webhook_delivery::next_attempt.eq(delivery.next_attempt),
+                webhook_delivery::response_code.eq(delivery.response_code),
+                webhook_delivery::error.eq(&delivery.error),
+            ));
+        let _ = exec!(query, conn, execute)?;
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@ use block_modes::{block_padding::Pkcs7, BlockMode, Cbc};
 use core_common::{
     async_trait::async_trait,
     base64,
-    database::{Create, Database, FetchByUid, Save},
+    database::{ApiTokenStore, Create, Database, FetchByUid, Save},
     http::{
         header::{HeaderValue, SET_COOKIE},
         response,
@@ -22,7 +22,7 @@ use core_common::{
     },
 };
 use rand::{rngs::OsRng, RngCore};
-use std::{error, fmt, string};
+use std::{borrow::Cow, error, fmt, string};
 
 /// Database encountered an Error
 #[derive(Debug)]
@@ -94,6 +94,11 @@ pub struct Token {
     pub name: Option<String>,
     /// Type of the user account
     pub type_: UserTypes,
+    /// The id of the stored api token if the token was created for api
+    /// access. Such tokens are only accepted in the `Authorization` header
+    /// and only as long as the stored token exists
+    #[serde(default)]
+    pub token_id: Option<Id>,
 }
 
 impl Token {
@@ -124,6 +129,38 @@ impl Token {
             .map(|token| token.claims)
     }
 
+    /// Checks that the api token is still stored and takes the type of the
+    /// user from the database, so revoked tokens and changed permissions
+    /// take effect on the next request
+    fn check_api_token<D, T, R>(req: &R, token: Self) -> Option<Self>
+    where
+        for<'a> D: Database + ApiTokenStore + FetchByUid<PreAuth, User<'a>, D>,
+        T: TemplateEngine,
+        R: Request<Self, D, T>,
+    {
+        let db = req.get_database();
+        let stored = db
+            .fetch_api_token(token.token_id.as_ref()?)
+            .map_err(|err| {
+                log::warn!("Unable to fetch api token: {}", err);
+            })
+            .ok()??;
+        let user = db
+            .fetch_by_uid(&token.uid, &PreAuth)
+            .map_err(|err| {
+                log::warn!("Unable to fetch user of api token: {}", err);
+            })
+            .ok()??;
+        if *stored.entity_id != token.id || *user.entity_id != token.id {
+            return None;
+        }
+        Some(Self {
+            name: user.name.map(Cow::into_owned),
+            type_: user.type_,
+            ..token
+        })
+    }
+
     fn decrypt<D: Database, T: TemplateEngine, R: Request<Self, D, T>>(
         data: &str,
         key: &[u8],
@@ -192,14 +229,23 @@ impl Auth for Token {
     ) -> Option<Self>
     where
         for<'a> D: Database
+            + ApiTokenStore
             + FetchByUid<PreAuth, User<'a>, D>
             + Create<PreAuth, User<'a>, D>
             + Save<PreAuth, User<'a>, D>,
         T: TemplateEngine,
         R: Request<Self, D, T> + Sync,
     {
+        if let Some(token) = req
+            .get_authorization()
+            .and_then(|v| v.strip_prefix("Bearer "))
+        {
+            return Self::decode_token_string(req, token)
+                .and_then(|token| Self::check_api_token(req, token));
+        }
         let mut auth = OAuth2::get_token_cookie(req)
-            .and_then(|token| Self::decode_token_string(req, token));
+            .and_then(|token| Self::decode_token_string(req, token))
+            .filter(|token| token.token_id.is_none());
         if auth.is_none() {
             log::warn!("Using refresh token");
             if let Some(refresh_token) = OAuth2::get_refresh_cookie(req) {
@@ -259,6 +305,7 @@ impl Auth for Token {
             uid: username,
             name: None,
             type_,
+            token_id: None,
         };
         Ok(Some(token))
     }
@@ -314,4 +361,29 @@ impl Auth for Token {
                 .map_err(AppError::AuthError)?;
         Self::encrypt(&token_str, &data.app_secret).map(Some)
     }
+
+    #[inline]
+    fn get_api_token<D, T, R>(
+        &self,
+        req: &R,
+        token_id: &Id,
+        exp: u64,
+    ) -> Result<Option<String>, AppError<Self, D, T, R>>
+    where
+        D: Database,
+        T: TemplateEngine,
+        R: Request<Self, D, T>,
+    {
+        let token = Self {
+            exp,
+            token_id: Some(token_id.clone()),
+            ..self.clone()
+        };
+        token.get_str(req)
+    }
+
+    #[inline]
+    fn is_api_token(&self) -> bool {
+        self.token_id.is_some()
+    }
 }
@@ -1,8 +1,16 @@
 use clap::{
     app_from_crate, crate_authors, crate_description, crate_name, crate_version,
-    Arg, ArgMatches,
+    Arg, ArgMatches, SubCommand,
 };
-use std::process::exit;
+use core_common::{
+    mail::{MailConfig, SmtpSecurity},
+    objects::{
+        CertificatePolicy, KeyBlocklist, KeyPolicy,
+        DEFAULT_CERTIFICATE_MAX_VALIDITY, DEFAULT_CERTIFICATE_VALIDITY,
+        DEFAULT_EXPIRY_WARNING, DEFAULT_HOST_CERTIFICATE_VALIDITY,
+    },
+};
+use std::{convert::TryFrom, fs, process::exit, time::Duration};
 
 const ARGS_LISTEN: &str = "listen";
 const ARGS_LISTEN_ENV: &str = "LISTEN";
@@ -14,6 +22,7 @@ const ARGS_PORT_DEFAULT_U16: u16 = 8080;
 const ARGS_VERBOSE: &str = "verbose";
 const ARGS_SILENT: &str = "silent";
 const ARGS_VERSION: &str = "version";
+const ARGS_VERIFY_AUDIT: &str = "verify-audit";
 
 const ARGS_DATABASE_HOST: &str = "db-host";
 const ARGS_DATABASE_HOST_ENV: &str = "DB_HOST";
@@ -23,6 +32,115 @@ const ARGS_DATABASE_USER: &str = "db-user";
 const ARGS_DATABASE_USER_ENV: &str = "DB_USER";
 const ARGS_DATABASE_PASS: &str = "db-pass";
 const ARGS_DATABASE_PASS_ENV: &str = "DB_PASS";
+const ARGS_DATABASE_POOL_SIZE: &str = "db-pool-size";
+const ARGS_DATABASE_POOL_SIZE_ENV: &str = "DB_POOL_SIZE";
+const ARGS_DATABASE_POOL_SIZE_DEFAULT: &str = "20";
+const ARGS_DATABASE_POOL_SIZE_DEFAULT_U32: u32 = 20;
+const ARGS_DATABASE_POOL_IDLE: &str = "db-pool-idle";
+const ARGS_DATABASE_POOL_IDLE_ENV: &str = "DB_POOL_IDLE";
+const ARGS_DATABASE_POOL_IDLE_DEFAULT: &str = "2";
+const ARGS_DATABASE_POOL_IDLE_DEFAULT_U32: u32 = 2;
+const ARGS_DATABASE_CONNECT_TIMEOUT: &str = "db-connect-timeout";
+const ARGS_DATABASE_CONNECT_TIMEOUT_ENV: &str = "DB_CONNECT_TIMEOUT";
+const ARGS_DATABASE_CONNECT_TIMEOUT_DEFAULT: &str = "1000";
+const ARGS_DATABASE_CONNECT_TIMEOUT_DEFAULT_U64: u64 = 1000;
+const ARGS_DATABASE_CHECKOUT_TIMEOUT: &str = "db-checkout-timeout";
+const ARGS_DATABASE_CHECKOUT_TIMEOUT_ENV: &str = "DB_CHECKOUT_TIMEOUT";
+const ARGS_DATABASE_CHECKOUT_TIMEOUT_DEFAULT: &str = "100";
+const ARGS_DATABASE_CHECKOUT_TIMEOUT_DEFAULT_U64: u64 = 100;
+const ARGS_DATABASE_HEALTH_INTERVAL: &str = "db-health-interval";
+const ARGS_DATABASE_HEALTH_INTERVAL_ENV: &str = "DB_HEALTH_INTERVAL";
+const ARGS_DATABASE_HEALTH_INTERVAL_DEFAULT: &str = "10";
+const ARGS_DATABASE_HEALTH_INTERVAL_DEFAULT_U64: u64 = 10;
+
+const ARGS_AUDIT_CHECKPOINT_INTERVAL: &str = "audit-checkpoint-interval";
+const ARGS_AUDIT_CHECKPOINT_INTERVAL_ENV: &str = "AUDIT_CHECKPOINT_INTERVAL";
+const ARGS_AUDIT_CHECKPOINT_INTERVAL_DEFAULT: &str = "3600";
+const ARGS_AUDIT_CHECKPOINT_INTERVAL_DEFAULT_U64: u64 = 3600;
+
+const ARGS_WEBHOOK_INTERVAL: &str = "webhook-interval";
+const ARGS_WEBHOOK_INTERVAL_ENV: &str = "WEBHOOK_INTERVAL";
+const ARGS_WEBHOOK_INTERVAL_DEFAULT: &str = "10";
+const ARGS_WEBHOOK_INTERVAL_DEFAULT_U64: u64 = 10;
+const ARGS_WEBHOOK_TIMEOUT: &str = "webhook-timeout";
+const ARGS_WEBHOOK_TIMEOUT_ENV: &str = "WEBHOOK_TIMEOUT";
+const ARGS_WEBHOOK_TIMEOUT_DEFAULT: &str = "10";
+const ARGS_WEBHOOK_TIMEOUT_DEFAULT_U64: u64 = 10;
+
+const ARGS_SMTP_HOST: &str = "smtp-host";
+const ARGS_SMTP_HOST_ENV: &str = "SMTP_HOST";
+const ARGS_SMTP_PORT: &str = "smtp-port";
+const ARGS_SMTP_PORT_ENV: &str = "SMTP_PORT";
+const ARGS_SMTP_PORT_DEFAULT: &str = "587";
+const ARGS_SMTP_PORT_DEFAULT_U16: u16 = 587;
+const ARGS_SMTP_SECURITY: &str = "smtp-security";
+const ARGS_SMTP_SECURITY_ENV: &str = "SMTP_SECURITY";
+const ARGS_SMTP_SECURITY_DEFAULT: &str = "starttls";
+const ARGS_SMTP_USER: &str = "smtp-user";
+const ARGS_SMTP_USER_ENV: &str = "SMTP_USER";
+const ARGS_SMTP_PASS: &str = "smtp-pass";
+const ARGS_SMTP_PASS_ENV: &str = "SMTP_PASS";
+const ARGS_SMTP_TIMEOUT: &str = "smtp-timeout";
+const ARGS_SMTP_TIMEOUT_ENV: &str = "SMTP_TIMEOUT";
+const ARGS_SMTP_TIMEOUT_DEFAULT: &str = "10";
+const ARGS_SMTP_TIMEOUT_DEFAULT_U64: u64 = 10;
+const ARGS_MAIL_FROM: &str = "mail-from";
+const ARGS_MAIL_FROM_ENV: &str = "MAIL_FROM";
+const ARGS_MAIL_FROM_NAME: &str = "mail-from-name";
+const ARGS_MAIL_FROM_NAME_ENV: &str = "MAIL_FROM_NAME";
+const ARGS_MAIL_FROM_NAME_DEFAULT: &str = "SSH Key Authority";
+const ARGS_NOTIFICATION_INTERVAL: &str = "notification-interval";
+const ARGS_NOTIFICATION_INTERVAL_ENV: &str = "NOTIFICATION_INTERVAL";
+const ARGS_NOTIFICATION_INTERVAL_DEFAULT: &str = "30";
+const ARGS_NOTIFICATION_INTERVAL_DEFAULT_U64: u64 = 30;
+
+const ARGS_KEY_TYPES: &str = "key-types";
+const ARGS_KEY_TYPES_ENV: &str = "KEY_TYPES";
+const ARGS_KEY_TYPES_DEFAULT: &str = concat!(
+    "ssh-ed25519,ecdsa-sha2-nistp256,ecdsa-sha2-nistp384,ecdsa-sha2-nistp521,",
+    "ssh-rsa:3072,sk-ssh-ed25519@openssh.com,sk-ecdsa-sha2-nistp256@openssh.com"
+);
+const ARGS_MAX_KEYS: &str = "max-keys";
+const ARGS_MAX_KEYS_ENV: &str = "MAX_KEYS";
+const ARGS_MAX_KEYS_DEFAULT: &str = "0";
+const ARGS_KEY_MAX_AGE: &str = "key-max-age";
+const ARGS_KEY_MAX_AGE_ENV: &str = "KEY_MAX_AGE";
+const ARGS_KEY_MAX_AGE_DEFAULT: &str = "0";
+const ARGS_KEY_EXPIRY_WARNING: &str = "key-expiry-warning";
+const ARGS_KEY_EXPIRY_WARNING_ENV: &str = "KEY_EXPIRY_WARNING";
+const ARGS_KEY_EXPIRY_WARNING_DEFAULT: &str = "14";
+const ARGS_KEY_EXPIRY_INTERVAL: &str = "key-expiry-interval";
+const ARGS_KEY_EXPIRY_INTERVAL_ENV: &str = "KEY_EXPIRY_INTERVAL";
+const ARGS_KEY_EXPIRY_INTERVAL_DEFAULT: &str = "3600";
+const ARGS_KEY_EXPIRY_INTERVAL_DEFAULT_U64: u64 = 3600;
+const ARGS_KEY_RETENTION: &str = "key-retention";
+const ARGS_KEY_RETENTION_ENV: &str = "KEY_RETENTION";
+const ARGS_KEY_RETENTION_DEFAULT: &str = "0";
+const ARGS_KEY_RETENTION_INTERVAL: &str = "key-retention-interval";
+const ARGS_KEY_RETENTION_INTERVAL_ENV: &str = "KEY_RETENTION_INTERVAL";
+const ARGS_KEY_RETENTION_INTERVAL_DEFAULT: &str = "3600";
+const ARGS_KEY_RETENTION_INTERVAL_DEFAULT_U64: u64 = 3600;
+const ARGS_ACCESS_SCHEDULE_INTERVAL: &str = "access-schedule-interval";
+const ARGS_ACCESS_SCHEDULE_INTERVAL_ENV: &str = "ACCESS_SCHEDULE_INTERVAL";
+const ARGS_ACCESS_SCHEDULE_INTERVAL_DEFAULT: &str = "60";
+const ARGS_ACCESS_SCHEDULE_INTERVAL_DEFAULT_U64: u64 = 60;
+const ARGS_KEY_BLOCKLIST: &str = "key-blocklist";
+const ARGS_KEY_BLOCKLIST_ENV: &str = "KEY_BLOCKLIST";
+const ARGS_CERT_VALIDITY: &str = "cert-validity";
+const ARGS_CERT_VALIDITY_ENV: &str = "CERT_VALIDITY";
+const ARGS_CERT_VALIDITY_DEFAULT: &str = "8";
+const ARGS_CERT_MAX_VALIDITY: &str = "cert-max-validity";
+const ARGS_CERT_MAX_VALIDITY_ENV: &str = "CERT_MAX_VALIDITY";
+const ARGS_CERT_MAX_VALIDITY_DEFAULT: &str = "24";
+const ARGS_HOST_CERT_VALIDITY: &str = "host-cert-validity";
+const ARGS_HOST_CERT_VALIDITY_ENV: &str = "HOST_CERT_VALIDITY";
+const ARGS_HOST_CERT_VALIDITY_DEFAULT: &str = "30";
+const ARGS_API_TOKEN_VALIDITY: &str = "api-token-validity";
+const ARGS_API_TOKEN_VALIDITY_ENV: &str = "API_TOKEN_VALIDITY";
+const ARGS_API_TOKEN_VALIDITY_DEFAULT: &str = "90";
+const ARGS_API_TOKEN_VALIDITY_DEFAULT_U32: u32 = 90;
+const ARGS_BREAK_GLASS_ACCOUNT: &str = "break-glass-account";
+const ARGS_BREAK_GLASS_ACCOUNT_ENV: &str = "BREAK_GLASS_ACCOUNT";
 
 const ARGS_APP_SECRET: &str = "app-secret";
 const ARGS_APP_SECRET_ENV: &str = "APP_SECRET";
@@ -46,6 +164,7 @@ const ARGS_OAUTH_CLIENT_SUPERUSER_SCOPE_ENV: &str = "OAUTH_SUPERUSER_SCOPE";
 #[derive(Debug, Clone)]
 pub struct CliArguments {
     pub print_version: bool,
+    pub verify_audit: bool,
 
     pub listen: String,
     pub port: u16,
@@ -57,6 +176,26 @@ pub struct CliArguments {
     pub db_name: String,
     pub db_user: String,
     pub db_pass: String,
+    pub db_pool_size: u32,
+    pub db_pool_idle: u32,
+    pub db_connect_timeout: Duration,
+    pub db_checkout_timeout: Duration,
+    pub db_health_interval: Duration,
+
+    pub audit_checkpoint_interval: Option<Duration>,
+    pub webhook_interval: Option<Duration>,
+    pub webhook_timeout: Duration,
+    pub mail: Option<MailConfig>,
+    pub notification_interval: Option<Duration>,
+
+    pub key_policy: KeyPolicy,
+    pub key_expiry_interval: Option<Duration>,
+    pub key_retention_interval: Option<Duration>,
+    pub access_schedule_interval: Option<Duration>,
+    pub key_blocklist: KeyBlocklist,
+    pub certificate_policy: CertificatePolicy,
+    pub api_token_validity: u32,
+    pub break_glass_accounts: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -80,6 +219,7 @@ pub struct OAuth {
 pub fn get_arguments() -> CliArguments {
     let matches = get_cli_config();
     let print_version = matches.is_present(ARGS_VERSION);
+    let verify_audit = matches.subcommand_matches(ARGS_VERIFY_AUDIT).is_some();
     let listen = matches
         .value_of(ARGS_LISTEN)
         .unwrap_or(ARGS_LISTEN_DEFAULT)
@@ -124,6 +264,269 @@ pub fn get_arguments() -> CliArguments {
         eprintln!("Database Password is required");
         exit(1);
     };
+    let db_pool_size =
+        match matches.value_of(ARGS_DATABASE_POOL_SIZE).map(str::parse) {
+            None => ARGS_DATABASE_POOL_SIZE_DEFAULT_U32,
+            Some(Ok(v)) if v > 0 => v,
+            Some(_) => {
+                eprintln!("Database pool size must be a positive number");
+                exit(1);
+            }
+        };
+    let db_pool_idle =
+        match matches.value_of(ARGS_DATABASE_POOL_IDLE).map(str::parse) {
+            None => ARGS_DATABASE_POOL_IDLE_DEFAULT_U32,
+            Some(Ok(v)) => v,
+            Some(Err(_)) => {
+                eprintln!("Database idle connections must be a number");
+                exit(1);
+            }
+        };
+    let db_connect_timeout = match matches
+        .value_of(ARGS_DATABASE_CONNECT_TIMEOUT)
+        .map(str::parse)
+    {
+        None => Duration::from_millis(ARGS_DATABASE_CONNECT_TIMEOUT_DEFAULT_U64),
+        Some(Ok(v)) if v > 0 => Duration::from_millis(v),
+        Some(_) => {
+            eprintln!("Database connect timeout must be a positive number");
+            exit(1);
+        }
+    };
+    let db_checkout_timeout = match matches
+        .value_of(ARGS_DATABASE_CHECKOUT_TIMEOUT)
+        .map(str::parse)
+    {
+        None => Duration::from_millis(ARGS_DATABASE_CHECKOUT_TIMEOUT_DEFAULT_U64),
+        Some(Ok(v)) if v > 0 => Duration::from_millis(v),
+        Some(_) => {
+            eprintln!("Database checkout timeout must be a positive number");
+            exit(1);
+        }
+    };
+    let db_health_interval = match matches
+        .value_of(ARGS_DATABASE_HEALTH_INTERVAL)
+        .map(str::parse)
+    {
+        None => Duration::from_secs(ARGS_DATABASE_HEALTH_INTERVAL_DEFAULT_U64),
+        Some(Ok(v)) if v > 0 => Duration::from_secs(v),
+        Some(_) => {
+            eprintln!("Database health check interval must be a positive number");
+            exit(1);
+        }
+    };
+    if db_pool_idle > db_pool_size {
+        eprintln!("Database idle connections must not exceed the pool size");
+        exit(1);
+    }
+    let audit_checkpoint_interval = match matches
+        .value_of(ARGS_AUDIT_CHECKPOINT_INTERVAL)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(ARGS_AUDIT_CHECKPOINT_INTERVAL_DEFAULT_U64)
+    {
+        0 => None,
+        v => Some(Duration::from_secs(v)),
+    };
+    let webhook_interval = match matches
+        .value_of(ARGS_WEBHOOK_INTERVAL)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(ARGS_WEBHOOK_INTERVAL_DEFAULT_U64)
+    {
+        0 => None,
+        v => Some(Duration::from_secs(v)),
+    };
+    let webhook_timeout = Duration::from_secs(
+        matches
+            .value_of(ARGS_WEBHOOK_TIMEOUT)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(ARGS_WEBHOOK_TIMEOUT_DEFAULT_U64),
+    );
+    let mail = matches.value_of(ARGS_SMTP_HOST).map(|host| {
+        let security = match matches
+            .value_of(ARGS_SMTP_SECURITY)
+            .map(SmtpSecurity::try_from)
+        {
+            None => SmtpSecurity::StartTls,
+            Some(Ok(v)) => v,
+            Some(Err(_)) => {
+                eprintln!("SMTP security must be one of none, starttls or tls");
+                exit(1);
+            }
+        };
+        let from_address = if let Some(v) = matches.value_of(ARGS_MAIL_FROM) {
+            v.into()
+        } else {
+            eprintln!("Mail sender address is required when using SMTP");
+            exit(1);
+        };
+        MailConfig {
+            host: host.into(),
+            port: matches
+                .value_of(ARGS_SMTP_PORT)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(ARGS_SMTP_PORT_DEFAULT_U16),
+            security,
+            username: matches.value_of(ARGS_SMTP_USER).map(Into::into),
+            password: matches.value_of(ARGS_SMTP_PASS).map(Into::into),
+            from_address,
+            from_name: matches
+                .value_of(ARGS_MAIL_FROM_NAME)
+                .unwrap_or(ARGS_MAIL_FROM_NAME_DEFAULT)
+                .into(),
+            timeout: Duration::from_secs(
+                matches
+                    .value_of(ARGS_SMTP_TIMEOUT)
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(ARGS_SMTP_TIMEOUT_DEFAULT_U64),
+            ),
+        }
+    });
+    let notification_interval = match matches
+        .value_of(ARGS_NOTIFICATION_INTERVAL)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(ARGS_NOTIFICATION_INTERVAL_DEFAULT_U64)
+    {
+        0 => None,
+        v => Some(Duration::from_secs(v)),
+    };
+    let max_keys = match matches.value_of(ARGS_MAX_KEYS).map(str::parse) {
+        None | Some(Ok(0)) => None,
+        Some(Ok(v)) => Some(v),
+        Some(Err(_)) => {
+            eprintln!("Maximum number of keys must be a number");
+            exit(1);
+        }
+    };
+    let mut key_policy = match KeyPolicy::parse(
+        matches
+            .value_of(ARGS_KEY_TYPES)
+            .unwrap_or(ARGS_KEY_TYPES_DEFAULT),
+        max_keys,
+    ) {
+        Ok(policy) => policy,
+        Err(err) => {
+            eprintln!("Key policy is invalid: {}", err);
+            exit(1);
+        }
+    };
+    key_policy.max_age = match matches.value_of(ARGS_KEY_MAX_AGE).map(str::parse) {
+        None | Some(Ok(0)) => None,
+        Some(Ok(v)) => Some(v),
+        Some(Err(_)) => {
+            eprintln!("Maximum key age must be a number of days");
+            exit(1);
+        }
+    };
+    key_policy.expiry_warning =
+        match matches.value_of(ARGS_KEY_EXPIRY_WARNING).map(str::parse) {
+            None => DEFAULT_EXPIRY_WARNING,
+            Some(Ok(v)) => v,
+            Some(Err(_)) => {
+                eprintln!("Key expiry warning must be a number of days");
+                exit(1);
+            }
+        };
+    let key_expiry_interval = match matches
+        .value_of(ARGS_KEY_EXPIRY_INTERVAL)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(ARGS_KEY_EXPIRY_INTERVAL_DEFAULT_U64)
+    {
+        0 => None,
+        v => Some(Duration::from_secs(v)),
+    };
+    key_policy.retention = match matches.value_of(ARGS_KEY_RETENTION).map(str::parse)
+    {
+        None | Some(Ok(0)) => None,
+        Some(Ok(v)) => Some(v),
+        Some(Err(_)) => {
+            eprintln!("Key retention must be a number of days");
+            exit(1);
+        }
+    };
+    let key_retention_interval = match matches
+        .value_of(ARGS_KEY_RETENTION_INTERVAL)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(ARGS_KEY_RETENTION_INTERVAL_DEFAULT_U64)
+    {
+        0 => None,
+        v => Some(Duration::from_secs(v)),
+    };
+    let access_schedule_interval = match matches
+        .value_of(ARGS_ACCESS_SCHEDULE_INTERVAL)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(ARGS_ACCESS_SCHEDULE_INTERVAL_DEFAULT_U64)
+    {
+        0 => None,
+        v => Some(Duration::from_secs(v)),
+    };
+    let mut key_blocklist = KeyBlocklist::default();
+    for path in matches.values_of(ARGS_KEY_BLOCKLIST).into_iter().flatten() {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("Unable to read key blocklist {}: {}", path, err);
+                exit(1);
+            }
+        };
+        if let Err(err) = key_blocklist.extend(&text) {
+            eprintln!("Key blocklist {} is invalid: {}", path, err);
+            exit(1);
+        }
+    }
+    let certificate_policy = CertificatePolicy {
+        default_validity: match matches.value_of(ARGS_CERT_VALIDITY).map(str::parse)
+        {
+            None => DEFAULT_CERTIFICATE_VALIDITY,
+            Some(Ok(v)) if v > 0 => v,
+            Some(_) => {
+                eprintln!("Certificate validity must be a positive number of hours");
+                exit(1);
+            }
+        },
+        max_validity: match matches.value_of(ARGS_CERT_MAX_VALIDITY).map(str::parse)
+        {
+            None => DEFAULT_CERTIFICATE_MAX_VALIDITY,
+            Some(Ok(v)) if v > 0 => v,
+            Some(_) => {
+                eprintln!(
+                    "Maximum certificate validity must be a positive number of hours"
+                );
+                exit(1);
+            }
+        },
+        host_validity: match matches
+            .value_of(ARGS_HOST_CERT_VALIDITY)
+            .map(str::parse)
+        {
+            None => DEFAULT_HOST_CERTIFICATE_VALIDITY,
+            Some(Ok(v)) if v > 0 => v,
+            Some(_) => {
+                eprintln!(
+                    "Host certificate validity must be a positive number of days"
+                );
+                exit(1);
+            }
+        },
+    };
+    if certificate_policy.default_validity > certificate_policy.max_validity {
+        eprintln!("Certificate validity must not exceed the maximum validity");
+        exit(1);
+    }
+    let api_token_validity =
+        match matches.value_of(ARGS_API_TOKEN_VALIDITY).map(str::parse) {
+            None => ARGS_API_TOKEN_VALIDITY_DEFAULT_U32,
+            Some(Ok(v)) if v > 0 => v,
+            Some(_) => {
+                eprintln!("Api token validity must be a positive number of days");
+                exit(1);
+            }
+        };
+    let break_glass_accounts = matches
+        .values_of(ARGS_BREAK_GLASS_ACCOUNT)
+        .into_iter()
+        .flatten()
+        .map(String::from)
+        .collect();
     let app_secret = if let Some(v) = matches
         .value_of(ARGS_APP_SECRET)
         .map(str::as_bytes)
@@ -201,6 +604,7 @@ pub fn get_arguments() -> CliArguments {
 
     CliArguments {
         print_version,
+        verify_audit,
 
         listen,
         port,
@@ -212,6 +616,26 @@ pub fn get_arguments() -> CliArguments {
         db_name,
         db_user,
         db_pass,
+        db_pool_size,
+        db_pool_idle,
+        db_connect_timeout,
+        db_checkout_timeout,
+        db_health_interval,
+
+        audit_checkpoint_interval,
+        webhook_interval,
+        webhook_timeout,
+        mail,
+        notification_interval,
+
+        key_policy,
+        key_expiry_interval,
+        key_retention_interval,
+        access_schedule_interval,
+        key_blocklist,
+        certificate_policy,
+        api_token_validity,
+        break_glass_accounts,
     }
 }
 
@@ -265,7 +689,7 @@ fn get_cli_config<'a>() -> ArgMatches<'a> {
                 .long(ARGS_DATABASE_HOST)
                 .env(ARGS_DATABASE_HOST_ENV)
                 .value_name("<hostname/ip>:<port>")
-                .help("Database Hostname or Ip with port")
+                .help("Database Hostname or Ip with port. The first host is used as primary, additional hosts as read replicas")
                 .takes_value(true)
                 .multiple(true)
                 .number_of_values(1)
@@ -298,6 +722,283 @@ fn get_cli_config<'a>() -> ArgMatches<'a> {
                 .takes_value(true)
                 .required(true),
         )
+        .arg(
+            Arg::with_name(ARGS_DATABASE_POOL_SIZE)
+                .long(ARGS_DATABASE_POOL_SIZE)
+                .env(ARGS_DATABASE_POOL_SIZE_ENV)
+                .value_name("connections")
+                .help("Maximum number of connections per database host")
+                .default_value(ARGS_DATABASE_POOL_SIZE_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_DATABASE_POOL_IDLE)
+                .long(ARGS_DATABASE_POOL_IDLE)
+                .env(ARGS_DATABASE_POOL_IDLE_ENV)
+                .value_name("connections")
+                .help("Minimum number of idle connections per database host")
+                .default_value(ARGS_DATABASE_POOL_IDLE_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_DATABASE_CONNECT_TIMEOUT)
+                .long(ARGS_DATABASE_CONNECT_TIMEOUT)
+                .env(ARGS_DATABASE_CONNECT_TIMEOUT_ENV)
+                .value_name("milliseconds")
+                .help("Timeout for establishing connections and migrating the database")
+                .default_value(ARGS_DATABASE_CONNECT_TIMEOUT_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_DATABASE_CHECKOUT_TIMEOUT)
+                .long(ARGS_DATABASE_CHECKOUT_TIMEOUT)
+                .env(ARGS_DATABASE_CHECKOUT_TIMEOUT_ENV)
+                .value_name("milliseconds")
+                .help("Timeout for retrieving a connection from a pool before falling back to the next host")
+                .default_value(ARGS_DATABASE_CHECKOUT_TIMEOUT_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_DATABASE_HEALTH_INTERVAL)
+                .long(ARGS_DATABASE_HEALTH_INTERVAL)
+                .env(ARGS_DATABASE_HEALTH_INTERVAL_ENV)
+                .value_name("seconds")
+                .help("Interval in which read replicas are checked for their health")
+                .default_value(ARGS_DATABASE_HEALTH_INTERVAL_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_AUDIT_CHECKPOINT_INTERVAL)
+                .long(ARGS_AUDIT_CHECKPOINT_INTERVAL)
+                .env(ARGS_AUDIT_CHECKPOINT_INTERVAL_ENV)
+                .value_name("seconds")
+                .help("Interval in which the end of the audit log is signed with the app secret. 0 disables signing")
+                .default_value(ARGS_AUDIT_CHECKPOINT_INTERVAL_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_WEBHOOK_INTERVAL)
+                .long(ARGS_WEBHOOK_INTERVAL)
+                .env(ARGS_WEBHOOK_INTERVAL_ENV)
+                .value_name("seconds")
+                .help("Interval in which pending webhook deliveries are sent. 0 disables deliveries")
+                .default_value(ARGS_WEBHOOK_INTERVAL_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_WEBHOOK_TIMEOUT)
+                .long(ARGS_WEBHOOK_TIMEOUT)
+                .env(ARGS_WEBHOOK_TIMEOUT_ENV)
+                .value_name("seconds")
+                .help("Time to wait for a webhook receiver to respond")
+                .default_value(ARGS_WEBHOOK_TIMEOUT_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_SMTP_HOST)
+                .long(ARGS_SMTP_HOST)
+                .env(ARGS_SMTP_HOST_ENV)
+                .value_name("hostname/ip")
+                .help("SMTP relay used to send mails. Sending mails is disabled if not set")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_SMTP_PORT)
+                .long(ARGS_SMTP_PORT)
+                .env(ARGS_SMTP_PORT_ENV)
+                .value_name("port")
+                .help("Port of the SMTP relay")
+                .default_value(ARGS_SMTP_PORT_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_SMTP_SECURITY)
+                .long(ARGS_SMTP_SECURITY)
+                .env(ARGS_SMTP_SECURITY_ENV)
+                .value_name("none|starttls|tls")
+                .help("How the connection to the SMTP relay is secured. Use none only for local relays or test sinks")
+                .default_value(ARGS_SMTP_SECURITY_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_SMTP_USER)
+                .long(ARGS_SMTP_USER)
+                .env(ARGS_SMTP_USER_ENV)
+                .value_name("username")
+                .help("Username for the SMTP relay. No authentication if not set")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_SMTP_PASS)
+                .long(ARGS_SMTP_PASS)
+                .env(ARGS_SMTP_PASS_ENV)
+                .value_name("password")
+                .help("Password for the SMTP relay")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_SMTP_TIMEOUT)
+                .long(ARGS_SMTP_TIMEOUT)
+                .env(ARGS_SMTP_TIMEOUT_ENV)
+                .value_name("seconds")
+                .help("Time to wait for the SMTP relay to respond")
+                .default_value(ARGS_SMTP_TIMEOUT_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_MAIL_FROM)
+                .long(ARGS_MAIL_FROM)
+                .env(ARGS_MAIL_FROM_ENV)
+                .value_name("address")
+                .help("Address mails are sent from. Required when using SMTP")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_MAIL_FROM_NAME)
+                .long(ARGS_MAIL_FROM_NAME)
+                .env(ARGS_MAIL_FROM_NAME_ENV)
+                .value_name("name")
+                .help("Name shown next to the sender address of mails")
+                .default_value(ARGS_MAIL_FROM_NAME_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_NOTIFICATION_INTERVAL)
+                .long(ARGS_NOTIFICATION_INTERVAL)
+                .env(ARGS_NOTIFICATION_INTERVAL_ENV)
+                .value_name("seconds")
+                .help("Interval in which pending notifications are mailed to users. Requires SMTP. 0 disables notifications")
+                .default_value(ARGS_NOTIFICATION_INTERVAL_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_KEY_TYPES)
+                .long(ARGS_KEY_TYPES)
+                .env(ARGS_KEY_TYPES_ENV)
+                .value_name("types")
+                .help("Comma separated list of allowed public key types. A minimum size in bits may be appended using a colon, e.g. ssh-rsa:3072. ssh-dss is always rejected")
+                .default_value(ARGS_KEY_TYPES_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_MAX_KEYS)
+                .long(ARGS_MAX_KEYS)
+                .env(ARGS_MAX_KEYS_ENV)
+                .value_name("count")
+                .help("Maximum number of public keys per user. 0 disables the limit")
+                .default_value(ARGS_MAX_KEYS_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_KEY_MAX_AGE)
+                .long(ARGS_KEY_MAX_AGE)
+                .env(ARGS_KEY_MAX_AGE_ENV)
+                .value_name("days")
+                .help("Number of days after which uploaded public keys expire. 0 disables the limit")
+                .default_value(ARGS_KEY_MAX_AGE_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_KEY_EXPIRY_WARNING)
+                .long(ARGS_KEY_EXPIRY_WARNING)
+                .env(ARGS_KEY_EXPIRY_WARNING_ENV)
+                .value_name("days")
+                .help("Number of days before the expiry of a public key in which its owner is reminded")
+                .default_value(ARGS_KEY_EXPIRY_WARNING_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_KEY_EXPIRY_INTERVAL)
+                .long(ARGS_KEY_EXPIRY_INTERVAL)
+                .env(ARGS_KEY_EXPIRY_INTERVAL_ENV)
+                .value_name("seconds")
+                .help("Interval in which expiring public keys are checked. 0 disables reminders")
+                .default_value(ARGS_KEY_EXPIRY_INTERVAL_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_KEY_RETENTION)
+                .long(ARGS_KEY_RETENTION)
+                .env(ARGS_KEY_RETENTION_ENV)
+                .value_name("days")
+                .help("Number of days after which deleted public keys are removed permanently. 0 keeps them forever")
+                .default_value(ARGS_KEY_RETENTION_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_KEY_RETENTION_INTERVAL)
+                .long(ARGS_KEY_RETENTION_INTERVAL)
+                .env(ARGS_KEY_RETENTION_INTERVAL_ENV)
+                .value_name("seconds")
+                .help("Interval in which deleted public keys are checked for removal")
+                .default_value(ARGS_KEY_RETENTION_INTERVAL_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_ACCESS_SCHEDULE_INTERVAL)
+                .long(ARGS_ACCESS_SCHEDULE_INTERVAL)
+                .env(ARGS_ACCESS_SCHEDULE_INTERVAL_ENV)
+                .value_name("seconds")
+                .help("Interval in which time-bound access grants are checked for a resync. 0 disables the check")
+                .default_value(ARGS_ACCESS_SCHEDULE_INTERVAL_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_KEY_BLOCKLIST)
+                .long(ARGS_KEY_BLOCKLIST)
+                .env(ARGS_KEY_BLOCKLIST_ENV)
+                .value_name("file")
+                .help("File with fingerprints of compromised public keys, one per line. May be given multiple times")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name(ARGS_CERT_VALIDITY)
+                .long(ARGS_CERT_VALIDITY)
+                .env(ARGS_CERT_VALIDITY_ENV)
+                .value_name("hours")
+                .help("Number of hours issued certificates are valid if not requested otherwise")
+                .default_value(ARGS_CERT_VALIDITY_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_CERT_MAX_VALIDITY)
+                .long(ARGS_CERT_MAX_VALIDITY)
+                .env(ARGS_CERT_MAX_VALIDITY_ENV)
+                .value_name("hours")
+                .help("Maximum number of hours issued certificates are valid")
+                .default_value(ARGS_CERT_MAX_VALIDITY_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_HOST_CERT_VALIDITY)
+                .long(ARGS_HOST_CERT_VALIDITY)
+                .env(ARGS_HOST_CERT_VALIDITY_ENV)
+                .value_name("days")
+                .help("Number of days issued host certificates are valid")
+                .default_value(ARGS_HOST_CERT_VALIDITY_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_API_TOKEN_VALIDITY)
+                .long(ARGS_API_TOKEN_VALIDITY)
+                .env(ARGS_API_TOKEN_VALIDITY_ENV)
+                .value_name("days")
+                .help("Number of days api tokens are valid")
+                .default_value(ARGS_API_TOKEN_VALIDITY_DEFAULT)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(ARGS_BREAK_GLASS_ACCOUNT)
+                .long(ARGS_BREAK_GLASS_ACCOUNT)
+                .env(ARGS_BREAK_GLASS_ACCOUNT_ENV)
+                .value_name("account@hostname")
+                .help("Server account anyone may take emergency access to, besides accounts a user was granted before. May be given multiple times")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
         .arg(
             Arg::with_name(ARGS_APP_SECRET)
                 .long(ARGS_APP_SECRET)
@@ -371,5 +1072,9 @@ fn get_cli_config<'a>() -> ArgMatches<'a> {
                 .takes_value(true)
                 .required_if(ARGS_AUTH_TYPE, "oauth"),
         )
+        .subcommand(
+            SubCommand::with_name(ARGS_VERIFY_AUDIT)
+                .about("Verifies the hash chain of the audit log and reports the first broken link"),
+        )
         .get_matches()
 }
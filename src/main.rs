@@ -57,16 +57,32 @@ mod routes;
 
 use crate::args::{get_arguments, AuthType, CliArguments};
 use core_common::{
-    database::{Create, Database, Delete, FetchAll, FetchById, FetchByUid, Save},
-    objects::{Entity, PublicKey, PublicKeyFilter, User},
+    database::{
+        AccessRequestStore, ApiTokenStore, BreakGlassStore, CertificateStore,
+        ContactStore, Create, Database, Delete, FetchAll, FetchAllFor, FetchById,
+        FetchByUid, KeyCount, LoginStore, NotificationStore, Purge,
+        RecertificationStore, Restore, Save, ServerAdminStore, ServerSettingsStore,
+        ServerTokenStore,
+    },
+    expiry::ExpiryWorker,
+    mail::Mailer,
+    notification::NotificationWorker,
+    objects::{
+        Entity, Event, EventFilter, PublicKey, PublicKeyFilter,
+        Server as ServerObject, ServerFilter, ServerNote, ServerNoteFilter, User,
+        Webhook, WebhookDelivery, WebhookDeliveryFilter, WebhookFilter,
+    },
+    retention::RetentionWorker,
+    schedule::AccessScheduleWorker,
     sec::{Auth, OAuth2, PreAuth},
-    tokio::{fs, signal},
+    tokio::{fs, signal, spawn},
     web::{BaseData, BaseView, Server, TemplateEngine},
+    webhook::WebhookWorker,
 };
 use std::{process::exit, sync::Arc, time::SystemTime};
 
 #[cfg(feature = "diesel")]
-use database_diesel::{DieselDB, PgConnection};
+use database_diesel::{DieselDB, PgConnection, PoolConfig};
 #[cfg(feature = "jwt")]
 use sec_token::Token;
 #[cfg(not(feature = "handlebars"))]
@@ -113,12 +129,24 @@ async fn main() {
                     args.db_user, args.db_pass, host, args.db_name
                 )
             });
-            match DieselDB::<PgConnection>::new(urls) {
+            let config = PoolConfig {
+                max_size: args.db_pool_size,
+                min_idle: Some(args.db_pool_idle),
+                connection_timeout: args.db_connect_timeout,
+                checkout_timeout: args.db_checkout_timeout,
+                health_check_interval: args.db_health_interval,
+            };
+            match DieselDB::<PgConnection>::new(urls, config) {
                 Err(err) => {
                     eprintln!("Unable to setup database: {}", err);
                     exit(1);
                 }
-                Ok(db) => db,
+                Ok(db) => {
+                    let _ = db.spawn_health_check();
+                    db.with_notifications(
+                        args.mail.is_some() && args.notification_interval.is_some(),
+                    )
+                }
             }
         }
     };
@@ -126,6 +154,68 @@ async fn main() {
         log::error!("Unable to migrate database: {}", err);
         exit(1);
     }
+    if args.verify_audit {
+        match database.verify_audit(&args.app_secret) {
+            Ok(report) => {
+                println!("{}", report);
+                exit(if report.broken.is_some() { 1 } else { 0 });
+            }
+            Err(err) => {
+                eprintln!("Unable to verify audit log: {}", err);
+                exit(1);
+            }
+        }
+    }
+    match database.seal_events() {
+        Ok(0) => {}
+        Ok(sealed) => log::info!("Added {} events to the audit log chain", sealed),
+        Err(err) => {
+            log::error!("Unable to chain audit log: {}", err);
+            exit(1);
+        }
+    }
+    if let Some(interval) = args.audit_checkpoint_interval {
+        let _ = database.spawn_checkpoint(args.app_secret, interval);
+    }
+    let database = Arc::new(database);
+    if let Some(interval) = args.webhook_interval {
+        match WebhookWorker::new(
+            Arc::clone(&database),
+            interval,
+            args.webhook_timeout,
+            args.app_secret,
+        ) {
+            Ok(worker) => {
+                let _ = spawn(worker.run());
+            }
+            Err(err) => {
+                log::error!("Unable to setup webhook delivery: {}", err);
+                exit(1);
+            }
+        }
+    }
+    if let Some(interval) = args.key_expiry_interval {
+        let worker = ExpiryWorker::new(
+            Arc::clone(&database),
+            args.key_policy.clone(),
+            interval,
+        );
+        let _ = spawn(worker.run());
+    }
+    if let (Some(interval), Some(_)) =
+        (args.key_retention_interval, args.key_policy.retention)
+    {
+        let worker = RetentionWorker::new(
+            Arc::clone(&database),
+            args.key_policy.clone(),
+            interval,
+        );
+        let _ = spawn(worker.run());
+    }
+    if let Some(interval) = args.access_schedule_interval {
+        let worker = AccessScheduleWorker::new(Arc::clone(&database), interval);
+        let _ = spawn(worker.run());
+    }
     let templates = {
         #[cfg(not(feature = "handlebars"))]
         {
@@ -142,14 +232,23 @@ async fn main() {
             hbse
         }
     };
+    let templates = Arc::new(templates);
+    if let (Some(interval), Some(mail)) = (args.notification_interval, &args.mail) {
+        let worker = NotificationWorker::new(
+            Arc::clone(&database),
+            Arc::clone(&templates),
+            Mailer::new(mail.clone()),
+            interval,
+        );
+        let _ = spawn(worker.run());
+    }
     #[cfg(not(feature = "jwt"))]
     {
         compile_error!("Auth backend is required")
     }
     #[cfg(feature = "jwt")]
     {
-        build_server::<Token, _, _>(&args, Arc::new(database), Arc::new(templates))
-            .await;
+        build_server::<Token, _, _>(&args, database, templates).await;
     }
 }
 
@@ -162,15 +261,45 @@ async fn build_server<A, D, T>(
     A: 'static + Auth,
     for<'a, 'b, 'c> D: 'static
         + Database
+        + ApiTokenStore
         + FetchByUid<PreAuth, User<'a>, D>
         + FetchByUid<A, User<'a>, D>
         + FetchById<'b, A, PublicKey<'a>, D>
         + FetchById<'b, A, Entity<'a>, D>
+        + FetchById<'b, A, ServerObject<'a>, D>
+        + FetchAllFor<A, ServerObject<'a>, ServerFilter<'c>, D>
+        + LoginStore
+        + KeyCount
+        + AccessRequestStore
+        + BreakGlassStore
+        + RecertificationStore
+        + ServerAdminStore
+        + ServerSettingsStore
+        + ServerTokenStore
+        + ContactStore
+        + NotificationStore
         + Create<PreAuth, User<'a>, D>
         + Create<A, PublicKey<'a>, D>
         + Delete<A, PublicKey<'a>, D>
+        + Restore<A, PublicKey<'a>, D>
+        + Purge<A, PublicKey<'a>, D>
+        + CertificateStore
+        + Create<A, Event<'a>, D>
         + Save<PreAuth, User<'a>, D>
-        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>,
+        + Create<PreAuth, Event<'a>, D>
+        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>
+        + FetchAll<'b, A, ServerObject<'a>, ServerFilter<'c>, D>
+        + Create<A, ServerObject<'a>, D>
+        + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>
+        + Create<A, ServerNote<'a>, D>
+        + Save<A, ServerNote<'a>, D>
+        + Delete<A, ServerNote<'a>, D>
+        + FetchAll<'b, A, Webhook<'a>, WebhookFilter<'c>, D>
+        + FetchAll<'b, A, WebhookDelivery<'a>, WebhookDeliveryFilter<'c>, D>
+        + FetchById<'b, A, Webhook<'a>, D>
+        + Create<A, Webhook<'a>, D>
+        + Delete<A, Webhook<'a>, D>,
     T: 'static + TemplateEngine,
 {
     let oauth = if let AuthType::OAuth(ref oauth) = args.auth_type {
@@ -205,6 +334,12 @@ async fn build_server<A, D, T>(
     let data = Arc::new(BaseData {
         app_secret: args.app_secret,
         oauth,
+        key_policy: args.key_policy.clone(),
+        key_blocklist: args.key_blocklist.clone(),
+        certificate_policy: args.certificate_policy,
+        api_token_validity: args.api_token_validity,
+        break_glass_accounts: args.break_glass_accounts.clone(),
+        mailer: args.mail.clone().map(Mailer::new),
     });
     let server = {
         #[cfg(not(feature = "hyper"))]
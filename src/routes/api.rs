@@ -0,0 +1,71 @@
+use core_api::{events, public_keys, servers};
+use core_common::{
+    database::{
+        CertificateStore, Create, Database, FetchAll, FetchAllFor, FetchById,
+        FetchByUid, KeyCount, LoginStore, ServerTokenStore,
+    },
+    http::{method::Method, response::Response},
+    objects::{
+        Entity, Event, EventFilter, PublicKey, PublicKeyFilter, Server,
+        ServerFilter, User,
+    },
+    sec::Auth,
+    web::{
+        not_found, redirect, route_at, unauthorized, AppError, Request,
+        ResponseType, TemplateEngine,
+    },
+};
+
+#[allow(single_use_lifetimes)]
+pub async fn index<A, D, T, R>(
+    req: &mut R,
+    path: &[String],
+) -> Result<Response<ResponseType>, AppError<A, D, T, R>>
+where
+    A: Auth,
+    for<'a, 'b, 'c> D: Database
+        + FetchById<'b, A, Entity<'a>, D>
+        + FetchByUid<A, User<'a>, D>
+        + Create<A, PublicKey<'a>, D>
+        + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>
+        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + FetchById<'b, A, Server<'a>, D>
+        + FetchAllFor<A, Server<'a>, ServerFilter<'c>, D>
+        + LoginStore
+        + KeyCount
+        + CertificateStore
+        + ServerTokenStore
+        + Create<A, Event<'a>, D>,
+    T: TemplateEngine,
+    R: Request<A, D, T>,
+{
+    let mut res = Response::builder();
+    if path.last().map(|s| s.as_ref()) != Some("") {
+        let path = req.get_uri().path();
+        let path = format!("{}/", path);
+        let res = Response::builder();
+        redirect(req, res, &path, true, true, false)
+    } else if route_at(path, 2) == Some("servers")
+        && route_at(path, 4) == Some("certificates")
+    {
+        servers::certificates(req, res, path).await
+    } else if req.authenticate(&mut res).await {
+        // api tokens only download the exports, anything else needs a login
+        let export = route_at(path, 2) == Some("servers")
+            && (route_at(path, 3) == Some("known_hosts")
+                || route_at(path, 3) == Some("ssh_config"));
+        if req.get_auth().is_api_token()
+            && (!export || *req.get_method() != Method::GET)
+        {
+            return unauthorized();
+        }
+        match route_at(path, 2) {
+            Some("events") => events::index(req, res, path).await,
+            Some("publickeys") => public_keys::index(req, res, path).await,
+            Some("servers") => servers::index(req, res, path).await,
+            _ => not_found(),
+        }
+    } else {
+        unauthorized()
+    }
+}
@@ -1,8 +1,21 @@
-use core_app::public_keys;
+use core_app::{
+    access_requests, break_glass, events, home, public_keys, recertifications,
+    servers, webhooks,
+};
 use core_common::{
-    database::{Create, Database, Delete, FetchAll, FetchById, FetchByUid, Save},
+    database::{
+        AccessRequestStore, ApiTokenStore, BreakGlassStore, CertificateStore,
+        ContactStore, Create, Database, Delete, FetchAll, FetchAllFor, FetchById,
+        FetchByUid, KeyCount, LoginStore, NotificationStore, Purge,
+        RecertificationStore, Restore, Save, ServerAdminStore, ServerSettingsStore,
+        ServerTokenStore,
+    },
     http::response::Response,
-    objects::{Entity, PublicKey, PublicKeyFilter, User},
+    objects::{
+        Entity, Event, EventFilter, PublicKey, PublicKeyFilter, Server,
+        ServerFilter, ServerNote, ServerNoteFilter, User, Webhook, WebhookDelivery,
+        WebhookDeliveryFilter, WebhookFilter,
+    },
     sec::{Auth, PreAuth},
     web::{
         not_found, redirect, route_at, serve_login, AppError, Request, ResponseType,
@@ -18,6 +31,7 @@ pub async fn index<A, D, T, R>(
 where
     A: Auth,
     for<'a, 'b, 'c> D: Database
+        + ApiTokenStore
         + FetchByUid<PreAuth, User<'a>, D>
         + FetchByUid<A, User<'a>, D>
         + FetchById<'b, A, PublicKey<'a>, D>
@@ -25,8 +39,36 @@ where
         + Create<PreAuth, User<'a>, D>
         + Create<A, PublicKey<'a>, D>
         + Delete<A, PublicKey<'a>, D>
+        + Restore<A, PublicKey<'a>, D>
+        + Purge<A, PublicKey<'a>, D>
+        + CertificateStore
+        + FetchAllFor<A, Server<'a>, ServerFilter<'c>, D>
+        + LoginStore
+        + KeyCount
+        + AccessRequestStore
+        + BreakGlassStore
+        + RecertificationStore
+        + ServerAdminStore
+        + ServerSettingsStore
+        + ServerTokenStore
+        + ContactStore
+        + NotificationStore
+        + Create<A, Event<'a>, D>
         + Save<PreAuth, User<'a>, D>
-        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>,
+        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>
+        + FetchAll<'b, A, Server<'a>, ServerFilter<'c>, D>
+        + FetchById<'b, A, Server<'a>, D>
+        + Create<A, Server<'a>, D>
+        + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>
+        + Create<A, ServerNote<'a>, D>
+        + Save<A, ServerNote<'a>, D>
+        + Delete<A, ServerNote<'a>, D>
+        + FetchAll<'b, A, Webhook<'a>, WebhookFilter<'c>, D>
+        + FetchAll<'b, A, WebhookDelivery<'a>, WebhookDeliveryFilter<'c>, D>
+        + FetchById<'b, A, Webhook<'a>, D>
+        + Create<A, Webhook<'a>, D>
+        + Delete<A, Webhook<'a>, D>,
     T: TemplateEngine,
     R: Request<A, D, T>,
 {
@@ -36,10 +78,18 @@ where
         let path = format!("{}/", path);
         let res = Response::builder();
         redirect(req, res, &path, true, true, false)
-    } else if req.authenticate(&mut res).await {
+    } else if req.authenticate(&mut res).await && !req.get_auth().is_api_token() {
         match route_at(path, 2) {
-            // Some("") => index_method(req),
+            Some("") => home::index(req, res).await,
+            Some("access_requests") => access_requests::index(req, res, path).await,
+            Some("break_glass") => break_glass::index(req, res, path).await,
+            Some("events") => events::index(req, res, path).await,
             Some("publickeys") => public_keys::index(req, res, path).await,
+            Some("recertifications") => {
+                recertifications::index(req, res, path).await
+            }
+            Some("servers") => servers::index(req, res, path).await,
+            Some("webhooks") => webhooks::index(req, res, path).await,
             _ => not_found(),
         }
     } else {
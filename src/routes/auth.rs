@@ -3,7 +3,7 @@ use core_app::auth::{logout, oauth};
 use core_common::{
     database::{Create, Database, FetchByUid, Save},
     http::response::Response,
-    objects::User,
+    objects::{Event, User},
     sec::{Auth, AuthMethod, PreAuth},
     web::{route_at, AppError, Request, ResponseType, TemplateEngine},
 };
@@ -17,7 +17,8 @@ where
     for<'a> D: Database
         + FetchByUid<PreAuth, User<'a>, D>
         + Create<PreAuth, User<'a>, D>
-        + Save<PreAuth, User<'a>, D>,
+        + Save<PreAuth, User<'a>, D>
+        + Create<PreAuth, Event<'a>, D>,
     T: TemplateEngine,
     R: Request<A, D, T>,
 {
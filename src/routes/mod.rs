@@ -1,11 +1,22 @@
+mod api;
 mod app;
 mod auth;
 
 use core_app::rstatic;
 use core_common::{
-    database::{Create, Database, Delete, FetchAll, FetchById, FetchByUid, Save},
+    database::{
+        AccessRequestStore, ApiTokenStore, BreakGlassStore, CertificateStore,
+        ContactStore, Create, Database, Delete, FetchAll, FetchAllFor, FetchById,
+        FetchByUid, KeyCount, LoginStore, NotificationStore, Purge,
+        RecertificationStore, Restore, Save, ServerAdminStore, ServerSettingsStore,
+        ServerTokenStore,
+    },
     http::{method::Method, response::Response, status::StatusCode},
-    objects::{Entity, PublicKey, PublicKeyFilter, User},
+    objects::{
+        Entity, Event, EventFilter, PublicKey, PublicKeyFilter, Server,
+        ServerFilter, ServerNote, ServerNoteFilter, User, Webhook, WebhookDelivery,
+        WebhookDeliveryFilter, WebhookFilter,
+    },
     sec::{Auth, PreAuth},
     web::{
         invalid_method, not_found, redirect_home, route_at, AppError, Request,
@@ -22,15 +33,45 @@ pub async fn handler<A, D, T, R>(
 where
     A: Auth,
     for<'a, 'b, 'c> D: Database
+        + ApiTokenStore
         + FetchByUid<PreAuth, User<'a>, D>
         + FetchByUid<A, User<'a>, D>
         + FetchById<'b, A, PublicKey<'a>, D>
         + FetchById<'b, A, Entity<'a>, D>
+        + FetchById<'b, A, Server<'a>, D>
+        + FetchAllFor<A, Server<'a>, ServerFilter<'c>, D>
+        + LoginStore
+        + KeyCount
+        + AccessRequestStore
+        + BreakGlassStore
+        + RecertificationStore
+        + ServerAdminStore
+        + ServerSettingsStore
+        + ServerTokenStore
+        + ContactStore
+        + NotificationStore
         + Create<PreAuth, User<'a>, D>
         + Create<A, PublicKey<'a>, D>
         + Delete<A, PublicKey<'a>, D>
+        + Restore<A, PublicKey<'a>, D>
+        + Purge<A, PublicKey<'a>, D>
+        + CertificateStore
+        + Create<A, Event<'a>, D>
         + Save<PreAuth, User<'a>, D>
-        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>,
+        + Create<PreAuth, Event<'a>, D>
+        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>
+        + FetchAll<'b, A, Server<'a>, ServerFilter<'c>, D>
+        + Create<A, Server<'a>, D>
+        + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>
+        + Create<A, ServerNote<'a>, D>
+        + Save<A, ServerNote<'a>, D>
+        + Delete<A, ServerNote<'a>, D>
+        + FetchAll<'b, A, Webhook<'a>, WebhookFilter<'c>, D>
+        + FetchAll<'b, A, WebhookDelivery<'a>, WebhookDeliveryFilter<'c>, D>
+        + FetchById<'b, A, Webhook<'a>, D>
+        + Create<A, Webhook<'a>, D>
+        + Delete<A, Webhook<'a>, D>,
     T: TemplateEngine,
     R: Request<A, D, T>,
 {
@@ -76,15 +117,45 @@ pub async fn index<A, D, T, R>(
 where
     A: Auth,
     for<'a, 'b, 'c> D: Database
+        + ApiTokenStore
         + FetchByUid<PreAuth, User<'a>, D>
         + FetchByUid<A, User<'a>, D>
         + FetchById<'b, A, PublicKey<'a>, D>
         + FetchById<'b, A, Entity<'a>, D>
+        + FetchById<'b, A, Server<'a>, D>
+        + FetchAllFor<A, Server<'a>, ServerFilter<'c>, D>
+        + LoginStore
+        + KeyCount
+        + AccessRequestStore
+        + BreakGlassStore
+        + RecertificationStore
+        + ServerAdminStore
+        + ServerSettingsStore
+        + ServerTokenStore
+        + ContactStore
+        + NotificationStore
         + Create<PreAuth, User<'a>, D>
         + Create<A, PublicKey<'a>, D>
         + Delete<A, PublicKey<'a>, D>
+        + Restore<A, PublicKey<'a>, D>
+        + Purge<A, PublicKey<'a>, D>
+        + CertificateStore
+        + Create<A, Event<'a>, D>
         + Save<PreAuth, User<'a>, D>
-        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>,
+        + Create<PreAuth, Event<'a>, D>
+        + FetchAll<'b, A, PublicKey<'a>, PublicKeyFilter<'c>, D>
+        + FetchAll<'b, A, Event<'a>, EventFilter<'c>, D>
+        + FetchAll<'b, A, Server<'a>, ServerFilter<'c>, D>
+        + Create<A, Server<'a>, D>
+        + FetchAll<'b, A, ServerNote<'a>, ServerNoteFilter<'c>, D>
+        + Create<A, ServerNote<'a>, D>
+        + Save<A, ServerNote<'a>, D>
+        + Delete<A, ServerNote<'a>, D>
+        + FetchAll<'b, A, Webhook<'a>, WebhookFilter<'c>, D>
+        + FetchAll<'b, A, WebhookDelivery<'a>, WebhookDeliveryFilter<'c>, D>
+        + FetchById<'b, A, Webhook<'a>, D>
+        + Create<A, Webhook<'a>, D>
+        + Delete<A, Webhook<'a>, D>,
     T: TemplateEngine,
     R: Request<A, D, T>,
 {
@@ -92,6 +163,7 @@ where
         req.get_uri().path().split('/').map(Into::into).collect();
     match route_at(&path, 1) {
         None | Some("") => index_method(req),
+        Some("api") => api::index(req, &path).await,
         Some("app") => app::index(req, &path).await,
         Some("auth") => auth::index(req, &path).await,
         Some("static") => rstatic::index(req, &path).await,
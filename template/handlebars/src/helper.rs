@@ -1,5 +1,8 @@
 #![allow(clippy::integer_arithmetic)]
-use core_common::serde_json::Value;
+use core_common::{
+    chrono::{NaiveDateTime, Utc},
+    serde_json::Value,
+};
 use handlebars::{handlebars_helper, HelperDef};
 
 pub(crate) fn get_helpers() -> Vec<(&'static str, Box<dyn HelperDef>)> {
@@ -16,6 +19,9 @@ pub(crate) fn get_helpers() -> Vec<(&'static str, Box<dyn HelperDef>)> {
         ("transform_sync", Box::new(transform_sync)),
         ("transform_sync_label", Box::new(transform_sync_label)),
         ("transform_config", Box::new(transform_config)),
+        ("date", Box::new(date)),
+        ("expired", Box::new(expired)),
+        ("remaining", Box::new(remaining)),
     ]
 }
 
@@ -68,7 +74,7 @@ handlebars_helper!(is_admin: |type_: str| {
 });
 
 handlebars_helper!(transform_sync_label: |sync_status: str, key_mgmnt: str| {
-    if key_mgmnt == "Keys" {
+    if key_mgmnt == "Keys" || key_mgmnt == "Certificates" {
         match sync_status {
             "NotSyncedYet" | "SyncWarning" => "warning",
             "SyncFailure" => "danger",
@@ -97,8 +103,46 @@ handlebars_helper!(transform_config: |key_mgmnt: str, auth: str| {
             "Automatic" => "Automatic account management",
             _ => "",
         },
+        "Certificates" => "Certificate based access",
         "Other" => "Managed by another system",
         "None" => "Unmanaged",
         _ => "",
     }
 });
+
+handlebars_helper!(date: |v: Json| {
+    if let Value::String(v) = v {
+        v.split('T').next().unwrap_or_default().to_string()
+    } else {
+        String::new()
+    }
+});
+
+handlebars_helper!(expired: |v: Json| {
+    if let Value::String(v) = v {
+        v.parse::<NaiveDateTime>()
+            .map_or(false, |expires| expires <= Utc::now().naive_utc())
+    } else {
+        false
+    }
+});
+
+handlebars_helper!(remaining: |v: Json| {
+    let left = if let Value::String(v) = v {
+        v.parse::<NaiveDateTime>()
+            .ok()
+            .map(|until| until - Utc::now().naive_utc())
+    } else {
+        None
+    };
+    left.map_or_else(String::new, |left| {
+        let (count, unit) = if left.num_days() > 0 {
+            (left.num_days(), "day")
+        } else if left.num_hours() > 0 {
+            (left.num_hours(), "hour")
+        } else {
+            (left.num_minutes().max(0), "minute")
+        };
+        format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+    })
+});
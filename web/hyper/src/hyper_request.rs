@@ -1,6 +1,6 @@
 use core_common::{
     async_trait::async_trait,
-    database::{Create, Database, FetchByUid, Save},
+    database::{ApiTokenStore, Create, Database, FetchByUid, Save},
     http::{method::Method, request::Parts, response, uri::Uri, version::Version},
     objects::User,
     sec::{Auth, PreAuth},
@@ -8,7 +8,7 @@ use core_common::{
 };
 use hyper::{
     body::to_bytes,
-    header::{CONTENT_LENGTH, COOKIE, REFERER, USER_AGENT},
+    header::{AUTHORIZATION, CONTENT_LENGTH, COOKIE, REFERER, USER_AGENT},
     Body,
 };
 use std::{net::SocketAddr, sync::Arc};
@@ -44,6 +44,7 @@ where
     A: Auth,
     for<'a> D: 'static
         + Database
+        + ApiTokenStore
         + FetchByUid<PreAuth, User<'a>, D>
         + Create<PreAuth, User<'a>, D>
         + Save<PreAuth, User<'a>, D>,
@@ -127,6 +128,14 @@ where
             .and_then(|v| v.to_str().ok())
     }
 
+    #[inline]
+    fn get_authorization(&self) -> Option<&str> {
+        self.header
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+    }
+
     #[inline]
     fn get_uri(&self) -> &Uri {
         &self.header.uri
@@ -1,7 +1,7 @@
 use crate::hyper_request::HyperRequest;
 use core_common::{
     async_trait::async_trait,
-    database::{Create, Database, FetchByUid, Save},
+    database::{ApiTokenStore, Create, Database, FetchByUid, Save},
     http::response::Response,
     objects::User,
     sec::{Auth, PreAuth},
@@ -114,6 +114,7 @@ impl<A, D, T> Server<A, D, T, HyperRequest<A, D, T>> for HyperServer<A, D, T>
 where
     A: Auth,
     for<'a> D: Database
+        + ApiTokenStore
         + FetchByUid<PreAuth, User<'a>, D>
         + Create<PreAuth, User<'a>, D>
         + Save<PreAuth, User<'a>, D>,